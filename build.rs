@@ -0,0 +1,50 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc::compile();
+}
+
+// grpc generates engine service from messages defined in src/grpc.rs, definition is mirrored in
+// proto/chust.proto for clients written in other languages.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    fn method(
+        name: &str,
+        route: &str,
+        input: &str,
+        output: &str,
+    ) -> tonic_build::manual::MethodBuilder {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{}", input))
+            .output_type(format!("crate::grpc::{}", output))
+            .codec_path("tonic::codec::ProstCodec")
+    }
+
+    pub fn compile() {
+        let service = Service::builder()
+            .name("Engine")
+            .package("chust")
+            .method(method("best_move", "BestMove", "BestMoveRequest", "BestMoveReply").build())
+            .method(
+                method("analyze", "Analyze", "AnalyzeRequest", "AnalysisInfo")
+                    .server_streaming()
+                    .build(),
+            )
+            .method(
+                method(
+                    "legal_moves",
+                    "LegalMoves",
+                    "LegalMovesRequest",
+                    "LegalMovesReply",
+                )
+                .build(),
+            )
+            .build();
+        // transport helpers need TryInto from 2021 prelude, clients connect with tonic Channel.
+        Builder::new().build_transport(false).compile(&[service]);
+    }
+}
@@ -0,0 +1,877 @@
+use crate::backend::{SearchBackend, SearchLimits};
+use crate::board::{has_mating_material, Board, DrawReason, GameState, Transition};
+use crate::elo;
+use crate::game::Game;
+use crate::notation::line_to_san;
+use crate::pgn::{split_games, MoveAnnotation, PgnExportOptions, PgnGame};
+use crate::piece::Color;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// MAX_DEPTH bounds the search when moves are limited only by the clock.
+const MAX_DEPTH: usize = 64;
+
+// TimeControl is base time of a game with increment added after every move, written as in PGN
+// TimeControl tag, in seconds: '60+0.6' or '300'.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl FromStr for TimeControl {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seconds = |v: &str| {
+            v.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or("invalid time control")
+        };
+        let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+        let base = seconds(base)?;
+        if base.is_zero() {
+            return Err("base time must be positive");
+        }
+        Ok(TimeControl {
+            base,
+            increment: seconds(increment)?,
+        })
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.base.as_secs_f64())?;
+        if !self.increment.is_zero() {
+            write!(f, "+{}", self.increment.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+// Clock is a clock of one player.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clock {
+    pub remaining: Duration,
+    pub increment: Duration,
+}
+
+impl Clock {
+    pub fn new(time_control: &TimeControl) -> Self {
+        Clock {
+            remaining: time_control.base,
+            increment: time_control.increment,
+        }
+    }
+
+    // budget returns time the engine gets for the next move: a part of remaining time and most
+    // of the increment, but never more than half of remaining time.
+    pub fn budget(&self) -> Duration {
+        let budget = self.remaining / 30 + self.increment * 3 / 4;
+        budget.min(self.remaining / 2)
+    }
+
+    // punch stops the clock after a move that took 'used' time, returns false when flag fell.
+    pub fn punch(&mut self, used: Duration) -> bool {
+        match self.remaining.checked_sub(used) {
+            Some(remaining) => {
+                self.remaining = remaining + self.increment;
+                true
+            }
+            None => {
+                self.remaining = Duration::ZERO;
+                false
+            }
+        }
+    }
+}
+
+// Player is a search backend playing in a match.
+pub struct Player<'a> {
+    pub name: String,
+    pub backend: Box<dyn SearchBackend + 'a>,
+}
+
+// MatchSettings controls how games are played. Without time control every move is searched to
+// 'depth', with it engines get time from their clocks and 'depth' is ignored.
+#[derive(Clone, Debug)]
+pub struct MatchSettings {
+    pub depth: usize,
+    pub time_control: Option<TimeControl>,
+    // lag is added to time of every move, emulating delay of communication with a GUI or server.
+    pub lag: Duration,
+    pub max_plies: usize, // game is adjudicated as a draw after this number of plies
+    pub event: String,    // PGN Event tag of played games
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            depth: 3,
+            time_control: None,
+            lag: Duration::ZERO,
+            max_plies: 300,
+            event: "?".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    fn win(color: Color) -> Self {
+        if color == Color::WHITE {
+            GameResult::WhiteWins
+        } else {
+            GameResult::BlackWins
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        };
+        write!(f, "{}", result)
+    }
+}
+
+// Termination is a reason why the game ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    TimeForfeit, // flag fell, it's a draw when opponent can't mate
+    Repetition,
+    InsufficientMaterial,
+    FiftyMoves,
+    MoveLimit,
+}
+
+impl Termination {
+    // pgn_tag returns value of PGN Termination tag, None for games ended by the rules, which
+    // cutechess-cli doesn't tag either.
+    pub fn pgn_tag(&self) -> Option<&'static str> {
+        match self {
+            Termination::TimeForfeit => Some("time forfeit"),
+            Termination::MoveLimit => Some("adjudication"),
+            _ => None,
+        }
+    }
+}
+
+// GameRecord is a played game, clocks contain remaining time of the player after every move.
+#[derive(Clone)]
+pub struct GameRecord {
+    pub event: String,
+    pub round: usize,
+    pub white: String,
+    pub black: String,
+    pub start: Board,
+    pub moves: Vec<Transition>,
+    pub clocks: Vec<Duration>,
+    pub result: GameResult,
+    pub termination: Termination,
+    pub time_control: Option<TimeControl>,
+    pub started: SystemTime,
+    pub duration: Duration,
+}
+
+impl GameRecord {
+    // description returns reason of the result as written by cutechess-cli, e.g. 'White mates'.
+    pub fn description(&self) -> String {
+        let loser = match self.result {
+            GameResult::WhiteWins => "Black",
+            _ => "White",
+        };
+        let winner = if loser == "White" { "Black" } else { "White" };
+        match (self.termination, self.result) {
+            (Termination::Checkmate, _) => format!("{} mates", winner),
+            (Termination::TimeForfeit, GameResult::Draw) => {
+                "Draw by timeout vs insufficient mating material".to_string()
+            }
+            (Termination::TimeForfeit, _) => format!("{} loses on time", loser),
+            (Termination::Stalemate, _) => "Draw by stalemate".to_string(),
+            (Termination::Repetition, _) => "Draw by 3-fold repetition".to_string(),
+            (Termination::InsufficientMaterial, _) => {
+                "Draw by insufficient mating material".to_string()
+            }
+            (Termination::FiftyMoves, _) => "Draw by fifty moves rule".to_string(),
+            (Termination::MoveLimit, _) => "Draw by adjudication".to_string(),
+        }
+    }
+
+    // to_pgn returns the game with tags in order used by cutechess-cli and %clk comments after
+    // every move when played with clocks.
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut tags = vec![
+            ("Event".to_string(), self.event.clone()),
+            ("Site".to_string(), "?".to_string()),
+            ("Date".to_string(), pgn_date(self.started)),
+            ("Round".to_string(), self.round.to_string()),
+            ("White".to_string(), self.white.clone()),
+            ("Black".to_string(), self.black.clone()),
+            ("Result".to_string(), self.result.to_string()),
+        ];
+        let custom_start = self.start.to_fen() != Board::default().to_fen();
+        if custom_start {
+            tags.push(("FEN".to_string(), self.start.to_fen()));
+        }
+        let seconds = self.duration.as_secs();
+        tags.push((
+            "GameDuration".to_string(),
+            format!(
+                "{:02}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ),
+        ));
+        tags.push(("PlyCount".to_string(), self.moves.len().to_string()));
+        if custom_start {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+        }
+        if let Some(termination) = self.termination.pgn_tag() {
+            tags.push(("Termination".to_string(), termination.to_string()));
+        }
+        if let Some(tc) = self.time_control {
+            tags.push(("TimeControl".to_string(), tc.to_string()));
+        }
+        let game = PgnGame {
+            tags,
+            movetext: format!("{} {}", line_to_san(&self.start, &self.moves), self.result),
+        };
+        let annotations: Vec<MoveAnnotation> = self
+            .clocks
+            .iter()
+            .map(|c| MoveAnnotation {
+                clock: Some(*c),
+                ..Default::default()
+            })
+            .collect();
+        match game.export(&PgnExportOptions::default(), &annotations) {
+            Ok(pgn) => split_games(&pgn).remove(0),
+            Err(_) => game,
+        }
+    }
+}
+
+// pgn_date returns date in PGN format, e.g. 2024.03.17.
+fn pgn_date(time: SystemTime) -> String {
+    let days = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() / 86_400) as i64,
+        Err(_) => return "????.??.??".to_string(),
+    };
+    // civil date from days since 1970-01-01, proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+// play_game plays a game from the start position until it's decided or adjudicated.
+pub fn play_game(
+    white: &mut Player,
+    black: &mut Player,
+    start: &Board,
+    settings: &MatchSettings,
+) -> GameRecord {
+    let mut game = Game::new(start.clone());
+    let mut clocks = settings.time_control.map(|tc| [Clock::new(&tc); 2]);
+    let started = SystemTime::now();
+    let timer = Instant::now();
+    let mut record = GameRecord {
+        event: settings.event.clone(),
+        round: 1,
+        white: white.name.clone(),
+        black: black.name.clone(),
+        start: start.clone(),
+        moves: Vec::new(),
+        clocks: Vec::new(),
+        result: GameResult::Draw,
+        termination: Termination::MoveLimit,
+        time_control: settings.time_control,
+        started,
+        duration: Duration::ZERO,
+    };
+
+    let (result, termination) = loop {
+        // repetitions are found by Zobrist hashes, so castling and en passant rights count too.
+        match game.game_state() {
+            GameState::Ongoing => {}
+            GameState::Checkmate(winner) => {
+                break (GameResult::win(winner), Termination::Checkmate)
+            }
+            GameState::Stalemate => break (GameResult::Draw, Termination::Stalemate),
+            GameState::Draw(DrawReason::InsufficientMaterial) => {
+                break (GameResult::Draw, Termination::InsufficientMaterial)
+            }
+            GameState::Draw(DrawReason::FiftyMoves) => {
+                break (GameResult::Draw, Termination::FiftyMoves)
+            }
+            GameState::Draw(DrawReason::Repetition) => {
+                break (GameResult::Draw, Termination::Repetition)
+            }
+        }
+        let board = game.board();
+        let color = board.color_to_move;
+        if record.moves.len() >= settings.max_plies {
+            break (GameResult::Draw, Termination::MoveLimit);
+        }
+
+        let side = if color == Color::WHITE { 0 } else { 1 };
+        let limits = match &clocks {
+            Some(clocks) => SearchLimits {
+                time: Some(clocks[side].budget()),
+                quiet_plies: board.halfmove_clock(),
+                ..SearchLimits::depth(MAX_DEPTH)
+            },
+            None => SearchLimits {
+                quiet_plies: board.halfmove_clock(),
+                ..SearchLimits::depth(settings.depth)
+            },
+        };
+        let started = Instant::now();
+        let result = if side == 0 {
+            white.backend.search(board, &limits, &mut |_, _| {})
+        } else {
+            black.backend.search(board, &limits, &mut |_, _| {})
+        };
+        let used = started.elapsed() + settings.lag;
+
+        if let Some(clocks) = clocks.as_mut() {
+            if !clocks[side].punch(used) {
+                break if has_mating_material(board, color.opposite()) {
+                    (GameResult::win(color.opposite()), Termination::TimeForfeit)
+                } else {
+                    (GameResult::Draw, Termination::TimeForfeit)
+                };
+            }
+            record.clocks.push(clocks[side].remaining);
+        }
+        // backend without a line, e.g. mate solver without a mate, plays the first legal move.
+        let t = match result.lines.first() {
+            Some(line) => line.pv[0],
+            None => board.generate_transitions()[0],
+        };
+        game.make_move(t).expect("backends play legal moves");
+        record.moves.push(t);
+    };
+    record.result = result;
+    record.termination = termination;
+    record.duration = timer.elapsed();
+    record
+}
+
+// MatchScore counts results from the point of view of the first player.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchScore {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    // points returns score of the first player, a win is 1 point and a draw is half.
+    pub fn points(&self) -> f32 {
+        self.wins as f32 + self.draws as f32 / 2.0
+    }
+
+    // ratio returns points per game, 0.5 when no games were played.
+    pub fn ratio(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    pub fn draw_ratio(&self) -> f64 {
+        self.draws as f64 / self.games().max(1) as f64
+    }
+
+    // elo returns Elo difference of the first player and its 95% confidence margin, computed as
+    // by cutechess-cli. Both are infinite or NaN when one of players scored all points.
+    pub fn elo(&self) -> (f64, f64) {
+        let estimate = elo::difference(self.wins, self.draws, self.losses);
+        (estimate.elo, estimate.margin())
+    }
+
+    // los returns likelihood of superiority of the first player, from 0 to 1.
+    pub fn los(&self) -> f64 {
+        let (wins, losses) = (self.wins as f64, self.losses as f64);
+        if wins + losses == 0.0 {
+            return 0.5;
+        }
+        0.5 * (1.0 + erf((wins - losses) / (2.0 * (wins + losses)).sqrt()))
+    }
+
+    // cutechess_summary returns score lines written by cutechess-cli after a match, e.g.
+    //   Score of a vs b: 3 - 1 - 2  [0.667] 6
+    //   Elo difference: 120.4 +/- 263.1, LOS: 84.1 %, DrawRatio: 33.3 %
+    pub fn cutechess_summary(&self, first: &str, second: &str) -> String {
+        let (elo, margin) = self.elo();
+        format!(
+            "Score of {} vs {}: {} - {} - {}  [{:.3}] {}\nElo difference: {:.1} +/- {:.1}, LOS: {:.1} %, DrawRatio: {:.1} %",
+            first,
+            second,
+            self.wins,
+            self.losses,
+            self.draws,
+            self.ratio(),
+            self.games(),
+            elo,
+            margin,
+            self.los() * 100.0,
+            self.draw_ratio() * 100.0
+        )
+    }
+
+    // flip returns the score from the point of view of the second player.
+    pub fn flip(&self) -> Self {
+        MatchScore {
+            wins: self.losses,
+            losses: self.wins,
+            draws: self.draws,
+        }
+    }
+}
+
+// erf is an approximation of the error function with maximal error 1.5e-7, formula 7.1.26 from
+// Abramowitz and Stegun.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1.0 - polynomial * (-x * x).exp();
+    if x >= 0.0 {
+        y
+    } else {
+        -y
+    }
+}
+
+// standings returns ranking table of players as written by cutechess-cli at the end of a
+// tournament, players are sorted by Elo.
+pub fn standings(players: &[(String, MatchScore)]) -> String {
+    let mut rows: Vec<&(String, MatchScore)> = players.iter().collect();
+    rows.sort_by(|a, b| b.1.ratio().total_cmp(&a.1.ratio()));
+    let mut table = format!(
+        "{:>4} {:<25} {:>7} {:>7} {:>7} {:>7} {:>7}",
+        "Rank", "Name", "Elo", "+/-", "Games", "Score", "Draw"
+    );
+    for (rank, (name, score)) in rows.into_iter().enumerate() {
+        let (elo, margin) = score.elo();
+        table.push_str(&format!(
+            "\n{:>4} {:<25} {:>7.0} {:>7.0} {:>7} {:>6.1}% {:>6.1}%",
+            rank + 1,
+            name,
+            elo,
+            margin,
+            score.games(),
+            score.ratio() * 100.0,
+            score.draw_ratio() * 100.0
+        ));
+    }
+    table
+}
+
+// read_openings reads an opening suite: PGN games, whose positions after the last move are the
+// openings, or EPD with a position per line. The suite is PGN when its first line is a tag, a
+// comment or starts with a move number, so tagless games like '1. e4 c5 *' work too. Only the
+// first four EPD fields are used, operations like 'bm' or 'id' are ignored. Empty lines and lines
+// starting with '#' are skipped.
+pub fn read_openings(content: &str) -> Result<Vec<Board>, String> {
+    let first = content
+        .lines()
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .unwrap_or_default();
+    let digits = first.trim_start_matches(|c: char| c.is_ascii_digit());
+    let pgn = first.starts_with('[')
+        || first.starts_with('{')
+        || (digits.len() < first.len() && digits.starts_with('.'));
+    let openings = if pgn {
+        split_games(content)
+            .iter()
+            .enumerate()
+            .map(|(i, game)| {
+                game.replay(|_| {})
+                    .map_err(|e| e.with_game(i + 1).to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        content
+            .lines()
+            .map(|l| l.trim())
+            .enumerate()
+            .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+            .map(|(n, l)| {
+                let fen = l.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+                let mut board = Board::default();
+                board
+                    .read_fen(&fen)
+                    .map_err(|e| format!("line {}: {}", n + 1, e))?;
+                Ok(board)
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
+    if openings.is_empty() {
+        return Err("no openings found".to_string());
+    }
+    Ok(openings)
+}
+
+// play_match plays games between two players from the start position, colors alternate and the
+// first player has white in the first game. on_game is called after every game.
+pub fn play_match<F>(
+    first: &mut Player,
+    second: &mut Player,
+    games: usize,
+    start: &Board,
+    settings: &MatchSettings,
+    on_game: F,
+) -> MatchScore
+where
+    F: FnMut(usize, &GameRecord),
+{
+    play_openings(
+        first,
+        second,
+        games,
+        std::slice::from_ref(start),
+        settings,
+        on_game,
+    )
+}
+
+// play_openings plays games between two players in pairs, both games of a pair start from the same
+// opening with colors reversed, so neither player profits from a lopsided opening. Openings are
+// taken in order and repeated from the first one when there are more pairs than openings.
+pub fn play_openings<F>(
+    first: &mut Player,
+    second: &mut Player,
+    games: usize,
+    openings: &[Board],
+    settings: &MatchSettings,
+    mut on_game: F,
+) -> MatchScore
+where
+    F: FnMut(usize, &GameRecord),
+{
+    let mut score = MatchScore::default();
+    for i in 0..games {
+        let start = &openings[i / 2 % openings.len()];
+        let first_white = i % 2 == 0;
+        let mut record = if first_white {
+            play_game(first, second, start, settings)
+        } else {
+            play_game(second, first, start, settings)
+        };
+        record.round = i + 1;
+        match (record.result, first_white) {
+            (GameResult::Draw, _) => score.draws += 1,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => score.wins += 1,
+            _ => score.losses += 1,
+        }
+        on_game(i, &record);
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::{
+        pgn_date, play_game, play_match, play_openings, read_openings, standings, Clock,
+        GameResult, MatchScore, MatchSettings, Player, Termination, TimeControl,
+    };
+    use crate::backend::{SearchBackend, SearchLimits, SearchResult};
+    use crate::board::{has_mating_material, Board};
+    use crate::evaluation::SimpleEvaluator;
+    use crate::mate::MateSolver;
+    use crate::notation::parse_uci;
+    use crate::piece::Color;
+    use crate::search::{Score, Search, SearchLine, StopSignal};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // Shuffle plays its moves over and over, in UCI notation.
+    struct Shuffle(&'static [&'static str], usize);
+
+    impl SearchBackend for Shuffle {
+        fn name(&self) -> &'static str {
+            "shuffle"
+        }
+
+        fn set_stop(&mut self, _stop: StopSignal, _poll_interval: u64) {}
+
+        fn search(
+            &mut self,
+            board: &Board,
+            _limits: &SearchLimits,
+            _on_info: &mut dyn FnMut(usize, &[SearchLine]),
+        ) -> SearchResult {
+            let t = parse_uci(board, self.0[self.1 % self.0.len()], false).unwrap();
+            self.1 += 1;
+            SearchResult {
+                lines: vec![SearchLine {
+                    score: Score::Centipawns(0),
+                    pv: vec![t],
+                }],
+                nodes: 1,
+                depth: 1,
+                policy: Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_time_control() {
+        let tc: TimeControl = "60+0.5".parse().unwrap();
+        assert_eq!(tc.base, Duration::from_secs(60));
+        assert_eq!(tc.increment, Duration::from_millis(500));
+        assert_eq!(tc.to_string(), "60+0.5");
+        assert_eq!("300".parse::<TimeControl>().unwrap().to_string(), "300");
+        assert!("0+1".parse::<TimeControl>().is_err());
+        assert!("a+1".parse::<TimeControl>().is_err());
+
+        let mut clock = Clock::new(&tc);
+        assert_eq!(clock.budget(), Duration::from_millis(2375));
+        assert!(clock.punch(Duration::from_secs(10)));
+        assert_eq!(clock.remaining, Duration::from_millis(50_500));
+        assert!(!clock.punch(Duration::from_secs(51)));
+        assert_eq!(clock.remaining, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_play_game() {
+        let e = SimpleEvaluator {};
+        let mut white = Player {
+            name: "search".to_string(),
+            backend: Box::new(Search::new(&e)),
+        };
+        let mut black = Player {
+            name: "mate".to_string(),
+            backend: Box::new(MateSolver::new()),
+        };
+        let mut start = Board::default();
+        start.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w").unwrap();
+        let record = play_game(&mut white, &mut black, &start, &MatchSettings::default());
+        assert_eq!(record.result, GameResult::WhiteWins);
+        assert_eq!(record.termination, Termination::Checkmate);
+        assert_eq!(record.moves.len(), 1);
+        let pgn = record.to_pgn();
+        assert_eq!(pgn.tag("FEN"), Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"));
+        assert_eq!(pgn.movetext, "1. Ra8# 1-0");
+        let tags: Vec<&str> = pgn.tags.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            tags,
+            vec![
+                "Event",
+                "Site",
+                "Date",
+                "Round",
+                "White",
+                "Black",
+                "Result",
+                "FEN",
+                "GameDuration",
+                "PlyCount",
+                "SetUp"
+            ]
+        );
+        assert_eq!(record.description(), "White mates");
+
+        // lag longer than the whole clock makes white lose on time, black can still mate.
+        let settings = MatchSettings {
+            time_control: Some("1+0".parse().unwrap()),
+            lag: Duration::from_secs(2),
+            ..MatchSettings::default()
+        };
+        start.read_fen("6k1/5ppp/8/8/8/8/8/r5K1 w").unwrap();
+        let record = play_game(&mut white, &mut black, &start, &settings);
+        assert_eq!(record.result, GameResult::BlackWins);
+        assert_eq!(record.termination, Termination::TimeForfeit);
+        assert_eq!(record.to_pgn().tag("TimeControl"), Some("1"));
+        assert_eq!(record.to_pgn().tag("Termination"), Some("time forfeit"));
+        assert_eq!(record.description(), "White loses on time");
+
+        // the first quiet move reaches fifty-move rule.
+        let mut fifty = Board::default();
+        fifty.read_fen("8/8/8/4k3/8/8/8/KR6 w - - 99 80").unwrap();
+        let record = play_game(&mut white, &mut black, &fifty, &MatchSettings::default());
+        assert_eq!(record.result, GameResult::Draw);
+        assert_eq!(record.termination, Termination::FiftyMoves);
+        assert_eq!(record.moves.len(), 1);
+        assert_eq!(record.description(), "Draw by fifty moves rule");
+        assert!(!has_mating_material(&start, Color::WHITE));
+        assert!(has_mating_material(&start, Color::BLACK));
+    }
+
+    #[test]
+    fn test_repetition_with_castling_rights() {
+        let mut white = Player {
+            name: "white".to_string(),
+            backend: Box::new(Shuffle(&["h1h2", "h2h1"], 0)),
+        };
+        let mut black = Player {
+            name: "black".to_string(),
+            backend: Box::new(Shuffle(&["e8d8", "d8e8"], 0)),
+        };
+        let mut start = Board::default();
+        start.read_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        // the start position can't come back once white can't castle, the third repetition is of
+        // the position after the first rook move. Counting positions without rights ends the game
+        // a ply earlier.
+        let record = play_game(&mut white, &mut black, &start, &MatchSettings::default());
+        assert_eq!(record.termination, Termination::Repetition);
+        assert_eq!(record.result, GameResult::Draw);
+        assert_eq!(record.moves.len(), 9);
+    }
+
+    #[test]
+    fn test_play_match_with_clock() {
+        let e = SimpleEvaluator {};
+        let mut first = Player {
+            name: "a".to_string(),
+            backend: Box::new(Search::new(&e)),
+        };
+        let mut second = Player {
+            name: "b".to_string(),
+            backend: Box::new(Search::new(&e)),
+        };
+        let settings = MatchSettings {
+            time_control: Some("0.5+0.01".parse().unwrap()),
+            max_plies: 10,
+            ..MatchSettings::default()
+        };
+        let mut records = Vec::new();
+        let score = play_match(
+            &mut first,
+            &mut second,
+            2,
+            &Board::default(),
+            &settings,
+            |_, r| records.push(r.clone()),
+        );
+        assert_eq!(score.games(), 2);
+        assert_eq!(records[0].white, "a");
+        assert_eq!(records[1].white, "b");
+        assert_eq!(records[1].to_pgn().tag("Round"), Some("2"));
+        for record in &records {
+            assert_eq!(record.clocks.len(), record.moves.len());
+            assert!(record
+                .clocks
+                .iter()
+                .all(|c| *c < Duration::from_millis(600)));
+            assert!(record.to_pgn().movetext.contains("[%clk 0:00:00]"));
+        }
+    }
+
+    #[test]
+    fn test_match_statistics() {
+        let score = MatchScore {
+            wins: 45,
+            losses: 30,
+            draws: 25,
+        };
+        let (elo, margin) = score.elo();
+        assert!((elo - 52.5).abs() < 0.1);
+        assert!((margin - 60.0).abs() < 0.1);
+        assert!((score.los() - 0.958).abs() < 0.001);
+        assert_eq!(
+            score.cutechess_summary("a", "b"),
+            "Score of a vs b: 45 - 30 - 25  [0.575] 100\n\
+             Elo difference: 52.5 +/- 60.0, LOS: 95.8 %, DrawRatio: 25.0 %"
+        );
+        assert_eq!(MatchScore::default().los(), 0.5);
+
+        let table = standings(&[("b".to_string(), score.flip()), ("a".to_string(), score)]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("   1 a"));
+        assert!(lines[2].contains("-53"));
+    }
+
+    #[test]
+    fn test_pgn_date() {
+        assert_eq!(pgn_date(UNIX_EPOCH), "1970.01.01");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(11_016 * 86_400 + 3600);
+        assert_eq!(pgn_date(leap_day), "2000.02.29");
+    }
+
+    #[test]
+    fn test_openings() {
+        let epd = "# suite\n\
+            rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 id \"e4\";\n\
+            \n\
+            rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - bm d5;\n";
+        let openings = read_openings(epd).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].color_to_move, Color::BLACK);
+        let pgn = "[Event \"a\"]\n\n1. e4 c5 *\n\n[Event \"b\"]\n\n1. d4 Nf6 2. c4 *\n";
+        let openings = read_openings(pgn).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[1].color_to_move, Color::BLACK);
+        let openings = read_openings("1. e4 c5 *\n\n1. d4 Nf6 2. c4 *\n").unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].color_to_move, Color::WHITE);
+        assert_eq!(openings[1].color_to_move, Color::BLACK);
+        assert_eq!(
+            read_openings("8/8/8 w\n").err(),
+            Some("line 1: fen must describe 8 ranks".to_string())
+        );
+        assert!(read_openings("[Event \"a\"]\n\n1. e5 *\n").is_err());
+        assert!(read_openings("# nothing\n").is_err());
+
+        let e = SimpleEvaluator {};
+        let mut first = Player {
+            name: "a".to_string(),
+            backend: Box::new(Search::new(&e)),
+        };
+        let mut second = Player {
+            name: "b".to_string(),
+            backend: Box::new(Search::new(&e)),
+        };
+        let settings = MatchSettings {
+            depth: 1,
+            max_plies: 4,
+            ..MatchSettings::default()
+        };
+        let mut records = Vec::new();
+        let score = play_openings(&mut first, &mut second, 6, &openings, &settings, |_, r| {
+            records.push(r.clone())
+        });
+        assert_eq!(score.games(), 6);
+        let starts: Vec<String> = records.iter().map(|r| r.start.position_fen()).collect();
+        assert_eq!(starts[0], starts[1]);
+        assert_ne!(starts[1], starts[2]);
+        assert_eq!(starts[2], starts[3]);
+        assert_eq!(starts[4], starts[0]);
+        assert_eq!((&*records[2].white, &*records[3].white), ("a", "b"));
+        assert!(records[2].to_pgn().tag("FEN").is_some());
+    }
+}
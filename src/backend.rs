@@ -0,0 +1,347 @@
+use crate::board::{Board, Transition};
+use crate::evaluation::Evaluator;
+use crate::mate::MateSolver;
+use crate::mcts::{Mcts, UniformPrior};
+use crate::notation::to_internal;
+use crate::search::{Score, Search, SearchLine, StopSignal};
+use std::time::{Duration, Instant};
+
+// DEFAULT_PLAYOUTS is number of Monte Carlo tree search playouts when nodes are not limited.
+pub const DEFAULT_PLAYOUTS: u64 = 2000;
+
+// SearchLimits bounds the search, every backend uses limits that make sense for it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchLimits {
+    pub depth: usize, // in plies, mate solver looks for mates up to (depth + 1) / 2 moves
+    pub nodes: Option<u64>, // Monte Carlo tree search playouts
+    pub multipv: usize, // number of best lines to return
+    pub policy: bool, // return probability distribution over root moves
+    pub time: Option<Duration>, // thinking time, the search stops early when it's used up
+    pub quiet_plies: usize, // plies without pawn moves and captures played before the position
+    pub contempt: i32, // centipawns lost with a draw, see Search::contempt
+}
+
+impl SearchLimits {
+    pub fn depth(depth: usize) -> Self {
+        SearchLimits {
+            depth,
+            nodes: None,
+            multipv: 1,
+            policy: false,
+            time: None,
+            quiet_plies: 0,
+            contempt: 0,
+        }
+    }
+
+    // deadline returns the moment when search started now has to stop.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.time.map(|t| Instant::now() + t)
+    }
+}
+
+// SearchResult is the outcome of a search, lines are sorted from the best one.
+#[derive(Clone)]
+pub struct SearchResult {
+    pub lines: Vec<SearchLine>,
+    pub nodes: u64,
+    pub depth: usize, // depth reached, for Monte Carlo tree search length of the best line
+    pub policy: Vec<(Transition, f32)>, // empty unless requested with limits
+}
+
+// SearchBackend is a search algorithm that can be selected at runtime. on_info is called with
+// depth and best lines whenever backend has new results, e.g. after every iteration. Time limit
+// is respected by alpha-beta and Monte Carlo tree search, mate solver is bounded only by depth.
+// Backends keep state of the search, so every thread needs its own one, but they can be moved
+// between threads.
+pub trait SearchBackend: Send {
+    fn name(&self) -> &'static str;
+
+    // set_stop makes all following searches return soon after 'stop' is set, it's checked every
+    // 'poll_interval' nodes. Stopped backend returns the best lines it has, which may be none.
+    fn set_stop(&mut self, stop: StopSignal, poll_interval: u64);
+
+    // set_history gives hashes of positions played in the game before the searched one, see
+    // zobrist::hash, for all following searches. Backends that don't detect repetitions ignore it.
+    fn set_history(&mut self, _history: &[u64]) {}
+
+    fn search(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(usize, &[SearchLine]),
+    ) -> SearchResult;
+}
+
+pub const BACKEND_NAMES: [&str; 3] = ["alphabeta", "mcts", "mate"];
+
+// backend_by_name returns search backend for name used in command line.
+pub fn backend_by_name<'a>(
+    name: &str,
+    evaluator: &'a dyn Evaluator,
+) -> Option<Box<dyn SearchBackend + 'a>> {
+    match name {
+        "alphabeta" => Some(Box::new(Search::new(evaluator))),
+        "mcts" => Some(Box::new(Mcts::new(evaluator, &UniformPrior))),
+        "mate" => Some(Box::new(MateSolver::new())),
+        _ => None,
+    }
+}
+
+impl SearchBackend for Search<'_> {
+    fn name(&self) -> &'static str {
+        "alphabeta"
+    }
+
+    fn set_stop(&mut self, stop: StopSignal, poll_interval: u64) {
+        self.stop = Some(stop);
+        self.poll_interval = poll_interval;
+    }
+
+    fn set_history(&mut self, history: &[u64]) {
+        self.history = history.to_vec();
+    }
+
+    fn search(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(usize, &[SearchLine]),
+    ) -> SearchResult {
+        let nodes = self.nodes;
+        self.deadline = limits.deadline();
+        self.quiet_plies = limits.quiet_plies;
+        self.contempt = limits.contempt;
+        // policy needs scores of all root moves.
+        let multipv = if limits.policy {
+            board.generate_transitions().len()
+        } else {
+            limits.multipv
+        };
+        let mut reached = 0;
+        let mut lines = self.iterate(board, limits.depth, multipv, |d, lines| {
+            reached = d;
+            on_info(d, &lines[..limits.multipv.max(1).min(lines.len())])
+        });
+        let policy = if limits.policy {
+            softmax_policy(&lines, DEFAULT_TEMPERATURE)
+        } else {
+            Vec::new()
+        };
+        lines.truncate(limits.multipv.max(1));
+        SearchResult {
+            lines,
+            nodes: self.nodes - nodes,
+            depth: reached,
+            policy,
+        }
+    }
+}
+
+impl SearchBackend for Mcts<'_> {
+    fn name(&self) -> &'static str {
+        "mcts"
+    }
+
+    // every playout is checked, poll interval is too coarse for them.
+    fn set_stop(&mut self, stop: StopSignal, _poll_interval: u64) {
+        self.stop = Some(stop);
+    }
+
+    fn search(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(usize, &[SearchLine]),
+    ) -> SearchResult {
+        let nodes = self.nodes;
+        self.deadline = limits.deadline();
+        // with time limit playouts are run until the time is up, unless nodes are limited too.
+        let playouts = match (limits.nodes, limits.time) {
+            (Some(nodes), _) => nodes as usize,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => DEFAULT_PLAYOUTS as usize,
+        };
+        let lines = Mcts::search(self, board, playouts, limits.multipv);
+        let depth = lines.first().map_or(0, |l| l.pv.len());
+        if !lines.is_empty() {
+            on_info(depth, &lines);
+        }
+        let policy = if limits.policy {
+            visit_policy(&self.root_visits())
+        } else {
+            Vec::new()
+        };
+        SearchResult {
+            lines,
+            nodes: self.nodes - nodes,
+            depth,
+            policy,
+        }
+    }
+}
+
+impl SearchBackend for MateSolver {
+    fn name(&self) -> &'static str {
+        "mate"
+    }
+
+    fn set_stop(&mut self, stop: StopSignal, poll_interval: u64) {
+        self.stop = Some(stop);
+        self.poll_interval = poll_interval;
+    }
+
+    // search returns the shortest mate as the only line, no lines when there's no mate. Policy
+    // puts all probability on the mating move.
+    fn search(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(usize, &[SearchLine]),
+    ) -> SearchResult {
+        let nodes = self.nodes;
+        let max_moves = limits.depth.div_ceil(2).max(1);
+        let lines: Vec<SearchLine> = self.solve(board, max_moves).into_iter().collect();
+        if let Some(line) = lines.first() {
+            on_info(line.pv.len(), &lines);
+        }
+        let policy = match lines.first() {
+            Some(line) if limits.policy => vec![(line.pv[0], 1.0)],
+            _ => Vec::new(),
+        };
+        SearchResult {
+            depth: lines.first().map_or(max_moves * 2 - 1, |l| l.pv.len()),
+            lines,
+            nodes: self.nodes - nodes,
+            policy,
+        }
+    }
+}
+
+// DEFAULT_TEMPERATURE is a temperature of softmax over scores in centipawns, with 100 a move
+// better by a pawn is e times more probable.
+pub const DEFAULT_TEMPERATURE: f32 = 100.0;
+
+// softmax_policy converts scores of root moves into probabilities with softmax, mates are
+// treated as very high scores. Lines are expected to start with different root moves.
+pub fn softmax_policy(lines: &[SearchLine], temperature: f32) -> Vec<(Transition, f32)> {
+    let values: Vec<f32> = lines
+        .iter()
+        .map(|l| match l.score {
+            Score::Centipawns(cp) => cp as f32,
+            Score::Mate(moves) if moves > 0 => 10_000.0 - moves as f32,
+            Score::Mate(moves) => -10_000.0 - moves as f32,
+        })
+        .collect();
+    let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let weights: Vec<f32> = values
+        .iter()
+        .map(|v| ((v - max) / temperature).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    lines
+        .iter()
+        .zip(weights)
+        .map(|(l, w)| (l.pv[0], w / sum))
+        .collect()
+}
+
+// visit_policy converts visit counts of root moves into probabilities.
+pub fn visit_policy(visits: &[(Transition, u32)]) -> Vec<(Transition, f32)> {
+    let sum: u32 = visits.iter().map(|(_, v)| v).sum();
+    visits
+        .iter()
+        .map(|(t, v)| (*t, *v as f32 / sum.max(1) as f32))
+        .collect()
+}
+
+// format_policy writes policy as space separated moves with probabilities, e.g. 'e2e4:0.612'.
+pub fn format_policy(policy: &[(Transition, f32)]) -> String {
+    policy
+        .iter()
+        .map(|(t, p)| format!("{}:{:.3}", to_internal(t), p))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        backend_by_name, format_policy, softmax_policy, SearchLimits, BACKEND_NAMES,
+    };
+    use crate::board::{Board, Transition};
+    use crate::evaluation::SimpleEvaluator;
+    use crate::notation::{to_internal, to_san};
+    use crate::search::{Score, SearchLine};
+
+    #[test]
+    fn test_backends_find_mate() {
+        let mut b = Board::default();
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w").unwrap();
+        let e = SimpleEvaluator {};
+        let limits = SearchLimits {
+            depth: 2,
+            nodes: Some(200),
+            multipv: 1,
+            policy: true,
+            time: None,
+            quiet_plies: 0,
+            contempt: 0,
+        };
+        for name in BACKEND_NAMES {
+            let mut backend = backend_by_name(name, &e).unwrap();
+            assert_eq!(backend.name(), name);
+            let mut infos = 0;
+            let result = backend.search(&b, &limits, &mut |_, _| infos += 1);
+            assert_eq!(to_san(&b, &result.lines[0].pv[0]), "Ra8#", "{}", name);
+            assert_eq!(result.lines[0].score, Score::Mate(1), "{}", name);
+            assert!(result.nodes > 0 && infos > 0, "{}", name);
+            assert_eq!(result.lines.len(), 1);
+
+            let sum: f32 = result.policy.iter().map(|(_, p)| p).sum();
+            assert!((sum - 1.0).abs() < 1e-4, "{}", name);
+            let best = result
+                .policy
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            assert_eq!(to_san(&b, &best.0), "Ra8#", "{}", name);
+        }
+        assert!(backend_by_name("minimax", &e).is_none());
+
+        b.read_fen("4k3/8/8/8/8/8/8/4K2R w").unwrap();
+        let mut mate = backend_by_name("mate", &e).unwrap();
+        assert!(mate
+            .search(&b, &SearchLimits::depth(3), &mut |_, _| {})
+            .lines
+            .is_empty());
+    }
+
+    #[test]
+    fn test_softmax_policy() {
+        let b = Board::default();
+        let moves = b.generate_transitions();
+        let line = |t: Transition, score| SearchLine { score, pv: vec![t] };
+        let lines = vec![
+            line(moves[0], Score::Centipawns(100)),
+            line(moves[1], Score::Centipawns(0)),
+            line(moves[2], Score::Centipawns(0)),
+        ];
+        let policy = softmax_policy(&lines, 100.0);
+        assert!((policy[0].1 / policy[1].1 - std::f32::consts::E).abs() < 1e-4);
+        assert_eq!(policy[1].1, policy[2].1);
+        assert_eq!(
+            format_policy(&policy[..1]),
+            format!("{}:0.576", to_internal(&moves[0]))
+        );
+
+        let mate = softmax_policy(
+            &[
+                line(moves[0], Score::Mate(2)),
+                line(moves[1], Score::Centipawns(900)),
+            ],
+            100.0,
+        );
+        assert!(mate[0].1 > 0.999);
+    }
+}
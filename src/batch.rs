@@ -0,0 +1,246 @@
+use crate::board::Board;
+use crate::evaluation::{EvalTerm, Evaluator};
+use crate::mate::MateSolver;
+use crate::notation::line_to_san;
+use crate::progress::Progress;
+use rayon::prelude::*;
+use std::io::{self, Write};
+
+// BatchRow is an evaluation of one position read from batch file.
+pub struct BatchRow {
+    pub fen: String,
+    pub terms: Result<Vec<EvalTerm>, &'static str>,
+}
+
+// read_fens returns FENs from file content, one per line. Empty lines and lines starting with '#'
+// are skipped.
+pub fn read_fens(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+// eval_batch evaluates every position in parallel, result order is the same as input order.
+pub fn eval_batch(
+    fens: &[String],
+    evaluator: &dyn Evaluator,
+    progress: &dyn Progress,
+) -> Vec<BatchRow> {
+    progress.start("positions", fens.len() as u64);
+    let rows = fens
+        .par_iter()
+        .map(|fen| {
+            let mut board = Board::default();
+            let terms = board.read_fen(fen).map(|_| evaluator.breakdown(&board));
+            progress.advance(1);
+            BatchRow {
+                fen: fen.clone(),
+                terms,
+            }
+        })
+        .collect();
+    progress.finish();
+    rows
+}
+
+// write_csv writes rows as CSV with fen, one column per evaluation term, total and error columns.
+pub fn write_csv<W: Write>(w: &mut W, rows: &[BatchRow]) -> io::Result<()> {
+    let names: Vec<&str> = rows
+        .iter()
+        .find_map(|r| r.terms.as_ref().ok())
+        .map(|terms| terms.iter().map(|t| t.name).collect())
+        .unwrap_or_default();
+    writeln!(w, "fen,{},total,error", names.join(","))?;
+
+    for row in rows {
+        match &row.terms {
+            Ok(terms) => {
+                let values: Vec<String> = terms.iter().map(|t| format!("{:.2}", t.value)).collect();
+                let total: f32 = terms.iter().map(|t| t.value).sum();
+                writeln!(w, "{},{},{:.2},", row.fen, values.join(","), total)?;
+            }
+            Err(e) => {
+                writeln!(w, "{},{},{}", row.fen, ",".repeat(names.len()), e)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// SolveStatus tells how the mate solver did on a puzzle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SolveStatus {
+    Solved,
+    Wrong,  // mate found, but not in the number of moves of EPD 'dm' operation
+    Failed, // no mate found up to the maximum number of moves
+    Invalid,
+}
+
+impl SolveStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SolveStatus::Solved => "solved",
+            SolveStatus::Wrong => "wrong",
+            SolveStatus::Failed => "failed",
+            SolveStatus::Invalid => "invalid",
+        }
+    }
+}
+
+// SolveRow is a result of the mate solver for one puzzle read from batch file.
+pub struct SolveRow {
+    pub epd: String,
+    pub expected: Option<usize>, // moves of EPD 'dm' operation
+    pub solution: Result<Option<(usize, String)>, &'static str>, // moves to mate and SAN line
+}
+
+impl SolveRow {
+    pub fn status(&self) -> SolveStatus {
+        match (&self.solution, self.expected) {
+            (Err(_), _) => SolveStatus::Invalid,
+            (Ok(None), _) => SolveStatus::Failed,
+            (Ok(Some((moves, _))), Some(expected)) if *moves != expected => SolveStatus::Wrong,
+            _ => SolveStatus::Solved,
+        }
+    }
+}
+
+// read_epd splits EPD line into FEN of the first four fields and the number of moves of 'dm'
+// operation. Full FENs are accepted too, their move counters are skipped.
+fn read_epd(line: &str) -> (String, Option<usize>) {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let fen = fields.iter().take(4).copied().collect::<Vec<_>>().join(" ");
+    let expected = fields[fields.len().min(4)..]
+        .join(" ")
+        .split(';')
+        .filter_map(|op| op.trim().strip_prefix("dm "))
+        .find_map(|n| n.trim().parse().ok());
+    (fen, expected)
+}
+
+// solve_batch runs the mate solver on every puzzle in parallel, looking for mates up to max_moves
+// moves. Result order is the same as input order.
+pub fn solve_batch(epds: &[String], max_moves: usize, progress: &dyn Progress) -> Vec<SolveRow> {
+    progress.start("puzzles", epds.len() as u64);
+    let rows = epds
+        .par_iter()
+        .map(|epd| {
+            let (fen, expected) = read_epd(epd);
+            let mut board = Board::default();
+            let solution = board.read_fen(&fen).map(|_| {
+                MateSolver::new()
+                    .solve(&board, max_moves)
+                    .map(|line| (line.pv.len().div_ceil(2), line_to_san(&board, &line.pv)))
+            });
+            progress.advance(1);
+            SolveRow {
+                epd: epd.clone(),
+                expected,
+                solution,
+            }
+        })
+        .collect();
+    progress.finish();
+    rows
+}
+
+// write_solutions writes rows as CSV with epd, expected and found mate, status and solution
+// columns. EPD is quoted, its operations may have commas.
+pub fn write_solutions<W: Write>(w: &mut W, rows: &[SolveRow]) -> io::Result<()> {
+    writeln!(w, "epd,expected,mate,status,solution")?;
+    for row in rows {
+        let expected = row.expected.map(|n| n.to_string()).unwrap_or_default();
+        let (mate, solution) = match &row.solution {
+            Ok(Some((moves, line))) => (moves.to_string(), line.to_string()),
+            Ok(None) => (String::new(), String::new()),
+            Err(e) => (String::new(), e.to_string()),
+        };
+        writeln!(
+            w,
+            "\"{}\",{},{},{},{}",
+            row.epd.replace('"', "\"\""),
+            expected,
+            mate,
+            row.status().name(),
+            solution
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::{
+        eval_batch, read_fens, solve_batch, write_csv, write_solutions, SolveStatus,
+    };
+    use crate::evaluation::MaterialMobilityEvaluator;
+    use crate::progress::NoProgress;
+
+    #[test]
+    fn test_eval_batch_csv() {
+        let fens = read_fens(
+            "# comment
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w
+
+4k3/8/8/8/8/8/8/3QK3 b
+not a fen
+",
+        );
+        assert_eq!(fens.len(), 3);
+        let rows = eval_batch(&fens, &MaterialMobilityEvaluator {}, &NoProgress);
+        let mut out = Vec::new();
+        write_csv(&mut out, &rows).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "fen,material,doubled pawns,blocked pawns,isolated pawns,mobility,king safety,king centralization,total,error"
+        );
+        assert_eq!(
+            lines[1],
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w,0.00,0.00,0.00,0.00,0.00,0.00,0.00,0.00,"
+        );
+        assert!(lines[2].starts_with("4k3/8/8/8/8/8/8/3QK3 b,9.00,"));
+        assert_eq!(lines[3], "not a fen,,,,,,,,,fen must describe 8 ranks");
+    }
+
+    #[test]
+    fn test_solve_batch() {
+        let epds = read_fens(
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - dm 1; id \"back rank\";
+6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1
+6k1/5ppp/8/8/8/8/8/R5K1 w - - dm 2;
+4k3/8/8/8/8/8/8/4K3 w - -
+not a fen
+",
+        );
+        let rows = solve_batch(&epds, 2, &NoProgress);
+        let statuses: Vec<_> = rows.iter().map(|r| r.status()).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                SolveStatus::Solved,
+                SolveStatus::Solved,
+                SolveStatus::Wrong,
+                SolveStatus::Failed,
+                SolveStatus::Invalid
+            ]
+        );
+        assert_eq!(rows[0].expected, Some(1));
+        assert_eq!(rows[1].solution, Ok(Some((1, "1. Ra8#".to_string()))));
+
+        let mut out = Vec::new();
+        write_solutions(&mut out, &rows).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "epd,expected,mate,status,solution");
+        assert_eq!(
+            lines[1],
+            "\"6k1/5ppp/8/8/8/8/8/R5K1 w - - dm 1; id \"\"back rank\"\";\",1,1,solved,1. Ra8#"
+        );
+        assert_eq!(lines[4], "\"4k3/8/8/8/8/8/8/4K3 w - -\",,,failed,");
+    }
+}
@@ -1,13 +1,65 @@
 #![allow(warnings, unused)]
 
-use crate::evaluation::{Evaluator, SimpleEvaluator};
+use crate::notation::{
+    castle_flag, file_from_letter, file_rank, parse_lan, parse_square, square_at, square_name,
+};
+use crate::pgn::movetext_tokens;
 use crate::piece::{Color, Piece, PieceType};
+use crate::pst;
 use std::borrow::Borrow;
 use std::cmp::{max, min};
 use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Copy, Clone, PartialEq)]
+// Square is an index of a square, from 0 (a1) to 63 (h8), see notation for conversions.
+pub type Square = usize;
+
+// Move is a legal move as returned by legal_moves, the name used in public API.
+pub type Move = Transition;
+
+// GameStatus tells if the game is over according to rules of moving pieces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate(Color), // color of the winner
+    Stalemate,
+}
+
+// GameState tells if the game is over according to all rules of chess that the position knows
+// of, see Game::game_state for repetitions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameState {
+    Ongoing,
+    Checkmate(Color), // color of the winner
+    Stalemate,
+    Draw(DrawReason),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrawReason {
+    InsufficientMaterial, // neither side can ever mate
+    FiftyMoves,           // 50 moves of both sides without a capture or a pawn move
+    Repetition,           // the same position for the third time
+}
+
+impl GameState {
+    pub fn is_over(&self) -> bool {
+        *self != GameState::Ongoing
+    }
+
+    // result returns result of the game as written in PGN, '*' when it goes on.
+    pub fn result(&self) -> &'static str {
+        match self {
+            GameState::Ongoing => "*",
+            GameState::Checkmate(Color::WHITE) => "1-0",
+            GameState::Checkmate(_) => "0-1",
+            GameState::Stalemate | GameState::Draw(_) => "1/2-1/2",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TransitionFlag {
     None,
     Promotion, // used when pawn is promoted
@@ -18,15 +70,15 @@ pub enum TransitionFlag {
     Move,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 // Transition represents: from, to, promotion(if necessary).
 pub struct Transition {
-    from: usize,
-    to: usize,
-    flag: TransitionFlag,
-    promotion: PieceType,
-    from_piece: Piece,
-    to_piece: Piece,
+    pub from: usize,
+    pub to: usize,
+    pub flag: TransitionFlag,
+    pub promotion: PieceType,
+    pub from_piece: Piece,
+    pub to_piece: Piece,
 }
 
 impl Transition {
@@ -118,13 +170,88 @@ impl Transition {
     }
 }
 
+// KingsPositions keeps squares of both kings, it's a plain array so cloning a board doesn't
+// allocate.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct KingsPositions([Option<usize>; 2]);
+
+impl KingsPositions {
+    fn slot(color: &Color) -> Option<usize> {
+        match color {
+            Color::WHITE => Some(0),
+            Color::BLACK => Some(1),
+            Color::NONE => None,
+        }
+    }
+
+    fn get(&self, color: &Color) -> Option<&usize> {
+        self.0[KingsPositions::slot(color)?].as_ref()
+    }
+
+    fn insert(&mut self, color: Color, square: usize) {
+        if let Some(slot) = KingsPositions::slot(&color) {
+            self.0[slot] = Some(square);
+        }
+    }
+}
+
+// Board is a position with everything needed to generate moves. It has no heap allocated state,
+// so clones are cheap, and it's Send and Sync, so positions can be analyzed in parallel threads.
 #[derive(Clone)]
 pub struct Board {
-    pub squares: [Piece; 64], // 0 is left lower corner
+    squares: [Piece; 64], // 0 is left lower corner, see squares
     pub color_to_move: Color,
-    kings_positions: HashMap<Color, usize>,
+    kings_positions: KingsPositions,
     debug: bool,
     last_transition: Transition,
+    halfmove_clock: usize,
+    fullmove_number: usize,
+    material: i32,            // kept up to date by make_move, see material
+    piece_square: (i32, i32), // kept up to date by make_move, see piece_square
+}
+
+pub const PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::QUEEN,
+    PieceType::ROOK,
+    PieceType::BISHOP,
+    PieceType::KNIGHT,
+];
+
+// CastlingNotation selects how castling rights are written in FEN. Castles are modelled only with
+// the king on the e-file and rooks in corners, Chess960 castles from other files aren't, so
+// Standard is valid X-FEN too and Shredder files are always 'a' and 'h'.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CastlingNotation {
+    Standard, // KQkq
+    Shredder, // HAha
+}
+
+// Side is a side of the board a king castles to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    King,  // short castle, O-O
+    Queen, // long castle, O-O-O
+}
+
+// CastlingRights tells which castles are still allowed by moves made, see Board::castling_rights.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CastlingRights {
+    pub white_king: bool,
+    pub white_queen: bool,
+    pub black_king: bool,
+    pub black_queen: bool,
+}
+
+impl CastlingRights {
+    pub fn has(&self, color: Color, side: Side) -> bool {
+        match (color, side) {
+            (Color::WHITE, Side::King) => self.white_king,
+            (Color::WHITE, Side::Queen) => self.white_queen,
+            (Color::BLACK, Side::King) => self.black_king,
+            (Color::BLACK, Side::Queen) => self.black_queen,
+            (Color::NONE, _) => false,
+        }
+    }
 }
 
 const FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
@@ -134,21 +261,97 @@ impl Board {
         let mut b = Board {
             squares: [Piece::default(); 64],
             color_to_move: Color::WHITE,
-            kings_positions: HashMap::new(),
+            kings_positions: KingsPositions::default(),
             debug: false,
             last_transition: Transition::default(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            material: 0,
+            piece_square: (0, 0),
         };
-        b.read_fen(FEN);
+        b.read_fen(FEN).expect("start position is valid");
         b
     }
 
+    // from_pieces sets up a position without FEN. Rooks without castling rights are marked as
+    // moved, rights need the king and the rook on their squares and en passant square needs the
+    // pawn that has just moved two squares. Counters start from 0 and 1.
+    pub fn from_pieces<I>(
+        pieces: I,
+        color_to_move: Color,
+        rights: CastlingRights,
+        en_passant: Option<Square>,
+    ) -> Result<Board, &'static str>
+    where
+        I: IntoIterator<Item = (Square, Piece)>,
+    {
+        let mut board = Board::default();
+        board.squares = [Piece::default(); 64];
+        board.kings_positions = KingsPositions::default();
+        board.color_to_move = color_to_move;
+        for (square, piece) in pieces {
+            if square >= 64 {
+                return Err("square is out of the board");
+            }
+            board.squares[square] = piece;
+            if piece.p_type == PieceType::KING {
+                board.kings_positions.insert(piece.color, square);
+            }
+        }
+        board.material = board.squares.iter().map(signed_points).sum();
+        board.piece_square = pst::sum(&board.squares);
+        for color in [Color::WHITE, Color::BLACK] {
+            for side in [Side::King, Side::Queen] {
+                let rook = board.castle_transitions(color, side == Side::King)[1].from;
+                if !rights.has(color, side) && board.squares[rook].p_type == PieceType::ROOK {
+                    board.squares[rook].has_moved = true;
+                }
+            }
+        }
+        if board.castling_rights() != rights {
+            return Err("castling rights need the king and the rook on their squares");
+        }
+        match en_passant {
+            Some(square) if square / 8 != 2 && square / 8 != 5 => {
+                return Err("invalid en passant square")
+            }
+            Some(square) => board.read_en_passant(square)?,
+            None => {}
+        }
+        Ok(board)
+    }
+
     pub fn allow_debug(&mut self) {
         self.debug = true
     }
 
-    pub fn read_fen(&mut self, fen: &str) {
+    // read_fen reads all six FEN fields. Fields after pieces placement may be omitted: color to
+    // move doesn't change, all castles are allowed, there's no en passant square and counters
+    // start from 0 and 1. Board isn't changed when FEN is invalid.
+    pub fn read_fen(&mut self, fen: &str) -> Result<(), &'static str> {
+        Board::validate_fen(fen)?;
+        let mut board = self.clone();
+        board.read_fields(fen)?;
+        *self = board;
+        Ok(())
+    }
+
+    // read_fields reads fields of valid FEN.
+    fn read_fields(&mut self, fen: &str) -> Result<(), &'static str> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().unwrap_or("");
+        match fields.next() {
+            Some("w") => self.color_to_move = Color::WHITE,
+            Some("b") => self.color_to_move = Color::BLACK,
+            _ => {}
+        }
+        let castling = fields.next();
+        let en_passant = fields.next().and_then(parse_square);
         self.squares = [Piece::default(); 64]; // reset board
-        self.kings_positions = HashMap::new();
+        self.kings_positions = KingsPositions::default();
+        self.last_transition = Transition::default();
+        self.halfmove_clock = 0;
+        self.fullmove_number = 1;
         let piece_from_char: HashMap<char, PieceType> = [
             ('r', PieceType::ROOK),
             ('k', PieceType::KING),
@@ -164,7 +367,7 @@ impl Board {
         let mut rank: i32 = 7;
         let mut file: i32 = 0;
 
-        for (_i, c) in fen.chars().enumerate() {
+        for (_i, c) in placement.chars().enumerate() {
             match c {
                 '/' => {
                     file = 0;
@@ -196,58 +399,412 @@ impl Board {
                 }
             }
         }
+        self.material = self.squares.iter().map(signed_points).sum();
+        self.piece_square = pst::sum(&self.squares);
+        if let Some(castling) = castling {
+            self.read_castling(castling)?;
+        }
+        if let Some(square) = en_passant {
+            self.read_en_passant(square)?;
+        }
+        // validate_fen checked the counters.
+        if let Some(halfmove) = fields.next() {
+            self.halfmove_clock = halfmove.parse().unwrap_or_default();
+        }
+        if let Some(fullmove) = fields.next() {
+            self.fullmove_number = fullmove.parse().unwrap_or(1);
+        }
+        Ok(())
     }
 
-    // read_pgn is an entry point for pgn game.
-    //
-    // method reads whole game description and call make_pgn_move one by one.
-    pub fn read_pgn(&mut self, pgn: &str, vis_flag: bool) -> Result<(), &'static str> {
-        let mut game = String::from(pgn.replace("\n", " ").replace("  ", " "));
-        let mut general_counter = 1;
-        let mut color_counter = 0;
-        loop {
-            if game.len() == 0 {
-                break;
+    // read_en_passant remembers the double push that allows en passant capture on given square.
+    fn read_en_passant(&mut self, square: Square) -> Result<(), &'static str> {
+        let (from, to, color) = if square / 8 == 2 {
+            (square - 8, square + 8, Color::WHITE)
+        } else {
+            (square + 8, square - 8, Color::BLACK)
+        };
+        let pawn = self.squares[to];
+        if pawn.p_type != PieceType::PAWN
+            || pawn.color != color
+            || color == self.color_to_move
+            || !self.squares[square].is_none()
+            || !self.squares[from].is_none()
+        {
+            return Err("en passant square doesn't follow a double push");
+        }
+        self.last_transition = Transition::new(
+            from,
+            to,
+            TransitionFlag::Move,
+            PieceType::NONE,
+            pawn,
+            Piece::default(),
+        );
+        Ok(())
+    }
+
+    // read_castling marks rooks without castling rights as moved. Rights can be written in FEN
+    // (KQkq), X-FEN or Shredder-FEN (files of castling rooks, e.g. HAha) notation. Rights of a king
+    // standing on its first rank off the e-file, or of rooks on other files than 'a' and 'h', as in
+    // Chess960, are an error, such castles can't be made.
+    fn read_castling(&mut self, field: &str) -> Result<(), &'static str> {
+        const UNSUPPORTED: &str = "castling needs the king on e-file and rooks on a and h files";
+        let mut allowed = Vec::new();
+        for c in field.chars() {
+            let (rank, color) = if c.is_ascii_uppercase() {
+                (0, Color::WHITE)
+            } else {
+                (7, Color::BLACK)
+            };
+            let file = match c.to_ascii_lowercase() {
+                'k' => 7,
+                'q' => 0,
+                f @ 'a'..='h' => f as usize - 'a' as usize,
+                _ => continue,
+            };
+            let own = |f: usize, p_type: PieceType| {
+                let p = self.squares[rank * 8 + f];
+                p.p_type == p_type && p.color == color
+            };
+            // X-FEN 'K' and 'Q' name the outermost rook, which may be off the corner in Chess960.
+            let inner_rook = !own(file, PieceType::ROOK)
+                && (file.min(4) + 1..file.max(4)).any(|f| own(f, PieceType::ROOK));
+            let king_off_file = (0..8).any(|f| f != 4 && own(f, PieceType::KING));
+            if file != 0 && file != 7 || inner_rook || king_off_file {
+                return Err(UNSUPPORTED);
             }
-            if color_counter == 0 {
-                game = game.replacen(format!("{}.", general_counter).as_str(), "", 1);
+            allowed.push(rank * 8 + file);
+        }
+        for rook in [0, 7, 56, 63] {
+            if !allowed.contains(&rook) && self.squares[rook].p_type == PieceType::ROOK {
+                self.squares[rook].has_moved = true;
             }
-            let mut temp_game = game.to_owned();
-            while temp_game.starts_with(" ") {
-                temp_game = temp_game.replacen(" ", "", 1)
+        }
+        Ok(())
+    }
+
+    // castling_rights returns castling rights of both colors: kings and rooks that haven't moved,
+    // whether castle is possible right now or not.
+    pub fn castling_rights(&self) -> CastlingRights {
+        CastlingRights {
+            white_king: self.has_castling_right(Color::WHITE, Side::King),
+            white_queen: self.has_castling_right(Color::WHITE, Side::Queen),
+            black_king: self.has_castling_right(Color::BLACK, Side::King),
+            black_queen: self.has_castling_right(Color::BLACK, Side::Queen),
+        }
+    }
+
+    // can_castle checks if color can castle now: it has the right, squares between the king and
+    // the rook are empty and the king doesn't pass through or land on an attacked square.
+    pub fn can_castle(&self, color: Color, side: Side) -> bool {
+        let ct = self.castle_transitions(color, side == Side::King);
+        self.has_castling_right(color, side) && self.validate_castle(ct[0].from, ct[1].from)
+    }
+
+    fn has_castling_right(&self, color: Color, side: Side) -> bool {
+        let ct = self.castle_transitions(color, side == Side::King);
+        let (king, rook) = (self.squares[ct[0].from], self.squares[ct[1].from]);
+        king.p_type == PieceType::KING
+            && king.color == color
+            && !king.has_moved
+            && rook.p_type == PieceType::ROOK
+            && rook.color == color
+            && !rook.has_moved
+    }
+
+    // castling_fen writes castling rights in given notation, '-' when no castle is possible.
+    pub fn castling_fen(&self, notation: CastlingNotation) -> String {
+        let mut field = String::new();
+        for color in [Color::WHITE, Color::BLACK] {
+            for side in [Side::King, Side::Queen] {
+                if !self.has_castling_right(color, side) {
+                    continue;
+                }
+                let c = match notation {
+                    CastlingNotation::Standard if side == Side::King => 'k',
+                    CastlingNotation::Standard => 'q',
+                    CastlingNotation::Shredder => {
+                        let rook = self.castle_transitions(color, side == Side::King)[1].from;
+                        (b'a' + (rook % 8) as u8) as char
+                    }
+                };
+                field.push(if color == Color::WHITE {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                });
             }
+        }
+        if field.is_empty() {
+            field.push('-');
+        }
+        field
+    }
 
-            let (chess_move, trimmed) = match temp_game.split_once(" ") {
-                Some((chess_move, trimmed)) => (chess_move, trimmed),
-                None => (temp_game.as_str(), ""), // last move
-            };
-            if trimmed != "" {
-                game = String::from(trimmed);
-            } else {
-                game = String::new();
+    // is_castling_field checks if castling rights are written in FEN, X-FEN or Shredder-FEN
+    // notation.
+    fn is_castling_field(field: &str) -> bool {
+        field == "-"
+            || (field.len() <= 4
+                && field
+                    .chars()
+                    .all(|c| "KQkq".contains(c) || matches!(c.to_ascii_lowercase(), 'a'..='h')))
+    }
+
+    // validate_fen checks syntax of FEN fields, trailing fields may be omitted.
+    pub fn validate_fen(fen: &str) -> Result<(), &'static str> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or("empty fen")?;
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err("fen must describe 8 ranks");
+        }
+        for rank in ranks {
+            let mut files = 0;
+            for c in rank.chars() {
+                match c {
+                    '1'..='8' => files += c.to_digit(10).unwrap(),
+                    'r' | 'n' | 'b' | 'q' | 'k' | 'p' | 'R' | 'N' | 'B' | 'Q' | 'K' | 'P' => {
+                        files += 1
+                    }
+                    _ => return Err("invalid character in pieces placement"),
+                }
+            }
+            if files != 8 {
+                return Err("every rank must describe 8 files");
+            }
+        }
+        match fields.next() {
+            None | Some("w") | Some("b") => {}
+            _ => return Err("color to move must be 'w' or 'b'"),
+        }
+        match fields.next() {
+            Some(castling) if !Board::is_castling_field(castling) => {
+                return Err("invalid castling field")
             }
+            _ => {}
+        }
+        match fields.next() {
+            Some(en_passant) if !Board::is_en_passant_field(en_passant) => {
+                return Err("invalid en passant square")
+            }
+            _ => {}
+        }
+        match fields.next().map(str::parse::<usize>) {
+            Some(Err(_)) => return Err("halfmove clock must be a number"),
+            _ => {}
+        }
+        match fields.next().map(str::parse::<usize>) {
+            Some(Ok(0)) | Some(Err(_)) => return Err("fullmove number must be a positive number"),
+            _ => {}
+        }
+        match fields.next() {
+            Some(_) => Err("fen has more than six fields"),
+            None => Ok(()),
+        }
+    }
 
-            match self.make_pgn_move(chess_move) {
-                Err(e) => return Err(e),
-                _ => {}
+    // is_en_passant_field checks if en passant field is '-' or a square on the 3rd or 6th rank.
+    fn is_en_passant_field(field: &str) -> bool {
+        field == "-" || parse_square(field).is_some_and(|s| s / 8 == 2 || s / 8 == 5)
+    }
+
+    // read_fen_lenient reads FEN that may be malformed, e.g. scraped from the web. Problems that
+    // can be repaired are returned as warnings, the rest are errors.
+    pub fn read_fen_lenient(&mut self, fen: &str) -> Result<Vec<String>, &'static str> {
+        let (fen, warnings) = Board::repair_fen(fen)?;
+        self.read_fen(&fen)?;
+        Ok(warnings)
+    }
+
+    // repair_fen returns FEN with repaired pieces placement and color to move, and list of
+    // repairs that were made.
+    pub fn repair_fen(fen: &str) -> Result<(String, Vec<String>), &'static str> {
+        let mut warnings = Vec::new();
+        let normalized: String = fen
+            .chars()
+            .map(|c| match c {
+                '–' | '—' | '−' => '-',
+                _ => c,
+            })
+            .collect();
+        if normalized != fen {
+            warnings.push("dashes replaced with '-'".to_string());
+        }
+        let mut fields = normalized.split_whitespace();
+        if fen.trim() != fen || fen.split_whitespace().count() != fen.split(' ').count() {
+            warnings.push("extra whitespace removed".to_string());
+        }
+
+        let placement = fields.next().ok_or("empty fen")?;
+        let mut ranks: Vec<String> = placement
+            .split('/')
+            .filter(|r| !r.is_empty())
+            .map(String::from)
+            .collect();
+        if ranks.len() != 8 {
+            return Err("fen must describe 8 ranks");
+        }
+        for (i, rank) in ranks.iter_mut().enumerate() {
+            let mut files = 0;
+            for c in rank.chars() {
+                match c {
+                    '1'..='8' => files += c.to_digit(10).unwrap(),
+                    'r' | 'n' | 'b' | 'q' | 'k' | 'p' | 'R' | 'N' | 'B' | 'Q' | 'K' | 'P' => {
+                        files += 1
+                    }
+                    _ => return Err("invalid character in pieces placement"),
+                }
+            }
+            if files > 8 {
+                return Err("rank describes more than 8 files");
+            }
+            if files < 8 {
+                rank.push_str(&(8 - files).to_string());
+                warnings.push(format!("rank {} filled with empty squares", 8 - i));
+            }
+        }
+        for (king, color) in [('K', "white"), ('k', "black")] {
+            if !ranks.iter().any(|r| r.contains(king)) {
+                warnings.push(format!("{} king is missing", color));
             }
+        }
+
+        let color = match fields.next() {
+            Some("w") => "w",
+            Some("b") => "b",
+            Some("W") => {
+                warnings.push("color to move written in uppercase".to_string());
+                "w"
+            }
+            Some("B") => {
+                warnings.push("color to move written in uppercase".to_string());
+                "b"
+            }
+            Some(_) => return Err("color to move must be 'w' or 'b'"),
+            None => {
+                warnings.push("color to move is missing, white assumed".to_string());
+                "w"
+            }
+        };
+        let mut repaired = format!("{} {}", ranks.join("/"), color);
+        // the rest is kept up to the first invalid field.
+        let rest: [(&str, fn(&str) -> bool); 4] = [
+            ("castling", Board::is_castling_field),
+            ("en passant", Board::is_en_passant_field),
+            ("halfmove clock", |f| f.parse::<usize>().is_ok()),
+            ("fullmove number", |f| {
+                f.parse::<usize>().is_ok_and(|n| n > 0)
+            }),
+        ];
+        for (name, is_valid) in rest {
+            match fields.next() {
+                Some(field) if is_valid(field) => repaired = format!("{} {}", repaired, field),
+                Some(field) => {
+                    warnings.push(format!("invalid {} field '{}' ignored", name, field));
+                    break;
+                }
+                None => break,
+            }
+        }
+        Ok((repaired, warnings))
+    }
+
+    // read_pgn is an entry point for pgn game.
+    //
+    // method reads whole game description and call make_pgn_move one by one.
+    pub fn read_pgn(&mut self, pgn: &str, vis_flag: bool) -> Result<(), &'static str> {
+        for chess_move in movetext_tokens(pgn) {
+            self.make_pgn_move(&chess_move)?;
 
             if self.debug {
                 println!("making {} move", chess_move,);
             }
+        }
+        Ok(())
+    }
 
-            if color_counter == 1 {
-                color_counter = 0;
-                general_counter += 1;
-            } else {
-                color_counter += 1;
+    // to_fen writes complete FEN with all six fields, which read_fen reads back.
+    pub fn to_fen(&self) -> String {
+        let en_passant = self
+            .en_passant_square()
+            .map_or("-".to_string(), square_name);
+        format!(
+            "{} {} {} {} {}",
+            self.position_fen(),
+            self.castling_fen(CastlingNotation::Standard),
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    // position_fen writes pieces placement and color to move in FEN notation. It's a key of
+    // positions where castling rights, en passant square and counters don't matter.
+    pub fn position_fen(&self) -> String {
+        let mut ranks = Vec::new();
+        for rank in (0..8).rev() {
+            let mut row = String::new();
+            let mut empty = 0;
+            for file in 0..8 {
+                let p = self.squares[rank * 8 + file];
+                if p.is_none() {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    row.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                row.push_str(&p.visualize());
             }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            ranks.push(row);
         }
-        Ok(())
+        let color = if self.color_to_move == Color::WHITE {
+            "w"
+        } else {
+            "b"
+        };
+        format!("{} {}", ranks.join("/"), color)
+    }
+
+    // last_move returns the last move made on the board, castle is the king's move. After
+    // read_fen it's only known from the en passant square.
+    pub fn last_move(&self) -> Option<Move> {
+        if self.last_transition.is_default() {
+            return None;
+        }
+        Some(self.last_transition)
     }
 
-    // make_pgn_move method parses pgn move, validates and performs.
-    fn make_pgn_move(&mut self, m: &str) -> Result<(), &'static str> {
+    // en_passant_square returns square behind a pawn that has just moved two squares, which can
+    // be captured en passant in the next move.
+    pub fn en_passant_square(&self) -> Option<usize> {
+        let t = self.last_transition;
+        let double_push = self.squares[t.to].p_type == PieceType::PAWN
+            && (t.from as i32 - t.to as i32).abs() == 16;
+        if t.is_default() || !double_push {
+            return None;
+        }
+        Some((t.from + t.to) / 2)
+    }
+
+    // make_pgn_move method parses pgn move, validates and performs. Moves in long algebraic
+    // notation e.g. Ng1-f3 are accepted too.
+    pub fn make_pgn_move(&mut self, m: &str) -> Result<(), &'static str> {
+        if let Some(lan) = parse_lan(m) {
+            let piece = self.squares[lan.from];
+            if piece.p_type != lan.piece || piece.color != self.color_to_move {
+                return Err("invalid move");
+            }
+            let mut internal = format!("{}{}", square_name(lan.from), square_name(lan.to));
+            internal.push_str(&lan.promotion.sign().to_lowercase());
+            return self.make_move_internal_notation(&internal);
+        }
         let transitions = match self.translate_pgn_move(m) {
             Ok(transitions) => transitions,
             Err(err) => return Err(err),
@@ -263,10 +820,7 @@ impl Board {
                     transitions.get(0).unwrap().from,
                     transitions.get(1).unwrap().from,
                 ) {
-                    for t in transitions {
-                        self.make_move(t, false);
-                    }
-                    self.swap_color_to_move();
+                    self.make_castle(transitions);
                     Ok(())
                 } else {
                     Err("invalid castle")
@@ -277,6 +831,7 @@ impl Board {
         for t in transitions {
             match self.validate_move(t.from, t.to) {
                 Ok(r) => {
+                    validate_promotion(&t.from_piece, t.to, t.promotion)?;
                     match r {
                         Some(additional_transition) => {
                             self.make_move(additional_transition, false);
@@ -293,37 +848,328 @@ impl Board {
     }
 
     // validate_castle check if wanted castle is valid.
+    //
+    // king and rook cannot have moved, squares between them must be empty, king cannot be in check
+    // and cannot pass through or land on attacked square.
     fn validate_castle(&self, king_pos: usize, rook_pos: usize) -> bool {
-        if !self.squares[king_pos].has_moved && !self.squares[rook_pos].has_moved {
+        let king = self.squares[king_pos];
+        let rook = self.squares[rook_pos];
+        if king.p_type != PieceType::KING
+            || rook.p_type != PieceType::ROOK
+            || king.color != rook.color
+        {
+            return false;
+        }
+        if !king.has_moved && !rook.has_moved {
             // iterate all places between king and rook.
             for inx in min(king_pos, rook_pos) + 1..max(king_pos, rook_pos) {
                 if !self.squares[inx].is_none() {
                     return false;
                 }
             }
+            // king moves two squares towards the rook.
+            let king_to = if rook_pos > king_pos {
+                king_pos + 2
+            } else {
+                king_pos - 2
+            };
+            for inx in min(king_pos, king_to)..max(king_pos, king_to) + 1 {
+                if !attackers(&self.squares, inx, king.color.opposite()).is_empty() {
+                    return false;
+                }
+            }
             return true;
         }
         return false;
     }
 
+    // castle_transitions returns king and rook transitions for castle of given color. King
+    // transition is always first.
+    fn castle_transitions(&self, color: Color, short: bool) -> Vec<Transition> {
+        let king_pos = if color == Color::BLACK { 60 } else { 4 };
+        if short {
+            vec![
+                Transition::new_short_castle(king_pos, king_pos + 2, self.squares[king_pos]),
+                Transition::new_short_castle(
+                    king_pos + 3,
+                    king_pos + 1,
+                    self.squares[king_pos + 3],
+                ),
+            ]
+        } else {
+            vec![
+                Transition::new_long_castle(king_pos, king_pos - 2, self.squares[king_pos]),
+                Transition::new_long_castle(king_pos - 4, king_pos - 1, self.squares[king_pos - 4]),
+            ]
+        }
+    }
+
+    // make_move_internal_notation makes move written as 'from' and 'to' squares e.g. e2e4, e7e8q.
+    // Castle is written as king move e.g. e1g1.
+    pub fn make_move_internal_notation(&mut self, m: &str) -> Result<(), &'static str> {
+        if m.len() != 4 && m.len() != 5 {
+            return Err("invalid move notation");
+        }
+        let (from, to) = match (parse_square(&m[0..2]), parse_square(&m[2..4])) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Err("invalid move notation"),
+        };
+        let piece = self.squares[from];
+
+        if piece.p_type == PieceType::KING && (to as i32 - from as i32).abs() == 2 {
+            let transitions = self.castle_transitions(piece.color, to > from);
+            if piece.color != self.color_to_move
+                || transitions[0].to != to
+                || !self.validate_castle(transitions[0].from, transitions[1].from)
+            {
+                return Err("invalid castle");
+            }
+            self.make_castle(transitions);
+            return Ok(());
+        }
+
+        let mut promotion = PieceType::NONE;
+        if m.len() == 5 {
+            promotion = PieceType::from_sign(&m[4..5].to_uppercase());
+            if promotion == PieceType::NONE {
+                return Err("invalid promotion piece");
+            }
+        }
+        validate_promotion(&piece, to, promotion)?;
+
+        if let Some(additional_transition) = self.validate_move(from, to)? {
+            self.make_move(additional_transition, false);
+        }
+        self.make_move(
+            Transition::new_promotion(from, to, piece, self.squares[to], promotion),
+            true,
+        );
+        Ok(())
+    }
+
+    // make_transition makes move returned by generate_transitions, castle and en passant
+    // transitions move additional pieces.
+    pub fn make_transition(&mut self, t: Transition) {
+        match t.flag {
+            TransitionFlag::ShortCastle | TransitionFlag::LongCastle => {
+                let color = self.squares[t.from].color;
+                self.make_castle(
+                    self.castle_transitions(color, t.flag == TransitionFlag::ShortCastle),
+                );
+            }
+            TransitionFlag::EnPassant => {
+                let captured = if t.to > t.from { t.to - 8 } else { t.to + 8 };
+                self.make_move(
+                    Transition::remove_piece(captured, self.squares[captured]),
+                    false,
+                );
+                self.make_move(t, true);
+            }
+            _ => self.make_move(t, true),
+        }
+    }
+
+    // make_null_move passes the move to the other color without moving a piece, as null move
+    // pruning of the search supposes. En passant capture is no longer possible after it.
+    pub fn make_null_move(&mut self) {
+        self.last_transition = Transition::default();
+        self.swap_color_to_move();
+    }
+
+    // legal_moves returns all fully legal moves of color that has the move: moves leaving the king
+    // in check are excluded, castles, en passant and every promotion piece are included.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.generate_transitions()
+    }
+
+    // generate_transitions returns all legal moves for color that has the move. Castle is
+    // represented by king transition, en passant and promotions have their own flags.
+    pub fn generate_transitions(&self) -> Vec<Transition> {
+        let mut transitions = Vec::new();
+        for (from, p) in self.squares.iter().enumerate() {
+            if p.color != self.color_to_move || p.is_none() {
+                continue;
+            }
+            for m in p.get_moves(from) {
+                let to = from as i32 + m;
+                if !(0..64).contains(&to) {
+                    continue;
+                }
+                let to = to as usize;
+                let additional_transition = match self.validate_move(from, to) {
+                    Ok(adt) => adt,
+                    Err(_) => continue,
+                };
+                let to_piece = self.squares[to];
+                if additional_transition.is_some() {
+                    transitions.push(Transition::new(
+                        from,
+                        to,
+                        TransitionFlag::EnPassant,
+                        PieceType::NONE,
+                        *p,
+                        to_piece,
+                    ));
+                } else if p.p_type == PieceType::PAWN && (to / 8 == 0 || to / 8 == 7) {
+                    for promotion in &PROMOTION_PIECES {
+                        transitions.push(Transition::new(
+                            from,
+                            to,
+                            TransitionFlag::Promotion,
+                            *promotion,
+                            *p,
+                            to_piece,
+                        ));
+                    }
+                } else {
+                    transitions.push(Transition::new(
+                        from,
+                        to,
+                        TransitionFlag::Move,
+                        PieceType::NONE,
+                        *p,
+                        to_piece,
+                    ));
+                }
+            }
+            if p.p_type == PieceType::KING && !p.has_moved {
+                for short in [true, false] {
+                    let ct = self.castle_transitions(p.color, short);
+                    if ct[0].from == from && self.validate_castle(ct[0].from, ct[1].from) {
+                        transitions.push(ct[0]);
+                    }
+                }
+            }
+        }
+        transitions
+    }
+
+    // destinations returns legal target squares of the piece on a square, sorted. It's empty when
+    // the square is empty or the piece's color doesn't have the move. Castle is a king's move to
+    // its target square, promotions to different pieces are one destination.
+    pub fn destinations(&self, square: Square) -> Vec<Square> {
+        let mut destinations: Vec<Square> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|t| t.from == square)
+            .map(|t| t.to)
+            .collect();
+        destinations.sort_unstable();
+        destinations.dedup();
+        destinations
+    }
+
+    // squares returns pieces of all squares, 0 is left lower corner. They're read-only, positions
+    // are edited with from_pieces so material and piece_square stay in sync.
+    pub fn squares(&self) -> &[Piece; 64] {
+        &self.squares
+    }
+
+    // piece_at returns piece on the square, empty piece when there is none.
+    pub fn piece_at(&self, square: Square) -> Piece {
+        self.squares[square]
+    }
+
+    // material returns sum of points of white pieces minus black ones, see PieceType::points. It's
+    // updated by every move instead of counting pieces on all squares.
+    pub fn material(&self) -> i32 {
+        self.material
+    }
+
+    // piece_square returns middlegame and endgame piece-square bonuses of white pieces minus black
+    // ones in centipawns, see pst. Like material, they're updated by every move.
+    pub fn piece_square(&self) -> (i32, i32) {
+        self.piece_square
+    }
+
+    // halfmove_clock returns number of moves since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> usize {
+        self.halfmove_clock
+    }
+
+    // fullmove_number returns number of the current move, it starts at 1 and grows after black's
+    // move.
+    pub fn fullmove_number(&self) -> usize {
+        self.fullmove_number
+    }
+
+    // ply returns number of half-moves played since the start of the game, e.g. 0 in the
+    // starting position and 1 after 1. e4.
+    pub fn ply(&self) -> usize {
+        let black = usize::from(self.color_to_move == Color::BLACK);
+        (self.fullmove_number - 1) * 2 + black
+    }
+
+    // is_in_check checks if king of color that has the move is in check.
+    pub fn is_in_check(&self) -> bool {
+        !self.checkers(self.color_to_move).is_empty()
+    }
+
+    // king_square returns square of the king of given color, None if there's no such king.
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.kings_positions.get(&color).copied()
+    }
+
+    // status tells if the game goes on or is over because color to move has no legal moves.
+    pub fn status(&self) -> GameStatus {
+        if !self.generate_transitions().is_empty() {
+            return GameStatus::Ongoing;
+        }
+        if self.is_in_check() {
+            return GameStatus::Checkmate(self.color_to_move.opposite());
+        }
+        GameStatus::Stalemate
+    }
+
+    // game_state tells if the game is over by checkmate, stalemate, insufficient material or the
+    // fifty-move rule. Checkmate on the last move of the fifty stands.
+    pub fn game_state(&self) -> GameState {
+        match self.status() {
+            GameStatus::Checkmate(winner) => GameState::Checkmate(winner),
+            GameStatus::Stalemate => GameState::Stalemate,
+            GameStatus::Ongoing
+                if !has_mating_material(self, Color::WHITE)
+                    && !has_mating_material(self, Color::BLACK) =>
+            {
+                GameState::Draw(DrawReason::InsufficientMaterial)
+            }
+            GameStatus::Ongoing if self.halfmove_clock >= 100 => {
+                GameState::Draw(DrawReason::FiftyMoves)
+            }
+            GameStatus::Ongoing => GameState::Ongoing,
+        }
+    }
+
     // make_move changes places of pieces and their types in squares vector.
     pub(crate) fn make_move(&mut self, tr: Transition, swap_color: bool) {
         let from = tr.from;
         let to = tr.to;
 
+        if tr.flag == TransitionFlag::Remove {
+            // only remove piece, there's no 'to' square.
+            self.account(from, -1);
+            self.squares[from] = Piece::default();
+            return;
+        }
+        self.account(to, -1);
+        self.account(from, -1);
+        // pawn moves and captures reset the halfmove clock, en passant is a pawn move too.
+        let irreversible =
+            self.squares[from].p_type == PieceType::PAWN || !self.squares[to].is_none();
         self.squares[to] = self.squares[from];
         self.squares[to].has_moved = true;
         if tr.flag == TransitionFlag::Promotion {
             // promotion (type change) needed.
             self.squares[to].p_type = tr.promotion;
-        } else if tr.flag == TransitionFlag::Remove {
-            self.squares[from] = Piece::default();
-            return;
         }
         self.squares[from] = Piece::default();
+        self.account(to, 1);
         if swap_color {
             // swap color wanted.
             self.swap_color_to_move();
+            if irreversible {
+                self.halfmove_clock = 0;
+            }
         }
         if self.squares[to].p_type == PieceType::KING {
             // update position of king.
@@ -332,181 +1178,115 @@ impl Board {
         self.last_transition = tr; // save transition.
     }
 
-    fn swap_color_to_move(&mut self) {
-        self.color_to_move = self.color_to_move.opposite();
+    // account adds the piece on the square to material and piece-square bonuses, or takes it away
+    // when sign is -1.
+    fn account(&mut self, square: Square, sign: i32) {
+        let piece = &self.squares[square];
+        let (mg, eg) = pst::bonus(piece, square);
+        self.material += sign * signed_points(piece);
+        self.piece_square.0 += sign * mg;
+        self.piece_square.1 += sign * eg;
     }
 
-    // translate_move gets algebraic notation and parses it to vec of possible 'from' -> 'to' move
-    // e.g. Nxe5, Qh5+, g5, hxg5+
-    fn translate_pgn_move(&mut self, m: &str) -> Result<Vec<Transition>, &'static str> {
-        if m == "O-O" {
-            return if self.color_to_move == Color::BLACK {
-                Ok(vec![
-                    Transition::new_short_castle(60, 62, self.squares[60]),
-                    Transition::new_short_castle(63, 61, self.squares[63]),
-                ])
-            } else {
-                Ok(vec![
-                    Transition::new_short_castle(4, 6, self.squares[4]),
-                    Transition::new_short_castle(7, 5, self.squares[7]),
-                ])
-            };
-        } else if m == "O-O-O" {
-            return if self.color_to_move == Color::BLACK {
-                Ok(vec![
-                    Transition::new_short_castle(60, 58, self.squares[60]),
-                    Transition::new_short_castle(56, 59, self.squares[56]),
-                ])
-            } else {
-                Ok(vec![
-                    Transition::new_short_castle(4, 2, self.squares[4]),
-                    Transition::new_short_castle(0, 3, self.squares[0]),
-                ])
-            };
+    // make_castle moves the king and the rook of castle transitions, the king's move is the last
+    // move.
+    fn make_castle(&mut self, transitions: Vec<Transition>) {
+        for t in &transitions {
+            self.make_move(*t, false);
         }
+        self.last_transition = transitions[0];
+        self.swap_color_to_move();
+    }
 
-        let mut pawn_move = false; // is pawn move?
-        let mut promotion = PieceType::NONE; // is pawn promotion?
-        let pawn_letters = vec!["a", "b", "c", "d", "e", "f", "g", "h"];
-        let mut m = m.replace("x", "").replace("+", "").replace("#", "");
-
-        for l in &pawn_letters {
-            if m.starts_with(l) {
-                let temp_m = m.to_owned();
-                // handle promotion e.g. hxg8=Q
-                if temp_m.contains("=") {
-                    let (f, p) = temp_m.split_once("=").unwrap();
-                    m = String::from(f);
-                    promotion = PieceType::from_sign(p);
-                }
-                pawn_move = true;
-                break;
-            }
+    // swap_color_to_move ends a move, so it counts it too.
+    fn swap_color_to_move(&mut self) {
+        if self.color_to_move == Color::BLACK {
+            self.fullmove_number += 1;
         }
+        self.halfmove_clock += 1;
+        self.color_to_move = self.color_to_move.opposite();
+    }
 
-        let mut transitions = Vec::new();
-
-        let piece_to_find;
-        let places;
-        let direction;
-        if pawn_move {
-            piece_to_find = PieceType::PAWN;
-            if m.len() == 3 {
-                // pawn takes
-                let (first, second) = m.split_at(1);
-                places = self.find_pawn_places(first);
-                direction = self.translate_position(second);
-            } else {
-                // basic move
-                direction = self.translate_position(m.as_str());
-                let (first, _) = m.split_at(1);
-                places = self.find_pawn_places(first);
+    // translate_pgn_move reads a move in standard algebraic notation, e.g. Nxe5, Qh5+, g5, hxg5+,
+    // exd8=Q, R1e2 or Qh4e1, and returns transitions of all pieces that match it. One grammar is
+    // used for every piece: piece letter (none for pawns), optional file, rank or square of
+    // origin, capture marker, destination square and promotion. Castle returns king and rook
+    // transitions, the king's first.
+    fn translate_pgn_move(&mut self, m: &str) -> Result<Vec<Transition>, &'static str> {
+        match castle_flag(m) {
+            Some(TransitionFlag::ShortCastle) => {
+                return Ok(self.castle_transitions(self.color_to_move, true))
             }
-        } else {
-            let (first, mut second) = m.split_at(1);
-            let mut additional_info = String::new();
-            let piece_to_find = match first {
-                "N" => {
-                    // both knights can jump into the same square
-                    // we need to check if that is happening
-                    //
-                    // basically check len of move and check for given row/column of a knight
-                    if second.len() != 2 {
-                        let mut chars = second.chars();
-                        additional_info = chars.next().unwrap().to_string();
-                        second = chars.as_str();
-                    }
-                    PieceType::KNIGHT
-                }
-                "Q" => PieceType::QUEEN,
-                "B" => PieceType::BISHOP,
-                "R" => {
-                    if second.len() != 2 {
-                        let mut chars = second.chars();
-                        additional_info = chars.next().unwrap().to_string();
-                        second = chars.as_str();
-                    }
-                    PieceType::ROOK
-                }
-                "K" => PieceType::KING,
-                _ => return Err("invalid piece"),
-            };
-            places = self.find_piece_places(piece_to_find, self.color_to_move, additional_info);
-            direction = self.translate_position(second);
-        }
-        for p in &places {
-            transitions.push(Transition::new_promotion(
-                *p,
-                direction,
-                self.squares[*p],
-                self.squares[direction],
-                promotion,
-            ));
+            Some(_) => return Ok(self.castle_transitions(self.color_to_move, false)),
+            None => {}
         }
-        return Ok(transitions);
-    }
-
-    fn find_piece_places(
-        &self,
-        piece_type: PieceType,
-        color: Color,
-        additional_info: String,
-    ) -> Vec<usize> {
-        let mut places: Vec<usize> = Vec::new();
-
-        self.squares
-            .iter()
-            .enumerate()
-            .map(|(i, p)| (i, p)) // vec of pieces -> vec of (index, piece)
-            .filter(|(_, p)| p.p_type == piece_type && p.color == color) // only wanted pieces
-            .for_each(|(i, p)| {
-                if additional_info.len() == 1 {
-                    // there's additional info
-                    let info = additional_info.chars().next().unwrap();
-                    if info.is_digit(10) {
-                        // check for row
-                        let row = info.to_digit(10).unwrap() as usize;
-                        if (row - 1) * 8 >= i && row * 8 < i {
-                            places.push(i);
-                        }
-                    } else {
-                        // check for column
-                        let column = letter_to_i32(&info);
-                        let possible_indexes: Vec<usize> =
-                            (1..9).map(|x| (column + 8 * (x - 1)) as usize).collect();
-                        if possible_indexes.contains(&i) {
-                            places.push(i)
-                        }
-                    }
-                } else {
-                    places.push(i)
-                }
-            });
-        places
-    }
 
-    // find_pawn_places takes e.g. 'e' and returns all pawn position that is on 'e' line
-    fn find_pawn_places(&self, line: &str) -> Vec<usize> {
-        let mut places: Vec<usize> = Vec::new();
-        if line.len() != 1 {
-            panic!("line len must be 1")
+        let m = m.trim_end_matches(['+', '#']);
+        let (m, promotion) = match m.split_once('=') {
+            Some((m, piece)) => match PieceType::from_sign(piece) {
+                PieceType::NONE => return Err("invalid promotion piece"),
+                promotion => (m, promotion),
+            },
+            None => (m, PieceType::NONE),
+        };
+        let chars: Vec<char> = m.chars().collect();
+        let (piece_type, rest) = match chars.split_first() {
+            Some((c, _)) if file_from_letter(*c).is_some() => (PieceType::PAWN, &chars[..]),
+            Some(('K', rest)) => (PieceType::KING, rest),
+            Some((c, rest)) => match PieceType::from_sign(&c.to_string()) {
+                PieceType::NONE => return Err("invalid piece"),
+                piece_type => (piece_type, rest),
+            },
+            None => return Err("move is too short"),
+        };
+        if rest.len() < 2 {
+            return Err("move is too short");
         }
-        let mut inx = 0;
-        line.chars().for_each(|c| inx = c as i32 - 'a' as i32); // only 1 iteration
-
-        for i in 0..7 {
-            let index = (inx + 8 * i) as usize;
-            let p = self.squares[index];
-            if p.p_type == PieceType::PAWN && p.color == self.color_to_move {
-                places.push(index);
-            }
+        if promotion != PieceType::NONE && piece_type != PieceType::PAWN {
+            return Err("only pawns are promoted");
         }
+        let (origin, destination) = rest.split_at(rest.len() - 2);
+        let to = parse_square(&destination.iter().collect::<String>()).ok_or("invalid square")?;
+        let origin = origin.strip_suffix(&['x']).unwrap_or(origin);
+        let rank = |c: &char| {
+            c.to_digit(10)
+                .filter(|r| (1..=8).contains(r))
+                .map(|r| r as usize - 1)
+        };
+        let (file, rank) = match origin {
+            // pawn moves without origin stay on their file.
+            [] if piece_type == PieceType::PAWN => (Some(file_rank(to).0), None),
+            [] => (None, None),
+            [f] if file_from_letter(*f).is_some() => (file_from_letter(*f), None),
+            [r] if rank(r).is_some() => (None, rank(r)),
+            [f, r] if file_from_letter(*f).is_some() && rank(r).is_some() => {
+                (file_from_letter(*f), rank(r))
+            }
+            _ => return Err("invalid origin square"),
+        };
 
-        places
+        Ok((0..64)
+            .filter(|i| {
+                let p = self.squares[*i];
+                let (f, r) = file_rank(*i);
+                p.p_type == piece_type
+                    && p.color == self.color_to_move
+                    && file.is_none_or(|file| file == f)
+                    && rank.is_none_or(|rank| rank == r)
+            })
+            .map(|from| {
+                Transition::new_promotion(from, to, self.squares[from], self.squares[to], promotion)
+            })
+            .collect())
     }
 
     #[warn(dead_code)]
     pub fn visualize(&self) {
+        println!("{}", self.draw())
+    }
+
+    // draw returns board as text, white pieces are uppercase and empty squares are 'x'.
+    pub fn draw(&self) -> String {
         let mut rank = 7;
         let mut file = 0;
         let mut board = String::new();
@@ -528,7 +1308,35 @@ impl Board {
             rank -= 1;
             file = 0;
         }
-        println!("{}", board)
+        board
+    }
+
+    // perft counts leaf nodes of legal moves tree of given depth, used to verify move generation.
+    pub fn perft(&self, depth: usize) -> u64 {
+        self.perft_stoppable(depth, &AtomicBool::new(false))
+            .unwrap_or_default()
+    }
+
+    // perft_stoppable is perft that checks 'stop' in every inner node, returns None when stopped.
+    pub fn perft_stoppable(&self, depth: usize, stop: &AtomicBool) -> Option<u64> {
+        if depth == 0 {
+            return Some(1);
+        }
+        if stop.load(Ordering::Relaxed) {
+            return None;
+        }
+        let transitions = self.generate_transitions();
+        if depth == 1 {
+            return Some(transitions.len() as u64);
+        }
+        transitions
+            .iter()
+            .map(|t| {
+                let mut child = self.clone();
+                child.make_transition(*t);
+                child.perft_stoppable(depth - 1, stop)
+            })
+            .sum()
     }
 
     // validate_move validates if move is legit. It checks every aspect of a game.
@@ -537,6 +1345,9 @@ impl Board {
         from: usize,
         to: usize,
     ) -> Result<Option<Transition>, &'static str> {
+        if from > 63 || to > 63 {
+            return Err("square is outside of the board");
+        }
         let piece = self.squares[from];
         let position_to = self.squares[to];
 
@@ -547,6 +1358,11 @@ impl Board {
             return Err("piece is none, position_to is occupied by the same color piece or it is not your move");
         }
 
+        // in double check blocking or capturing cannot help, king has to move.
+        if piece.p_type != PieceType::KING && self.checkers(piece.color).len() > 1 {
+            return Err("double check, only king can move");
+        }
+
         let mut additional_transition = Transition::default(); // possible additional transition
         match self.is_move_possible(&piece, from, to, self.squares) {
             Ok(r) => match r {
@@ -560,6 +1376,11 @@ impl Board {
         let to = to as usize;
         squares_copy[from as usize] = Piece::default();
         squares_copy[to] = piece;
+        if additional_transition.flag == TransitionFlag::Remove {
+            // en passant removes captured pawn from other square than 'to', it can expose the king
+            // e.g. when both pawns disappear from the rank shared with king and enemy rook.
+            squares_copy[additional_transition.from] = Piece::default();
+        }
         let mut kings_positions = self.kings_positions.clone();
         if piece.p_type == PieceType::KING {
             kings_positions.insert(piece.color, to);
@@ -587,21 +1408,22 @@ impl Board {
         &self,
         color: Color,
         squares_copy: [Piece; 64],
-        kings_positions: &HashMap<Color, usize>,
+        kings_positions: &KingsPositions,
     ) -> bool {
-        // check for check
-        let king_pos = kings_positions.get(&color).unwrap();
-        for (inx, p) in squares_copy.iter().enumerate() {
-            if color != p.color && !p.is_none() {
-                if self
-                    .is_move_possible(p, inx, *king_pos, squares_copy)
-                    .is_ok()
-                {
-                    return true;
-                }
-            }
+        match kings_positions.get(&color) {
+            Some(king_pos) => !attackers(&squares_copy, *king_pos, color.opposite()).is_empty(),
+            None => false,
+        }
+    }
+
+    // checkers returns positions of all pieces that give check to the king of given color.
+    //
+    // more than one checker means double check, only king move can be a response then.
+    pub fn checkers(&self, color: Color) -> Vec<Square> {
+        match self.kings_positions.get(&color) {
+            Some(king_pos) => attackers(&self.squares, *king_pos, color.opposite()),
+            None => Vec::new(),
         }
-        return false;
     }
 
     // is_move_possible checks is move is 'physically' legit.
@@ -614,15 +1436,29 @@ impl Board {
     ) -> Result<Option<Transition>, &'static str> {
         let available_moves = piece.get_moves(from);
         let transition = to as i32 - from as i32;
-        if !available_moves.contains(&transition) {
+        if !available_moves.contains(&transition) || !is_shape_valid(piece, from, to) {
             return Err("that piece cannot make moves like that!");
         }
 
         if piece.p_type == PieceType::PAWN {
-            if (transition == 8 || transition == -8 || transition == 16 || transition == -16)
-                && !squares[to].is_none()
-            {
-                return Err("pawn cannot move to occupied place");
+            if transition == 8 || transition == -8 {
+                if !squares[to].is_none() {
+                    return Err("pawn cannot move to occupied place");
+                }
+                return Ok(None);
+            }
+            if transition == 16 || transition == -16 {
+                let start_rank = if piece.color == Color::WHITE { 1 } else { 6 };
+                if from / 8 != start_rank {
+                    return Err("pawn can move two squares only from its starting rank");
+                }
+                if !squares[to].is_none() || !squares[(from + to) / 2].is_none() {
+                    return Err("pawn cannot move to occupied place");
+                }
+                return Ok(None);
+            }
+            if !squares[to].is_none() {
+                return Ok(None); // regular capture
             }
             return match self.check_en_passant(piece, from, to, transition, squares) {
                 Ok(r) => Ok(r),
@@ -631,41 +1467,16 @@ impl Board {
         }
 
         // check if there's no other piece on your way
-        if piece.is_sliding() {
-            let to = to as i32;
-            let from = from as i32;
-
-            let sliding_moves = piece.get_sliding_moves();
-            let mut blocked = false;
-            let mut is_valid = false;
-            for m in &sliding_moves {
-                let mut from_temp = from.clone();
-                loop {
-                    from_temp += m;
-                    if from_temp > 63 || from_temp < 0 {
-                        break;
-                    }
-                    if from_temp == to {
-                        if blocked {
-                            return Err("your move is blocked");
-                        }
-                        is_valid = true;
-                        break;
-                    }
-                    if !squares[from_temp as usize].is_none() {
-                        blocked = true;
-                    }
-                }
-                if is_valid {
-                    break;
-                }
-                blocked = false;
-            }
+        if piece.is_sliding() && !is_path_clear(from, to, &squares) {
+            return Err("your move is blocked");
         }
         Ok(None)
     }
 
     // check_en_passant checks if move is en passant, if so, returns needed Transition.
+    //
+    // it's called for pawn going diagonally onto an empty square, so the only legit option here is
+    // en passant.
     fn check_en_passant(
         &self,
         piece: &Piece,
@@ -674,48 +1485,31 @@ impl Board {
         transition: i32,
         squares: [Piece; 64],
     ) -> Result<Option<Transition>, &'static str> {
-        if (transition == 7 || transition == -7 || transition == -9 || transition == 9)
-            && squares[to].is_none()
+        let en_passant_rank = if piece.color == Color::WHITE { 5 } else { 2 };
+        if to / 8 != en_passant_rank {
+            return Err("pawn can move diagonally only when capturing");
+        }
+        let (check_opposite_pawn_position, check_opposite_pawn_position_from) = if transition > 0 {
+            // check if below 'to' is pawn with opposite color
+            (to - 8, to + 8)
+        } else {
+            // check if above 'to' is pawn with opposite color
+            (to + 8, to - 8)
+        };
+        let c_piece = squares[check_opposite_pawn_position];
+        if c_piece.p_type != PieceType::PAWN || c_piece.color != piece.color.opposite() {
+            return Err("invalid en passant");
+        }
+        // check if that pawn made 2 moves before
+        if self.last_transition.from == check_opposite_pawn_position_from
+            && self.last_transition.to == check_opposite_pawn_position
         {
-            let mut check_opposite_pawn_position = 0;
-            let mut check_opposite_pawn_position_from = 0;
-            // check en passant
-            if transition > 0 {
-                // check if below 'to' is pawn with opposite color
-                check_opposite_pawn_position = to - 8;
-                check_opposite_pawn_position_from = to + 8;
-            } else {
-                // check if above 'to' is pawn with opposite color
-                check_opposite_pawn_position = to + 8;
-                check_opposite_pawn_position_from = to - 8;
-            }
-            let c_piece = squares[check_opposite_pawn_position];
-            if c_piece.p_type != PieceType::PAWN {
-                return Ok(None);
-            }
-            if c_piece.color != piece.color.opposite() {
-                return Err("invalid en passant");
-            }
-            // check if that pawn made 2 moves before
-            if self.last_transition.from == check_opposite_pawn_position_from
-                && self.last_transition.to == check_opposite_pawn_position
-            {
-                return Ok(Some(Transition::remove_piece(
-                    check_opposite_pawn_position,
-                    self.squares[check_opposite_pawn_position],
-                )));
-            }
+            return Ok(Some(Transition::remove_piece(
+                check_opposite_pawn_position,
+                self.squares[check_opposite_pawn_position],
+            )));
         }
-        Ok(None)
-    }
-
-    fn translate_position(&self, pos: &str) -> usize {
-        let mut inx: i32 = 0;
-        let (col, row) = pos.split_at(1);
-        col.chars().for_each(|c| inx += letter_to_i32(&c));
-        row.chars()
-            .for_each(|c| inx += (c.to_digit(10).unwrap() as i32 - 1) * 8);
-        inx as usize
+        Err("invalid en passant")
     }
 
     // is_check_mate takes current position and checks if it's check mate.
@@ -750,42 +1544,194 @@ impl Board {
     }
 }
 
-fn letter_to_i32(l: &char) -> i32 {
-    *l as i32 - 'a' as i32
+// validate_promotion checks if promotion piece is given exactly when pawn reaches the last rank.
+fn validate_promotion(piece: &Piece, to: usize, promotion: PieceType) -> Result<(), &'static str> {
+    let last_rank = if piece.color == Color::WHITE { 7 } else { 0 };
+    if piece.p_type == PieceType::PAWN && to / 8 == last_rank {
+        if promotion == PieceType::NONE {
+            return Err("promotion piece is missing");
+        }
+    } else if promotion != PieceType::NONE {
+        return Err("only pawn reaching last rank can be promoted");
+    }
+    Ok(())
+}
+
+// file_rank_distance returns (file, rank) difference between two squares.
+fn file_rank_distance(from: usize, to: usize) -> (i32, i32) {
+    (
+        (to % 8) as i32 - (from % 8) as i32,
+        (to / 8) as i32 - (from / 8) as i32,
+    )
+}
+
+// is_shape_valid checks if move does not 'wrap' around the board edge, move deltas returned by
+// Piece::get_moves do not take that into account for every piece.
+fn is_shape_valid(piece: &Piece, from: usize, to: usize) -> bool {
+    let (df, dr) = file_rank_distance(from, to);
+    match piece.p_type {
+        PieceType::KNIGHT => df.abs() * dr.abs() == 2,
+        PieceType::KING | PieceType::PAWN => df.abs() <= 1,
+        PieceType::ROOK => df == 0 || dr == 0,
+        PieceType::BISHOP => df.abs() == dr.abs(),
+        PieceType::QUEEN => df == 0 || dr == 0 || df.abs() == dr.abs(),
+        PieceType::NONE => false,
+    }
+}
+
+// is_path_clear checks if all squares between 'from' and 'to' are empty. Squares must be placed
+// on the same line, column or diagonal.
+fn is_path_clear(from: usize, to: usize, squares: &[Piece; 64]) -> bool {
+    let (df, dr) = file_rank_distance(from, to);
+    let step = dr.signum() * 8 + df.signum();
+    let mut inx = from as i32 + step;
+    while inx != to as i32 {
+        if !squares[inx as usize].is_none() {
+            return false;
+        }
+        inx += step;
+    }
+    true
+}
+
+pub(crate) const KNIGHT_JUMPS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+pub(crate) const KING_STEPS: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+// signed_points returns points of a piece, positive for white and negative for black.
+pub(crate) fn signed_points(piece: &Piece) -> i32 {
+    match piece.color {
+        Color::WHITE => piece.p_type.points(),
+        Color::BLACK => -piece.p_type.points(),
+        Color::NONE => 0,
+    }
+}
+
+// has_mating_material checks if color has enough pieces to ever mate, a lone king or king with a
+// single minor piece can't.
+pub fn has_mating_material(board: &Board, color: Color) -> bool {
+    let pieces: Vec<PieceType> = board
+        .squares
+        .iter()
+        .filter(|p| p.color == color && p.p_type != PieceType::NONE && p.p_type != PieceType::KING)
+        .map(|p| p.p_type)
+        .collect();
+    !matches!(
+        pieces.as_slice(),
+        [] | [PieceType::KNIGHT] | [PieceType::BISHOP]
+    )
+}
+
+// attackers returns positions of all pieces of given color that attack given square.
+pub fn attackers(squares: &[Piece; 64], square: Square, color: Color) -> Vec<Square> {
+    let mut result = Vec::new();
+    let file = (square % 8) as i32;
+    let rank = (square / 8) as i32;
+    let is_attacker = |inx: usize, p_type: PieceType| {
+        squares[inx].color == color && squares[inx].p_type == p_type
+    };
+
+    // pawns attack diagonally forward, so look for them one rank 'behind' the square.
+    let pawn_rank = if color == Color::WHITE {
+        rank - 1
+    } else {
+        rank + 1
+    };
+    for df in [-1, 1] {
+        if let Some(inx) = square_at(file + df, pawn_rank) {
+            if is_attacker(inx, PieceType::PAWN) {
+                result.push(inx);
+            }
+        }
+    }
+    for (df, dr) in KNIGHT_JUMPS {
+        if let Some(inx) = square_at(file + df, rank + dr) {
+            if is_attacker(inx, PieceType::KNIGHT) {
+                result.push(inx);
+            }
+        }
+    }
+    for (df, dr) in KING_STEPS {
+        if let Some(inx) = square_at(file + df, rank + dr) {
+            if is_attacker(inx, PieceType::KING) {
+                result.push(inx);
+            }
+        }
+        // walk the ray until first piece is found.
+        let slider = if df == 0 || dr == 0 {
+            PieceType::ROOK
+        } else {
+            PieceType::BISHOP
+        };
+        let mut distance = 1;
+        while let Some(inx) = square_at(file + df * distance, rank + dr * distance) {
+            if !squares[inx].is_none() {
+                if is_attacker(inx, slider) || is_attacker(inx, PieceType::QUEEN) {
+                    result.push(inx);
+                }
+                break;
+            }
+            distance += 1;
+        }
+    }
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use crate::board;
-    use crate::board::{Board, Color};
+    use crate::board::{
+        Board, CastlingNotation, CastlingRights, Color, DrawReason, GameState, Side, Square,
+        TransitionFlag,
+    };
+    use crate::piece::{Piece, PieceType};
+    use std::sync::atomic::AtomicBool;
 
-    // #[test]
-    // fn block_detection() {
-    //     let mut b = board::Board::default();
-    //     b.read_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
-    //     assert_eq!(
-    //         b.make_move_internal_notation("c1g5").err().unwrap(),
-    //         "your move is blocked"
-    //     );
-    //
-    //     b.read_fen("q7/pppppppp/8/8/8/8/8/8");
-    //     b.color_to_move = Color::BLACK;
-    //     assert_eq!(
-    //         b.make_move_internal_notation("a8a1").err().unwrap(),
-    //         "your move is blocked"
-    //     );
-    // }
+    #[test]
+    fn block_detection() {
+        let mut b = board::Board::default();
+        b.read_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR")
+            .unwrap();
+        assert_eq!(
+            b.make_move_internal_notation("c1g5").err().unwrap(),
+            "your move is blocked"
+        );
+
+        b.read_fen("q7/pppppppp/8/8/8/8/8/8").unwrap();
+        b.color_to_move = Color::BLACK;
+        assert_eq!(
+            b.make_move_internal_notation("a8a1").err().unwrap(),
+            "your move is blocked"
+        );
+    }
 
-    // #[test]
-    // fn invalid_move() {
-    //     let mut b = board::Board::default();
-    //     b.read_fen("r7/8/8/8/8/8/8/8");
-    //     b.color_to_move = Color::BLACK;
-    //     assert_eq!(
-    //         b.make_move_internal_notation("a8b1").err().unwrap(),
-    //         "that piece cannot make moves like that!"
-    //     );
-    // }
+    #[test]
+    fn invalid_move() {
+        let mut b = board::Board::default();
+        b.read_fen("r7/8/8/8/8/8/8/8").unwrap();
+        b.color_to_move = Color::BLACK;
+        assert_eq!(
+            b.make_move_internal_notation("a8b1").err().unwrap(),
+            "that piece cannot make moves like that!"
+        );
+    }
 
     #[test]
     fn king_position() {
@@ -794,31 +1740,31 @@ mod tests {
         assert_eq!(*b.kings_positions.get(&Color::WHITE).unwrap(), 4);
     }
 
-    // #[test]
-    // fn blocked_move() {
-    //     let mut b = board::Board::default();
-    //     b.color_to_move = Color::BLACK;
-    //
-    //     b.read_fen("r7/p7/8/8/8/8/8/8");
-    //     assert_eq!(
-    //         b.make_move_internal_notation("a8a1").err().unwrap(),
-    //         "your move is blocked"
-    //     );
-    // }
+    #[test]
+    fn blocked_move() {
+        let mut b = board::Board::default();
+        b.color_to_move = Color::BLACK;
+
+        b.read_fen("r7/p7/8/8/8/8/8/8").unwrap();
+        assert_eq!(
+            b.make_move_internal_notation("a8a1").err().unwrap(),
+            "your move is blocked"
+        );
+    }
 
-    // #[test]
-    // fn check_after_move() {
-    //     let mut b = board::Board::default();
-    //     b.color_to_move = Color::BLACK;
-    //     b.read_fen("k7/q7/8/8/8/8/R7/K7");
-    //     assert_eq!(
-    //         b.make_move_internal_notation("a7b7").err().unwrap(),
-    //         "there will be check after a move"
-    //     );
-    //
-    //     b.read_fen("k7/q7/p7/8/8/8/R7/K7");
-    //     assert_eq!(b.make_move_internal_notation("a7b7").is_ok(), true);
-    // }
+    #[test]
+    fn check_after_move() {
+        let mut b = board::Board::default();
+        b.color_to_move = Color::BLACK;
+        b.read_fen("k7/q7/8/8/8/8/R7/K7").unwrap();
+        assert_eq!(
+            b.make_move_internal_notation("a7b7").err().unwrap(),
+            "there will be check after a move"
+        );
+
+        b.read_fen("k7/q7/p7/8/8/8/R7/K7").unwrap();
+        assert_eq!(b.make_move_internal_notation("a7b7").is_ok(), true);
+    }
 
     #[test]
     fn read_pgn() {
@@ -867,6 +1813,84 @@ Kxe6 8. Qg4+ Kd5 9. Nc3+ Kc5 10. Qc4+ Kb6 11. Qb5#";
         assert_eq!(b.is_check_mate(), true);
     }
 
+    #[test]
+    fn test_promotion_delivers_check() {
+        // (position, move, check, mate)
+        let cases = [
+            ("k7/4P3/1K6/8/8/8/8/8", "e7e8q", true, true),
+            ("k7/4P3/1K6/8/8/8/8/8", "e7e8r", true, true),
+            ("8/4P3/8/7k/8/8/8/K7", "e7e8b", true, false),
+            ("8/4P1k1/8/8/8/8/8/K7", "e7e8n", true, false),
+            ("3r4/4Pk2/8/8/8/8/8/K7", "e7d8n", true, false),
+            ("3r4/4Pk2/8/8/8/8/8/K7", "e7d8q", false, false),
+            ("8/8/8/8/8/4K3/4p3/5R1k", "e2f1n", true, false),
+        ];
+        for (fen, m, check, mate) in cases.iter() {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            if m.starts_with("e2") {
+                b.color_to_move = Color::BLACK;
+            }
+            assert_eq!(b.make_move_internal_notation(m).is_ok(), true, "{}", m);
+            assert_eq!(b.is_in_check(), *check, "{} {}", fen, m);
+            assert_eq!(b.is_check_mate(), *mate, "{} {}", fen, m);
+        }
+    }
+
+    #[test]
+    fn test_promotion_legality() {
+        let mut b = Board::default();
+        // pawn is pinned by bishop, it can only capture the pinning piece.
+        b.read_fen("6b1/5P2/4K3/8/8/8/8/k7").unwrap();
+        assert_eq!(
+            b.make_move_internal_notation("f7f8q").err().unwrap(),
+            "there will be check after a move"
+        );
+        assert_eq!(
+            b.make_move_internal_notation("f7f8").err().unwrap(),
+            "promotion piece is missing"
+        );
+        assert_eq!(b.make_move_internal_notation("f7g8k").is_err(), true);
+        assert_eq!(b.make_move_internal_notation("f7g8q").is_ok(), true);
+        assert_eq!(b.squares[62].p_type, PieceType::QUEEN);
+
+        let mut b = Board::default();
+        b.read_fen("4k3/1P6/8/8/8/8/8/4K3").unwrap();
+        assert_eq!(
+            b.make_pgn_move("b8").err().unwrap(),
+            "promotion piece is missing"
+        );
+        assert_eq!(b.make_pgn_move("b8=N").is_ok(), true);
+        assert_eq!(b.squares[57].p_type, PieceType::KNIGHT);
+    }
+
+    #[test]
+    fn test_pgn_with_double_check() {
+        // Reti - Tartakower, Vienna 1910, 10. Bg5+ is double check.
+        let pgn = "1. e4 c6 2. d4 d5 3. Nc3 dxe4 4. Nxe4 Nf6 5. Qd3 e5 6. dxe5 Qa5+ 7. Bd2 Qxe5
+8. O-O-O Nxe4 9. Qd8+ Kxd8 10. Bg5+";
+        let mut b = Board::default();
+        assert_eq!(b.read_pgn(pgn, true).is_ok(), true);
+        assert_eq!(b.checkers(Color::BLACK).len(), 2);
+        // queen can capture checking bishop, but it's not enough in double check.
+        assert_eq!(
+            b.validate_move(36, 38).err().unwrap(),
+            "double check, only king can move"
+        );
+        assert_eq!(b.is_check_mate(), false);
+        assert_eq!(b.make_pgn_move("Kc7").is_ok(), true);
+        assert_eq!(b.make_pgn_move("Bd8#").is_ok(), true);
+        assert_eq!(b.is_check_mate(), true);
+
+        // Ed. Lasker - Thomas, London 1912, 12. Nxf6+ is double check.
+        let pgn = "1. d4 e6 2. Nf3 f5 3. Nc3 Nf6 4. Bg5 Be7 5. Bxf6 Bxf6 6. e4 fxe4 7. Nxe4 b6
+8. Ne5 O-O 9. Bd3 Bb7 10. Qh5 Qe7 11. Qxh7+ Kxh7 12. Nxf6+ Kh6 13. Neg4+ Kg5
+14. h4+ Kf4 15. g3+ Kf3 16. Be2+ Kg2 17. Rh2+ Kg1 18. Kd2#";
+        let mut b = Board::default();
+        assert_eq!(b.read_pgn(pgn, true).is_ok(), true);
+        assert_eq!(b.is_check_mate(), true);
+    }
+
     // #[test]
     // fn translate_pgn_move() {
     //     let mut b = Board::default();
@@ -907,25 +1931,440 @@ Kxe6 8. Qg4+ Kd5 9. Nc3+ Kc5 10. Qc4+ Kb6 11. Qb5#";
     //     assert_eq!(b.translate_pgn_move("bxa3").unwrap(), (vec![9], 16));
     // }
 
+    #[test]
+    fn test_to_fen() {
+        let mut b = Board::default();
+        assert_eq!(
+            b.position_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w"
+        );
+        assert_eq!(
+            b.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(b.read_pgn("1. e4 c5 2. Nf3", true).is_ok(), true);
+        assert_eq!(
+            b.position_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b"
+        );
+        assert_eq!(
+            b.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        b.make_pgn_move("d5").unwrap();
+        assert_eq!(
+            b.to_fen(),
+            "rnbqkbnr/pp2pppp/8/2pp4/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq d6 0 3"
+        );
+
+        // read_fen reads to_fen back.
+        for fen in [
+            "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 31",
+            "4k3/8/8/8/8/8/8/4K3 b - - 12 40",
+        ] {
+            b.read_fen(fen).unwrap();
+            assert_eq!(b.to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_castling_fen() {
+        let mut b = Board::default();
+        assert_eq!(b.castling_fen(CastlingNotation::Standard), "KQkq");
+        assert_eq!(b.castling_fen(CastlingNotation::Shredder), "HAha");
+
+        b.read_fen("r3k2r/8/8/8/8/8/8/R3K2R w HAh -").unwrap();
+        assert_eq!(b.castling_fen(CastlingNotation::Standard), "KQk");
+        assert_eq!(b.validate_castle(60, 63), true);
+        assert_eq!(b.validate_castle(60, 56), false);
+
+        b.read_fen("r3k2r/8/8/8/8/8/8/R3K2R b Kq -").unwrap();
+        assert_eq!(b.castling_fen(CastlingNotation::Shredder), "Ha");
+        b.read_fen("r3k2r/8/8/8/8/8/8/R3K2R b - -").unwrap();
+        assert_eq!(b.castling_fen(CastlingNotation::Standard), "-");
+
+        assert_eq!(
+            Board::validate_fen("4k3/8/8/8/8/8/8/4K2R w H -").is_ok(),
+            true
+        );
+        assert_eq!(
+            Board::validate_fen("4k3/8/8/8/8/8/8/4K2R w X -").is_err(),
+            true
+        );
+
+        // Chess960 castles can't be made, so their rights are rejected, the board isn't changed.
+        let unsupported = Err("castling needs the king on e-file and rooks on a and h files");
+        let chess960 = [
+            "rkrbbqnn/pppppppp/8/8/8/8/PPPPPPPP/RKRBBQNN w CAca - 0 1",
+            "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1",
+            "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1",
+            "rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1",
+        ];
+        for fen in chess960 {
+            assert_eq!(b.read_fen(fen), unsupported, "{}", fen);
+        }
+        assert_eq!(b.castling_fen(CastlingNotation::Standard), "-");
+        b.read_fen("rk5r/8/8/8/8/8/8/RK5R w - - 0 1").unwrap();
+    }
+
+    #[test]
+    fn test_castling_rights() {
+        let mut b = Board::default();
+        b.read_fen("r3k2r/8/8/8/8/8/6b1/RN2K2R w KQk -").unwrap();
+        let rights = b.castling_rights();
+        assert_eq!(
+            rights,
+            CastlingRights {
+                white_king: true,
+                white_queen: true,
+                black_king: true,
+                black_queen: false,
+            }
+        );
+        assert_eq!(rights.has(Color::BLACK, Side::Queen), false);
+        // the bishop attacks f1, the knight stands between the king and the rook.
+        assert_eq!(b.can_castle(Color::WHITE, Side::King), false);
+        assert_eq!(b.can_castle(Color::WHITE, Side::Queen), false);
+        assert_eq!(b.can_castle(Color::BLACK, Side::King), true);
+        assert_eq!(b.can_castle(Color::BLACK, Side::Queen), false);
+
+        b.make_move_internal_notation("e1d1").unwrap();
+        assert_eq!(b.castling_rights().has(Color::WHITE, Side::King), false);
+        assert_eq!(
+            Board::default()
+                .castling_rights()
+                .has(Color::WHITE, Side::Queen),
+            true
+        );
+    }
+
+    #[test]
+    fn test_read_fen_fields() {
+        let mut b = Board::default();
+        b.read_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w Kq e6 0 2")
+            .unwrap();
+        assert_eq!(b.color_to_move, Color::WHITE);
+        assert_eq!(b.castling_fen(CastlingNotation::Standard), "Kq");
+        assert_eq!(b.en_passant_square(), Some(44));
+        assert_eq!(
+            (b.halfmove_clock(), b.fullmove_number(), b.ply()),
+            (0, 2, 2)
+        );
+
+        b.read_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 3 10").unwrap();
+        let en_passant = b.legal_moves().into_iter().find(|m| m.to == 43);
+        assert_eq!(en_passant.map(|m| m.flag), Some(TransitionFlag::EnPassant));
+        assert_eq!(
+            (b.halfmove_clock(), b.fullmove_number(), b.ply()),
+            (3, 10, 18)
+        );
+
+        for (fen, error) in [
+            (
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d5 0 1",
+                "invalid en passant square",
+            ),
+            (
+                "4k3/8/8/3pP3/8/8/8/4K3 w - e6 0 1",
+                "en passant square doesn't follow a double push",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+                "halfmove clock must be a number",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+                "fullmove number must be a positive number",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 1 1",
+                "fen has more than six fields",
+            ),
+        ] {
+            assert_eq!(b.read_fen(fen), Err(error), "{}", fen);
+        }
+        // invalid FEN doesn't change the board.
+        assert_eq!(b.position_fen(), "4k3/8/8/3pP3/8/8/8/4K3 w");
+        assert_eq!(b.fullmove_number(), 10);
+
+        let warnings = b
+            .read_fen_lenient("4k3/8/8/8/4Pp2/8/8/4K3 b - e3 0 x")
+            .unwrap();
+        assert_eq!(warnings, vec!["invalid fullmove number field 'x' ignored"]);
+        assert_eq!(b.en_passant_square(), Some(20));
+        assert_eq!(b.fullmove_number(), 1);
+    }
+
+    #[test]
+    fn test_from_pieces() {
+        let white = |p_type| Piece::new(p_type, Color::WHITE);
+        let black = |p_type| Piece::new(p_type, Color::BLACK);
+        let pieces = [
+            (4, white(PieceType::KING)),
+            (7, white(PieceType::ROOK)),
+            (0, white(PieceType::ROOK)),
+            (60, black(PieceType::KING)),
+            (35, black(PieceType::PAWN)),
+            (36, white(PieceType::PAWN)),
+        ];
+        let rights = CastlingRights {
+            white_king: true,
+            ..CastlingRights::default()
+        };
+        let b = Board::from_pieces(pieces, Color::WHITE, rights, Some(43)).unwrap();
+        assert_eq!(b.to_fen(), "4k3/8/8/3pP3/8/8/8/R3K2R w K d6 0 1");
+        assert_eq!(b.king_square(Color::BLACK), Some(60));
+        assert_eq!(b.destinations(36), vec![43, 44]);
+
+        let b = Board::from_pieces(pieces, Color::BLACK, CastlingRights::default(), None);
+        assert_eq!(b.unwrap().to_fen(), "4k3/8/8/3pP3/8/8/8/R3K2R b - - 0 1");
+        let all = CastlingRights {
+            white_king: true,
+            black_king: true,
+            ..CastlingRights::default()
+        };
+        assert_eq!(
+            Board::from_pieces(pieces, Color::WHITE, all, None).err(),
+            Some("castling rights need the king and the rook on their squares")
+        );
+        assert_eq!(
+            Board::from_pieces(pieces, Color::WHITE, rights, Some(44)).err(),
+            Some("en passant square doesn't follow a double push")
+        );
+        assert_eq!(
+            Board::from_pieces(pieces, Color::WHITE, rights, Some(35)).err(),
+            Some("invalid en passant square")
+        );
+        let outside = [(64, white(PieceType::KING))];
+        assert_eq!(
+            Board::from_pieces(outside, Color::WHITE, rights, None).err(),
+            Some("square is out of the board")
+        );
+    }
+
+    #[test]
+    fn test_read_fen_lenient() {
+        let mut b = Board::default();
+        let warnings = b
+            .read_fen_lenient("  rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR   B KQkq –  ")
+            .unwrap();
+        assert_eq!(
+            b.position_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b"
+        );
+        assert_eq!(warnings.len(), 3);
+
+        let warnings = b.read_fen_lenient("4k/8/8/8/8/8/8/4K3").unwrap();
+        assert_eq!(b.position_fen(), "4k3/8/8/8/8/8/8/4K3 w");
+        assert_eq!(
+            warnings,
+            vec![
+                "rank 8 filled with empty squares",
+                "color to move is missing, white assumed"
+            ]
+        );
+        assert_eq!(b.color_to_move, Color::WHITE);
+
+        assert_eq!(b.read_fen_lenient("8/8/8/8/8/8/8/8 w").unwrap().len(), 2);
+        assert_eq!(b.read_fen_lenient("9/8/8/8/8/8/8/8 w").is_err(), true);
+        assert_eq!(b.read_fen_lenient("8/8/8/8 w").is_err(), true);
+        assert_eq!(b.read_fen_lenient("4k3/8/8/8/8/8/8/4K3 x").is_err(), true);
+    }
+
+    #[test]
+    fn test_perft() {
+        let b = Board::default();
+        assert_eq!(b.perft(1), 20);
+        assert_eq!(b.perft(2), 400);
+        assert_eq!(b.perft(3), 8902);
+
+        // castles, promotions and en passant
+        let mut b = Board::default();
+        b.read_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w").unwrap();
+        assert_eq!(b.perft(1), 34);
+        assert_eq!(b.perft_stoppable(2, &AtomicBool::new(true)), None);
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        assert_eq!(Board::default().legal_moves().len(), 20);
+
+        let mut b = Board::default();
+        b.read_fen("r3k3/8/8/8/5p2/8/1p2P3/4K3 w").unwrap();
+        b.make_move_internal_notation("e2e4").unwrap();
+        let moves = b.legal_moves();
+        let flagged = |flag| moves.iter().filter(|m| m.flag == flag).count();
+        assert_eq!(flagged(TransitionFlag::EnPassant), 1);
+        assert_eq!(flagged(TransitionFlag::Promotion), 4);
+        assert_eq!(flagged(TransitionFlag::LongCastle), 1);
+        assert_eq!(moves.len(), 10 + 6 + 2 + 4); // rook, king, f-pawn and b-pawn
+
+        // in check only moves resolving it are legal.
+        b.read_fen("4k3/8/8/8/8/8/8/r3K3 w").unwrap();
+        let mut targets: Vec<Square> = b.legal_moves().iter().map(|m| m.to).collect();
+        targets.sort_unstable();
+        assert_eq!(targets, vec![11, 12, 13]);
+    }
+
+    #[test]
+    fn test_game_state() {
+        let state = |fen: &str| {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            b.game_state()
+        };
+        let white_mates = state("R5k1/5ppp/8/8/8/8/8/6K1 b");
+        assert_eq!(white_mates, GameState::Checkmate(Color::WHITE));
+        assert_eq!((white_mates.is_over(), white_mates.result()), (true, "1-0"));
+        assert_eq!(state("7k/5Q2/6K1/8/8/8/8/8 b"), GameState::Stalemate);
+        assert_eq!(
+            state("4k3/8/8/8/8/8/8/2B1K3 w"),
+            GameState::Draw(DrawReason::InsufficientMaterial)
+        );
+        assert_eq!(
+            state("4k3/8/8/8/8/8/8/R3K3 b - - 100 80"),
+            GameState::Draw(DrawReason::FiftyMoves)
+        );
+        // mate on the last move of the fifty wins.
+        assert_eq!(
+            state("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80"),
+            GameState::Checkmate(Color::WHITE)
+        );
+        let ongoing = state("4k3/8/8/8/8/8/8/R3K3 b - - 99 80");
+        assert_eq!((ongoing.is_over(), ongoing.result()), (false, "*"));
+    }
+
+    #[test]
+    fn test_material() {
+        let counted = |b: &Board| -> i32 { b.squares.iter().map(super::signed_points).sum() };
+        let mut b = Board::default();
+        assert_eq!(b.material(), 0);
+        // captures, promotions with capture, en passant and castles.
+        b.read_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1")
+            .unwrap();
+        assert_eq!(b.material(), 1);
+        for first in b.legal_moves() {
+            let mut after = b.clone();
+            after.make_transition(first);
+            for second in after.legal_moves() {
+                let mut b = after.clone();
+                b.make_transition(second);
+                assert_eq!(b.material(), counted(&b));
+                assert_eq!(b.piece_square(), crate::pst::sum(&b.squares));
+            }
+        }
+        b.make_pgn_move("exd6").unwrap();
+        assert_eq!(b.material(), 2);
+        b.make_pgn_move("O-O").unwrap();
+        b.make_move_internal_notation("b7a8q").unwrap();
+        assert_eq!(b.material(), 2 + 5 + 8);
+
+        // edits rebuild the board from pieces, so cached values follow them.
+        let without_queen = b
+            .squares()
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(i, _)| *i != 56);
+        let edited =
+            Board::from_pieces(without_queen, b.color_to_move, Default::default(), None).unwrap();
+        assert!(edited.piece_at(56).is_none());
+        assert_eq!(edited.material(), 2 + 5 + 8 - 9);
+        assert_eq!(edited.material(), counted(&edited));
+        assert_eq!(edited.piece_square(), crate::pst::sum(edited.squares()));
+    }
+
+    #[test]
+    fn test_last_move() {
+        let mut b = Board::default();
+        assert!(b.last_move().is_none());
+        b.make_move_internal_notation("e2e4").unwrap();
+        assert_eq!(b.last_move().map(|m| (m.from, m.to)), Some((12, 28)));
+
+        // castle is the king's move whichever way it's made.
+        b.read_fen("r3k2r/8/8/8/8/8/8/R3K2R w").unwrap();
+        assert!(b.last_move().is_none());
+        let castle = b
+            .legal_moves()
+            .into_iter()
+            .find(|m| m.from == 4 && m.to == 6);
+        b.make_transition(castle.unwrap());
+        assert_eq!(b.last_move().map(|m| (m.from, m.to)), Some((4, 6)));
+        b.make_pgn_move("O-O-O").unwrap();
+        assert_eq!(b.last_move().map(|m| (m.from, m.to)), Some((60, 58)));
+
+        b.read_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(b.last_move().map(|m| (m.from, m.to)), Some((51, 35)));
+    }
+
+    #[test]
+    fn test_move_counters() {
+        let mut b = Board::default();
+        assert_eq!(
+            (b.halfmove_clock(), b.fullmove_number(), b.ply()),
+            (0, 1, 0)
+        );
+        for (m, counters) in [
+            ("e2e4", (0, 1, 1)),
+            ("g8f6", (1, 2, 2)),
+            ("g1f3", (2, 2, 3)),
+            ("f6e4", (0, 3, 4)), // capture
+            ("f1c4", (1, 3, 5)),
+            ("b8c6", (2, 4, 6)),
+            ("e1g1", (3, 4, 7)), // castle is one move
+        ] {
+            b.make_move_internal_notation(m).unwrap();
+            assert_eq!(
+                (b.halfmove_clock(), b.fullmove_number(), b.ply()),
+                counters,
+                "{}",
+                m
+            );
+        }
+        b.make_pgn_move("d5").unwrap();
+        assert_eq!((b.halfmove_clock(), b.fullmove_number()), (0, 5));
+        b.read_fen("4k3/8/8/8/8/8/8/4K3 b").unwrap();
+        assert_eq!(
+            (b.halfmove_clock(), b.fullmove_number(), b.ply()),
+            (0, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_destinations() {
+        let b = Board::default();
+        assert_eq!(b.destinations(12), vec![20, 28]); // e2
+        assert_eq!(b.destinations(6), vec![21, 23]); // g1
+        assert!(b.destinations(0).is_empty()); // blocked rook
+        assert!(b.destinations(30).is_empty()); // empty square
+        assert!(b.destinations(52).is_empty()); // black doesn't have the move
+
+        // castles go to the king's target squares, four promotions are one destination.
+        let mut b = Board::default();
+        b.read_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w").unwrap();
+        assert_eq!(b.destinations(4), vec![2, 3, 5, 6, 11, 12, 13]);
+        assert_eq!(b.destinations(49), vec![56, 57]);
+    }
+
     #[test]
     fn test_validate_castle() {
         let mut b = Board::default();
-        b.read_fen("8/8/8/8/8/8/8/R3K3");
+        b.read_fen("8/8/8/8/8/8/8/R3K3").unwrap();
         assert_eq!(b.validate_castle(4, 0), true);
-        b.read_fen("8/8/8/8/8/8/8/4K2R");
+        b.read_fen("8/8/8/8/8/8/8/4K2R").unwrap();
         assert_eq!(b.validate_castle(4, 7), true);
-        b.read_fen("r3k3/8/8/8/8/8/8");
+        b.read_fen("r3k3/8/8/8/8/8/8/8").unwrap();
         assert_eq!(b.validate_castle(60, 56), true);
-        b.read_fen("4k2r/8/8/8/8/8/8/8");
+        b.read_fen("4k2r/8/8/8/8/8/8/8").unwrap();
         assert_eq!(b.validate_castle(60, 63), true);
 
-        b.read_fen("8/8/8/8/8/8/8/R2PK3");
+        b.read_fen("8/8/8/8/8/8/8/R2PK3").unwrap();
         assert_eq!(b.validate_castle(4, 0), false);
-        b.read_fen("8/8/8/8/8/8/8/4K1PR");
+        b.read_fen("8/8/8/8/8/8/8/4K1PR").unwrap();
         assert_eq!(b.validate_castle(4, 7), false);
-        b.read_fen("r2pk3/8/8/8/8/8/8");
+        b.read_fen("r2pk3/8/8/8/8/8/8/8").unwrap();
         assert_eq!(b.validate_castle(60, 56), false);
-        b.read_fen("4kp1r/8/8/8/8/8/8/8");
+        b.read_fen("4kp1r/8/8/8/8/8/8/8").unwrap();
         assert_eq!(b.validate_castle(60, 63), false);
     }
 }
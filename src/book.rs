@@ -0,0 +1,115 @@
+use crate::board::{Board, Transition};
+use crate::notation::{parse_san, to_internal};
+use crate::pgn::split_games;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// BOOK_PLIES is the number of moves of every game kept in the book, deeper moves are not opening
+// theory any more.
+pub const BOOK_PLIES: usize = 24;
+
+// OpeningBook knows moves played in the openings of PGN games and how many times. Positions are
+// keyed by pieces placement and color to move, as in ExperienceBook, so transpositions meet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpeningBook {
+    positions: HashMap<String, HashMap<String, u32>>,
+}
+
+impl OpeningBook {
+    // from_pgn reads the first plies of every game. Games are read up to their first illegal move.
+    pub fn from_pgn(content: &str, plies: usize) -> Self {
+        let mut book = OpeningBook::default();
+        for game in split_games(content) {
+            let mut board = match game.start_position() {
+                Ok(board) => board,
+                Err(_) => continue,
+            };
+            for san in game.moves().iter().take(plies) {
+                let t = match parse_san(&board, san) {
+                    Ok(t) => t,
+                    Err(_) => break,
+                };
+                *book
+                    .positions
+                    .entry(board.position_fen())
+                    .or_default()
+                    .entry(to_internal(&t))
+                    .or_default() += 1;
+                board.make_transition(t);
+            }
+        }
+        book
+    }
+
+    // load reads the book from PGN file, BOOK_PLIES of every game.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(OpeningBook::from_pgn(
+            &fs::read_to_string(path)?,
+            BOOK_PLIES,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // moves returns legal moves of the position found in the book with the number of games they
+    // were played in, the most played first.
+    pub fn moves(&self, board: &Board) -> Vec<(Transition, u32)> {
+        let moves = match self.positions.get(&board.position_fen()) {
+            Some(moves) => moves,
+            None => return Vec::new(),
+        };
+        let mut known: Vec<(Transition, u32)> = board
+            .generate_transitions()
+            .into_iter()
+            .filter_map(|t| moves.get(&to_internal(&t)).map(|n| (t, *n)))
+            .collect();
+        known.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+        known
+    }
+
+    // best_move returns the most played move of the position.
+    pub fn best_move(&self, board: &Board) -> Option<Transition> {
+        self.moves(board).first().map(|(t, _)| *t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::book::OpeningBook;
+    use crate::notation::to_san;
+
+    #[test]
+    fn test_opening_book() {
+        let pgn = "1. e4 c5 2. Nf3 *\n\n1. e4 e5 *\n\n1. d4 d5 2. Nf3 Nf6 *\n\n\
+                   1. e4 c5 2. Nc3 *\n\n1. Nf3 d5 2. d4 e6 *\n\n1. e4 f6 2. Qxf7 *";
+        let book = OpeningBook::from_pgn(pgn, 4);
+        let mut b = Board::default();
+        let moves = book.moves(&b);
+        assert_eq!(moves.len(), 3);
+        assert_eq!((to_san(&b, &moves[0].0), moves[0].1), ("e4".to_string(), 4));
+
+        // 1. d4 d5 2. Nf3 and 1. Nf3 d5 2. d4 meet in the same position.
+        b.read_fen("rnbqkbnr/ppp1pppp/8/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 1 2")
+            .unwrap();
+        let sans: Vec<_> = book.moves(&b).iter().map(|(t, _)| to_san(&b, t)).collect();
+        assert_eq!(sans.len(), 2);
+        assert!(sans.contains(&"Nf6".to_string()) && sans.contains(&"e6".to_string()));
+
+        // the illegal move ends the game, moves before it are kept.
+        b.read_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+            .unwrap();
+        assert_eq!(book.moves(&b).len(), 3);
+        // moves after the first plies are not in the book.
+        assert!(OpeningBook::from_pgn(pgn, 1).best_move(&b).is_none());
+        assert!(OpeningBook::from_pgn("", 10).is_empty());
+    }
+}
@@ -0,0 +1,303 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// VARIANTS are keys of Lichess variants the engine plays: standard chess, also from a custom
+// position. Chess960 isn't, castles from its start positions aren't modelled.
+const VARIANTS: [&str; 2] = ["standard", "fromPosition"];
+
+// SPEEDS are speeds of Lichess time controls, from the fastest.
+const SPEEDS: [&str; 6] = [
+    "ultraBullet",
+    "bullet",
+    "blitz",
+    "rapid",
+    "classical",
+    "correspondence",
+];
+
+// Challenge is a challenge of Lichess event stream, only fields the policy looks at are read.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Challenge {
+    pub id: String,
+    #[serde(default)]
+    pub challenger: Option<Challenger>, // None for anonymous challengers
+    pub variant: Variant,
+    pub rated: bool,
+    pub speed: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Challenger {
+    pub name: String,
+    #[serde(default)]
+    pub rating: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Variant {
+    pub key: String,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    challenge: Option<Challenge>,
+}
+
+// read_event reads a line of Lichess event stream, returns the challenge of 'challenge' events.
+// Other events and empty lines, which keep the stream alive, are None.
+pub fn read_event(line: &str) -> Result<Option<Challenge>, String> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    let event: Event = serde_json::from_str(line).map_err(|e| format!("invalid event: {}", e))?;
+    match (event.kind.as_str(), event.challenge) {
+        ("challenge", Some(challenge)) => Ok(Some(challenge)),
+        ("challenge", None) => Err("challenge event without challenge".to_string()),
+        _ => Ok(None),
+    }
+}
+
+// DeclineReason is a reason of declining a challenge, as sent to Lichess.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeclineReason {
+    Generic,
+    TooFast,
+    TooSlow,
+    TimeControl,
+    Rated,  // a rated challenge is wanted instead
+    Casual, // a casual challenge is wanted instead
+    Standard,
+    Variant,
+}
+
+impl DeclineReason {
+    // key returns the reason as accepted by Lichess API, e.g. 'tooFast'.
+    pub fn key(&self) -> &'static str {
+        match self {
+            DeclineReason::Generic => "generic",
+            DeclineReason::TooFast => "tooFast",
+            DeclineReason::TooSlow => "tooSlow",
+            DeclineReason::TimeControl => "timeControl",
+            DeclineReason::Rated => "rated",
+            DeclineReason::Casual => "casual",
+            DeclineReason::Standard => "standard",
+            DeclineReason::Variant => "variant",
+        }
+    }
+}
+
+// ChallengePolicy decides which challenges of Lichess a bot accepts. It's kept in a text file,
+// every line is a name and values:
+//
+//   variants standard            keys of Lichess variants, standard or fromPosition
+//   speeds bullet blitz          also ultraBullet, rapid, classical and correspondence
+//   modes rated casual
+//   min-rating 1200              rating of the challenger in the speed of the challenge
+//   max-rating 2500
+//   banned alice bob             challengers always declined, names are case insensitive
+//
+// Missing lines keep defaults: standard chess of every speed but correspondence, rated and casual,
+// any rating. Challengers without rating are declined when ratings are limited. '#' starts a
+// comment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChallengePolicy {
+    pub variants: Vec<String>,
+    pub speeds: Vec<String>,
+    pub rated: bool,
+    pub casual: bool,
+    pub min_rating: Option<u32>,
+    pub max_rating: Option<u32>,
+    pub banned: Vec<String>,
+}
+
+impl Default for ChallengePolicy {
+    fn default() -> Self {
+        ChallengePolicy {
+            variants: vec!["standard".to_string()],
+            speeds: SPEEDS[..5].iter().map(|s| s.to_string()).collect(),
+            rated: true,
+            casual: true,
+            min_rating: None,
+            max_rating: None,
+            banned: Vec::new(),
+        }
+    }
+}
+
+impl ChallengePolicy {
+    // load reads the policy from file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?
+            .parse()
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // check accepts the challenge or returns the reason to decline it.
+    pub fn check(&self, challenge: &Challenge) -> Result<(), DeclineReason> {
+        let name = challenge.challenger.as_ref().map(|c| c.name.as_str());
+        if name.is_some_and(|n| self.banned.iter().any(|b| b.eq_ignore_ascii_case(n))) {
+            return Err(DeclineReason::Generic);
+        }
+        if !self.variants.contains(&challenge.variant.key) {
+            return Err(match self.variants.as_slice() {
+                [only] if only == "standard" => DeclineReason::Standard,
+                _ => DeclineReason::Variant,
+            });
+        }
+        if !self.speeds.contains(&challenge.speed) {
+            return Err(self.speed_reason(&challenge.speed));
+        }
+        if challenge.rated && !self.rated {
+            return Err(DeclineReason::Casual);
+        }
+        if !challenge.rated && !self.casual {
+            return Err(DeclineReason::Rated);
+        }
+        if self.min_rating.is_some() || self.max_rating.is_some() {
+            let rating = challenge.challenger.as_ref().and_then(|c| c.rating);
+            let allowed = rating.is_some_and(|r| {
+                self.min_rating.is_none_or(|min| r >= min)
+                    && self.max_rating.is_none_or(|max| r <= max)
+            });
+            if !allowed {
+                return Err(DeclineReason::Generic);
+            }
+        }
+        Ok(())
+    }
+
+    // speed_reason tells if not allowed speed is faster or slower than all allowed ones.
+    fn speed_reason(&self, speed: &str) -> DeclineReason {
+        let index = |s: &str| SPEEDS.iter().position(|known| *known == s);
+        let allowed: Vec<usize> = self.speeds.iter().filter_map(|s| index(s)).collect();
+        match (index(speed), allowed.iter().min(), allowed.iter().max()) {
+            (Some(i), Some(fastest), _) if i < *fastest => DeclineReason::TooFast,
+            (Some(i), _, Some(slowest)) if i > *slowest => DeclineReason::TooSlow,
+            _ => DeclineReason::TimeControl,
+        }
+    }
+}
+
+impl FromStr for ChallengePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = ChallengePolicy::default();
+        for (n, line) in s.lines().enumerate() {
+            let error = |e: &str| format!("line {}: {}", n + 1, e);
+            let words: Vec<&str> = line
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect();
+            let rating = |value: &str| {
+                value
+                    .parse()
+                    .map_err(|_| error(&format!("invalid rating {}", value)))
+            };
+            let list = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+            match words.as_slice() {
+                [] => {}
+                ["variants", values @ ..] => {
+                    if let Some(variant) = values.iter().find(|v| !VARIANTS.contains(v)) {
+                        return Err(error(&format!("unsupported variant {}", variant)));
+                    }
+                    policy.variants = list(values);
+                }
+                ["speeds", values @ ..] => {
+                    if let Some(speed) = values.iter().find(|v| !SPEEDS.contains(v)) {
+                        return Err(error(&format!("unknown speed {}", speed)));
+                    }
+                    policy.speeds = list(values);
+                }
+                ["modes", values @ ..] => {
+                    if let Some(mode) = values.iter().find(|v| **v != "rated" && **v != "casual") {
+                        return Err(error(&format!("unknown mode {}", mode)));
+                    }
+                    policy.rated = values.contains(&"rated");
+                    policy.casual = values.contains(&"casual");
+                }
+                ["min-rating", value] => policy.min_rating = Some(rating(value)?),
+                ["max-rating", value] => policy.max_rating = Some(rating(value)?),
+                ["banned", values @ ..] => policy.banned.extend(list(values)),
+                [name, ..] => return Err(error(&format!("unknown setting {}", name))),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::challenge::{read_event, ChallengePolicy, DeclineReason};
+
+    #[test]
+    fn test_challenge_policy() {
+        let policy: ChallengePolicy = "# casual blitz and rapid only\n\
+                                       variants standard fromPosition\n\
+                                       speeds blitz rapid\n\
+                                       modes casual\n\
+                                       min-rating 1500\n\
+                                       banned Troll"
+            .parse()
+            .unwrap();
+        let event = |name: &str, rating: u32, variant: &str, speed: &str, rated: bool| {
+            let line = format!(
+                r#"{{"type":"challenge","challenge":{{"id":"abcd1234","challenger":{{"name":"{}","rating":{},"title":null}},"variant":{{"key":"{}","name":"x"}},"rated":{},"speed":"{}","timeControl":{{"type":"clock","limit":300,"increment":3}}}}}}"#,
+                name, rating, variant, rated, speed
+            );
+            read_event(&line).unwrap().unwrap()
+        };
+        let check = |challenge| policy.check(&challenge).map_err(|r| r.key());
+        assert_eq!(
+            check(event("alice", 1800, "fromPosition", "blitz", false)),
+            Ok(())
+        );
+        assert_eq!(
+            check(event("troll", 1800, "standard", "blitz", false)),
+            Err("generic")
+        );
+        assert_eq!(
+            check(event("alice", 1800, "atomic", "blitz", false)),
+            Err("variant")
+        );
+        assert_eq!(
+            check(event("alice", 1800, "standard", "bullet", false)),
+            Err("tooFast")
+        );
+        assert_eq!(
+            check(event("alice", 1800, "standard", "correspondence", false)),
+            Err("tooSlow")
+        );
+        assert_eq!(
+            check(event("alice", 1800, "standard", "rapid", true)),
+            Err("casual")
+        );
+        assert_eq!(
+            check(event("alice", 1200, "standard", "rapid", false)),
+            Err("generic")
+        );
+
+        let standard = ChallengePolicy::default();
+        let atomic = event("alice", 1800, "atomic", "blitz", true);
+        assert_eq!(standard.check(&atomic), Err(DeclineReason::Standard));
+        assert_eq!(read_event(r#"{"type":"gameStart","game":{}}"#), Ok(None));
+        assert_eq!(read_event(""), Ok(None));
+        assert_eq!(
+            "variants standard chess960".parse::<ChallengePolicy>(),
+            Err("line 1: unsupported variant chess960".to_string())
+        );
+        assert_eq!(
+            "speeds lightning".parse::<ChallengePolicy>(),
+            Err("line 1: unknown speed lightning".to_string())
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::board::{Board, CastlingNotation};
+use crate::notation::to_internal;
+use crate::search::{Score, SearchLine};
+use crate::tt::TranspositionTable;
+use crate::zobrist::hash;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+const HEADER: &str = "chust checkpoint 1";
+
+// Checkpoint is a saved state of alpha-beta search: lines of the last completed depth and the
+// transposition table, so a long analysis of a position can be stopped and resumed later.
+//
+// File starts with text header, one field per line, followed by entries of the table:
+//
+//   chust checkpoint 1
+//   fen r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq
+//   hash 5f2c0b8e3a1d7c44
+//   depth 12
+//   nodes 183274651
+//   line cp 35 g1f3 b8c6
+//   entries 65536
+#[derive(Debug)]
+pub struct Checkpoint {
+    pub hash: u64, // Zobrist hash of the analyzed position
+    pub depth: usize,
+    pub nodes: u64,
+    pub lines: Vec<SearchLine>,
+    pub tt: TranspositionTable,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Checkpoint {
+    // save writes checkpoint to a temporary file first and renames it, so the previous checkpoint
+    // survives a crash in the middle of writing.
+    pub fn save<P: AsRef<Path>>(&self, path: P, board: &Board) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let mut out = io::BufWriter::new(fs::File::create(&temp)?);
+        writeln!(out, "{}", HEADER)?;
+        writeln!(
+            out,
+            "fen {} {}",
+            board.position_fen(),
+            board.castling_fen(CastlingNotation::Standard)
+        )?;
+        writeln!(out, "hash {:016x}", self.hash)?;
+        writeln!(out, "depth {}", self.depth)?;
+        writeln!(out, "nodes {}", self.nodes)?;
+        for line in &self.lines {
+            let score = match line.score {
+                Score::Centipawns(cp) => format!("cp {}", cp),
+                Score::Mate(moves) => format!("mate {}", moves),
+            };
+            let moves: Vec<String> = line.pv.iter().map(to_internal).collect();
+            writeln!(out, "line {} {}", score, moves.join(" "))?;
+        }
+        writeln!(out, "entries {}", self.tt.len())?;
+        self.tt.write(&mut out)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp, path)
+    }
+
+    // load reads checkpoint of the position from file, checkpoint of another position or without
+    // hash of its position is an error.
+    pub fn load<P: AsRef<Path>>(path: P, board: &Board) -> io::Result<Self> {
+        let mut input = BufReader::new(fs::File::open(path)?);
+        let mut checkpoint = Checkpoint {
+            hash: 0,
+            depth: 0,
+            nodes: 0,
+            lines: Vec::new(),
+            tt: TranspositionTable::new(1),
+        };
+        let mut has_hash = false;
+        let mut header = String::new();
+        input.read_line(&mut header)?;
+        if header.trim_end() != HEADER {
+            return Err(invalid("not a checkpoint file".to_string()));
+        }
+        loop {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(invalid("checkpoint has no transposition table".to_string()));
+            }
+            let (field, value) = line.trim_end().split_once(' ').unwrap_or((&line, ""));
+            let number = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| invalid(format!("invalid {} '{}'", field, value)))
+            };
+            match field {
+                "fen" => {}
+                "hash" => {
+                    checkpoint.hash = u64::from_str_radix(value, 16)
+                        .map_err(|_| invalid(format!("invalid hash '{}'", value)))?;
+                    if checkpoint.hash != hash(board) {
+                        return Err(invalid("checkpoint is of another position".to_string()));
+                    }
+                    has_hash = true;
+                }
+                "depth" => checkpoint.depth = number(value)? as usize,
+                "nodes" => checkpoint.nodes = number(value)?,
+                "line" => checkpoint.lines.push(read_line(board, value)?),
+                "entries" if !has_hash => {
+                    return Err(invalid("checkpoint has no position hash".to_string()))
+                }
+                "entries" => {
+                    checkpoint.tt = TranspositionTable::read(&mut input, number(value)? as usize)?;
+                    return Ok(checkpoint);
+                }
+                _ => return Err(invalid(format!("unknown field '{}'", field))),
+            }
+        }
+    }
+}
+
+// read_line parses score and moves of a line, e.g. 'cp 35 g1f3 b8c6'.
+fn read_line(board: &Board, text: &str) -> io::Result<SearchLine> {
+    let error = || invalid(format!("invalid line '{}'", text));
+    let mut words = text.split_whitespace();
+    let value = words
+        .nth(1)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(error)?;
+    let score = match text.split_whitespace().next() {
+        Some("cp") => Score::Centipawns(value),
+        Some("mate") => Score::Mate(value),
+        _ => return Err(error()),
+    };
+    let mut b = board.clone();
+    let mut pv = Vec::new();
+    for m in words {
+        let t = b
+            .generate_transitions()
+            .into_iter()
+            .find(|t| to_internal(t) == m)
+            .ok_or_else(error)?;
+        b.make_transition(t);
+        pv.push(t);
+    }
+    if pv.is_empty() {
+        return Err(error());
+    }
+    Ok(SearchLine { score, pv })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::checkpoint::Checkpoint;
+    use crate::evaluation::SimpleEvaluator;
+    use crate::notation::to_san;
+    use crate::search::Search;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_save_and_resume() {
+        let path = env::temp_dir().join(format!("chust-checkpoint-{}", std::process::id()));
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/3q4/8/8/3R4/4K3 w").unwrap();
+        let e = SimpleEvaluator {};
+
+        let mut search = Search::new(&e);
+        search.checkpoint = Some(path.clone());
+        let lines = search.analyze(&b, 2, 2);
+        assert!(search.checkpoint_error.is_none());
+
+        let checkpoint = Checkpoint::load(&path, &b).unwrap();
+        assert_eq!(checkpoint.depth, 2);
+        assert_eq!(checkpoint.nodes, search.nodes);
+        assert_eq!(checkpoint.tt, search.tt);
+        assert_eq!(checkpoint.lines.len(), 2);
+        assert_eq!(
+            to_san(&b, &checkpoint.lines[0].pv[0]),
+            to_san(&b, &lines[0].pv[0])
+        );
+
+        let mut other = Board::default();
+        other.read_fen("4k3/8/8/3q4/8/8/3R4/4K3 b").unwrap();
+        assert!(Checkpoint::load(&path, &other).is_err());
+
+        // the position can't be checked without hash.
+        let saved = fs::read(&path).unwrap();
+        let start = saved.windows(6).position(|w| w == b"\nhash ").unwrap() + 1;
+        let end = start + saved[start..].iter().position(|c| *c == b'\n').unwrap() + 1;
+        fs::write(&path, [&saved[..start], &saved[end..]].concat()).unwrap();
+        let error = Checkpoint::load(&path, &b).unwrap_err();
+        assert_eq!(error.to_string(), "checkpoint has no position hash");
+        fs::write(&path, saved).unwrap();
+
+        let mut depths = Vec::new();
+        let mut resumed = Search::new(&e);
+        let lines = resumed.resume(&b, checkpoint, 3, 2, |d, _| depths.push(d));
+        assert_eq!(depths, vec![2, 3]);
+        assert_eq!(to_san(&b, &lines[0].pv[0]), "Rxd5");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+use crate::board::{Board, Transition};
+use crate::evaluation::Evaluator;
+use crate::notation::{file_letter, square_name};
+use crate::piece::{Color, PieceType};
+use std::fmt::{Display, Formatter};
+
+// MIN_CHANGE is the smallest change of an evaluation term, in pawns, worth explaining.
+pub const MIN_CHANGE: f32 = 0.3;
+
+// Explanation is a reason why a move is good or bad, with change of evaluation it causes from the
+// point of view of the player making the move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    pub term: &'static str,
+    pub change: f32,
+    pub reason: String,
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:+.1})", self.reason, self.change)
+    }
+}
+
+// explain compares evaluation terms before and after the move and describes the ones that changed
+// by at least MIN_CHANGE, biggest changes first. Terms unknown to the coach get a generic reason,
+// so it works with any evaluator breakdown. Weakening of king's pawn shield is reported even
+// when evaluator doesn't score it.
+pub fn explain(evaluator: &dyn Evaluator, board: &Board, t: &Transition) -> Vec<Explanation> {
+    let mut after = board.clone();
+    after.make_transition(*t);
+    let mover = board.color_to_move;
+    let sign = if mover == Color::BLACK { -1.0 } else { 1.0 };
+
+    let terms_after = evaluator.breakdown(&after);
+    let mut explanations: Vec<Explanation> = evaluator
+        .breakdown(board)
+        .iter()
+        .filter_map(|before| {
+            let value = terms_after.iter().find(|a| a.name == before.name)?.value;
+            let change = sign * (value - before.value);
+            if change.abs() < MIN_CHANGE {
+                return None;
+            }
+            Some(Explanation {
+                term: before.name,
+                change,
+                reason: reason(before.name, change, board, &after, mover),
+            })
+        })
+        .collect();
+
+    let shield = |b: &Board| pawn_shield(b, mover);
+    if let (Some(before), Some(now)) = (shield(board), shield(&after)) {
+        if now < before {
+            explanations.push(Explanation {
+                term: "king safety",
+                change: 0.0,
+                reason: "this weakens your king's pawn shield".to_string(),
+            });
+        }
+    }
+    explanations.sort_by(|a, b| b.change.abs().total_cmp(&a.change.abs()));
+    explanations
+}
+
+// reason describes change of a single term, in words of the player making the move.
+fn reason(term: &str, change: f32, before: &Board, after: &Board, mover: Color) -> String {
+    let good = change > 0.0;
+    match term {
+        "material" if good => "this wins material".to_string(),
+        "material" => "this gives away material".to_string(),
+        "isolated pawns" => {
+            let (own, theirs) = (
+                new_squares(before, after, mover, isolated_pawns),
+                new_squares(before, after, mover.opposite(), isolated_pawns),
+            );
+            match (good, own.is_empty(), theirs.is_empty()) {
+                (false, false, _) => format!("this creates {}", isolated(&own)),
+                (true, _, false) => format!("this leaves your opponent {}", isolated(&theirs)),
+                (true, _, _) => "this fixes your isolated pawn".to_string(),
+                (false, _, _) => "this helps your opponent's isolated pawn".to_string(),
+            }
+        }
+        "doubled pawns" => {
+            let doubled = |color| new_squares(before, after, color, doubled_pawns);
+            let (color, whose) = if good {
+                (mover.opposite(), "your opponent's")
+            } else {
+                (mover, "your")
+            };
+            match doubled(color).first() {
+                Some(square) => format!(
+                    "this doubles {} pawns on the {}-file",
+                    whose,
+                    file_letter(square % 8)
+                ),
+                None if good => "this undoubles your pawns".to_string(),
+                None => "this undoubles your opponent's pawns".to_string(),
+            }
+        }
+        "blocked pawns" if good => "this unblocks your pawns".to_string(),
+        "blocked pawns" => "this blocks your own pawns".to_string(),
+        "mobility" if good => "this activates your pieces".to_string(),
+        "mobility" => "this makes your pieces passive".to_string(),
+        _ if good => format!("this improves {}", term),
+        _ => format!("this worsens {}", term),
+    }
+}
+
+// isolated describes isolated pawns on given squares e.g. 'an isolated pawn on d4'.
+fn isolated(squares: &[usize]) -> String {
+    let names: Vec<String> = squares.iter().map(|s| square_name(*s)).collect();
+    match names.as_slice() {
+        [name] => format!("an isolated pawn on {}", name),
+        [rest @ .., last] => format!("isolated pawns on {} and {}", rest.join(", "), last),
+        [] => "no isolated pawns".to_string(),
+    }
+}
+
+// new_squares returns squares found by given function after the move but not before it.
+fn new_squares(
+    before: &Board,
+    after: &Board,
+    color: Color,
+    find: fn(&Board, Color) -> Vec<usize>,
+) -> Vec<usize> {
+    let old = find(before, color);
+    find(after, color)
+        .into_iter()
+        .filter(|s| !old.contains(s))
+        .collect()
+}
+
+fn pawn_files(board: &Board, color: Color) -> Vec<usize> {
+    (0..64)
+        .filter(|&i| {
+            board.piece_at(i).p_type == PieceType::PAWN && board.piece_at(i).color == color
+        })
+        .map(|i| i % 8)
+        .collect()
+}
+
+// isolated_pawns returns squares of pawns without pawns of the same color on adjacent files.
+fn isolated_pawns(board: &Board, color: Color) -> Vec<usize> {
+    let files = pawn_files(board, color);
+    (0..64)
+        .filter(|&i| {
+            let p = board.piece_at(i);
+            let file = i % 8;
+            p.p_type == PieceType::PAWN
+                && p.color == color
+                && !files.iter().any(|&f| f + 1 == file || f == file + 1)
+        })
+        .collect()
+}
+
+// doubled_pawns returns squares of pawns that share file with another pawn of the same color.
+fn doubled_pawns(board: &Board, color: Color) -> Vec<usize> {
+    let files = pawn_files(board, color);
+    (0..64)
+        .filter(|&i| {
+            let p = board.piece_at(i);
+            p.p_type == PieceType::PAWN
+                && p.color == color
+                && files.iter().filter(|&&f| f == i % 8).count() > 1
+        })
+        .collect()
+}
+
+// pawn_shield counts pawns on three squares in front of the king standing on its first rank,
+// None when king has left it and there is no shield to speak of.
+fn pawn_shield(board: &Board, color: Color) -> Option<usize> {
+    let (first_rank, forward) = if color == Color::WHITE {
+        (0, 1)
+    } else {
+        (7, -1)
+    };
+    let king = (0..64).find(|&i| {
+        board.piece_at(i).p_type == PieceType::KING && board.piece_at(i).color == color
+    })?;
+    if king / 8 != first_rank {
+        return None;
+    }
+    let file = (king % 8) as i32;
+    let rank = first_rank as i32 + forward;
+    let shield = (file - 1..=file + 1)
+        .filter(|f| (0..8).contains(f))
+        .map(|f| board.piece_at((rank * 8 + f) as usize))
+        .filter(|p| p.p_type == PieceType::PAWN && p.color == color)
+        .count();
+    Some(shield)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::coach::explain;
+    use crate::evaluation::MaterialMobilityEvaluator;
+    use crate::notation::parse_san;
+
+    #[test]
+    fn test_explain() {
+        let evaluator = MaterialMobilityEvaluator {};
+        let reasons = |fen: &str, m: &str| -> Vec<String> {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            let t = parse_san(&b, m).unwrap();
+            explain(&evaluator, &b, &t)
+                .iter()
+                .map(|e| e.reason.clone())
+                .collect()
+        };
+
+        let r = reasons("4k3/8/8/3p4/4P3/8/8/4K3 w", "exd5");
+        assert_eq!(r[0], "this wins material");
+        let r = reasons("6k1/5ppp/8/8/8/8/5PPP/6K1 w", "g4");
+        assert!(r.contains(&"this weakens your king's pawn shield".to_string()));
+        let r = reasons("4k3/8/8/8/8/2n5/PPP5/4K3 w", "bxc3");
+        assert_eq!(r[0], "this wins material");
+        assert!(r.contains(&"this doubles your pawns on the c-file".to_string()));
+        let r = reasons("4k3/8/2p5/3p4/4P3/8/8/4K3 b", "dxe4");
+        assert!(r.contains(&"this creates isolated pawns on e4 and c6".to_string()));
+    }
+}
@@ -0,0 +1,500 @@
+use crate::game::{Game, PositionIndex};
+use crate::notation::{parse_san_mode, ParseMode};
+use crate::pgn::{PgnGame, ReplayOptions};
+use crate::progress::Progress;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+// process_games runs f for every game in parallel and writes its output. With 'ordered' output
+// is written in the same order as games in database, otherwise as soon as the game is processed.
+// Empty output is not written. Progress is advanced after every processed game.
+pub fn process_games<W, F>(
+    games: &[PgnGame],
+    ordered: bool,
+    progress: &dyn Progress,
+    w: &mut W,
+    f: F,
+) -> io::Result<()>
+where
+    W: Write,
+    F: Fn(usize, &PgnGame) -> String + Sync,
+{
+    progress.start("games", games.len() as u64);
+    let f = |i, g: &PgnGame| {
+        let output = f(i, g);
+        progress.advance(1);
+        output
+    };
+    let result = write_outputs(games, ordered, w, f);
+    progress.finish();
+    result
+}
+
+fn write_outputs<W, F>(games: &[PgnGame], ordered: bool, w: &mut W, f: F) -> io::Result<()>
+where
+    W: Write,
+    F: Fn(usize, &PgnGame) -> String + Sync,
+{
+    if ordered {
+        let outputs: Vec<String> = games.par_iter().enumerate().map(|(i, g)| f(i, g)).collect();
+        for output in outputs.iter().filter(|o| !o.is_empty()) {
+            w.write_all(output.as_bytes())?;
+        }
+        return Ok(());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|s| {
+        s.spawn(|| {
+            games
+                .par_iter()
+                .enumerate()
+                .for_each_with(sender, |sender, (i, g)| {
+                    // receiver is gone only when writing failed, the error is returned below.
+                    let _ = sender.send(f(i, g));
+                });
+        });
+        for output in receiver.iter().filter(|o| !o.is_empty()) {
+            w.write_all(output.as_bytes())?;
+        }
+        Ok(())
+    })
+}
+
+// replay_report replays the game with given options and describes the result e.g.
+// "game 3: ok, 81 plies". Warnings found by cross-checking moves follow on separate lines.
+pub fn replay_report(index: usize, game: &PgnGame, options: &ReplayOptions) -> String {
+    let mut plies = 0;
+    match game.replay_with(options, |_| plies += 1) {
+        Ok(replay) => {
+            let mut report = format!("game {}: ok, {} plies\n", index + 1, plies);
+            for warning in replay.warnings {
+                report.push_str(&format!("warning: {}\n", warning.with_game(index + 1)));
+            }
+            report
+        }
+        Err(e) => format!("{}\n", e.with_game(index + 1)),
+    }
+}
+
+// index_positions indexes positions of games of a reference database, games are indexed up to
+// the first illegal move.
+pub fn index_positions(games: &[PgnGame]) -> PositionIndex {
+    let mut index = PositionIndex::default();
+    for game in games {
+        let mut played = match game.start_position() {
+            Ok(start) => Game::new(start),
+            Err(_) => continue,
+        };
+        for token in game.moves() {
+            match parse_san_mode(played.board(), &token, ParseMode::Normal) {
+                Ok(t) if played.make_move(t).is_ok() => {}
+                _ => break,
+            }
+        }
+        index.add(&played);
+    }
+    index
+}
+
+// novelty_report names the first move of the game that leaves positions of the reference database
+// e.g. "game 3: novelty at ply 9, Bc4, after a position from 12 games".
+pub fn novelty_report(index: usize, game: &PgnGame, positions: &PositionIndex) -> String {
+    let played = match game.to_game() {
+        Ok(played) => played,
+        Err(e) => return format!("{}\n", e.with_game(index + 1)),
+    };
+    match played.find_novelty(positions) {
+        Some(ply) => format!(
+            "game {}: novelty at ply {}, {}, after a position from {} games\n",
+            index + 1,
+            ply,
+            game.moves()[ply - 1],
+            positions.games(played.hash_history()[ply - 1])
+        ),
+        None => format!("game {}: no novelty\n", index + 1),
+    }
+}
+
+// positions returns FEN of every position that occurred in the game, one per line.
+pub fn positions(game: &PgnGame) -> String {
+    let mut fens = Vec::new();
+    if let Ok(board) = game.start_position() {
+        fens.push(board.to_fen());
+    }
+    // positions are written up to the first illegal move.
+    let _ = game.replay(|board| fens.push(board.to_fen()));
+    fens.iter().map(|f| format!("{}\n", f)).collect()
+}
+
+// GameFilter selects games by tag values and number of plies.
+#[derive(Default)]
+pub struct GameFilter {
+    pub tags: Vec<(String, String)>, // tag value has to contain given text
+    pub min_plies: Option<usize>,
+    pub max_plies: Option<usize>,
+}
+
+impl GameFilter {
+    // parse_tag parses tag condition given as 'Name=Value'.
+    pub fn parse_tag(condition: &str) -> Result<(String, String), &'static str> {
+        match condition.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+            _ => Err("tag condition must look like Name=Value"),
+        }
+    }
+
+    pub fn matches(&self, game: &PgnGame) -> bool {
+        let tags_match = self.tags.iter().all(|(name, value)| {
+            game.tag(name)
+                .map(|v| v.contains(value.as_str()))
+                .unwrap_or(false)
+        });
+        if !tags_match {
+            return false;
+        }
+        let plies = game.moves().len();
+        self.min_plies.map(|min| plies >= min).unwrap_or(true)
+            && self.max_plies.map(|max| plies <= max).unwrap_or(true)
+    }
+}
+
+// dedup returns indexes of games that are not duplicates of earlier games. Games are the same
+// when they start from the same position and have the same moves, tags are not compared.
+pub fn dedup(games: &[PgnGame]) -> Vec<usize> {
+    let keys: Vec<(Option<&str>, Vec<String>)> = games
+        .par_iter()
+        .map(|g| (g.tag("FEN"), g.moves()))
+        .collect();
+    let mut seen = HashSet::new();
+    keys.iter()
+        .enumerate()
+        .filter(|(_, key)| seen.insert(*key))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// PgnWriter writes games to a database file, every game is followed by an empty line. When
+// appending, the existing content is separated from new games, even if it doesn't end with a
+// newline. Buffered games are flushed when the writer is dropped, use finish to see errors.
+//
+// Atomic writer writes to a temporary file next to the database, which replaces the database in
+// finish (or on drop), so readers never see a partially written file.
+pub struct PgnWriter {
+    file: Option<BufWriter<File>>,
+    path: PathBuf,
+    temp: Option<PathBuf>, // temporary file of atomic writer
+    pub games: usize,      // games written so far
+}
+
+impl PgnWriter {
+    // create creates the database, existing file is truncated.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::create(path.as_ref())?;
+        Ok(PgnWriter::new(file, path.as_ref(), None))
+    }
+
+    // append opens the database for appending games, file is created when it doesn't exist.
+    pub fn append<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path.as_ref())?;
+        separate(&mut file)?;
+        Ok(PgnWriter::new(file, path.as_ref(), None))
+    }
+
+    // atomic writes the database through a temporary file, with 'append' games are added after
+    // the content of existing database.
+    pub fn atomic<P: AsRef<Path>>(path: P, append: bool) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        let temp = path.with_file_name(name);
+        if append && path.exists() {
+            fs::copy(path, &temp)?;
+        } else {
+            File::create(&temp)?;
+        }
+        let mut file = OpenOptions::new().read(true).append(true).open(&temp)?;
+        separate(&mut file)?;
+        Ok(PgnWriter::new(file, path, Some(temp)))
+    }
+
+    fn new(file: File, path: &Path, temp: Option<PathBuf>) -> Self {
+        PgnWriter {
+            file: Some(BufWriter::new(file)),
+            path: path.to_path_buf(),
+            temp,
+            games: 0,
+        }
+    }
+
+    // write_game writes the game followed by an empty line.
+    pub fn write_game(&mut self, game: &PgnGame) -> io::Result<()> {
+        self.write_pgn(&game.to_string())
+    }
+
+    // write_pgn writes game already written as PGN, e.g. by PgnGame::export. Trailing newlines
+    // are normalized to a single empty line.
+    pub fn write_pgn(&mut self, pgn: &str) -> io::Result<()> {
+        let pgn = pgn.trim_end();
+        if pgn.is_empty() {
+            return Ok(());
+        }
+        self.write_all(pgn.as_bytes())?;
+        self.write_all(b"\n\n")?;
+        self.games += 1;
+        Ok(())
+    }
+
+    // finish flushes the games to disk, atomic writer replaces the database with temporary file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let file = file.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        if let Some(temp) = self.temp.take() {
+            fs::rename(temp, &self.path)?;
+        }
+        Ok(())
+    }
+}
+
+// separate makes sure that non-empty file ends with an empty line, so appended game is not
+// glued to the last one.
+fn separate(file: &mut File) -> io::Result<()> {
+    let len = file.seek(SeekFrom::End(0))?;
+    if len == 0 {
+        return Ok(());
+    }
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(len.saturating_sub(2)))?;
+    file.read_to_end(&mut tail)?;
+    let separator: &[u8] = match tail.as_slice() {
+        [.., b'\n', b'\n'] => b"",
+        [b'\n'] | [.., b'\n'] => b"\n",
+        _ => b"\n\n",
+    };
+    file.write_all(separator)
+}
+
+// PgnWriter can be used as output of process_games, outputs are written as they are.
+impl Write for PgnWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.file.as_mut() {
+            Some(file) => file.write(buf),
+            None => Err(io::Error::other("writer is finished")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PgnWriter {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::{
+        dedup, index_positions, novelty_report, positions, process_games, replay_report,
+        GameFilter, PgnWriter,
+    };
+    use crate::notation::ParseMode;
+    use crate::pgn::{split_games, PgnGame, ReplayOptions};
+    use crate::progress::{Counter, NoProgress};
+    use std::io::Write;
+    use std::sync::atomic::Ordering;
+
+    const DATABASE: &str = r#"[White "Fool"]
+[Black "Mate"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+
+[White "Scholar"]
+[Black "Victim"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0
+
+[White "Fool"]
+[Black "Again"]
+
+1.f3 e5 2.g4 Qh4# 0-1
+
+[White "Broken"]
+
+1. e4 e5 2. Ke3 *
+"#;
+
+    #[test]
+    fn test_process_games() {
+        let games = split_games(DATABASE);
+        let mut ordered = Vec::new();
+        let report = |i, g: &PgnGame| replay_report(i, g, &ReplayOptions::default());
+        process_games(&games, true, &NoProgress, &mut ordered, report).unwrap();
+        assert_eq!(
+            String::from_utf8(ordered).unwrap(),
+            "game 1: ok, 4 plies\ngame 2: ok, 7 plies\ngame 3: ok, 4 plies\ngame 4: move 2. Ke3 (ply 3, white to move): invalid move (rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2)\n"
+        );
+
+        let mut unordered = Vec::new();
+        let counter = Counter::default();
+        process_games(&games, false, &counter, &mut unordered, report).unwrap();
+        assert_eq!(counter.done.load(Ordering::Relaxed), 4);
+        assert_eq!(counter.total.load(Ordering::Relaxed), 4);
+        let mut lines: Vec<String> = String::from_utf8(unordered)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        lines.sort();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "game 1: ok, 4 plies");
+
+        let sloppy = &split_games("1. e4 e5 2. ngf3 Nc6 3. Bb5 a6 4. bxc6 dc6 5. 0-0")[0];
+        let options = |mode, cross_check| ReplayOptions { mode, cross_check };
+        assert_eq!(
+            replay_report(0, sloppy, &options(ParseMode::Lenient, false)),
+            "game 1: ok, 9 plies\n"
+        );
+        assert!(replay_report(0, sloppy, &options(ParseMode::Normal, false)).contains("ply 3"));
+        let strict = options(ParseMode::Strict, false);
+        assert!(replay_report(1, &games[1], &strict).ends_with("ok, 7 plies\n"));
+
+        assert_eq!(
+            replay_report(0, sloppy, &options(ParseMode::Lenient, true)),
+            "game 1: ok, 9 plies\n\
+             warning: game 1: move 4... dc6 (ply 8, black to move): capture is not marked \
+             (r1bqkbnr/1ppp1ppp/p1B5/4p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 0 4)\n"
+        );
+    }
+
+    #[test]
+    fn test_filter_and_dedup() {
+        let games = split_games(DATABASE);
+        let filter = GameFilter {
+            tags: vec![GameFilter::parse_tag("White=Foo").unwrap()],
+            min_plies: Some(4),
+            max_plies: None,
+        };
+        let selected: Vec<bool> = games.iter().map(|g| filter.matches(g)).collect();
+        assert_eq!(selected, vec![true, false, true, false]);
+        assert!(GameFilter::parse_tag("White").is_err());
+
+        assert_eq!(dedup(&games), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_positions() {
+        let games = split_games(DATABASE);
+        let fens: Vec<String> = positions(&games[0]).lines().map(String::from).collect();
+        assert_eq!(fens.len(), 5);
+        assert_eq!(
+            fens[0],
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            fens[1],
+            "rnbqkbnr/pppppppp/8/8/8/5P2/PPPPP1PP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(positions(&games[3]).lines().count(), 3);
+    }
+
+    #[test]
+    fn test_novelty_report() {
+        let index = index_positions(&split_games(DATABASE));
+        let games = split_games(
+            "1. e4 e5 2. Bc4 Nc6 3. Qf3 *\n\n1. f3 e5 2. g4 Qh4# 0-1\n\n1. e4 e5 2. Ke3 *",
+        );
+        assert_eq!(
+            novelty_report(0, &games[0], &index),
+            "game 1: novelty at ply 5, Qf3, after a position from 1 games\n"
+        );
+        assert_eq!(novelty_report(1, &games[1], &index), "game 2: no novelty\n");
+        assert!(novelty_report(2, &games[2], &index).contains("invalid move"));
+        // the broken game of the database is indexed up to its illegal move.
+        let broken = &split_games("1. e4 e5 2. Nf3 *")[0];
+        assert!(
+            novelty_report(0, broken, &index).contains("ply 3, Nf3, after a position from 2 games")
+        );
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("chust-{}-{}.pgn", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_pgn_writer_append() {
+        let path = temp_path("append");
+        std::fs::write(&path, "[White \"Old\"]\n\n1. e4 *").unwrap();
+        let games = split_games(DATABASE);
+        {
+            let mut writer = PgnWriter::append(&path).unwrap();
+            writer.write_game(&games[0]).unwrap();
+            writer.write_pgn("1. d4 *\n\n\n").unwrap();
+            assert_eq!(writer.games, 2);
+            // games are flushed on drop.
+        }
+        let mut writer = PgnWriter::append(&path).unwrap();
+        writer.write_game(&games[1]).unwrap();
+        writer.finish().unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("[White \"Old\"]\n\n1. e4 *\n\n[White"));
+        assert!(content.contains("\n\n1. d4 *\n\n["));
+        assert!(content.ends_with("\n\n") && !content.ends_with("\n\n\n"));
+        let read = split_games(&content);
+        assert_eq!(read.len(), 4);
+        assert_eq!(read[1], games[0]);
+        assert_eq!(read[3], games[1]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_pgn_writer_atomic() {
+        let path = temp_path("atomic");
+        std::fs::write(&path, "1. e4 *\n").unwrap();
+        let temp = path.with_file_name(format!(
+            "{}.tmp",
+            path.file_name().unwrap().to_str().unwrap()
+        ));
+        let mut writer = PgnWriter::atomic(&path, true).unwrap();
+        writer.write_pgn("1. d4 *").unwrap();
+        writer.flush().unwrap();
+        // database is not touched until the writer is finished.
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1. e4 *\n");
+        assert!(temp.exists());
+        writer.finish().unwrap();
+        assert!(!temp.exists());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "1. e4 *\n\n1. d4 *\n\n"
+        );
+
+        let mut writer = PgnWriter::atomic(&path, false).unwrap();
+        writer.write_pgn("1. c4 *").unwrap();
+        drop(writer);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1. c4 *\n\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use crate::board::{Board, Transition, TransitionFlag};
+use crate::notation::{castle_flag, line_to_san};
+use crate::pgn::{movetext_tokens, PgnGame, ReplayError, RESULTS};
+use crate::piece::{Color, PieceType};
+
+// Files in English descriptive notation are named after pieces standing on them at the start,
+// ranks are counted from the side of moving player e.g. P-K4 is e4 for white and e5 for black.
+const FILES: [(&str, &[usize]); 11] = [
+    ("QR", &[0]),
+    ("QN", &[1]),
+    ("QB", &[2]),
+    ("Q", &[3]),
+    ("K", &[4]),
+    ("KB", &[5]),
+    ("KN", &[6]),
+    ("KR", &[7]),
+    ("R", &[0, 7]),
+    ("N", &[1, 6]),
+    ("B", &[2, 5]),
+];
+
+// suffixes of a move that don't change its meaning, e.g. 'ch' for check.
+const SUFFIXES: [&str; 10] = ["e.p.", "ep", "mate", "dis", "ch", "+", "#", "!", "?", "."];
+
+// PieceDescription is a piece named in a move, e.g. KN - king's knight, QBP - queen's bishop
+// pawn. files is empty when the piece is not narrowed down to a side of the board.
+struct PieceDescription {
+    p_type: PieceType,
+    files: &'static [usize],
+}
+
+// parse_descriptive finds legal move written in English descriptive notation, e.g. P-K4, NxB,
+// QN-Q2, PxP e.p., P-K8=Q, O-O. Move has to describe exactly one legal move.
+pub fn parse_descriptive(board: &Board, m: &str) -> Result<Transition, &'static str> {
+    let mut m = m.trim().replace("Kt", "N");
+    while let Some(suffix) = SUFFIXES
+        .iter()
+        .find(|s| m.len() > s.len() && m.ends_with(*s))
+    {
+        m.truncate(m.trim_end_matches(suffix).trim_end().len());
+    }
+
+    let castle = castle_flag(&m);
+    let transitions = board.generate_transitions();
+    if let Some(flag) = castle {
+        return transitions
+            .into_iter()
+            .find(|t| t.flag == flag)
+            .ok_or("invalid castle");
+    }
+
+    let (m, promotion) = split_promotion(&m)?;
+    let (piece, capture, target) = match (m.split_once('-'), m.split_once('x')) {
+        (Some((piece, target)), None) => (piece, false, target),
+        (None, Some((piece, target))) => (piece, true, target),
+        _ => return Err("move must contain '-' or 'x'"),
+    };
+    let piece = parse_piece(piece).ok_or("invalid piece")?;
+    // target of a capture is usually a piece, but can be a square too.
+    let target_square = if target.ends_with(|c: char| c.is_ascii_digit()) {
+        Some(parse_square(target, board.color_to_move).ok_or("invalid square")?)
+    } else {
+        None
+    };
+    let captured = match target_square {
+        Some(_) => None,
+        None if capture => Some(parse_piece(target).ok_or("invalid captured piece")?),
+        None => return Err("invalid square"),
+    };
+
+    let mut matching = transitions.into_iter().filter(|t| {
+        let moving = board.piece_at(t.from);
+        let taken = if t.flag == TransitionFlag::EnPassant {
+            PieceType::PAWN
+        } else {
+            board.piece_at(t.to).p_type
+        };
+        moving.p_type == piece.p_type
+            && (piece.files.is_empty() || piece.files.contains(&(t.from % 8)))
+            && (taken != PieceType::NONE) == capture
+            && target_square
+                .is_none_or(|(files, rank)| files.contains(&(t.to % 8)) && t.to / 8 == rank)
+            && captured.as_ref().is_none_or(|c| {
+                c.p_type == taken && (c.files.is_empty() || c.files.contains(&(t.to % 8)))
+            })
+            && match promotion {
+                Some(p) => t.promotion == p,
+                // promotion to queen is often not written in old games.
+                None => t.promotion == PieceType::NONE || t.promotion == PieceType::QUEEN,
+            }
+    });
+    match (matching.next(), matching.next()) {
+        (Some(t), None) => Ok(t),
+        (Some(_), Some(_)) => Err("ambiguous move"),
+        _ => Err("invalid move"),
+    }
+}
+
+// split_promotion splits promotion piece written as P-K8=Q, P-K8(Q) or P-K8/Q.
+fn split_promotion(m: &str) -> Result<(&str, Option<PieceType>), &'static str> {
+    let m = m.trim_end_matches(')');
+    match m.rfind(['=', '(', '/']) {
+        Some(i) => match PieceType::from_sign(&m[i + 1..]) {
+            PieceType::NONE => Err("invalid promotion piece"),
+            p => Ok((&m[..i], Some(p))),
+        },
+        None => Ok((m, None)),
+    }
+}
+
+// parse_piece parses piece e.g. N, KN, P, QBP.
+fn parse_piece(s: &str) -> Option<PieceDescription> {
+    let (side, letter) = s.split_at(s.len().checked_sub(1)?);
+    let p_type = match letter {
+        "P" => PieceType::PAWN,
+        "K" => PieceType::KING,
+        l => PieceType::from_sign(l),
+    };
+    let files: &'static [usize] = match (p_type, side) {
+        (PieceType::NONE, _) => return None,
+        (_, "") => &[],
+        // pawn is named after its file, other pieces after side of the board.
+        (PieceType::PAWN, side) => file_names(side)?,
+        (PieceType::KNIGHT, "K") | (PieceType::BISHOP, "K") | (PieceType::ROOK, "K") => {
+            &[4, 5, 6, 7]
+        }
+        (PieceType::KNIGHT, "Q") | (PieceType::BISHOP, "Q") | (PieceType::ROOK, "Q") => {
+            &[0, 1, 2, 3]
+        }
+        _ => return None,
+    };
+    Some(PieceDescription { p_type, files })
+}
+
+fn file_names(name: &str) -> Option<&'static [usize]> {
+    FILES.iter().find(|(n, _)| *n == name).map(|(_, f)| *f)
+}
+
+// parse_square parses square e.g. KB3 into possible files and a rank, counted from white side.
+fn parse_square(s: &str, color: Color) -> Option<(&'static [usize], usize)> {
+    let (file, rank) = s.split_at(s.len().checked_sub(1)?);
+    let rank = rank.parse::<usize>().ok().filter(|r| (1..=8).contains(r))?;
+    let rank = if color == Color::WHITE {
+        rank - 1
+    } else {
+        8 - rank
+    };
+    Some((file_names(file)?, rank))
+}
+
+// descriptive_to_san converts movetext of a game from descriptive notation to SAN by replaying it.
+pub fn descriptive_to_san(game: &PgnGame) -> Result<PgnGame, ReplayError> {
+    let start = game.start_position().map_err(|e| game.start_error(e))?;
+    let mut board = start.clone();
+    let mut line = Vec::new();
+    let tokens = movetext_tokens(&game.movetext);
+    for token in tokens.iter().filter(|t| !SUFFIXES.contains(&t.as_str())) {
+        let t = parse_descriptive(&board, token)
+            .map_err(|e| game.replay_error(&board, line.len() + 1, token, e))?;
+        board.make_transition(t);
+        line.push(t);
+    }
+
+    let mut movetext = line_to_san(&start, &line);
+    if let Some(result) = game
+        .movetext
+        .split_whitespace()
+        .last()
+        .filter(|r| RESULTS.contains(r))
+    {
+        movetext = format!("{} {}", movetext, result);
+    }
+    Ok(PgnGame {
+        tags: game.tags.clone(),
+        movetext,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::descriptive::{descriptive_to_san, parse_descriptive};
+    use crate::notation::to_san;
+    use crate::pgn::split_games;
+
+    // Morphy - Duke of Brunswick and Count Isouard, Paris 1858.
+    const OPERA_GAME: &str = "[White \"Morphy, Paul\"]
+
+1 P-K4 P-K4 2 N-KB3 P-Q3 3 P-Q4 B-N5 4 PxP BxN 5 QxB PxP 6 B-QB4 N-KB3 7 Q-QN3 Q-K2
+8 N-B3 P-B3 9 B-KN5 P-QN4 10 NxP PxN 11 BxNP ch QN-Q2 12 O-O-O R-Q1 13 RxN RxR
+14 R-Q1 Q-K3 15 BxR ch NxB 16 Q-N8 ch NxQ 17 R-Q8 mate 1-0";
+
+    #[test]
+    fn test_descriptive_to_san() {
+        let game = descriptive_to_san(&split_games(OPERA_GAME)[0]).unwrap();
+        assert_eq!(
+            game.movetext,
+            "1. e4 e5 2. Nf3 d6 3. d4 Bg4 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7 \
+             8. Nc3 c6 9. Bg5 b5 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 \
+             14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0"
+        );
+        assert_eq!(
+            game.tags,
+            vec![("White".to_string(), "Morphy, Paul".to_string())]
+        );
+
+        let error = descriptive_to_san(&split_games("1 P-K4 P-K4 2 P-KB4 P-K5")[0]).unwrap_err();
+        assert_eq!((error.ply, error.error), (4, "invalid move"));
+    }
+
+    #[test]
+    fn test_parse_descriptive() {
+        let san = |fen: &str, m: &str| -> Result<String, &'static str> {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            parse_descriptive(&b, m).map(|t| to_san(&b, &t))
+        };
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w";
+        assert_eq!(san(start, "P-K4"), Ok("e4".to_string()));
+        assert_eq!(san(start, "Kt-KB3"), Ok("Nf3".to_string()));
+        assert_eq!(san(start, "P-B4"), Err("ambiguous move"));
+        assert_eq!(san(start, "P-QB4"), Ok("c4".to_string()));
+        assert_eq!(san(start, "P-K5"), Err("invalid move"));
+        assert_eq!(san(start, "N-Q9"), Err("invalid square"));
+        assert_eq!(
+            san("rnbqkbnr/ppp1pppp/8/8/4p3/8/PPPP1PPP/RNBQKBNR b", "P-K4"),
+            Ok("e5".to_string())
+        );
+        assert_eq!(
+            san("4k3/1P6/8/8/8/8/8/4K3 w", "P-N8"),
+            Ok("b8=Q+".to_string())
+        );
+        assert_eq!(
+            san("4k3/1P6/8/8/8/8/8/4K3 w", "P-N8(N)"),
+            Ok("b8=N".to_string())
+        );
+        assert_eq!(san("4k3/8/8/8/8/8/8/4K2R w", "O-O"), Ok("O-O".to_string()));
+
+        let mut b = Board::default();
+        b.read_pgn("1. e4 a6 2. e5 d5", false).unwrap();
+        let t = parse_descriptive(&b, "PxP e.p.").unwrap();
+        assert_eq!(to_san(&b, &t), "exd6");
+    }
+}
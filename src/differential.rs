@@ -0,0 +1,107 @@
+use crate::backend::{SearchBackend, SearchLimits};
+use crate::board::Board;
+use crate::evaluation::Evaluator;
+use crate::score::Score;
+use std::io::{self, Write};
+
+// Disagreement is a position evaluated differently by an evaluator and a reference engine, both
+// scores are from white point of view.
+#[derive(Clone, Debug)]
+pub struct Disagreement {
+    pub fen: String,
+    pub evaluation: Score,
+    pub reference: Score,
+    pub gap: f32, // difference of win probabilities, from 0 to 1
+}
+
+// compare_evaluations evaluates every position statically and with a shallow search of the
+// reference engine, usually an external one behind UciClient, and returns disagreements from the
+// largest one. Scores are compared as win probabilities, so mates and big advantages the engines
+// only value differently don't hide real blind spots. Invalid FENs and positions without moves
+// are skipped. on_position is called after every position.
+pub fn compare_evaluations<F>(
+    fens: &[String],
+    evaluator: &dyn Evaluator,
+    reference: &mut dyn SearchBackend,
+    depth: usize,
+    mut on_position: F,
+) -> Vec<Disagreement>
+where
+    F: FnMut(usize),
+{
+    let mut disagreements = Vec::new();
+    for (i, fen) in fens.iter().enumerate() {
+        on_position(i);
+        let mut board = Board::default();
+        if board.read_fen(fen).is_err() {
+            continue;
+        }
+        if board.generate_transitions().is_empty() {
+            continue;
+        }
+        let result = reference.search(&board, &SearchLimits::depth(depth), &mut |_, _| {});
+        let reference = match result.lines.first() {
+            Some(line) => line.score.white_view(board.color_to_move),
+            None => continue,
+        };
+        let evaluation = Score::Centipawns((evaluator.evaluate(&board) * 100.0).round() as i32);
+        disagreements.push(Disagreement {
+            fen: fen.clone(),
+            evaluation,
+            reference,
+            gap: (evaluation.win_probability() - reference.win_probability()).abs(),
+        });
+    }
+    disagreements.sort_by(|a, b| b.gap.total_cmp(&a.gap));
+    disagreements
+}
+
+// write_disagreements writes CSV with the largest disagreements, at most 'top' of them.
+pub fn write_disagreements<W: Write>(
+    w: &mut W,
+    disagreements: &[Disagreement],
+    top: usize,
+) -> io::Result<()> {
+    writeln!(w, "fen,evaluation,reference,gap")?;
+    for d in disagreements.iter().take(top) {
+        writeln!(w, "{},{},{},{:.3}", d.fen, d.evaluation, d.reference, d.gap)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::differential::{compare_evaluations, write_disagreements};
+    use crate::evaluation::SimpleEvaluator;
+    use crate::score::Score;
+    use crate::search::Search;
+
+    #[test]
+    fn test_compare_evaluations() {
+        let e = SimpleEvaluator {};
+        let mut reference = Search::new(&e);
+        let fens: Vec<String> = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w",
+            "4k3/8/8/3q4/4P3/8/8/4K3 w", // the queen hangs, static evaluation doesn't see it
+            "invalid",
+            "7k/6Q1/6K1/8/8/8/8/8 b", // checkmate
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        let mut seen = 0;
+        let disagreements = compare_evaluations(&fens, &e, &mut reference, 2, |_| seen += 1);
+        assert_eq!(seen, 4);
+        assert_eq!(disagreements.len(), 2);
+        assert_eq!(disagreements[0].fen, fens[1]);
+        assert_eq!(disagreements[0].evaluation, Score::Centipawns(-800));
+        assert!(disagreements[0].gap > 0.5);
+        assert_eq!(disagreements[1].gap, 0.0);
+
+        let mut csv = Vec::new();
+        write_disagreements(&mut csv, &disagreements, 1).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.contains("4k3/8/8/3q4/4P3/8/8/4K3 w,-8.00,"));
+    }
+}
@@ -0,0 +1,128 @@
+// Z95 is the number of standard deviations of 95% confidence intervals.
+const Z95: f64 = 1.959_964;
+
+// RatedResult is a result of a game against an opponent of known rating, 1, 0.5 or 0 points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RatedResult {
+    pub opponent: f64,
+    pub points: f64,
+}
+
+// Estimate is a rating or Elo difference with bounds of its 95% confidence interval.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub elo: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Estimate {
+    // margin returns half of the confidence interval, as written after '+/-'.
+    pub fn margin(&self) -> f64 {
+        (self.high - self.low) / 2.0
+    }
+}
+
+// expected_score returns points per game expected from a player rated higher by the difference.
+pub fn expected_score(difference: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-difference / 400.0))
+}
+
+// elo_difference converts expected score to Elo difference.
+pub fn elo_difference(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+// difference estimates Elo difference of a player from results against one opponent, computed as
+// by cutechess-cli. Bounds are infinite or NaN when one of players scored all points.
+pub fn difference(wins: usize, draws: usize, losses: usize) -> Estimate {
+    let games = wins + draws + losses;
+    let n = games.max(1) as f64;
+    let score = if games == 0 {
+        0.5
+    } else {
+        (wins as f64 + draws as f64 / 2.0) / n
+    };
+    let deviation = |result: f64, count: usize| count as f64 / n * (result - score).powi(2);
+    let variance = deviation(1.0, wins) + deviation(0.0, losses) + deviation(0.5, draws);
+    let margin = Z95 * (variance / n).sqrt();
+    Estimate {
+        elo: elo_difference(score),
+        low: elo_difference(score - margin),
+        high: elo_difference(score + margin),
+    }
+}
+
+// performance returns performance rating: the rating expected to score the same points against
+// the opponents. Opponents may have different ratings. A perfect or zero score is moved by a
+// quarter of a point, so the rating stays finite.
+pub fn performance(results: &[RatedResult]) -> Option<Estimate> {
+    if results.is_empty() {
+        return None;
+    }
+    let n = results.len() as f64;
+    let points = results.iter().map(|r| r.points).sum::<f64>();
+    let mean = points / n;
+    let variance = results
+        .iter()
+        .map(|r| (r.points - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    let margin = Z95 * (variance / n).sqrt() * n; // in points
+    let rating = |points: f64| rating_for(results, points.clamp(0.25, n - 0.25));
+    Some(Estimate {
+        elo: rating(points),
+        low: rating(points - margin),
+        high: rating(points + margin),
+    })
+}
+
+// rating_for finds by bisection the rating expected to score the points against the opponents.
+fn rating_for(results: &[RatedResult], points: f64) -> f64 {
+    let opponents = results.iter().map(|r| r.opponent);
+    let mut low = opponents.clone().fold(f64::INFINITY, f64::min) - 4000.0;
+    let mut high = opponents.fold(f64::NEG_INFINITY, f64::max) + 4000.0;
+    for _ in 0..64 {
+        let rating = (low + high) / 2.0;
+        let expected: f64 = results
+            .iter()
+            .map(|r| expected_score(rating - r.opponent))
+            .sum();
+        if expected < points {
+            low = rating;
+        } else {
+            high = rating;
+        }
+    }
+    (low + high) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elo::{difference, elo_difference, expected_score, performance, RatedResult};
+
+    #[test]
+    fn test_performance() {
+        assert!((expected_score(elo_difference(0.75)) - 0.75).abs() < 1e-9);
+        let estimate = difference(30, 0, 10);
+        assert!((estimate.elo - 190.8).abs() < 0.1);
+        assert!(estimate.low < estimate.elo && estimate.elo < estimate.high);
+
+        let result = |opponent, points| RatedResult { opponent, points };
+        assert_eq!(performance(&[]), None);
+        // against one opponent performance is the rating plus Elo difference.
+        let mut games = vec![result(2000.0, 1.0); 40];
+        games[30..].iter_mut().for_each(|g| g.points = 0.0);
+        let rating = performance(&games).unwrap();
+        assert!((rating.elo - 2000.0 - estimate.elo).abs() < 0.1);
+        assert!((rating.low - 2000.0 - estimate.low).abs() < 0.1);
+        assert!((rating.high - 2000.0 - estimate.high).abs() < 0.1);
+
+        let mixed = performance(&[result(2100.0, 1.0), result(1900.0, 0.0)]).unwrap();
+        assert!((mixed.elo - 2000.0).abs() < 0.1);
+        // a perfect score is high, but finite.
+        let perfect = performance(&[result(1500.0, 1.0)]).unwrap();
+        assert!((perfect.elo - 1690.8).abs() < 0.1);
+        assert_eq!(perfect.high, perfect.elo);
+    }
+}
@@ -0,0 +1,2 @@
+// Endgame knowledge that doesn't need search.
+pub mod kpk;
@@ -0,0 +1,153 @@
+use crate::board::Board;
+use crate::piece::Color;
+use crate::tablebase::{self, Ending};
+
+// KPK is a bitbase of king and pawn against king endings: one bit per position telling if white,
+// the side with the pawn, wins. Positions with pawn on e-h files are mirrored to a-d files, so
+// it takes 24 kB. It is exported from the generated tablebase by test_bitbase run with
+// CHUST_WRITE_KPK=1 set.
+static KPK: &[u8; SIZE / 8] = include_bytes!("kpk.bin");
+
+const SIZE: usize = 2 * 64 * 24 * 64;
+
+// Outcome is a result of KPK position with perfect play.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Win,
+    Draw,
+}
+
+// index returns bit of position, None for pawn on first or last rank.
+fn index(white_king: usize, pawn: usize, black_king: usize, to_move: Color) -> Option<usize> {
+    if !(8..56).contains(&pawn) {
+        return None;
+    }
+    // mirroring files keeps pawn on a-d files.
+    let mirror = |s: usize| if pawn % 8 >= 4 { s ^ 7 } else { s };
+    let pawn = mirror(pawn);
+    let pawn_index = (pawn / 8 - 1) * 4 + pawn % 8;
+    let white_to_move = (to_move == Color::WHITE) as usize;
+    Some(((white_to_move * 64 + mirror(white_king)) * 24 + pawn_index) * 64 + mirror(black_king))
+}
+
+// probe returns outcome of position with white king, white pawn and black king on given squares
+// and color to move. Illegal positions are draws.
+pub fn probe(white_king: usize, pawn: usize, black_king: usize, to_move: Color) -> Outcome {
+    match index(white_king, pawn, black_king, to_move) {
+        Some(i) if KPK[i / 8] & (1 << (i % 8)) != 0 => Outcome::Win,
+        _ => Outcome::Draw,
+    }
+}
+
+// WIN_BONUS is added to evaluation of won KPK position, in pawns, so the engine goes for it.
+const WIN_BONUS: f32 = 2.0;
+// DRAW_SCALE scales evaluation of drawn KPK position, extra pawn is worth little there.
+const DRAW_SCALE: f32 = 0.1;
+
+// scale adjusts evaluation of KPK position, positive for white, by its outcome. Evaluation of
+// other positions is returned unchanged.
+pub fn scale(board: &Board, eval: f32) -> f32 {
+    let position = match tablebase::position(board) {
+        Some((Ending::KPK, position)) => position,
+        _ => return eval,
+    };
+    let to_move = if position.strong_to_move {
+        Color::WHITE
+    } else {
+        Color::BLACK
+    };
+    // position is mirrored when black has the pawn.
+    let black_pawn = position.strong_to_move != (board.color_to_move == Color::WHITE);
+    match probe(
+        position.strong_king,
+        position.piece,
+        position.weak_king,
+        to_move,
+    ) {
+        Outcome::Win if black_pawn => eval - WIN_BONUS,
+        Outcome::Win => eval + WIN_BONUS,
+        Outcome::Draw => eval * DRAW_SCALE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::endgame::kpk::{index, probe, scale, Outcome, KPK, SIZE};
+    use crate::notation::parse_square;
+    use crate::piece::Color;
+    use crate::tablebase::{self, Dtm, Ending};
+
+    // generate builds the bitbase from KPK tablebase.
+    fn generate() -> Vec<u8> {
+        let table = Ending::KPK.table();
+        let mut bits = vec![0u8; SIZE / 8];
+        for white_to_move in [false, true].iter() {
+            for white_king in 0..64 {
+                for pawn in (8..56).filter(|p| p % 8 < 4) {
+                    for black_king in 0..64 {
+                        let position = tablebase::Position {
+                            strong_to_move: *white_to_move,
+                            strong_king: white_king,
+                            piece: pawn,
+                            weak_king: black_king,
+                        };
+                        let win = match table.get(&position) {
+                            Dtm::Win(_) => *white_to_move,
+                            Dtm::Loss(_) => !*white_to_move,
+                            Dtm::Draw => false,
+                        };
+                        let to_move = if *white_to_move {
+                            Color::WHITE
+                        } else {
+                            Color::BLACK
+                        };
+                        if win {
+                            let i = index(white_king, pawn, black_king, to_move).unwrap();
+                            bits[i / 8] |= 1 << (i % 8);
+                        }
+                    }
+                }
+            }
+        }
+        bits
+    }
+
+    #[test]
+    fn test_bitbase() {
+        let generated = generate();
+        if std::env::var("CHUST_WRITE_KPK").is_ok() {
+            std::fs::write("src/endgame/kpk.bin", &generated).unwrap();
+        }
+        assert!(generated[..] == KPK[..], "kpk.bin is out of date");
+
+        let s = |name| parse_square(name).unwrap();
+        // king in front of the pawn on the sixth rank wins, also mirrored to the other wing.
+        assert_eq!(probe(s("e6"), s("e5"), s("e8"), Color::BLACK), Outcome::Win);
+        assert_eq!(probe(s("d6"), s("d5"), s("d8"), Color::BLACK), Outcome::Win);
+        // opposition decides with the king in front of the pawn.
+        assert_eq!(
+            probe(s("e1"), s("e2"), s("e3"), Color::WHITE),
+            Outcome::Draw
+        );
+        assert_eq!(probe(s("e3"), s("e2"), s("e5"), Color::BLACK), Outcome::Win);
+        assert_eq!(
+            probe(s("a1"), s("a5"), s("a8"), Color::WHITE),
+            Outcome::Draw
+        );
+        assert_eq!(
+            probe(s("a1"), s("a8"), s("c8"), Color::WHITE),
+            Outcome::Draw
+        );
+
+        let mut b = Board::default();
+        b.read_fen("4k3/8/4K3/4P3/8/8/8/8 b").unwrap();
+        assert_eq!(scale(&b, 1.0), 3.0);
+        b.read_fen("8/8/8/8/4p3/4k3/8/4K3 w").unwrap();
+        assert_eq!(scale(&b, -1.0), -3.0);
+        b.read_fen("k7/8/8/P7/8/8/8/K7 w").unwrap();
+        assert_eq!(scale(&b, 1.0), 0.1);
+        b.read_fen("k7/8/8/Q7/8/8/8/K7 w").unwrap();
+        assert_eq!(scale(&b, 9.0), 9.0);
+    }
+}
@@ -0,0 +1,221 @@
+#[cfg(feature = "search")]
+use crate::board::{Board, Transition};
+#[cfg(feature = "search")]
+use crate::book::OpeningBook;
+#[cfg(feature = "search")]
+use crate::evaluation::Evaluator;
+#[cfg(feature = "search")]
+use crate::search::{Score, Search, SearchLine};
+#[cfg(feature = "search")]
+use crate::tablebase::{self, Dtm};
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "search")]
+use std::time::{Duration, Instant};
+
+// EngineInfo identifies the engine, it's reported by UCI 'id' command, CLI --version and written
+// to PGN Annotator tags, so all of them always agree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EngineInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub author: &'static str,
+}
+
+pub const ENGINE_INFO: EngineInfo = EngineInfo {
+    name: "chust",
+    version: env!("CARGO_PKG_VERSION"),
+    author: env!("CARGO_PKG_AUTHORS"),
+};
+
+impl EngineInfo {
+    // annotator_tag returns PGN Annotator tag of games annotated by the engine.
+    pub fn annotator_tag(&self) -> (String, String) {
+        ("Annotator".to_string(), self.to_string())
+    }
+}
+
+// EngineInfo is written as name and version, e.g. 'chust 0.1.0'.
+impl Display for EngineInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+// DEFAULT_BLUNDER_THRESHOLD is the smallest loss in centipawns against the best move that makes a
+// move a blunder, more than a pawn and a half, so giving a piece for a pawn is one.
+#[cfg(feature = "search")]
+pub const DEFAULT_BLUNDER_THRESHOLD: i32 = 150;
+// MAX_CP limits scores compared by the blunder check, so missing a mate counts as a big loss
+// rather than an endless one.
+#[cfg(feature = "search")]
+const MAX_CP: i32 = 1000;
+#[cfg(feature = "search")]
+const MAX_DEPTH: usize = 64;
+
+// Engine answers quick questions of playing UIs, which don't run their own search.
+#[cfg(feature = "search")]
+pub struct Engine<'a> {
+    evaluator: &'a dyn Evaluator,
+    pub blunder_threshold: i32, // centipawns, see DEFAULT_BLUNDER_THRESHOLD
+}
+
+// BlunderCheck compares the intended move with the best one found, lines are from the point of
+// view of the side to move. The best line is the intended one when no better move was found.
+#[cfg(feature = "search")]
+#[derive(Clone, Debug)]
+pub struct BlunderCheck {
+    pub blunder: bool,
+    pub cp_loss: i32,
+    pub intended: SearchLine,
+    pub best: SearchLine,
+}
+
+#[cfg(feature = "search")]
+impl<'a> Engine<'a> {
+    pub fn new(evaluator: &'a dyn Evaluator) -> Self {
+        Engine {
+            evaluator,
+            blunder_threshold: DEFAULT_BLUNDER_THRESHOLD,
+        }
+    }
+
+    // quick_blunder_check tells if the intended move loses more than blunder_threshold against
+    // the best move, e.g. to ask 'are you sure?' before playing it. Half of the budget looks for
+    // the best move, the rest searches both moves to the same depth, so scores are comparable.
+    pub fn quick_blunder_check(
+        &self,
+        board: &Board,
+        intended: Transition,
+        budget_ms: u64,
+    ) -> Result<BlunderCheck, &'static str> {
+        let started = Instant::now();
+        let budget = Duration::from_millis(budget_ms);
+        let mut search = Search::new(self.evaluator);
+        search.deadline = Some(started + budget / 2);
+        let mut depth = 0;
+        let lines = search.iterate(board, MAX_DEPTH, 1, |d, _| depth = d);
+        let best = lines.first().ok_or("there are no legal moves")?.pv[0];
+        search.deadline = Some(started + budget);
+        let [intended, best] = search.compare(board, intended, best, depth)?;
+        let cp_loss = (centipawns(best.score) - centipawns(intended.score)).max(0);
+        Ok(BlunderCheck {
+            blunder: cp_loss > self.blunder_threshold,
+            cp_loss,
+            intended,
+            best,
+        })
+    }
+}
+
+// InstantSource tells where an instant move comes from.
+#[cfg(feature = "search")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InstantSource {
+    Book(u32), // games the move was played in
+    Tablebase(Dtm),
+}
+
+// InstantMove is a move known without a search, so it's played without spending clock time.
+#[cfg(feature = "search")]
+#[derive(Clone, Copy, Debug)]
+pub struct InstantMove {
+    pub t: Transition,
+    pub source: InstantSource,
+}
+
+// instant_move returns the most played move of the opening book, or the best move of a won or
+// drawn tablebase position. Lost positions are searched, the opponent may still go wrong.
+#[cfg(feature = "search")]
+pub fn instant_move(board: &Board, book: Option<&OpeningBook>) -> Option<InstantMove> {
+    if let Some((t, games)) = book.and_then(|b| b.moves(board).first().copied()) {
+        return Some(InstantMove {
+            t,
+            source: InstantSource::Book(games),
+        });
+    }
+    match tablebase::probe(board)? {
+        Dtm::Loss(_) => None,
+        dtm => Some(InstantMove {
+            t: tablebase::best_move(board)?,
+            source: InstantSource::Tablebase(dtm),
+        }),
+    }
+}
+
+#[cfg(feature = "search")]
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(cp) => cp.clamp(-MAX_CP, MAX_CP),
+        Score::Mate(moves) if moves > 0 => MAX_CP,
+        Score::Mate(_) => -MAX_CP,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::ENGINE_INFO;
+
+    #[test]
+    fn test_engine_info() {
+        assert_eq!(
+            ENGINE_INFO.to_string(),
+            format!("chust {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert!(!ENGINE_INFO.author.is_empty());
+        assert_eq!(ENGINE_INFO.annotator_tag().1, ENGINE_INFO.to_string());
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_quick_blunder_check() {
+        use crate::board::Board;
+        use crate::engine::Engine;
+        use crate::evaluation::SimpleEvaluator;
+        use crate::notation::to_san;
+
+        let mut b = Board::default();
+        b.read_fen("4k3/8/3p4/4p3/8/5N2/8/4K3 w").unwrap();
+        let e = SimpleEvaluator {};
+        let engine = Engine::new(&e);
+        let moves = b.generate_transitions();
+        let find = |san: &str| *moves.iter().find(|t| to_san(&b, t) == san).unwrap();
+        // the pawn is defended, so the knight is lost for it.
+        let check = engine.quick_blunder_check(&b, find("Nxe5"), 200).unwrap();
+        assert!(check.blunder);
+        assert_eq!(check.cp_loss, 200);
+        assert_eq!(to_san(&b, &check.intended.pv[0]), "Nxe5");
+        assert_ne!(to_san(&b, &check.best.pv[0]), "Nxe5");
+        let check = engine.quick_blunder_check(&b, find("Kd2"), 200).unwrap();
+        assert!(!check.blunder);
+        let mut illegal = find("Kd2");
+        illegal.to = 63;
+        assert_eq!(
+            engine.quick_blunder_check(&b, illegal, 50).err(),
+            Some("move is not legal")
+        );
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_instant_move() {
+        use crate::board::Board;
+        use crate::book::OpeningBook;
+        use crate::engine::{instant_move, InstantSource};
+        use crate::notation::to_san;
+        use crate::tablebase::Dtm;
+
+        let book = OpeningBook::from_pgn("1. e4 e5 *\n\n1. d4 d5 *\n\n1. e4 c5 *", 2);
+        let mut b = Board::default();
+        let m = instant_move(&b, Some(&book)).unwrap();
+        assert_eq!(to_san(&b, &m.t), "e4");
+        assert_eq!(m.source, InstantSource::Book(2));
+        assert!(instant_move(&b, None).is_none());
+
+        b.read_fen("7k/8/5K2/8/8/8/8/6Q1 w").unwrap();
+        let m = instant_move(&b, Some(&book)).unwrap();
+        assert!(matches!(m.source, InstantSource::Tablebase(Dtm::Win(_))));
+        // the weaker side searches for the opponent's mistakes.
+        b.read_fen("7k/8/5K2/8/8/8/8/6Q1 b").unwrap();
+        assert!(instant_move(&b, Some(&book)).is_none());
+    }
+}
@@ -1,35 +1,70 @@
 #![allow(warnings, unused)]
 
-use crate::board::{Board, Transition, TransitionFlag};
+use crate::board::{attackers, Board, Square, Transition, TransitionFlag, KING_STEPS};
+use crate::endgame::kpk;
+use crate::notation::square_at;
 use crate::piece::{Color, Piece, PieceType};
+use crate::position::Position;
+use crate::tablebase::TablebaseEvaluator;
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-fn simple_eval(game: [Piece; 64]) -> f32 {
-    return game
-        .iter()
-        .filter(|x| x.p_type != PieceType::NONE)
-        .map(|x| {
-            if x.color == Color::WHITE {
-                x.p_type.points() as f32
-            } else {
-                (x.p_type.points() * -1) as f32
-            }
-        })
-        .sum();
+// simple_eval returns material of a position, which a Board keeps up to date with every move.
+fn simple_eval(position: &dyn Position) -> f32 {
+    position.material() as f32
+}
+
+// Evaluator is shared by searches running in many threads, so it must be Send and Sync.
+pub trait Evaluator: Send + Sync {
+    // evaluate returns evaluation of a position, Board or any other. Positive value is advantage
+    // of white color.
+    fn evaluate(&self, position: &dyn Position) -> f32;
+
+    // breakdown returns evaluation split into terms that sum up to evaluate result.
+    fn breakdown(&self, position: &dyn Position) -> Vec<EvalTerm> {
+        vec![EvalTerm::new("total", self.evaluate(position))]
+    }
+}
+
+// EvalTerm is a named part of evaluation.
+#[derive(Clone, Debug)]
+pub struct EvalTerm {
+    pub name: &'static str,
+    pub value: f32,
+}
+
+impl EvalTerm {
+    pub fn new(name: &'static str, value: f32) -> Self {
+        EvalTerm { name, value }
+    }
 }
 
-pub trait Evaluator {
-    // evaluate returns evaluation of game board. Positive value is advantage of white color.
-    fn evaluate(&self, board: &Board) -> f32;
+pub const EVALUATOR_NAMES: [&str; 4] = ["simple", "material-mobility", "pst", "tablebase"];
+
+// evaluator_by_name returns evaluator for name used in command line.
+pub fn evaluator_by_name(name: &str) -> Option<Box<dyn Evaluator>> {
+    return match name {
+        "simple" => Some(Box::new(SimpleEvaluator {})),
+        "material-mobility" => Some(Box::new(MaterialMobilityEvaluator {})),
+        "pst" => Some(Box::new(PieceSquareEvaluator {})),
+        // generated tablebases in KQK, KRK and KPK, material and mobility in other positions.
+        "tablebase" => Some(Box::new(TablebaseEvaluator {
+            fallback: Box::new(MaterialMobilityEvaluator {}),
+        })),
+        _ => None,
+    };
 }
 
 // SimpleEvaluator evaluates game based on only material.
 pub struct SimpleEvaluator {}
 
 impl Evaluator for SimpleEvaluator {
-    fn evaluate(&self, board: &Board) -> f32 {
-        return simple_eval(board.squares);
+    fn evaluate(&self, position: &dyn Position) -> f32 {
+        return simple_eval(position);
+    }
+
+    fn breakdown(&self, position: &dyn Position) -> Vec<EvalTerm> {
+        vec![EvalTerm::new("material", simple_eval(position))]
     }
 }
 
@@ -46,20 +81,177 @@ impl Evaluator for SimpleEvaluator {
 // KQRBNP = number of kings, queens, rooks, bishops, knights and pawns
 // D,S,I = doubled, blocked and isolated pawns
 // M = Mobility (the number of legal moves)
+//
+// Kings are penalized for missing pawn shield, open files and attackers, see king_safety, and for
+// leaving the corner while there's material to attack them, see king_center_eval. Both terms are
+// tapered by game_phase. King and pawn against king positions are scaled by KPK bitbase.
 pub struct MaterialMobilityEvaluator {}
 
 impl Evaluator for MaterialMobilityEvaluator {
-    fn evaluate(&self, board: &Board) -> f32 {
-        let se = simple_eval(board.squares);
-        let ebp = self.eval_bad_pawns(board.squares);
+    fn evaluate(&self, position: &dyn Position) -> f32 {
+        let board: &Board = &position.to_board();
+        let se = simple_eval(board);
+        let ebp = self.eval_bad_pawns(*board.squares());
         let mob = self.eval_mobility(board);
+        let ks = king_safety_eval(board.squares()) * KING_SAFETY_EVAL_MODIFIER;
+        let kc = king_center_eval(board.squares()) * KING_CENTER_EVAL_MODIFIER;
+
+        return kpk::scale(board, se - ebp + mob + ks + kc);
+    }
+
+    fn breakdown(&self, position: &dyn Position) -> Vec<EvalTerm> {
+        let board: &Board = &position.to_board();
+        let d = self.count_doubled_pawns(*board.squares());
+        let b = self.count_blocked_pawns(*board.squares());
+        let i = self.count_isolated_pawns(*board.squares());
+        let mut terms = vec![
+            EvalTerm::new("material", simple_eval(board)),
+            EvalTerm::new("doubled pawns", -(d.0 - d.1) as f32 * PAWN_EVAL_MODIFIER),
+            EvalTerm::new("blocked pawns", -(b.0 - b.1) as f32 * PAWN_EVAL_MODIFIER),
+            EvalTerm::new("isolated pawns", -(i.0 - i.1) as f32 * PAWN_EVAL_MODIFIER),
+            EvalTerm::new("mobility", self.eval_mobility(board)),
+            EvalTerm::new(
+                "king safety",
+                king_safety_eval(board.squares()) * KING_SAFETY_EVAL_MODIFIER,
+            ),
+            EvalTerm::new(
+                "king centralization",
+                king_center_eval(board.squares()) * KING_CENTER_EVAL_MODIFIER,
+            ),
+        ];
+        let total: f32 = terms.iter().map(|t| t.value).sum();
+        let scaled = kpk::scale(board, total);
+        if scaled != total {
+            terms.push(EvalTerm::new("kpk bitbase", scaled - total));
+        }
+        terms
+    }
+}
+
+pub(crate) const PAWN_EVAL_MODIFIER: f32 = 0.5;
+pub(crate) const MOBILITY_EVAL_MODIFIER: f32 = 0.1;
+pub(crate) const KING_SAFETY_EVAL_MODIFIER: f32 = 1.0;
+pub(crate) const KING_CENTER_EVAL_MODIFIER: f32 = 0.1;
+
+// ENDGAME_MATERIAL is non-pawn material of both sides, in points, up to which positions are
+// evaluated as pure endgames, e.g. a rook and a minor piece each.
+const ENDGAME_MATERIAL: i32 = 16;
+// MIDGAME_MATERIAL is non-pawn material of both sides at the start of the game, positions with
+// that much are evaluated as pure middlegames.
+const MIDGAME_MATERIAL: i32 = 62;
+
+// game_phase returns 1 in the middlegame and 0 in the endgame, in between it falls linearly with
+// non-pawn material.
+pub fn game_phase(squares: &[Piece; 64]) -> f32 {
+    let material = non_pawn_material(squares).clamp(ENDGAME_MATERIAL, MIDGAME_MATERIAL);
+    (material - ENDGAME_MATERIAL) as f32 / (MIDGAME_MATERIAL - ENDGAME_MATERIAL) as f32
+}
 
-        return se - ebp + mob;
+// taper interpolates between middlegame and endgame values of a term by game phase.
+pub fn taper(midgame: f32, endgame: f32, phase: f32) -> f32 {
+    midgame * phase + endgame * (1.0 - phase)
+}
+
+// KingSafety counts what endangers the king of one color.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KingSafety {
+    pub missing_shield: i32, // files around the king without own pawn one or two squares ahead
+    pub open_files: i32,     // files around the king without own pawns
+    pub attackers: i32,      // pieces attacking squares around the king
+    pub attack_weight: i32,  // knights and bishops weigh 2, rooks 3, queens 5
+}
+
+impl KingSafety {
+    // penalty returns danger to the king in pawns. A single attacker is not counted, the weight
+    // of many grows with their number, as they attack together.
+    pub fn penalty(&self) -> f32 {
+        let attack = if self.attackers >= 2 {
+            (self.attack_weight * self.attackers) as f32 / 20.0
+        } else {
+            0.0
+        };
+        self.missing_shield as f32 * 0.2 + self.open_files as f32 * 0.25 + attack
+    }
+}
+
+// king_safety looks at files of the king and next to it and at squares the king can step to.
+pub fn king_safety(squares: &[Piece; 64], color: Color) -> KingSafety {
+    let mut safety = KingSafety::default();
+    let king = match squares
+        .iter()
+        .position(|p| p.p_type == PieceType::KING && p.color == color)
+    {
+        Some(king) => king as i32,
+        None => return safety,
+    };
+    let (file, rank) = (king % 8, king / 8);
+    let (forward, enemy) = match color {
+        Color::WHITE => (1, Color::BLACK),
+        _ => (-1, Color::WHITE),
+    };
+    let own_pawn = |square: Option<Square>| {
+        square.is_some_and(|s| squares[s].p_type == PieceType::PAWN && squares[s].color == color)
+    };
+    for f in (file - 1..=file + 1).filter(|f| (0..8).contains(f)) {
+        if !own_pawn(square_at(f, rank + forward)) && !own_pawn(square_at(f, rank + 2 * forward)) {
+            safety.missing_shield += 1;
+        }
+        if !(0..8).any(|r| own_pawn(square_at(f, r))) {
+            safety.open_files += 1;
+        }
+    }
+
+    let zone = KING_STEPS
+        .iter()
+        .filter_map(|(df, dr)| square_at(file + df, rank + dr))
+        .chain([king as Square]);
+    let pieces: HashSet<Square> = zone.flat_map(|s| attackers(squares, s, enemy)).collect();
+    for square in pieces {
+        let weight = match squares[square].p_type {
+            PieceType::KNIGHT | PieceType::BISHOP => 2,
+            PieceType::ROOK => 3,
+            PieceType::QUEEN => 5,
+            _ => continue, // pawns and the king are counted by the shield
+        };
+        safety.attackers += 1;
+        safety.attack_weight += weight;
     }
+    safety
+}
+
+// king_safety_eval returns danger to the black king minus danger to the white one. The danger
+// fades with material, kings are safe in the endgame, they should rather take part in it.
+pub(crate) fn king_safety_eval(squares: &[Piece; 64]) -> f32 {
+    let danger =
+        king_safety(squares, Color::BLACK).penalty() - king_safety(squares, Color::WHITE).penalty();
+    taper(danger, 0.0, game_phase(squares))
+}
+
+// king_centralization returns how far the white king is from the board edge minus the same of
+// the black one, in king steps: 0 on edges, 3 on d4, d5, e4 and e5.
+pub(crate) fn king_centralization(squares: &[Piece; 64]) -> i32 {
+    squares
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.p_type == PieceType::KING)
+        .map(|(square, p)| {
+            let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+            let steps = file.min(7 - file).min(rank).min(7 - rank);
+            if p.color == Color::WHITE {
+                steps
+            } else {
+                -steps
+            }
+        })
+        .sum()
 }
 
-const PAWN_EVAL_MODIFIER: f32 = 0.5;
-const MOBILITY_EVAL_MODIFIER: f32 = 0.1;
+// king_center_eval keeps kings in their corners in the middlegame and brings them to the center
+// in the endgame, the same centralization is a penalty in one phase and a bonus in the other.
+pub(crate) fn king_center_eval(squares: &[Piece; 64]) -> f32 {
+    let steps = king_centralization(squares) as f32;
+    taper(-steps, steps, game_phase(squares))
+}
 
 impl MaterialMobilityEvaluator {
     // get_pawn_negative_eval sums negative pawns locations and returns evaluation.
@@ -68,7 +260,7 @@ impl MaterialMobilityEvaluator {
         let b = self.count_blocked_pawns(game);
         let i = self.count_isolated_pawns(game);
 
-        return (d.0 + b.0 + i.0 - d.1 + b.1 + i.1) as f32 * PAWN_EVAL_MODIFIER;
+        return (d.0 + b.0 + i.0 - d.1 - b.1 - i.1) as f32 * PAWN_EVAL_MODIFIER;
     }
 
     // get_pawns_map maps pawns location to its columns.
@@ -96,7 +288,7 @@ impl MaterialMobilityEvaluator {
     // value for white color is returned first.
     //
     // e.g. 3 pawn on b, 1 on c, 1 on d, 2 on e -> 5
-    fn count_doubled_pawns(&self, game: [Piece; 64]) -> (i32, i32) {
+    pub(crate) fn count_doubled_pawns(&self, game: [Piece; 64]) -> (i32, i32) {
         let col_map = self.get_pawns_map(game);
         return (
             col_map
@@ -117,7 +309,7 @@ impl MaterialMobilityEvaluator {
     }
 
     // count_isolated_pawns counts isolated pawns for each color.
-    fn count_isolated_pawns(&self, game: [Piece; 64]) -> (i32, i32) {
+    pub(crate) fn count_isolated_pawns(&self, game: [Piece; 64]) -> (i32, i32) {
         fn count_per_color(m: &HashMap<usize, i32>) -> i32 {
             let mut w = 0;
 
@@ -145,7 +337,7 @@ impl MaterialMobilityEvaluator {
 
     // count_blocked_pawns counts blocked pawns for each color.
     // pawn is blocked when it cannot move forward.
-    fn count_blocked_pawns(&self, game: [Piece; 64]) -> (i32, i32) {
+    pub(crate) fn count_blocked_pawns(&self, game: [Piece; 64]) -> (i32, i32) {
         let mut w = 0;
         let mut b = 0;
 
@@ -169,11 +361,16 @@ impl MaterialMobilityEvaluator {
     }
 
     fn eval_mobility(&self, board: &Board) -> f32 {
+        return self.mobility(board) * MOBILITY_EVAL_MODIFIER;
+    }
+
+    // mobility returns difference of numbers of legal moves of white and black pieces.
+    pub(crate) fn mobility(&self, board: &Board) -> f32 {
         fn eval_mobility_for_color(board: &mut Board, color: Color) -> f32 {
             let mut eval: f32 = 0.0;
             board.color_to_move = color;
             board
-                .squares
+                .squares()
                 .iter()
                 .enumerate()
                 .map(|(inx, p)| (inx, p))
@@ -192,17 +389,55 @@ impl MaterialMobilityEvaluator {
             return eval;
         }
         let mut b_clone = board.clone();
-        return (eval_mobility_for_color(&mut b_clone, Color::WHITE)
-            - eval_mobility_for_color(&mut b_clone, Color::BLACK))
-            * MOBILITY_EVAL_MODIFIER;
+        return eval_mobility_for_color(&mut b_clone, Color::WHITE)
+            - eval_mobility_for_color(&mut b_clone, Color::BLACK);
     }
 }
 
+// PieceSquareEvaluator evaluates material and placement of pieces by piece-square tables of pst
+// module. Middlegame and endgame tables are tapered by game_phase, so e.g. the king is kept
+// behind pawns in the middlegame and brought to the center as material disappears.
+pub struct PieceSquareEvaluator {}
+
+impl Evaluator for PieceSquareEvaluator {
+    fn evaluate(&self, position: &dyn Position) -> f32 {
+        simple_eval(position) + self.placement(position)
+    }
+
+    fn breakdown(&self, position: &dyn Position) -> Vec<EvalTerm> {
+        vec![
+            EvalTerm::new("material", simple_eval(position)),
+            EvalTerm::new("piece-square", self.placement(position)),
+        ]
+    }
+}
+
+impl PieceSquareEvaluator {
+    // placement returns bonuses of piece-square tables in pawns, Board keeps them up to date.
+    fn placement(&self, position: &dyn Position) -> f32 {
+        let (midgame, endgame) = position.piece_square();
+        taper(
+            midgame as f32,
+            endgame as f32,
+            game_phase(&position.squares()),
+        ) / 100.0
+    }
+}
+
+// non_pawn_material returns points of knights, bishops, rooks and queens of both colors.
+pub(crate) fn non_pawn_material(squares: &[Piece; 64]) -> i32 {
+    squares
+        .iter()
+        .filter(|p| p.p_type != PieceType::PAWN && p.p_type != PieceType::KING)
+        .map(|p| p.p_type.points())
+        .sum()
+}
+
 pub struct MiniMaxiEvaluator {}
 
 impl Evaluator for MiniMaxiEvaluator {
-    fn evaluate(&self, board: &Board) -> f32 {
-        let mut b = board.clone();
+    fn evaluate(&self, position: &dyn Position) -> f32 {
+        let mut b = position.to_board().into_owned();
         let eval = self.maxi(&mut b, 3);
         return eval;
     }
@@ -211,7 +446,7 @@ impl Evaluator for MiniMaxiEvaluator {
 impl MiniMaxiEvaluator {
     fn maxi(&self, board: &mut Board, depth: usize) -> f32 {
         if depth == 0 {
-            return simple_eval(board.squares);
+            return simple_eval(board);
         }
 
         let moves = self.get_all_possible_moves(board);
@@ -225,7 +460,7 @@ impl MiniMaxiEvaluator {
         let mut best_evaluation = f32::NEG_INFINITY;
 
         for mv in &moves {
-            board.make_move(mv.clone(), true);
+            board.make_transition(mv.clone());
             let evaluation = -self.maxi(board, depth - 1);
             best_evaluation = f32::max(best_evaluation, evaluation);
             // board.unmake_move(); // TODO
@@ -235,91 +470,65 @@ impl MiniMaxiEvaluator {
     }
 
     fn get_all_possible_moves(&self, board: &Board) -> Vec<Transition> {
-        let mut transitions = Vec::new();
-        board
-            .squares
-            .iter()
-            .enumerate()
-            .map(|(inx, p)| (inx, p))
-            .filter(|(_, p)| p.color == board.color_to_move)
-            .for_each(|(inx, p)| {
-                let possible_moves = p.get_moves(inx);
-                for m in &possible_moves {
-                    match board.validate_move(inx, (inx as i32 + m) as usize) {
-                        Ok(adt) => {
-                            let from = inx;
-                            let to = (inx as i32 + m) as usize;
-                            transitions.push(Transition::new(
-                                from,
-                                to,
-                                TransitionFlag::Move,
-                                PieceType::NONE,
-                                board.squares[from],
-                                board.squares[to],
-                            ));
-                            if adt.is_some() {
-                                transitions.push(adt.unwrap());
-                            }
-                        }
-                        Err(_) => continue,
-                    }
-                }
-            });
-        return transitions;
+        return board.generate_transitions();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::board::Board;
-    use crate::evaluation::{Evaluator, MaterialMobilityEvaluator, MiniMaxiEvaluator};
+    use crate::board::{Board, CastlingRights, Square};
+    use crate::evaluation::{
+        game_phase, king_center_eval, king_centralization, king_safety, taper, Evaluator,
+        KingSafety, MaterialMobilityEvaluator, MiniMaxiEvaluator, PieceSquareEvaluator,
+        SimpleEvaluator,
+    };
     use crate::piece::{Color, Piece, PieceType};
+    use crate::position::Placement;
+
+    // pawns sets up a position with pawns only.
+    fn pawns(white: &[Square], black: &[Square]) -> Board {
+        let pawn = |color| move |s: &Square| (*s, Piece::new(PieceType::PAWN, color));
+        let pieces = white
+            .iter()
+            .map(pawn(Color::WHITE))
+            .chain(black.iter().map(pawn(Color::BLACK)));
+        Board::from_pieces(pieces, Color::WHITE, CastlingRights::default(), None).unwrap()
+    }
 
     #[test]
     fn test_isolated_pawns() {
         let m = MaterialMobilityEvaluator {};
-        let mut game = [Piece::default(); 64];
-        game[1] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[13] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[5] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[6] = Piece::new(PieceType::PAWN, Color::WHITE);
+        let b = pawns(&[1, 13, 5, 6], &[]);
+        assert_eq!(m.count_isolated_pawns(*b.squares()), (1, 0));
 
-        assert_eq!(m.count_isolated_pawns(game), (1, 0));
-
-        let mut game = [Piece::default(); 64];
-        game[1] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[17] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[14] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[6] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[3] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[4] = Piece::new(PieceType::PAWN, Color::WHITE);
-
-        assert_eq!(m.count_isolated_pawns(game), (4, 0));
+        let b = pawns(&[1, 17, 14, 6, 3, 4], &[]);
+        assert_eq!(m.count_isolated_pawns(*b.squares()), (4, 0));
     }
 
     #[test]
     fn test_count_double_pawns() {
         let m = MaterialMobilityEvaluator {};
-        let mut game = [Piece::default(); 64];
-        game[1] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[17] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[14] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[6] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[3] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[4] = Piece::new(PieceType::PAWN, Color::WHITE);
-        assert_eq!(m.count_doubled_pawns(game), (4, 0));
+        let b = pawns(&[1, 17, 14, 6, 3, 4], &[]);
+        assert_eq!(m.count_doubled_pawns(*b.squares()), (4, 0));
     }
 
     #[test]
     fn test_count_blocked_pawns() {
         let m = MaterialMobilityEvaluator {};
-        let mut game = [Piece::default(); 64];
-        game[1] = Piece::new(PieceType::PAWN, Color::WHITE);
-        game[17] = Piece::new(PieceType::PAWN, Color::WHITE);
+        let b = pawns(&[1, 17], &[9, 25]);
+        assert_eq!(m.count_blocked_pawns(*b.squares()), (2, 2));
+    }
 
-        game[9] = Piece::new(PieceType::PAWN, Color::BLACK);
-        game[25] = Piece::new(PieceType::PAWN, Color::BLACK);
-        assert_eq!(m.count_blocked_pawns(game), (2, 2));
+    #[test]
+    fn test_evaluate_placement() {
+        let b = pawns(&[12, 13], &[52]);
+        let placement = Placement {
+            squares: *b.squares(),
+            color_to_move: Color::WHITE,
+        };
+        assert_eq!(SimpleEvaluator {}.evaluate(&placement), 1.0);
+        let m = MaterialMobilityEvaluator {};
+        assert_eq!(m.evaluate(&placement), m.evaluate(&b));
     }
 
     #[test]
@@ -338,6 +547,83 @@ Bf4 Qxf4+ 21. Kb1";
         println!("{}", e)
     }
 
+    #[test]
+    fn test_piece_square_eval() {
+        let e = PieceSquareEvaluator {};
+        let mut b = Board::default();
+        assert_eq!(e.evaluate(&b), 0.0);
+        b.read_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+            .unwrap();
+        // the pawn leaves e2 (-20) for e4 (+20).
+        assert_eq!(e.evaluate(&b), 0.4);
+        let terms = e.breakdown(&b);
+        assert_eq!((terms[0].value, terms[1].value), (0.0, 0.4));
+
+        // the central king is bad in the middlegame and good in the endgame.
+        b.read_fen("rnbqkbnr/pppppppp/8/8/3K4/8/PPPPPPPP/RNBQ1BNR w kq - 0 1")
+            .unwrap();
+        assert_eq!(e.evaluate(&b), -0.4);
+        b.read_fen("4k3/8/8/8/3K4/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(e.evaluate(&b), 0.7);
+        // with queens only it's nearly an endgame.
+        b.read_fen("q3k3/8/8/8/3K4/8/8/7Q w - - 0 1").unwrap();
+        let phase = game_phase(b.squares());
+        assert!((e.evaluate(&b) - taper(-0.4, 0.7, phase)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_game_phase() {
+        let mut b = Board::default();
+        assert_eq!(game_phase(b.squares()), 1.0);
+        b.read_fen("r2qk2r/8/8/8/8/8/8/R2QK2R w - - 0 1").unwrap();
+        assert!((game_phase(b.squares()) - 22.0 / 46.0).abs() < 1e-6);
+        b.read_fen("4k3/8/8/8/3K4/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(game_phase(b.squares()), 0.0);
+        assert_eq!(taper(1.0, -1.0, 0.25), -0.5);
+
+        // centralization of the king changes sign with the phase.
+        assert_eq!(king_centralization(b.squares()), 3);
+        assert_eq!(king_center_eval(b.squares()), 3.0);
+        b.read_fen("rnbqkbnr/pppppppp/8/8/3K4/8/PPPPPPPP/RNBQ1BNR w kq - 0 1")
+            .unwrap();
+        assert_eq!(king_center_eval(b.squares()), -3.0);
+        let terms = MaterialMobilityEvaluator {}.breakdown(&b);
+        assert_eq!(terms[6].name, "king centralization");
+        assert!((terms[6].value + 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_king_safety() {
+        let mut b = Board::default();
+        assert_eq!(
+            king_safety(b.squares(), Color::WHITE),
+            KingSafety::default()
+        );
+        // white king behind its pawns, black king on an open file attacked by queen and rook.
+        b.read_fen("r2q1rk1/pp3p1p/2n3p1/8/8/3Q4/PPP2PPP/3R1RK1 w - - 0 1")
+            .unwrap();
+        let white = king_safety(b.squares(), Color::WHITE);
+        assert_eq!(white, KingSafety::default());
+        b.read_fen("r2q1r2/pp3p2/2n3k1/8/8/3Q4/PPP2PPP/3R1RK1 w - - 0 1")
+            .unwrap();
+        let black = king_safety(b.squares(), Color::BLACK);
+        assert_eq!(black.missing_shield, 3);
+        assert_eq!(black.open_files, 2);
+        assert_eq!((black.attackers, black.attack_weight), (1, 5));
+        b.read_fen("r2q1r2/pp3p2/2n3k1/6N1/8/3Q4/PPP2PPP/3R1RK1 w - - 0 1")
+            .unwrap();
+        let black = king_safety(b.squares(), Color::BLACK);
+        assert_eq!((black.attackers, black.attack_weight), (2, 7));
+        assert!(black.penalty() > 1.0);
+        let terms = MaterialMobilityEvaluator {}.breakdown(&b);
+        assert_eq!(terms[5].name, "king safety");
+        assert_eq!(terms[5].value, black.penalty() * game_phase(b.squares()));
+
+        // kings are not in danger in endgames.
+        b.read_fen("8/8/6k1/6N1/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(MaterialMobilityEvaluator {}.breakdown(&b)[5].value, 0.0);
+    }
+
     #[test]
     fn test_mini_maxi_eval() {
         let pgn = "1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 4. d3 c6 5. Bd2 Qc7 6. Qe2 Bd7 7. O-O-O Na6 8.
@@ -349,4 +635,14 @@ Bf4 Qxf4+ 21. Kb1";
         let e = MiniMaxiEvaluator {};
         // println!()("{}", e.evaluate(&b));
     }
+
+    #[test]
+    fn test_possible_moves_with_promotion() {
+        let mut b = Board::default();
+        b.read_fen("3r4/4P1k1/8/8/8/8/8/K7").unwrap();
+        let e = MiniMaxiEvaluator {};
+        let moves = e.get_all_possible_moves(&b);
+        // 3 king moves, 4 promotions by push and 4 by capture.
+        assert_eq!(moves.len(), 11);
+    }
 }
@@ -0,0 +1,211 @@
+use crate::board::{Board, Transition};
+use crate::notation::to_internal;
+use crate::search::{Score, SearchLine};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+// Experience is what the engine learned about a move in previous games: score of the deepest
+// search that played it and how many times it was played.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Experience {
+    pub depth: usize,
+    pub score: Score, // from the point of view of color that has the move
+    pub visits: u32,
+}
+
+// ExperienceBook is a learning file, it remembers moves the engine played in root positions with
+// their scores and suggests them again when they are better than what a shallower search finds.
+// Positions are keyed by pieces placement and color to move, as in repetition detection.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExperienceBook {
+    positions: HashMap<String, HashMap<String, Experience>>,
+}
+
+impl ExperienceBook {
+    // load reads the book from file, missing file is an empty book.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => text.parse(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(ExperienceBook::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    // save writes the book to a temporary file first and renames it, so the old book survives a
+    // crash in the middle of writing.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(self.to_string().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // get returns experience of all moves played in the position, the best one first.
+    pub fn get(&self, board: &Board) -> Vec<(Transition, Experience)> {
+        let moves = match self.positions.get(&board.position_fen()) {
+            Some(moves) => moves,
+            None => return Vec::new(),
+        };
+        let mut known: Vec<(Transition, Experience)> = board
+            .generate_transitions()
+            .into_iter()
+            .filter_map(|t| moves.get(&to_internal(&t)).map(|e| (t, *e)))
+            .collect();
+        known.sort_by(|(_, a), (_, b)| {
+            b.score
+                .win_probability()
+                .total_cmp(&a.score.win_probability())
+        });
+        known
+    }
+
+    // record remembers that the move was played after search of given depth. Deeper searches
+    // replace scores of shallower ones.
+    pub fn record(&mut self, board: &Board, t: &Transition, score: Score, depth: usize) {
+        let experience = self
+            .positions
+            .entry(board.position_fen())
+            .or_default()
+            .entry(to_internal(t))
+            .or_insert(Experience {
+                depth,
+                score,
+                visits: 0,
+            });
+        if depth >= experience.depth {
+            experience.depth = depth;
+            experience.score = score;
+        }
+        experience.visits += 1;
+    }
+
+    // choose returns a move from experience that was searched deeper than 'depth' and scored
+    // better than the best of search lines, None when search should be trusted.
+    pub fn choose(
+        &self,
+        board: &Board,
+        lines: &[SearchLine],
+        depth: usize,
+    ) -> Option<(Transition, Experience)> {
+        let best = lines.first()?.score.win_probability();
+        self.get(board)
+            .into_iter()
+            .find(|(_, e)| e.depth > depth && e.score.win_probability() > best)
+    }
+}
+
+// ExperienceBook is written as one move per line: position, move, depth, score and visits
+// separated by ';', e.g. 'rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w;e2e4;12;cp 35;3'.
+impl Display for ExperienceBook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut positions: Vec<_> = self.positions.iter().collect();
+        positions.sort_by_key(|(fen, _)| *fen);
+        for (fen, moves) in positions {
+            let mut moves: Vec<_> = moves.iter().collect();
+            moves.sort_by_key(|(m, _)| *m);
+            for (m, e) in moves {
+                let score = match e.score {
+                    Score::Centipawns(cp) => format!("cp {}", cp),
+                    Score::Mate(moves) => format!("mate {}", moves),
+                };
+                writeln!(f, "{};{};{};{};{}", fen, m, e.depth, score, e.visits)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ExperienceBook {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut book = ExperienceBook::default();
+        for (i, line) in s.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid experience in line {}", i + 1),
+                )
+            };
+            let fields: Vec<&str> = line.split(';').collect();
+            let (fen, m, depth, score, visits) = match fields.as_slice() {
+                [fen, m, depth, score, visits] => (fen, m, depth, score, visits),
+                _ => return Err(invalid()),
+            };
+            let score = match score.split_once(' ') {
+                Some(("cp", cp)) => Score::Centipawns(cp.parse().map_err(|_| invalid())?),
+                Some(("mate", moves)) => Score::Mate(moves.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            };
+            let experience = Experience {
+                depth: depth.parse().map_err(|_| invalid())?,
+                score,
+                visits: visits.parse().map_err(|_| invalid())?,
+            };
+            book.positions
+                .entry(fen.to_string())
+                .or_default()
+                .insert(m.to_string(), experience);
+        }
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::experience::ExperienceBook;
+    use crate::notation::to_internal;
+    use crate::search::{Score, SearchLine};
+
+    #[test]
+    fn test_experience_book() {
+        let board = Board::default();
+        let moves = board.generate_transitions();
+        let e4 = *moves.iter().find(|t| to_internal(t) == "e2e4").unwrap();
+        let d4 = *moves.iter().find(|t| to_internal(t) == "d2d4").unwrap();
+
+        let mut book = ExperienceBook::default();
+        book.record(&board, &e4, Score::Centipawns(30), 8);
+        book.record(&board, &e4, Score::Centipawns(10), 4);
+        book.record(&board, &d4, Score::Centipawns(50), 10);
+        let known = book.get(&board);
+        assert_eq!(known.len(), 2);
+        assert_eq!(to_internal(&known[0].0), "d2d4");
+        assert_eq!(known[1].1.score, Score::Centipawns(30));
+        assert_eq!(known[1].1.visits, 2);
+
+        // shallow search is overruled by deeper experience, deep one is not.
+        let lines = vec![SearchLine {
+            score: Score::Centipawns(20),
+            pv: vec![e4],
+        }];
+        assert_eq!(
+            to_internal(&book.choose(&board, &lines, 6).unwrap().0),
+            "d2d4"
+        );
+        assert!(book.choose(&board, &lines, 10).is_none());
+
+        let path = std::env::temp_dir().join("chust_test_experience.txt");
+        book.save(&path).unwrap();
+        assert_eq!(ExperienceBook::load(&path).unwrap(), book);
+        std::fs::remove_file(&path).unwrap();
+        assert!(ExperienceBook::load(&path).unwrap().is_empty());
+        assert!("fen;e2e4;x;cp 1;1".parse::<ExperienceBook>().is_err());
+    }
+}
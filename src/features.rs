@@ -0,0 +1,291 @@
+use crate::backend::format_policy;
+use crate::board::{attackers, Board, Transition};
+use crate::evaluation::MaterialMobilityEvaluator;
+use crate::notation::square_at;
+use crate::pgn::PgnGame;
+use crate::piece::{Color, PieceType};
+use std::io::{self, Write};
+
+// FEATURE_NAMES lists features returned by extract, in the same order. The order is stable,
+// new features are only appended at the end. Pairs of features are given for white first.
+//
+//  0      side_to_move          1 when white has the move, 0 otherwise
+//  1-5    pawns..queens         material difference (white - black) per piece type
+//  6, 7   mobility              number of legal moves of each side
+//  8, 9   king_zone_attacks     squares around the king (and king square) attacked by opponent
+//  10, 11 king_pawn_shield      own pawns on 2 ranks in front of the king, its and adjacent files
+//  12, 13 doubled_pawns         pawns on files with more than one own pawn
+//  14, 15 isolated_pawns        pawns without own pawns on adjacent files
+//  16, 17 passed_pawns          pawns without opponent pawns in front on the same or adjacent files
+//  18     in_check              1 when side to move is in check, 0 otherwise
+pub const FEATURE_NAMES: [&str; 19] = [
+    "side_to_move",
+    "pawns",
+    "knights",
+    "bishops",
+    "rooks",
+    "queens",
+    "white_mobility",
+    "black_mobility",
+    "white_king_zone_attacks",
+    "black_king_zone_attacks",
+    "white_king_pawn_shield",
+    "black_king_pawn_shield",
+    "white_doubled_pawns",
+    "black_doubled_pawns",
+    "white_isolated_pawns",
+    "black_isolated_pawns",
+    "white_passed_pawns",
+    "black_passed_pawns",
+    "in_check",
+];
+
+const MATERIAL: [PieceType; 5] = [
+    PieceType::PAWN,
+    PieceType::KNIGHT,
+    PieceType::BISHOP,
+    PieceType::ROOK,
+    PieceType::QUEEN,
+];
+
+// extract returns features of the position in order described by FEATURE_NAMES, e.g. for
+// training models predicting result of a game.
+pub fn extract(board: &Board) -> Vec<f32> {
+    let mut features = Vec::with_capacity(FEATURE_NAMES.len());
+    features.push(bool_feature(board.color_to_move == Color::WHITE));
+    for p_type in &MATERIAL {
+        features.push(
+            (count(board, *p_type, Color::WHITE) - count(board, *p_type, Color::BLACK)) as f32,
+        );
+    }
+    for color in [Color::WHITE, Color::BLACK] {
+        let mut b = board.clone();
+        b.color_to_move = color;
+        features.push(b.generate_transitions().len() as f32);
+    }
+    for color in [Color::WHITE, Color::BLACK] {
+        features.push(king_zone_attacks(board, color) as f32);
+    }
+    for color in [Color::WHITE, Color::BLACK] {
+        features.push(king_pawn_shield(board, color) as f32);
+    }
+    let evaluator = MaterialMobilityEvaluator {};
+    let doubled = evaluator.count_doubled_pawns(*board.squares());
+    let isolated = evaluator.count_isolated_pawns(*board.squares());
+    features.extend_from_slice(&[
+        doubled.0 as f32,
+        doubled.1 as f32,
+        isolated.0 as f32,
+        isolated.1 as f32,
+        passed_pawns(board, Color::WHITE) as f32,
+        passed_pawns(board, Color::BLACK) as f32,
+    ]);
+    features.push(bool_feature(board.is_in_check()));
+    features
+}
+
+fn bool_feature(value: bool) -> f32 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn count(board: &Board, p_type: PieceType, color: Color) -> i32 {
+    board
+        .squares()
+        .iter()
+        .filter(|p| p.p_type == p_type && p.color == color)
+        .count() as i32
+}
+
+fn king_square(board: &Board, color: Color) -> Option<usize> {
+    board
+        .squares()
+        .iter()
+        .position(|p| p.p_type == PieceType::KING && p.color == color)
+}
+
+// forward returns rank direction in which pawns of given color move.
+fn forward(color: Color) -> i32 {
+    if color == Color::WHITE {
+        1
+    } else {
+        -1
+    }
+}
+
+fn king_zone_attacks(board: &Board, color: Color) -> usize {
+    let king = match king_square(board, color) {
+        Some(king) => king,
+        None => return 0,
+    };
+    let (file, rank) = ((king % 8) as i32, (king / 8) as i32);
+    let mut attacked = 0;
+    for df in -1..=1 {
+        for dr in -1..=1 {
+            if let Some(square) = square_at(file + df, rank + dr) {
+                if !attackers(board.squares(), square, color.opposite()).is_empty() {
+                    attacked += 1;
+                }
+            }
+        }
+    }
+    attacked
+}
+
+fn king_pawn_shield(board: &Board, color: Color) -> usize {
+    let king = match king_square(board, color) {
+        Some(king) => king,
+        None => return 0,
+    };
+    let (file, rank) = ((king % 8) as i32, (king / 8) as i32);
+    let mut shield = 0;
+    for df in -1..=1 {
+        for distance in 1..=2 {
+            if let Some(square) = square_at(file + df, rank + forward(color) * distance) {
+                let p = board.piece_at(square);
+                if p.p_type == PieceType::PAWN && p.color == color {
+                    shield += 1;
+                }
+            }
+        }
+    }
+    shield
+}
+
+fn passed_pawns(board: &Board, color: Color) -> usize {
+    let is_opponent_pawn = |square: usize| {
+        let p = board.piece_at(square);
+        p.p_type == PieceType::PAWN && p.color == color.opposite()
+    };
+    (0..64)
+        .filter(|&square| {
+            let p = board.piece_at(square);
+            if p.p_type != PieceType::PAWN || p.color != color {
+                return false;
+            }
+            let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+            let mut r = rank + forward(color);
+            while (0..8).contains(&r) {
+                for df in -1..=1 {
+                    if square_at(file + df, r).is_some_and(is_opponent_pawn) {
+                        return false;
+                    }
+                }
+                r += forward(color);
+            }
+            true
+        })
+        .count()
+}
+
+// FeatureRow is a position with its features and, when known, result of the game from white
+// point of view: 1, 0.5 or 0. Policy over root moves can be added from search results.
+pub struct FeatureRow {
+    pub fen: String,
+    pub features: Vec<f32>,
+    pub result: Option<f32>,
+    pub policy: Vec<(Transition, f32)>,
+}
+
+impl FeatureRow {
+    pub fn new(board: &Board, result: Option<f32>) -> Self {
+        FeatureRow {
+            fen: board.to_fen(),
+            features: extract(board),
+            result,
+            policy: Vec::new(),
+        }
+    }
+}
+
+// game_rows returns rows for every position of the game labelled with its result. Positions are
+// returned up to the first illegal move.
+pub fn game_rows(game: &PgnGame) -> Vec<FeatureRow> {
+    let result = match game.tag("Result") {
+        Some("1-0") => Some(1.0),
+        Some("0-1") => Some(0.0),
+        Some("1/2-1/2") => Some(0.5),
+        _ => None,
+    };
+    let mut rows = Vec::new();
+    if let Ok(board) = game.start_position() {
+        rows.push(FeatureRow::new(&board, result));
+    }
+    let _ = game.replay(|board| rows.push(FeatureRow::new(board, result)));
+    rows
+}
+
+// write_csv writes rows as CSV with fen, one column per feature, result and policy columns.
+// Policy is written with format_policy, e.g. 'e2e4:0.612 d2d4:0.388'.
+pub fn write_csv<W: Write>(w: &mut W, rows: &[FeatureRow]) -> io::Result<()> {
+    writeln!(w, "fen,{},result,policy", FEATURE_NAMES.join(","))?;
+    for row in rows {
+        let values: Vec<String> = row.features.iter().map(|v| v.to_string()).collect();
+        let result = row.result.map(|r| r.to_string()).unwrap_or_default();
+        writeln!(
+            w,
+            "{},{},{},{}",
+            row.fen,
+            values.join(","),
+            result,
+            format_policy(&row.policy)
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::features::{extract, game_rows, write_csv, FEATURE_NAMES};
+    use crate::pgn::split_games;
+
+    fn feature(features: &[f32], name: &str) -> f32 {
+        features[FEATURE_NAMES.iter().position(|n| *n == name).unwrap()]
+    }
+
+    #[test]
+    fn test_extract() {
+        let start = extract(&Board::default());
+        assert_eq!(start.len(), FEATURE_NAMES.len());
+        assert_eq!(feature(&start, "side_to_move"), 1.0);
+        assert_eq!(feature(&start, "pawns"), 0.0);
+        assert_eq!(feature(&start, "white_mobility"), 20.0);
+        assert_eq!(feature(&start, "black_mobility"), 20.0);
+        assert_eq!(feature(&start, "white_king_pawn_shield"), 3.0);
+        assert_eq!(feature(&start, "white_passed_pawns"), 0.0);
+
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/8/1P6/8/P1PP4/4K2q w").unwrap();
+        let f = extract(&b);
+        assert_eq!(feature(&f, "pawns"), 4.0);
+        assert_eq!(feature(&f, "queens"), -1.0);
+        assert_eq!(feature(&f, "white_isolated_pawns"), 0.0);
+        assert_eq!(feature(&f, "white_passed_pawns"), 4.0);
+        assert_eq!(feature(&f, "in_check"), 1.0);
+        assert_eq!(feature(&f, "white_king_zone_attacks"), 2.0); // e1 and f1
+    }
+
+    #[test]
+    fn test_game_rows() {
+        let games = split_games("[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1");
+        let rows = game_rows(&games[0]);
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().all(|r| r.result == Some(0.0)));
+        assert_eq!(feature(&rows[4].features, "in_check"), 1.0);
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &rows).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 6);
+        assert!(csv
+            .lines()
+            .next()
+            .unwrap()
+            .starts_with("fen,side_to_move,pawns"));
+        assert!(csv.lines().nth(1).unwrap().ends_with(",0,"));
+    }
+}
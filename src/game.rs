@@ -0,0 +1,327 @@
+use crate::board::{Board, DrawReason, GameState, Move, Transition};
+use crate::notation::to_san;
+use crate::pgn::PgnGame;
+use crate::piece::Color;
+use crate::zobrist::hash;
+use std::collections::{HashMap, HashSet};
+
+// Game is a game being played or edited: the start position and moves made from it. Positions
+// and their Zobrist hashes are kept for every ply, so moves can be taken back and repetitions or
+// known positions found without replaying the game.
+#[derive(Clone)]
+pub struct Game {
+    boards: Vec<Board>, // the start position and positions after every move
+    moves: Vec<MoveRecord>,
+    hashes: Vec<u64>,
+}
+
+// MoveRecord is a move of the game with its SAN, written in the position it was played in.
+#[derive(Clone, Debug)]
+pub struct MoveRecord {
+    pub r#move: Move,
+    pub san: String,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(Board::default())
+    }
+}
+
+impl Game {
+    pub fn new(start: Board) -> Self {
+        Game {
+            hashes: vec![hash(&start)],
+            boards: vec![start],
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.boards[0]
+    }
+
+    // board returns the current position.
+    pub fn board(&self) -> &Board {
+        self.boards.last().expect("game has the start position")
+    }
+
+    // make_move plays legal move in the current position. The move is matched by its squares and
+    // promotion, the legal one is played and recorded, so flags of castles and en passant don't
+    // have to be set by the caller.
+    pub fn make_move(&mut self, t: Transition) -> Result<(), &'static str> {
+        let board = self.board();
+        let t = board
+            .generate_transitions()
+            .into_iter()
+            .find(|l| l.from == t.from && l.to == t.to && l.promotion == t.promotion)
+            .ok_or("illegal move")?;
+        let san = to_san(board, &t);
+        let mut board = board.clone();
+        board.make_transition(t);
+        self.hashes.push(hash(&board));
+        self.boards.push(board);
+        self.moves.push(MoveRecord { r#move: t, san });
+        Ok(())
+    }
+
+    // moves returns moves made from the start position.
+    pub fn moves(&self) -> &[MoveRecord] {
+        &self.moves
+    }
+
+    // undo takes back the last move, hashes of positions after it are forgotten too.
+    pub fn undo(&mut self) -> Option<Transition> {
+        let record = self.moves.pop()?;
+        self.boards.pop();
+        self.hashes.pop();
+        Some(record.r#move)
+    }
+
+    // hash_history returns Zobrist hashes of the start position and of positions after every move,
+    // so hash_history()[ply] is the position before move 'ply'. Hashes are the same in every
+    // process, see zobrist, so they can be compared with hashes stored in files.
+    pub fn hash_history(&self) -> &[u64] {
+        &self.hashes
+    }
+
+    // game_state tells if the game is over, the current position repeated for the third time is
+    // a draw too.
+    pub fn game_state(&self) -> GameState {
+        let state = self.board().game_state();
+        let current = self.hashes[self.hashes.len() - 1];
+        if state == GameState::Ongoing && self.hashes.iter().filter(|h| **h == current).count() >= 3
+        {
+            return GameState::Draw(DrawReason::Repetition);
+        }
+        state
+    }
+
+    // to_pgn returns the game with the Seven Tag Roster, unknown values are '?' and can be set
+    // with PgnGame::set_tag. Result is given by game_state, so games that go on have '*'. Games
+    // from other positions than the standard start have FEN and SetUp tags and their move
+    // numbers continue from the FEN.
+    pub fn to_pgn(&self) -> PgnGame {
+        let result = self.game_state().result();
+        let mut tags: Vec<(String, String)> = [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result),
+        ]
+        .iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect();
+        let fen = self.start().to_fen();
+        if fen != Board::default().to_fen() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), fen));
+        }
+
+        let mut movetext = Vec::new();
+        for (i, (board, record)) in self.boards.iter().zip(&self.moves).enumerate() {
+            if board.color_to_move == Color::WHITE {
+                movetext.push(format!("{}.", board.fullmove_number()));
+            } else if i == 0 {
+                movetext.push(format!("{}...", board.fullmove_number()));
+            }
+            movetext.push(record.san.clone());
+        }
+        movetext.push(result.to_string());
+        PgnGame {
+            tags,
+            movetext: movetext.join(" "),
+        }
+    }
+
+    // find_novelty returns ply of the first move, counted from 1, after which the game leaves
+    // positions of the index, transpositions included. None when the game stays in known positions
+    // or starts from a position the index doesn't know, so there's no theory to leave.
+    pub fn find_novelty(&self, index: &PositionIndex) -> Option<usize> {
+        if index.games(self.hashes[0]) == 0 {
+            return None;
+        }
+        self.hashes.iter().position(|h| index.games(*h) == 0)
+    }
+}
+
+// PositionIndex is a reference database indexed by positions: Zobrist hashes of positions with
+// the number of games they occur in.
+#[derive(Clone, Debug, Default)]
+pub struct PositionIndex {
+    games: HashMap<u64, usize>,
+}
+
+impl PositionIndex {
+    // add indexes positions of a game, a position repeated in the game is counted once.
+    pub fn add(&mut self, game: &Game) {
+        let unique: HashSet<&u64> = game.hash_history().iter().collect();
+        for h in unique {
+            *self.games.entry(*h).or_default() += 1;
+        }
+    }
+
+    // games returns number of indexed games in which position with given hash occurs.
+    pub fn games(&self, hash: u64) -> usize {
+        self.games.get(&hash).copied().unwrap_or_default()
+    }
+
+    // len returns number of different positions.
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Board, DrawReason, GameState, Transition, TransitionFlag};
+    use crate::game::{Game, PositionIndex};
+    use crate::notation::parse_san;
+    use crate::pgn::split_games;
+    use crate::piece::PieceType;
+    use crate::zobrist::hash;
+
+    #[test]
+    fn test_hash_history() {
+        let mut game = Game::default();
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+            let t = parse_san(game.board(), san).unwrap();
+            game.make_move(t).unwrap();
+        }
+        let history = game.hash_history();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history[0], hash(&Board::default()));
+        assert_eq!(history[4], history[0]); // knights are back, the start position repeats
+        assert_ne!(history[2], history[0]);
+        assert_eq!(history[4], hash(game.board()));
+
+        let sans: Vec<&str> = game.moves().iter().map(|m| m.san.as_str()).collect();
+        assert_eq!(sans, vec!["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(game.board().last_move().map(|m| m.to), Some(62));
+        assert_eq!(game.undo().map(|t| t.to), Some(62));
+        assert_eq!(game.moves().len(), 3);
+        assert_eq!(game.hash_history().len(), 4);
+        assert_eq!(game.hash_history()[3], hash(game.board()));
+        let illegal = parse_san(game.start(), "e4").unwrap();
+        assert_eq!(game.make_move(illegal), Err("illegal move"));
+        assert_eq!(game.hash_history().len(), 4);
+        while game.undo().is_some() {}
+        assert_eq!(game.hash_history(), &[hash(&Board::default())]);
+    }
+
+    #[test]
+    fn test_make_move_without_flags() {
+        let plain = |b: &Board, from, to| {
+            let piece = b.piece_at(from);
+            Transition::new(
+                from,
+                to,
+                TransitionFlag::Move,
+                PieceType::NONE,
+                piece,
+                b.piece_at(to),
+            )
+        };
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let mut game = Game::new(b.clone());
+        game.make_move(plain(game.board(), 4, 6)).unwrap();
+        assert_eq!(game.board().to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        assert_eq!(game.moves()[0].san, "O-O");
+
+        b.read_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        let mut game = Game::new(b);
+        game.make_move(plain(game.board(), 36, 43)).unwrap();
+        assert_eq!(game.board().to_fen(), "4k3/8/3P4/8/8/8/8/4K3 b - - 0 2");
+        assert_eq!(game.moves()[0].san, "exd6");
+        assert_eq!(
+            game.make_move(plain(game.board(), 60, 44)),
+            Err("illegal move")
+        );
+    }
+
+    #[test]
+    fn test_game_state() {
+        let mut game = Game::default();
+        for _ in 0..2 {
+            for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+                assert_eq!(game.game_state(), GameState::Ongoing);
+                let t = parse_san(game.board(), san).unwrap();
+                game.make_move(t).unwrap();
+            }
+        }
+        assert_eq!(game.game_state(), GameState::Draw(DrawReason::Repetition));
+        assert_eq!(game.game_state().result(), "1/2-1/2");
+        game.undo();
+        assert!(!game.game_state().is_over());
+    }
+
+    #[test]
+    fn test_to_pgn() {
+        let mut game = Game::default();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            let t = parse_san(game.board(), san).unwrap();
+            game.make_move(t).unwrap();
+        }
+        let mut pgn = game.to_pgn();
+        pgn.set_tag("White", "chust");
+        assert_eq!(
+            pgn.to_string(),
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"chust\"]\n[Black \"?\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 40").unwrap();
+        let mut game = Game::new(b);
+        for san in ["Kd7", "e4"] {
+            let t = parse_san(game.board(), san).unwrap();
+            game.make_move(t).unwrap();
+        }
+        let pgn = game.to_pgn();
+        assert_eq!(pgn.movetext, "40... Kd7 41. e4 *");
+        assert_eq!(pgn.tag("SetUp"), Some("1"));
+        // the written game is read back.
+        let read = split_games(&pgn.to_string()).remove(0).to_game().unwrap();
+        assert_eq!(read.board().to_fen(), game.board().to_fen());
+    }
+
+    #[test]
+    fn test_find_novelty() {
+        let play = |moves: &[&str]| {
+            let mut game = Game::default();
+            for san in moves {
+                let t = parse_san(game.board(), san).unwrap();
+                game.make_move(t).unwrap();
+            }
+            game
+        };
+        let mut index = PositionIndex::default();
+        index.add(&play(&["e4", "e5", "Nf3", "Nc6", "Nc3", "Nf6", "Bb5"]));
+        index.add(&play(&["e4", "c5", "Nf3", "d6"]));
+        index.add(&play(&["Nf3", "Nf6", "Ng1", "Ng8"]));
+        index.add(&play(&["e4", "e5", "Nf3", "Nf6", "Nc3"]));
+        assert_eq!(index.games(hash(&Board::default())), 4);
+        assert_eq!(index.len(), 16);
+
+        assert_eq!(
+            play(&["e4", "e5", "Nf3", "d6"]).find_novelty(&index),
+            Some(4)
+        );
+        assert_eq!(play(&["e4", "c5", "Nc3"]).find_novelty(&index), Some(3));
+        // 3. Nc3 Nc6 transposes to the Four Knights of the first game.
+        let four_knights = play(&["e4", "e5", "Nf3", "Nf6", "Nc3", "Nc6", "Bc4"]);
+        assert_eq!(four_knights.find_novelty(&index), Some(7));
+        assert_eq!(play(&["e4", "e5", "Nf3"]).find_novelty(&index), None);
+        let mut start = Board::default();
+        start.read_fen("4k3/8/8/8/8/8/8/4K3 w").unwrap();
+        assert_eq!(Game::new(start).find_novelty(&index), None);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::board::Board;
+use crate::evaluation::Evaluator;
+use crate::notation::{line_to_san, to_internal, to_san};
+use crate::search::{self, Search};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+include!(concat!(env!("OUT_DIR"), "/chust.Engine.rs"));
+
+pub use engine_client::EngineClient;
+pub use engine_server::{Engine, EngineServer};
+
+const DEFAULT_DEPTH: u32 = 4;
+
+// Messages below are described in proto/chust.proto.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BestMoveRequest {
+    #[prost(string, tag = "1")]
+    pub fen: String,
+    #[prost(uint32, tag = "2")]
+    pub depth: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BestMoveReply {
+    #[prost(string, tag = "1")]
+    pub best_move: String,
+    #[prost(string, tag = "2")]
+    pub san: String,
+    #[prost(message, optional, tag = "3")]
+    pub score: Option<Score>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AnalyzeRequest {
+    #[prost(string, tag = "1")]
+    pub fen: String,
+    #[prost(uint32, tag = "2")]
+    pub depth: u32,
+    #[prost(uint32, tag = "3")]
+    pub multipv: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AnalysisInfo {
+    #[prost(uint32, tag = "1")]
+    pub depth: u32,
+    #[prost(uint32, tag = "2")]
+    pub multipv: u32,
+    #[prost(message, optional, tag = "3")]
+    pub score: Option<Score>,
+    #[prost(string, repeated, tag = "4")]
+    pub pv: Vec<String>,
+    #[prost(string, tag = "5")]
+    pub pv_san: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LegalMovesRequest {
+    #[prost(string, tag = "1")]
+    pub fen: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LegalMovesReply {
+    #[prost(string, repeated, tag = "1")]
+    pub moves: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub san: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Score {
+    #[prost(oneof = "ScoreValue", tags = "1, 2")]
+    pub value: Option<ScoreValue>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum ScoreValue {
+    #[prost(int32, tag = "1")]
+    Centipawns(i32),
+    #[prost(int32, tag = "2")]
+    Mate(i32),
+}
+
+impl From<search::Score> for Score {
+    fn from(score: search::Score) -> Self {
+        let value = match score {
+            search::Score::Centipawns(cp) => ScoreValue::Centipawns(cp),
+            search::Score::Mate(moves) => ScoreValue::Mate(moves),
+        };
+        Score { value: Some(value) }
+    }
+}
+
+// EngineService serves engine over gRPC, every search runs on its own blocking thread so many
+// clients can be served at once.
+pub struct EngineService {
+    evaluator: Arc<dyn Evaluator>,
+}
+
+impl EngineService {
+    pub fn new(evaluator: Box<dyn Evaluator>) -> Self {
+        EngineService {
+            evaluator: Arc::from(evaluator),
+        }
+    }
+}
+
+// serve runs gRPC server until it fails.
+pub async fn serve(
+    addr: SocketAddr,
+    evaluator: Box<dyn Evaluator>,
+) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(EngineServer::new(EngineService::new(evaluator)))
+        .serve(addr)
+        .await
+}
+
+// position reads position from request, there has to be at least one legal move.
+#[allow(clippy::result_large_err)]
+fn position(fen: &str) -> Result<Board, Status> {
+    let mut board = Board::default();
+    board.read_fen(fen).map_err(Status::invalid_argument)?;
+    if board.generate_transitions().is_empty() {
+        return Err(Status::failed_precondition(
+            "there are no legal moves in given position",
+        ));
+    }
+    Ok(board)
+}
+
+fn depth_or_default(depth: u32) -> usize {
+    if depth == 0 {
+        DEFAULT_DEPTH as usize
+    } else {
+        depth as usize
+    }
+}
+
+type AnalysisStream = Pin<Box<dyn Stream<Item = Result<AnalysisInfo, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Engine for EngineService {
+    async fn best_move(
+        &self,
+        request: Request<BestMoveRequest>,
+    ) -> Result<Response<BestMoveReply>, Status> {
+        let request = request.into_inner();
+        let board = position(&request.fen)?;
+        let evaluator = self.evaluator.clone();
+        let depth = depth_or_default(request.depth);
+
+        let root = board.clone();
+        let lines = tokio::task::spawn_blocking(move || {
+            Search::new(evaluator.as_ref()).analyze(&root, depth, 1)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        let best = &lines[0];
+        Ok(Response::new(BestMoveReply {
+            best_move: to_internal(&best.pv[0]),
+            san: to_san(&board, &best.pv[0]),
+            score: Some(best.score.into()),
+        }))
+    }
+
+    type AnalyzeStream = AnalysisStream;
+
+    async fn analyze(
+        &self,
+        request: Request<AnalyzeRequest>,
+    ) -> Result<Response<Self::AnalyzeStream>, Status> {
+        let request = request.into_inner();
+        let board = position(&request.fen)?;
+        let evaluator = self.evaluator.clone();
+        let depth = depth_or_default(request.depth);
+        let multipv = request.multipv.max(1) as usize;
+
+        let (sender, receiver) = mpsc::channel(16);
+        tokio::task::spawn_blocking(move || {
+            Search::new(evaluator.as_ref()).iterate(&board, depth, multipv, |d, lines| {
+                for (i, line) in lines.iter().enumerate() {
+                    let info = AnalysisInfo {
+                        depth: d as u32,
+                        multipv: i as u32 + 1,
+                        score: Some(line.score.into()),
+                        pv: line.pv.iter().map(to_internal).collect(),
+                        pv_san: line_to_san(&board, &line.pv),
+                    };
+                    // client disconnected, search is finished anyway
+                    let _ = sender.blocking_send(Ok(info));
+                }
+            });
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    async fn legal_moves(
+        &self,
+        request: Request<LegalMovesRequest>,
+    ) -> Result<Response<LegalMovesReply>, Status> {
+        let fen = request.into_inner().fen;
+        let mut board = Board::default();
+        board.read_fen(&fen).map_err(Status::invalid_argument)?;
+
+        let transitions = board.generate_transitions();
+        Ok(Response::new(LegalMovesReply {
+            moves: transitions.iter().map(to_internal).collect(),
+            san: transitions.iter().map(|t| to_san(&board, t)).collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::SimpleEvaluator;
+    use crate::grpc::{
+        AnalyzeRequest, BestMoveRequest, Engine, EngineService, LegalMovesRequest, ScoreValue,
+    };
+    use tokio_stream::StreamExt;
+    use tonic::{Code, Request};
+
+    const MATE_IN_ONE: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w";
+
+    #[tokio::test]
+    async fn test_best_move() {
+        let service = EngineService::new(Box::new(SimpleEvaluator {}));
+        let reply = service
+            .best_move(Request::new(BestMoveRequest {
+                fen: MATE_IN_ONE.to_string(),
+                depth: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(reply.best_move, "a1a8");
+        assert_eq!(reply.san, "Ra8#");
+        assert_eq!(reply.score.unwrap().value, Some(ScoreValue::Mate(1)));
+
+        let err = service
+            .best_move(Request::new(BestMoveRequest {
+                fen: "8/8".to_string(),
+                depth: 2,
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_and_legal_moves() {
+        let service = EngineService::new(Box::new(SimpleEvaluator {}));
+        let stream = service
+            .analyze(Request::new(AnalyzeRequest {
+                fen: MATE_IN_ONE.to_string(),
+                depth: 2,
+                multipv: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let infos: Vec<_> = stream.map(|i| i.unwrap()).collect().await;
+        assert_eq!(infos.len(), 4);
+        assert_eq!((infos[3].depth, infos[3].multipv), (2, 2));
+        assert_eq!(infos[2].pv_san, "1. Ra8#");
+
+        let reply = service
+            .legal_moves(Request::new(LegalMovesRequest {
+                fen: MATE_IN_ONE.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(reply.moves.len(), 17);
+        assert!(reply.san.contains(&"Ra8#".to_string()));
+    }
+}
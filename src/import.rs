@@ -0,0 +1,247 @@
+use crate::pgn::{split_games, PgnGame};
+use reqwest::blocking::Client;
+use reqwest::header::ACCEPT;
+use serde::Deserialize;
+use std::time::Duration;
+
+const LICHESS_API: &str = "https://lichess.org/api";
+const CHESS_COM_API: &str = "https://api.chess.com/pub";
+// Chess.com asks clients of its public API to identify themselves.
+const USER_AGENT: &str = concat!("chust/", env!("CARGO_PKG_VERSION"));
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+// Site is a chess server games are imported from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Site {
+    Lichess,
+    ChessCom,
+}
+
+impl Site {
+    // from_name returns site named as on the command line, 'lichess' or 'chesscom'.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lichess" => Some(Site::Lichess),
+            "chesscom" | "chess.com" => Some(Site::ChessCom),
+            _ => None,
+        }
+    }
+}
+
+// Importer fetches games of users from public APIs of Lichess and Chess.com. Games are returned
+// as PGN, newest first, PgnGame::to_game converts them to the Game model.
+pub struct Importer {
+    client: Client,
+    lichess: String,
+    chess_com: String,
+}
+
+#[derive(Deserialize)]
+struct Archives {
+    archives: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MonthlyGames {
+    games: Vec<ChessComGame>,
+}
+
+#[derive(Deserialize)]
+struct ChessComGame {
+    #[serde(default)]
+    pgn: String,
+    #[serde(default = "standard_rules")]
+    rules: String,
+}
+
+fn standard_rules() -> String {
+    "chess".to_string()
+}
+
+impl Importer {
+    pub fn new() -> Result<Self, String> {
+        Importer::with_urls(LICHESS_API, CHESS_COM_API)
+    }
+
+    // with_urls returns importer using other addresses of the APIs, e.g. of a test server.
+    pub fn with_urls(lichess: &str, chess_com: &str) -> Result<Self, String> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Importer {
+            client,
+            lichess: lichess.trim_end_matches('/').to_string(),
+            chess_com: chess_com.trim_end_matches('/').to_string(),
+        })
+    }
+
+    // games returns games of the user, at most max_games of the latest ones when it's given.
+    pub fn games(
+        &self,
+        site: Site,
+        user: &str,
+        max_games: Option<usize>,
+    ) -> Result<Vec<PgnGame>, String> {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if user.is_empty() || !user.chars().all(valid) {
+            return Err(format!("invalid username: {}", user));
+        }
+        match site {
+            Site::Lichess => self.lichess_games(user, max_games),
+            Site::ChessCom => self.chess_com_games(user, max_games),
+        }
+    }
+
+    // lichess_games exports games of the user as PGN, without clocks and evaluations.
+    fn lichess_games(&self, user: &str, max_games: Option<usize>) -> Result<Vec<PgnGame>, String> {
+        let mut url = format!(
+            "{}/games/user/{}?clocks=false&evals=false",
+            self.lichess, user
+        );
+        if let Some(max) = max_games {
+            url.push_str(&format!("&max={}", max));
+        }
+        Ok(split_games(&self.get(&url, "application/x-chess-pgn")?))
+    }
+
+    // chess_com_games reads monthly archives of the user from the latest one. Games of variants,
+    // e.g. Chess960, are skipped.
+    fn chess_com_games(
+        &self,
+        user: &str,
+        max_games: Option<usize>,
+    ) -> Result<Vec<PgnGame>, String> {
+        let url = format!(
+            "{}/player/{}/games/archives",
+            self.chess_com,
+            user.to_lowercase()
+        );
+        let archives: Archives = self.get_json(&url)?;
+        let mut games = Vec::new();
+        for archive in archives.archives.iter().rev() {
+            if max_games.is_some_and(|max| games.len() >= max) {
+                break;
+            }
+            let month: MonthlyGames = self.get_json(archive)?;
+            for game in month.games.iter().rev().filter(|g| g.rules == "chess") {
+                games.extend(split_games(&game.pgn));
+            }
+        }
+        if let Some(max) = max_games {
+            games.truncate(max);
+        }
+        Ok(games)
+    }
+
+    fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        let body = self.get(url, "application/json")?;
+        serde_json::from_str(&body).map_err(|e| format!("invalid response of {}: {}", url, e))
+    }
+
+    fn get(&self, url: &str, accept: &str) -> Result<String, String> {
+        let response = self
+            .client
+            .get(url)
+            .header(ACCEPT, accept)
+            .send()
+            .map_err(|e| format!("cannot fetch {}: {}", url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("cannot fetch {}: {}", url, status));
+        }
+        response
+            .text()
+            .map_err(|e| format!("cannot fetch {}: {}", url, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::{Importer, Site};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // api answers requests of the importer with bodies of matching paths, other paths are 404.
+    // API in bodies is replaced by the address of the server.
+    fn api(responses: Vec<(String, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let base = addr.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let target = request.split(' ').nth(1).unwrap_or("");
+                let response = match responses.iter().find(|(path, _)| path == target) {
+                    Some((_, body)) => {
+                        let body = body.replace("API", &base);
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                    }
+                    None => {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    }
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_import() {
+        assert_eq!(Site::from_name("chess.com"), Some(Site::ChessCom));
+        assert_eq!(Site::from_name("fics"), None);
+
+        let lichess = api(vec![(
+            "/games/user/Alice?clocks=false&evals=false&max=2".to_string(),
+            "[White \"Alice\"]\n\n1. e4 e5 1-0\n\n[White \"Bob\"]\n\n1. d4 0-1\n".to_string(),
+        )]);
+        let importer = Importer::with_urls(&lichess, "").unwrap();
+        let games = importer.games(Site::Lichess, "Alice", Some(2)).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("Alice"));
+        assert_eq!(games[1].to_game().unwrap().moves()[0].san, "d4");
+        assert!(importer.games(Site::Lichess, "Carol", None).is_err());
+        assert!(importer.games(Site::Lichess, "../x", None).is_err());
+
+        // archives are read from the latest one until there are enough games.
+        let month = |games: &[(&str, &str)]| {
+            let games: Vec<_> = games
+                .iter()
+                .map(|(rules, moves)| format!(r#"{{"rules":"{}","pgn":"1. {}"}}"#, rules, moves))
+                .collect();
+            format!(r#"{{"games":[{}]}}"#, games.join(","))
+        };
+        let chess_com = api(vec![
+            (
+                "/player/alice/games/archives".to_string(),
+                r#"{"archives":["API/player/alice/games/2024/01","API/player/alice/games/2024/02"]}"#
+                    .to_string(),
+            ),
+            (
+                "/player/alice/games/2024/02".to_string(),
+                month(&[("chess", "e4"), ("chess960", "b3"), ("chess", "c4")]),
+            ),
+        ]);
+        let importer = Importer::with_urls("", &chess_com).unwrap();
+        let games = importer.games(Site::ChessCom, "Alice", Some(2)).unwrap();
+        let moves: Vec<_> = games.iter().map(|g| g.moves()).collect();
+        assert_eq!(moves, vec![vec!["c4"], vec!["e4"]]);
+        // the older archive is missing.
+        assert!(importer.games(Site::ChessCom, "Alice", Some(3)).is_err());
+    }
+}
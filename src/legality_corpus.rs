@@ -0,0 +1,302 @@
+// legality_corpus contains tricky positions that move validation has to handle correctly.
+//
+// Every case is described by piece placement, color to move, moves that lead to tested position
+// and the move under test written in internal notation (e.g. e2e4, e1g1 for castle).
+
+use crate::board::Board;
+use crate::piece::Color;
+
+pub struct LegalityCase {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub color_to_move: Color,
+    pub setup: &'static [&'static str],
+    pub mv: &'static str,
+    pub legal: bool,
+    pub check_after: bool, // is opponent in check after legal move?
+}
+
+pub const LEGALITY_CORPUS: &[LegalityCase] = &[
+    LegalityCase {
+        name: "pinned bishop cannot leave the file",
+        fen: "k3r3/8/8/8/8/8/4B3/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e2d3",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "pinned rook moves along the pin",
+        fen: "k3r3/8/8/8/8/8/4R3/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e2e5",
+        legal: true,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "pinned rook captures the pinning piece",
+        fen: "k3r3/8/8/8/8/8/4R3/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e2e8",
+        legal: true,
+        check_after: true,
+    },
+    LegalityCase {
+        name: "pinned rook cannot leave the pin",
+        fen: "k3r3/8/8/8/8/8/4R3/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e2d2",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "pinned knight cannot move",
+        fen: "4k3/4r3/8/8/8/8/4N3/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e2c3",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "diagonally pinned bishop moves along the pin",
+        fen: "4k3/8/8/b7/8/8/3B4/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "d2a5",
+        legal: true,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "diagonally pinned bishop cannot leave the pin",
+        fen: "4k3/8/8/b7/8/8/3B4/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "d2e3",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "knight move discovers check",
+        fen: "4k3/8/8/8/8/8/4N3/4R1K1",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e2c3",
+        legal: true,
+        check_after: true,
+    },
+    LegalityCase {
+        name: "pawn push discovers check",
+        fen: "7k/8/8/8/8/8/1P6/B5K1",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "b2b4",
+        legal: true,
+        check_after: true,
+    },
+    LegalityCase {
+        name: "king cannot capture protected piece",
+        fen: "3rk3/8/8/8/8/8/3q4/4K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1d2",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "king cannot retreat along checking ray",
+        fen: "4k3/8/8/8/8/8/8/r3K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1f1",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "king steps out of checking ray",
+        fen: "4k3/8/8/8/8/8/8/r3K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1e2",
+        legal: true,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "king cannot walk next to the other king",
+        fen: "8/8/8/3k4/8/3K4/8/8",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "d3d4",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "en passant",
+        fen: "4k3/3p4/8/4P3/8/8/1K6/8",
+        color_to_move: Color::BLACK,
+        setup: &["d7d5"],
+        mv: "e5d6",
+        legal: true,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "en passant only right after double push",
+        fen: "4k3/3p4/8/4P3/8/8/1K6/8",
+        color_to_move: Color::BLACK,
+        setup: &["d7d5", "b2b3", "e8e7"],
+        mv: "e5d6",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "en passant with diagonally pinned pawn",
+        fen: "4k2b/3p4/8/4P3/8/8/1K6/8",
+        color_to_move: Color::BLACK,
+        setup: &["d7d5"],
+        mv: "e5d6",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "en passant with vertically pinned pawn",
+        fen: "k3r3/3p4/8/4P3/8/8/8/4K3",
+        color_to_move: Color::BLACK,
+        setup: &["d7d5"],
+        mv: "e5d6",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "en passant reveals check on the rank",
+        fen: "4k3/2p5/8/KP5r/8/8/8/8",
+        color_to_move: Color::BLACK,
+        setup: &["c7c5"],
+        mv: "b5c6",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "black en passant reveals check on the rank",
+        fen: "4K3/8/8/8/R5pk/8/5P2/8",
+        color_to_move: Color::WHITE,
+        setup: &["f2f4"],
+        mv: "g4f3",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "en passant next to own king without attacker on the rank",
+        fen: "4k3/2p5/8/KP6/7r/8/8/8",
+        color_to_move: Color::BLACK,
+        setup: &["c7c5"],
+        mv: "b5c6",
+        legal: true,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "short castle",
+        fen: "4k3/8/8/8/8/8/8/4K2R",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1g1",
+        legal: true,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "castle through check",
+        fen: "4kr2/8/8/8/8/8/8/4K2R",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1g1",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "castle into check",
+        fen: "4k1r1/8/8/8/8/8/8/4K2R",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1g1",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "castle out of check",
+        fen: "4r1k1/8/8/8/8/8/8/4K2R",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1g1",
+        legal: false,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "long castle with attacked rook path",
+        fen: "1r2k3/8/8/8/8/8/8/R3K3",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1c1",
+        legal: true,
+        check_after: false,
+    },
+    LegalityCase {
+        name: "castle gives check",
+        fen: "5k2/8/8/8/8/8/8/4K2R",
+        color_to_move: Color::WHITE,
+        setup: &[],
+        mv: "e1g1",
+        legal: true,
+        check_after: true,
+    },
+    LegalityCase {
+        name: "black long castle through check",
+        fen: "r3k3/8/8/8/8/8/8/3RK3",
+        color_to_move: Color::BLACK,
+        setup: &[],
+        mv: "e8c8",
+        legal: false,
+        check_after: false,
+    },
+];
+
+// run_case plays given case and returns error describing a mismatch.
+pub fn run_case(case: &LegalityCase) -> Result<(), String> {
+    let mut b = Board::default();
+    b.read_fen(case.fen)?;
+    b.color_to_move = case.color_to_move;
+    for m in case.setup {
+        if let Err(e) = b.make_move_internal_notation(m) {
+            return Err(format!("setup move {} failed: {}", m, e));
+        }
+    }
+    match b.make_move_internal_notation(case.mv) {
+        Ok(_) if !case.legal => Err(format!("{} was accepted", case.mv)),
+        Err(e) if case.legal => Err(format!("{} was rejected: {}", case.mv, e)),
+        Ok(_) if b.is_in_check() != case.check_after => Err(format!(
+            "check after {} expected: {}",
+            case.mv, case.check_after
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::legality_corpus::{run_case, LEGALITY_CORPUS};
+
+    #[test]
+    fn test_legality_corpus() {
+        let failures: Vec<String> = LEGALITY_CORPUS
+            .iter()
+            .filter_map(|case| {
+                run_case(case)
+                    .err()
+                    .map(|e| format!("{}: {}", case.name, e))
+            })
+            .collect();
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+}
@@ -0,0 +1,151 @@
+#![allow(clippy::upper_case_acronyms, clippy::needless_return)]
+
+// Threads: positions (Board, Transition, PgnGame), evaluators, priors and transposition tables are
+// Send and Sync, so a thread pool can analyze different positions with one shared evaluator.
+// Searches (Search, Mcts, MateSolver and other SearchBackend) keep state of the running search,
+// they can be moved to another thread but every thread needs its own one. Tests below enforce that.
+
+// Features: rules of chess, FEN, PGN and notations are always built and have no dependencies.
+// 'search' adds evaluation and searches, 'uci' the UCI engine, and 'tools' the rest of the CLI
+// commands: databases, reviews, rendering and training, with rand and rayon. Libraries that only
+// need move generation use chust with default-features = false.
+
+#[cfg(feature = "search")]
+pub mod arena;
+#[cfg(feature = "search")]
+pub mod backend;
+#[cfg(feature = "tools")]
+pub mod batch;
+pub mod board;
+pub mod book;
+#[cfg(feature = "tools")]
+pub mod challenge;
+#[cfg(feature = "search")]
+pub mod checkpoint;
+#[cfg(feature = "search")]
+pub mod coach;
+#[cfg(feature = "tools")]
+pub mod database;
+pub mod descriptive;
+#[cfg(feature = "tools")]
+pub mod differential;
+pub mod elo;
+#[cfg(feature = "search")]
+pub mod endgame;
+pub mod engine;
+#[cfg(feature = "search")]
+pub mod evaluation;
+#[cfg(feature = "search")]
+pub mod experience;
+#[cfg(feature = "tools")]
+pub mod features;
+pub mod game;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "import")]
+pub mod import;
+#[cfg(test)]
+mod legality_corpus;
+#[cfg(feature = "search")]
+pub mod mate;
+#[cfg(feature = "search")]
+pub mod mcts;
+pub mod notation;
+#[cfg(feature = "search")]
+pub mod params;
+pub mod patterns;
+pub mod pgn;
+#[cfg(feature = "tools")]
+pub mod pgn_json;
+pub mod piece;
+pub mod position;
+pub mod prelude;
+#[cfg(feature = "tools")]
+pub mod progress;
+pub mod pst;
+#[cfg(feature = "tools")]
+pub mod render;
+#[cfg(feature = "tools")]
+pub mod repertoire;
+#[cfg(feature = "tools")]
+pub mod repl;
+#[cfg(feature = "tools")]
+pub mod review;
+pub mod score;
+#[cfg(feature = "search")]
+pub mod search;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "search")]
+pub mod sparring;
+#[cfg(feature = "tools")]
+pub mod spsa;
+#[cfg(feature = "tools")]
+pub mod stats;
+#[cfg(feature = "search")]
+pub mod tablebase;
+pub mod tactics;
+#[cfg(feature = "tools")]
+pub mod training;
+#[cfg(feature = "search")]
+pub mod tt;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "uci")]
+pub mod uci;
+#[cfg(feature = "uci")]
+pub mod uci_client;
+pub mod zobrist;
+
+#[cfg(all(test, feature = "search"))]
+mod tests {
+    use crate::board::{Board, Transition};
+    use crate::evaluation::{Evaluator, MaterialMobilityEvaluator};
+    use crate::mate::MateSolver;
+    use crate::mcts::{Mcts, UniformPrior};
+    use crate::pgn::PgnGame;
+    use crate::search::Search;
+    use crate::tt::TranspositionTable;
+    use std::thread;
+
+    fn shared<T: Send + Sync>() {}
+    fn movable<T: Send>() {}
+
+    #[test]
+    fn test_thread_safety() {
+        shared::<Board>();
+        shared::<Transition>();
+        shared::<PgnGame>();
+        shared::<Box<dyn Evaluator>>();
+        shared::<UniformPrior>();
+        shared::<TranspositionTable>();
+        movable::<Search>();
+        movable::<Mcts>();
+        movable::<MateSolver>();
+    }
+
+    #[test]
+    fn test_parallel_analysis() {
+        let evaluator = MaterialMobilityEvaluator {};
+        let fens = [
+            "4k3/8/8/3q4/8/8/3R4/4K3 w",
+            "6k1/5ppp/8/8/8/8/8/R5K1 w",
+            "4k3/8/8/8/8/8/3r4/3QK3 w",
+        ];
+        let analyze = |fen: &&str| {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            let line = &Search::new(&evaluator).analyze(&b, 2, 1)[0];
+            (line.pv[0].from, line.pv[0].to, line.score)
+        };
+        let parallel: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = fens
+                .iter()
+                .map(|fen| scope.spawn(move || analyze(fen)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let sequential: Vec<_> = fens.iter().map(analyze).collect();
+        assert_eq!(parallel, sequential);
+    }
+}
@@ -1,19 +1,1311 @@
-mod board;
-mod evaluation;
-mod piece;
+use chust::arena::{
+    play_openings, read_openings, standings, GameResult, MatchScore, MatchSettings, Player,
+};
+use chust::backend::{
+    backend_by_name, format_policy, SearchBackend, SearchLimits, SearchResult, BACKEND_NAMES,
+};
+use chust::batch::{eval_batch, read_fens, solve_batch, write_csv, write_solutions, SolveStatus};
+use chust::board::Board;
+use chust::challenge::{read_event, ChallengePolicy};
+use chust::checkpoint::Checkpoint;
+use chust::database::{
+    dedup, index_positions, novelty_report, positions, process_games, replay_report, GameFilter,
+    PgnWriter,
+};
+use chust::descriptive::descriptive_to_san;
+use chust::differential::{compare_evaluations, write_disagreements};
+use chust::engine::ENGINE_INFO;
+use chust::evaluation::{evaluator_by_name, Evaluator, EVALUATOR_NAMES};
+use chust::features::{self, game_rows, FeatureRow};
+#[cfg(feature = "import")]
+use chust::import::{Importer, Site};
+use chust::notation::{write_line, MoveNotation, ParseMode};
+use chust::params::EvalParams;
+use chust::pgn::{split_games, PgnExportOptions, PgnGame, ReplayOptions};
+use chust::pgn_json::{from_json, to_json, JsonGame};
+use chust::piece::Color;
+use chust::progress::Progress;
+use chust::render::{PieceSet, Renderer, Theme, THEME_NAMES};
+use chust::repertoire::{Drill, DrillScore, Repertoire};
+use chust::repl::Repl;
+use chust::review::{annotate_game, review_game, PlayerStats};
+use chust::search::{Search, SearchLine};
+use chust::spsa::{Spsa, SpsaSettings, Tunable};
+use chust::stats::{player_report, Record};
+use chust::training::{quiz, run_quiz, QuizKind};
+use chust::tt::{self, TranspositionTable};
+use chust::uci::Uci;
+use chust::uci_client::UciClient;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+use std::time::Duration;
 
-struct A {
-    a: i32,
-    b: bool,
+const USAGE: &str = "usage:
+    chust analyze \"FEN\" [--depth N] [--multipv N] [--evaluator NAME] [--notation san|lan]
+        [--search alphabeta|mcts|mate] [--nodes N] [--movetime MS] [--policy]
+        [--checkpoint FILE] [--hash MB] [--shared-hash FILE]
+    chust eval-batch FILE [--evaluator NAME] [--output FILE]
+    chust solve FILE [--max-mate N] [--threads N] [--output FILE]
+    chust eval-diff FILE --engine COMMAND [--depth N] [--evaluator NAME] [--top N] [--output FILE]
+    chust features FILE [--policy-depth N] [--evaluator NAME] [--output FILE]
+    chust pgn replay|dedup|filter|positions|descriptive|novelty FILE [--ordered] [--output FILE]
+        [--append]
+        replay options: [--mode normal|strict|lenient] [--cross-check]
+        novelty options: --db FILE
+        filter options: [--tag Name=Value]... [--min-plies N] [--max-plies N]
+        export options: [--width N] [--no-comments] [--no-variations] [--notation san|lan]
+    chust convert FILE [--to json|pgn] [--output FILE]
+    chust import lichess|chesscom USER [--max N] [--output FILE]   (requires 'import' feature)
+    chust match --engine SEARCH[:EVALUATOR]|uci:COMMAND --engine ... [--games N] [--tc BASE+INC]
+        [--depth N] [--lag MS] [--max-plies N] [--fen FEN | --openings FILE] [--event NAME]
+        [--output FILE]
+    chust render \"FEN\" [--output FILE] [--theme NAME|FILE] [--pieces FILE] [--size N]
+        [--no-coordinates] [--flip]
+    chust gif FILE [-o|--output FILE] [--game N] [--delay MS] [--hold MS] [render options]
+        (requires 'gif' feature)
+    chust repl [--evaluator NAME]
+    chust tui [--color white|black] [--depth N] [--evaluator NAME] [--tc BASE+INC]
+        [--recovery FILE]   (requires 'tui' feature)
+    chust uci
+    chust challenges POLICY [EVENTS]
+    chust --version
+    chust review FILE [--depth N] [--evaluator NAME] [--moves] [--max N]
+    chust annotate FILE [-o|--output FILE] [--depth N] [--evaluator NAME] [--width N] [--max N]
+    chust stats FILE --player NAME [--analyze] [--depth N] [--evaluator NAME] [--top N] [--max N]
+        (FILE of review, annotate and stats can be lichess:USER or chesscom:USER with 'import'
+        feature)
+    chust drill FILE [--color white|black] [--randomness R] [--lines N] [--seed N]
+    chust train colors|squares|knight [--count N] [--seed N]
+    chust tune --tune NAME[:MIN:MAX:STEP]... --output FILE [--params FILE] [--iterations N]
+        [--pairs N] [--random-plies N] [--depth N] [--search NAME] [--seed N]
+    chust grpc-serve [--addr ADDR] [--evaluator NAME]   (requires 'grpc' feature)
+    chust serve [--addr ADDR] [--evaluator NAME] [--connections N] [--movetime MS] [--nodes N]
+        (requires 'serve' feature)";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|s| s.as_str()) {
+        Some("analyze") => analyze(&args[1..]),
+        Some("eval-batch") => eval_batch_command(&args[1..]),
+        Some("solve") => solve(&args[1..]),
+        Some("eval-diff") => eval_diff(&args[1..]),
+        Some("features") => features(&args[1..]),
+        Some("pgn") => pgn_command(&args[1..]),
+        Some("convert") => convert(&args[1..]),
+        #[cfg(feature = "import")]
+        Some("import") => import(&args[1..]),
+        Some("match") => match_command(&args[1..]),
+        Some("repl") => repl(&args[1..]),
+        #[cfg(feature = "tui")]
+        Some("tui") => tui(&args[1..]),
+        Some("render") => render(&args[1..]),
+        #[cfg(feature = "gif")]
+        Some("gif") => gif(&args[1..]),
+        Some("uci") => Uci::new()
+            .run(io::stdin().lock(), &mut io::stdout())
+            .map_err(|e| e.to_string()),
+        Some("challenges") => challenges(&args[1..]),
+        Some("--version") | Some("version") => {
+            println!("{}", ENGINE_INFO);
+            Ok(())
+        }
+        Some("review") => review(&args[1..]),
+        Some("annotate") => annotate(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("drill") => drill(&args[1..]),
+        Some("train") => train(&args[1..]),
+        Some("tune") => tune(&args[1..]),
+        #[cfg(feature = "grpc")]
+        Some("grpc-serve") => grpc_serve(&args[1..]),
+        #[cfg(feature = "serve")]
+        Some("serve") => serve(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+// option_value returns value given after option name e.g. '--depth 5'.
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.as_str())
+}
+
+// option_values returns values of option that can be given many times e.g. '--tag A=1 --tag B=2'.
+fn option_values<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|w| w[0] == name)
+        .map(|w| w[1].as_str())
+        .collect()
 }
-impl A {
-    fn new(a: i32, b: bool) -> Self {
-        A { a, b }
+
+// parse_option parses option value, returns default when option is not given.
+fn parse_option<T: std::str::FromStr>(
+    args: &[String],
+    name: &str,
+    default: T,
+) -> Result<T, String> {
+    match option_value(args, name) {
+        Some(v) => v
+            .parse()
+            .map_err(|_| format!("invalid value of {}: {}", name, v)),
+        None => Ok(default),
     }
 }
 
-fn main() {
-    let v = vec![A::new(10, false), A::new(20, true), A::new(25, true)];
-    let val: i32 = v.iter().map(|x| if x.b { x.a } else { x.a * -1 }).sum();
-    println!("{}", val);
+// parse_optional parses option value, returns None when option is not given.
+fn parse_optional<T: std::str::FromStr>(args: &[String], name: &str) -> Result<Option<T>, String> {
+    option_value(args, name)
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("invalid value of {}: {}", name, v))
+        })
+        .transpose()
+}
+
+// cli_progress returns progress bar when built with 'progress' feature.
+fn cli_progress() -> Box<dyn Progress> {
+    #[cfg(feature = "progress")]
+    return Box::new(chust::progress::Bar::new());
+    #[cfg(not(feature = "progress"))]
+    return Box::new(chust::progress::NoProgress);
+}
+
+// rng_option returns random generator seeded with --seed option or with entropy.
+fn rng_option(args: &[String]) -> Result<StdRng, String> {
+    Ok(match parse_optional(args, "--seed")? {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    })
+}
+
+// evaluator_option returns evaluator chosen with --evaluator option.
+fn evaluator_option(name: &str) -> Result<Box<dyn Evaluator>, String> {
+    evaluator_by_name(name).ok_or(format!(
+        "unknown evaluator {}, available: {}",
+        name,
+        EVALUATOR_NAMES.join(", ")
+    ))
+}
+
+// backend_option returns search backend chosen with --search option.
+fn backend_option<'a>(
+    args: &[String],
+    evaluator: &'a dyn Evaluator,
+) -> Result<Box<dyn SearchBackend + 'a>, String> {
+    let name = option_value(args, "--search").unwrap_or("alphabeta");
+    backend_by_name(name, evaluator).ok_or(format!(
+        "unknown search {}, available: {}",
+        name,
+        BACKEND_NAMES.join(", ")
+    ))
+}
+
+// output_writer returns file given with --output option or stdout.
+fn output_writer(args: &[String]) -> Result<Box<dyn Write>, String> {
+    match option_value(args, "--output") {
+        Some(path) => File::create(path)
+            .map(|f| Box::new(BufWriter::new(f)) as Box<dyn Write>)
+            .map_err(|e| format!("cannot create {}: {}", path, e)),
+        None => Ok(Box::new(BufWriter::new(io::stdout()))),
+    }
+}
+
+// read_games reads games of PGN file. With 'import' feature path can be lichess:USER or
+// chesscom:USER, then at most --max latest games of the user are imported.
+#[cfg_attr(not(feature = "import"), allow(unused_variables))]
+fn read_games(path: &str, args: &[String]) -> Result<Vec<PgnGame>, String> {
+    #[cfg(feature = "import")]
+    if let Some((site, user)) = path
+        .split_once(':')
+        .and_then(|(site, user)| Some((Site::from_name(site)?, user)))
+    {
+        return Importer::new()?.games(site, user, parse_optional(args, "--max")?);
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    Ok(split_games(&content))
+}
+
+// analyze searches given position and prints best lines and static evaluation.
+fn analyze(args: &[String]) -> Result<(), String> {
+    let fen = match args.first() {
+        Some(fen) if !fen.starts_with("--") => fen,
+        _ => return Err(USAGE.to_string()),
+    };
+    let notation: MoveNotation = parse_option(args, "--notation", MoveNotation::San)?;
+    let evaluator_name = option_value(args, "--evaluator").unwrap_or("material-mobility");
+    let evaluator = evaluator_option(evaluator_name)?;
+
+    let mut board = Board::default();
+    for warning in board.read_fen_lenient(fen)? {
+        eprintln!("warning: {}", warning);
+    }
+    if board.generate_transitions().is_empty() {
+        return Err("there are no legal moves in given position".to_string());
+    }
+
+    let limits = SearchLimits {
+        depth: parse_option(args, "--depth", 4)?,
+        nodes: parse_optional(args, "--nodes")?,
+        multipv: parse_option(args, "--multipv", 1)?,
+        policy: args.iter().any(|a| a == "--policy"),
+        time: parse_optional(args, "--movetime")?.map(Duration::from_millis),
+        // halfmove clock of FEN.
+        quiet_plies: fen
+            .split_whitespace()
+            .nth(4)
+            .and_then(|h| h.parse().ok())
+            .unwrap_or_default(),
+        contempt: 0,
+    };
+    let mut backend = backend_option(args, evaluator.as_ref())?;
+    let mut on_info = |d: usize, lines: &[SearchLine]| {
+        println!(
+            "depth {:>2}  {:>7}  {}",
+            d,
+            lines[0].score.white_view(board.color_to_move).to_string(),
+            write_line(&board, &lines[0].pv, notation)
+        );
+    };
+    let hash = parse_option(args, "--hash", (tt::DEFAULT_ENTRIES * tt::ENTRY_SIZE) >> 20)?;
+    let checkpoint = option_value(args, "--checkpoint");
+    let shared_hash = option_value(args, "--shared-hash");
+    let result = match (checkpoint, shared_hash) {
+        (Some(_), Some(_)) => {
+            return Err("--checkpoint and --shared-hash can't be used together".to_string())
+        }
+        (Some(_), _) | (_, Some(_)) if backend.name() != "alphabeta" => {
+            return Err(
+                "--checkpoint and --shared-hash work only with alphabeta search".to_string(),
+            )
+        }
+        (Some(path), None) => {
+            search_with_checkpoint(&board, evaluator.as_ref(), &limits, path, hash, on_info)?
+        }
+        (None, Some(path)) => {
+            let tt = shared_table(path, hash)?;
+            let mut search = Search::new(evaluator.as_ref());
+            search.tt = tt;
+            let result = search.search(&board, &limits, &mut on_info);
+            search.tt.flush().map_err(|e| e.to_string())?;
+            result
+        }
+        (None, None) => backend.search(&board, &limits, &mut on_info),
+    };
+
+    if result.lines.is_empty() {
+        println!(
+            "\n{}: no line found (depth {})",
+            backend.name(),
+            result.depth
+        );
+    } else {
+        println!(
+            "\nbest lines ({}, depth {}, {} nodes):",
+            backend.name(),
+            result.depth,
+            result.nodes
+        );
+    }
+    for (i, line) in result.lines.iter().enumerate() {
+        print_line(&board, i + 1, line, notation);
+    }
+    if limits.policy {
+        println!("\npolicy: {}", format_policy(&result.policy));
+    }
+
+    println!("\nstatic evaluation ({}):", evaluator_name);
+    let terms = evaluator.breakdown(&board);
+    for term in &terms {
+        println!("  {:<16}{:>7.2}", term.name, term.value);
+    }
+    println!(
+        "  {:<16}{:>7.2}",
+        "total",
+        terms.iter().map(|t| t.value).sum::<f32>()
+    );
+    Ok(())
+}
+
+// search_with_checkpoint runs alpha-beta search that is saved to checkpoint file after every depth,
+// and resumes it when the file already has a checkpoint of the position.
+fn search_with_checkpoint(
+    board: &Board,
+    evaluator: &dyn Evaluator,
+    limits: &SearchLimits,
+    path: &str,
+    hash_megabytes: usize,
+    mut on_info: impl FnMut(usize, &[SearchLine]),
+) -> Result<SearchResult, String> {
+    let mut reached = 0;
+    let on_depth = |d: usize, lines: &[SearchLine]| {
+        reached = d;
+        on_info(d, lines)
+    };
+    let mut search = Search::new(evaluator);
+    search.deadline = limits.deadline();
+    search.quiet_plies = limits.quiet_plies;
+    search.checkpoint = Some(path.into());
+    let lines = match Checkpoint::load(path, board) {
+        Ok(checkpoint) => {
+            println!(
+                "resuming {} from depth {} ({} nodes)",
+                path, checkpoint.depth, checkpoint.nodes
+            );
+            search.resume(board, checkpoint, limits.depth, limits.multipv, on_depth)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            search.tt = TranspositionTable::with_megabytes(hash_megabytes);
+            search.iterate(board, limits.depth, limits.multipv, on_depth)
+        }
+        Err(e) => return Err(format!("cannot resume from {}: {}", path, e)),
+    };
+    if let Some(e) = search.checkpoint_error {
+        return Err(format!("cannot save checkpoint {}: {}", path, e));
+    }
+    Ok(SearchResult {
+        lines,
+        nodes: search.nodes,
+        depth: reached,
+        policy: Vec::new(),
+    })
+}
+
+// shared_table opens transposition table in memory mapped file, shared by processes.
+#[cfg(feature = "mmap")]
+fn shared_table(path: &str, megabytes: usize) -> Result<TranspositionTable, String> {
+    TranspositionTable::open_mapped(path, megabytes)
+        .map_err(|e| format!("cannot open {}: {}", path, e))
+}
+
+#[cfg(not(feature = "mmap"))]
+fn shared_table(_: &str, _: usize) -> Result<TranspositionTable, String> {
+    Err("--shared-hash needs chust built with --features mmap".to_string())
+}
+
+fn print_line(board: &Board, number: usize, line: &SearchLine, notation: MoveNotation) {
+    println!(
+        "{:>3}. {:>7}  {}",
+        number,
+        line.score.white_view(board.color_to_move).to_string(),
+        write_line(board, &line.pv, notation)
+    );
+}
+
+// eval_batch_command evaluates every FEN from file and writes CSV with evaluation terms.
+fn eval_batch_command(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+
+    let rows = eval_batch(
+        &read_fens(&content),
+        evaluator.as_ref(),
+        cli_progress().as_ref(),
+    );
+    let mut out = output_writer(args)?;
+    write_csv(&mut out, &rows)
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())
+}
+
+// challenges checks challenges of Lichess event stream, read from file or stdin, against the
+// policy and prints 'accept ID' or 'decline ID REASON' for every one of them as it comes.
+fn challenges(args: &[String]) -> Result<(), String> {
+    let policy = match args.first() {
+        Some(path) => ChallengePolicy::load(path)?,
+        None => return Err(USAGE.to_string()),
+    };
+    let input: Box<dyn BufRead> = match args.get(1) {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).map_err(|e| format!("cannot read {}: {}", path, e))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut out = io::stdout().lock();
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let challenge = match read_event(&line) {
+            Ok(Some(challenge)) => challenge,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        match policy.check(&challenge) {
+            Ok(()) => writeln!(out, "accept {}", challenge.id),
+            Err(reason) => writeln!(out, "decline {} {}", challenge.id, reason.key()),
+        }
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// solve runs the mate solver on every EPD puzzle from file and writes CSV with solutions, summary
+// is printed to stderr.
+fn solve(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let max_mate = parse_option(args, "--max-mate", 5)?;
+    let threads = parse_option(args, "--threads", 0)?;
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let rows =
+        pool.install(|| solve_batch(&read_fens(&content), max_mate, cli_progress().as_ref()));
+    let mut out = output_writer(args)?;
+    write_solutions(&mut out, &rows)
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())?;
+
+    let count = |status| rows.iter().filter(|r| r.status() == status).count();
+    eprintln!(
+        "{} puzzles: {} solved, {} wrong, {} failed, {} invalid",
+        rows.len(),
+        count(SolveStatus::Solved),
+        count(SolveStatus::Wrong),
+        count(SolveStatus::Failed),
+        count(SolveStatus::Invalid)
+    );
+    Ok(())
+}
+
+// eval_diff compares static evaluation of positions with shallow search of an external UCI
+// engine and writes CSV of the largest disagreements.
+fn eval_diff(args: &[String]) -> Result<(), String> {
+    let (path, command) = match (args.first(), option_value(args, "--engine")) {
+        (Some(path), Some(command)) if !path.starts_with("--") => (path, command),
+        _ => return Err(USAGE.to_string()),
+    };
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let mut engine =
+        UciClient::spawn(command).map_err(|e| format!("cannot start engine {}: {}", command, e))?;
+    let fens = read_fens(&content);
+    let progress = cli_progress();
+    progress.start("positions", fens.len() as u64);
+    let disagreements = compare_evaluations(
+        &fens,
+        evaluator.as_ref(),
+        &mut engine,
+        parse_option(args, "--depth", 1)?,
+        |_| progress.advance(1),
+    );
+    progress.finish();
+    let mut out = output_writer(args)?;
+    write_disagreements(&mut out, &disagreements, parse_option(args, "--top", 20)?)
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())
+}
+
+// features writes CSV with features of positions for training models, FILE is a list of FENs or
+// PGN database (.pgn) whose positions are labelled with game result.
+fn features(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let policy_depth: Option<usize> = parse_optional(args, "--policy-depth")?;
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let mut rows: Vec<FeatureRow> = if path.ends_with(".pgn") {
+        split_games(&content)
+            .par_iter()
+            .flat_map(game_rows)
+            .collect()
+    } else {
+        read_fens(&content)
+            .par_iter()
+            .filter_map(|fen| {
+                let mut board = Board::default();
+                board.read_fen(fen).ok()?;
+                Some(FeatureRow::new(&board, None))
+            })
+            .collect()
+    };
+    if let Some(depth) = policy_depth {
+        let limits = SearchLimits {
+            policy: true,
+            ..SearchLimits::depth(depth)
+        };
+        rows.par_iter_mut().for_each(|row| {
+            let mut board = Board::default();
+            board.read_fen(&row.fen).expect("rows have valid fens");
+            let mut search = Search::new(evaluator.as_ref());
+            row.policy = search.search(&board, &limits, &mut |_, _| {}).policy;
+        });
+    }
+    let mut out = output_writer(args)?;
+    features::write_csv(&mut out, &rows)
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())
+}
+
+// import writes games of Lichess or Chess.com user as PGN, the latest first.
+#[cfg(feature = "import")]
+fn import(args: &[String]) -> Result<(), String> {
+    let (site, user) = match args {
+        [site, user, ..] if !user.starts_with("--") => (
+            Site::from_name(site).ok_or_else(|| format!("unknown site: {}", site))?,
+            user,
+        ),
+        _ => return Err(USAGE.to_string()),
+    };
+    let games = Importer::new()?.games(site, user, parse_optional(args, "--max")?)?;
+    let mut out = output_writer(args)?;
+    for game in &games {
+        writeln!(out, "{}", game).map_err(|e| e.to_string())?;
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+
+// convert writes games of PGN database as JSON array of games, see JsonGame, or with --to pgn
+// reads such an array and writes games as PGN.
+fn convert(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let mut out = output_writer(args)?;
+    match option_value(args, "--to").unwrap_or("json") {
+        "json" => {
+            let games = split_games(&content)
+                .iter()
+                .enumerate()
+                .map(|(i, g)| to_json(g).map_err(|e| e.with_game(i + 1).to_string()))
+                .collect::<Result<Vec<JsonGame>, String>>()?;
+            serde_json::to_writer_pretty(&mut out, &games).map_err(|e| e.to_string())?;
+            writeln!(out).map_err(|e| e.to_string())?;
+        }
+        "pgn" => {
+            let games: Vec<JsonGame> = serde_json::from_str(&content)
+                .map_err(|e| format!("cannot read {}: {}", path, e))?;
+            for (i, game) in games.iter().enumerate() {
+                let pgn = from_json(game).map_err(|e| e.with_game(i + 1).to_string())?;
+                writeln!(out, "{}", pgn).map_err(|e| e.to_string())?;
+            }
+        }
+        to => return Err(format!("invalid format {}, must be json or pgn", to)),
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+
+// pgn_command runs one of PGN database tools, games are processed in parallel.
+fn pgn_command(args: &[String]) -> Result<(), String> {
+    let (tool, path) = match (args.first(), args.get(1)) {
+        (Some(tool), Some(path)) if !path.starts_with("--") => (tool.as_str(), path),
+        _ => return Err(USAGE.to_string()),
+    };
+    let ordered = args.iter().any(|a| a == "--ordered");
+    let export_options = PgnExportOptions {
+        line_width: parse_option(args, "--width", 80)?,
+        comments: !args.iter().any(|a| a == "--no-comments"),
+        variations: !args.iter().any(|a| a == "--no-variations"),
+        notation: parse_option(args, "--notation", MoveNotation::San)?,
+    };
+    let export = |g: &PgnGame| export_game(g, &export_options);
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let games = split_games(&content);
+
+    // database is written atomically, so an interrupted run doesn't leave a truncated file.
+    let mut out: Box<dyn Write> = match option_value(args, "--output") {
+        Some(path) => PgnWriter::atomic(path, args.iter().any(|a| a == "--append"))
+            .map(|w| Box::new(w) as Box<dyn Write>)
+            .map_err(|e| format!("cannot create {}: {}", path, e))?,
+        None => output_writer(args)?,
+    };
+    let progress = cli_progress();
+    let progress = progress.as_ref();
+    let result = match tool {
+        "replay" => {
+            let options = ReplayOptions {
+                mode: parse_option(args, "--mode", ParseMode::Normal)?,
+                cross_check: args.iter().any(|a| a == "--cross-check"),
+            };
+            process_games(&games, ordered, progress, &mut out, |i, g| {
+                replay_report(i, g, &options)
+            })
+        }
+        "positions" => process_games(&games, ordered, progress, &mut out, |_, g| positions(g)),
+        "novelty" => {
+            let db = option_value(args, "--db").ok_or_else(|| USAGE.to_string())?;
+            let content =
+                fs::read_to_string(db).map_err(|e| format!("cannot read {}: {}", db, e))?;
+            let index = index_positions(&split_games(&content));
+            process_games(&games, ordered, progress, &mut out, |i, g| {
+                novelty_report(i, g, &index)
+            })
+        }
+        "dedup" => {
+            let unique: HashSet<usize> = dedup(&games).into_iter().collect();
+            process_games(&games, ordered, progress, &mut out, |i, g| {
+                if unique.contains(&i) {
+                    export(g)
+                } else {
+                    String::new()
+                }
+            })
+        }
+        "filter" => {
+            let filter = GameFilter {
+                tags: option_values(args, "--tag")
+                    .into_iter()
+                    .map(GameFilter::parse_tag)
+                    .collect::<Result<_, _>>()?,
+                min_plies: parse_optional(args, "--min-plies")?,
+                max_plies: parse_optional(args, "--max-plies")?,
+            };
+            process_games(&games, ordered, progress, &mut out, |_, g| {
+                if filter.matches(g) {
+                    export(g)
+                } else {
+                    String::new()
+                }
+            })
+        }
+        "descriptive" => {
+            process_games(
+                &games,
+                ordered,
+                progress,
+                &mut out,
+                |i, g| match descriptive_to_san(g) {
+                    Ok(converted) => export(&converted),
+                    Err(e) => {
+                        eprintln!("{}", e.with_game(i + 1));
+                        String::new()
+                    }
+                },
+            )
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+    result.and_then(|_| out.flush()).map_err(|e| e.to_string())
+}
+
+// export_game writes game followed by an empty line, game that can't be replayed is written as
+// it was read.
+fn export_game(game: &PgnGame, options: &PgnExportOptions) -> String {
+    match game.export(options, &[]) {
+        Ok(pgn) => format!("{}\n", pgn),
+        Err(_) => format!("{}\n", game),
+    }
+}
+
+// match_command plays a match between two engines given as search backend and evaluator, e.g.
+// 'alphabeta:simple', or as an external UCI engine, e.g. 'uci:stockfish'. Games are appended to
+// --output file as they finish.
+fn match_command(args: &[String]) -> Result<(), String> {
+    let engines = option_values(args, "--engine");
+    if engines.len() != 2 {
+        return Err(USAGE.to_string());
+    }
+    let specs: Vec<(&str, &str)> = engines
+        .iter()
+        .map(|e| e.split_once(':').unwrap_or((e, "material-mobility")))
+        .collect();
+    let evaluators = specs
+        .iter()
+        .map(|(search, evaluator)| match *search {
+            "uci" => Ok(None),
+            _ => evaluator_option(evaluator).map(Some),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut players = specs
+        .iter()
+        .zip(&evaluators)
+        .map(|((search, evaluator), e)| match e {
+            Some(e) => {
+                let backend = backend_by_name(search, e.as_ref()).ok_or(format!(
+                    "unknown search {}, available: {}, uci",
+                    search,
+                    BACKEND_NAMES.join(", ")
+                ))?;
+                Ok(Player {
+                    name: format!("{}:{}", search, evaluator),
+                    backend,
+                })
+            }
+            None => {
+                let client = UciClient::spawn(evaluator)
+                    .map_err(|e| format!("cannot start engine {}: {}", evaluator, e))?;
+                Ok(Player {
+                    name: client.name.clone(),
+                    backend: Box::new(client),
+                })
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let settings = MatchSettings {
+        depth: parse_option(args, "--depth", 3)?,
+        time_control: parse_optional(args, "--tc")?,
+        lag: Duration::from_millis(parse_option(args, "--lag", 0)?),
+        max_plies: parse_option(args, "--max-plies", 300)?,
+        event: option_value(args, "--event").unwrap_or("?").to_string(),
+    };
+    // with an opening suite every opening is played twice with colors reversed, by default once.
+    let openings = match (
+        option_value(args, "--openings"),
+        option_value(args, "--fen"),
+    ) {
+        (Some(_), Some(_)) => return Err("--openings and --fen can't be used together".to_string()),
+        (Some(path), None) => read_openings(
+            &fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?,
+        )
+        .map_err(|e| format!("{}: {}", path, e))?,
+        (None, fen) => {
+            let mut start = Board::default();
+            if let Some(fen) = fen {
+                start.read_fen(fen)?;
+            }
+            vec![start]
+        }
+    };
+    let games = match option_value(args, "--openings") {
+        Some(_) => parse_option(args, "--games", openings.len() * 2)?,
+        None => parse_option(args, "--games", 2)?,
+    };
+    let mut writer = match option_value(args, "--output") {
+        Some(path) => {
+            Some(PgnWriter::append(path).map_err(|e| format!("cannot open {}: {}", path, e))?)
+        }
+        None => None,
+    };
+
+    // progress is printed as by cutechess-cli, so its output parsers work unchanged.
+    let names = (players[0].name.clone(), players[1].name.clone());
+    let (first, second) = players.split_at_mut(1);
+    let mut error = None;
+    let mut running = MatchScore::default();
+    let score = play_openings(
+        &mut first[0],
+        &mut second[0],
+        games,
+        &openings,
+        &settings,
+        |i, record| {
+            println!(
+                "Finished game {} ({} vs {}): {} {{{}}}",
+                i + 1,
+                record.white,
+                record.black,
+                record.result,
+                record.description()
+            );
+            match (record.result, record.white == names.0) {
+                (GameResult::Draw, _) => running.draws += 1,
+                (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => running.wins += 1,
+                _ => running.losses += 1,
+            }
+            println!("{}", running.cutechess_summary(&names.0, &names.1));
+            if let Some(w) = writer.as_mut() {
+                if let Err(e) = w.write_game(&record.to_pgn()) {
+                    error.get_or_insert(e);
+                }
+            }
+        },
+    );
+    println!(
+        "{}",
+        standings(&[(names.0.clone(), score), (names.1.clone(), score.flip())])
+    );
+    println!("Finished match");
+    match (error, writer) {
+        (Some(e), _) => Err(e.to_string()),
+        (None, Some(w)) => w.finish().map_err(|e| e.to_string()),
+        (None, None) => Ok(()),
+    }
+}
+
+// repl starts debugging console reading commands from stdin.
+fn repl(args: &[String]) -> Result<(), String> {
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    println!("chust debug console, type 'help' for commands");
+    Repl::new(evaluator)
+        .run(io::stdin().lock(), &mut io::stdout())
+        .map_err(|e| e.to_string())
+}
+
+// tui plays a game against the engine in terminal user interface. The game is saved to recovery
+// file after every move, by default in the temporary directory, and an unfinished game found there
+// is offered to be resumed.
+#[cfg(feature = "tui")]
+fn tui(args: &[String]) -> Result<(), String> {
+    use chust::tui::Play;
+    use std::path::PathBuf;
+
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let color = match option_value(args, "--color").unwrap_or("white") {
+        "white" => Color::WHITE,
+        "black" => Color::BLACK,
+        c => return Err(format!("invalid color {}, must be white or black", c)),
+    };
+    let depth = parse_option(args, "--depth", 4)?;
+    let time_control = parse_optional(args, "--tc")?;
+    let recovery = option_value(args, "--recovery")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| env::temp_dir().join("chust-tui-recovery.pgn"));
+
+    let mut play = None;
+    let saved = fs::read_to_string(&recovery)
+        .ok()
+        .map(|pgn| split_games(&pgn));
+    if let Some(game) = saved.as_ref().and_then(|games| games.first()) {
+        print!(
+            "unfinished game of {} moves in {}, resume? [Y/n] ",
+            game.moves().len(),
+            recovery.display()
+        );
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut answer = String::new();
+        io::stdin()
+            .read_line(&mut answer)
+            .map_err(|e| e.to_string())?;
+        if !answer.trim().eq_ignore_ascii_case("n") {
+            match Play::resume(evaluator.as_ref(), depth, game) {
+                Ok(resumed) => play = Some(resumed),
+                Err(e) => eprintln!("can't resume {}: {}", recovery.display(), e),
+            }
+        }
+    }
+    let play = play.unwrap_or_else(|| {
+        let play = Play::new(evaluator.as_ref(), color, depth);
+        match time_control {
+            Some(tc) => play.with_time_control(tc),
+            None => play,
+        }
+    });
+    chust::tui::run(play, Some(recovery)).map_err(|e| e.to_string())
+}
+
+// grpc_serve runs engine gRPC service, default address is 127.0.0.1:50051.
+#[cfg(feature = "grpc")]
+fn grpc_serve(args: &[String]) -> Result<(), String> {
+    let addr = parse_option(args, "--addr", "127.0.0.1:50051".parse().unwrap())?;
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    eprintln!("engine service listening on {}", addr);
+    runtime
+        .block_on(chust::grpc::serve(addr, evaluator))
+        .map_err(|e| e.to_string())
+}
+
+// serve runs HTTP analysis server with WebSocket live analysis, default address is
+// 127.0.0.1:8080. --connections limits open connections of a client, --movetime and --nodes
+// limit every search. SIGTERM and SIGINT shut it down after searches in progress.
+#[cfg(feature = "serve")]
+fn serve(args: &[String]) -> Result<(), String> {
+    let addr = option_value(args, "--addr").unwrap_or("127.0.0.1:8080");
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let defaults = chust::serve::ServeLimits::default();
+    let limits = chust::serve::ServeLimits {
+        connections_per_client: parse_option(
+            args,
+            "--connections",
+            defaults.connections_per_client,
+        )?,
+        search_time: parse_optional(args, "--movetime")?
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.search_time),
+        search_nodes: parse_option(args, "--nodes", defaults.search_nodes)?,
+        ..defaults
+    };
+    let listener = std::net::TcpListener::bind(addr)
+        .map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+    eprintln!("analysis server listening on {}", addr);
+    let shutdown = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    #[cfg(unix)]
+    for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+        signal_hook::flag::register(signal, shutdown.clone()).map_err(|e| e.to_string())?;
+    }
+    chust::serve::serve(listener, std::sync::Arc::from(evaluator), limits, shutdown)
+        .map_err(|e| e.to_string())?;
+    eprintln!("analysis server stopped");
+    Ok(())
+}
+
+// renderer_options returns renderer configured with --theme, --pieces, --size, --no-coordinates
+// and --flip options. Theme is a name of built-in theme or a file with colors.
+fn renderer_options(args: &[String]) -> Result<Renderer, String> {
+    let theme = match option_value(args, "--theme") {
+        Some(name) if THEME_NAMES.contains(&name) => Theme::by_name(name).unwrap_or_default(),
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| format!("cannot read theme {}: {}", path, e))?
+            .parse()
+            .map_err(|e| format!("invalid theme {}: {}", path, e))?,
+        None => Theme::default(),
+    };
+    let pieces = match option_value(args, "--pieces") {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| format!("cannot read pieces {}: {}", path, e))?
+            .parse::<PieceSet>()
+            .map_err(|e| format!("invalid pieces {}: {}", path, e))?,
+        None => PieceSet::default(),
+    };
+    Ok(Renderer {
+        theme,
+        pieces,
+        square: parse_option(args, "--size", 48)?,
+        coordinates: !args.iter().any(|a| a == "--no-coordinates"),
+        flipped: args.iter().any(|a| a == "--flip"),
+    })
+}
+
+// render writes SVG diagram of the position.
+fn render(args: &[String]) -> Result<(), String> {
+    let fen = match args.first() {
+        Some(fen) if !fen.starts_with("--") => fen,
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut board = Board::default();
+    for warning in board.read_fen_lenient(fen)? {
+        eprintln!("warning: {}", warning);
+    }
+    let svg = renderer_options(args)?.svg(&board, None);
+    let mut out = output_writer(args)?;
+    out.write_all(svg.as_bytes())
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())
+}
+
+// gif writes animated GIF of a game of PGN file, every position is shown for --delay and the
+// final one for --hold milliseconds.
+#[cfg(feature = "gif")]
+fn gif(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with('-') => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let output = option_value(args, "-o")
+        .or(option_value(args, "--output"))
+        .ok_or("output file is required, use -o FILE")?;
+    let number: usize = parse_option(args, "--game", 1)?;
+    let default = chust::render::Animation::default();
+    let animation = chust::render::Animation {
+        delay: parse_optional(args, "--delay")?
+            .map(Duration::from_millis)
+            .unwrap_or(default.delay),
+        hold: parse_optional(args, "--hold")?
+            .map(Duration::from_millis)
+            .unwrap_or(default.hold),
+    };
+    let renderer = renderer_options(args)?;
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let games = split_games(&content);
+    let game = number
+        .checked_sub(1)
+        .and_then(|i| games.get(i))
+        .ok_or_else(|| format!("there is no game {} in {}", number, path))?;
+    let frames = renderer
+        .game_frames(game)
+        .map_err(|e| e.with_game(number).to_string())?;
+    let file = File::create(output).map_err(|e| format!("cannot create {}: {}", output, e))?;
+    chust::render::write_gif(&frames, &animation, BufWriter::new(file)).map_err(|e| e.to_string())
+}
+
+// annotate writes games of PGN file with engine evaluation after every move, marked mistakes and
+// better lines, as computer analysis of Lichess.
+fn annotate(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with('-') => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let depth: usize = parse_option(args, "--depth", 2)?;
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let options = PgnExportOptions {
+        line_width: parse_option(args, "--width", 80)?,
+        ..Default::default()
+    };
+    let games = read_games(path, args)?;
+    let mut out: Box<dyn Write> = match option_value(args, "-o").or(option_value(args, "--output"))
+    {
+        Some(path) => PgnWriter::atomic(path, false)
+            .map(|w| Box::new(w) as Box<dyn Write>)
+            .map_err(|e| format!("cannot create {}: {}", path, e))?,
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    for (i, game) in games.iter().enumerate() {
+        let annotated = annotate_game(game, evaluator.as_ref(), depth).and_then(|annotations| {
+            let mut game = game.clone();
+            game.tags.retain(|(name, _)| name != "Annotator");
+            game.tags.push(ENGINE_INFO.annotator_tag());
+            game.export(&options, &annotations)
+        });
+        // game that can't be replayed is written as it is.
+        let pgn = annotated.unwrap_or_else(|e| {
+            eprintln!("{}", e.with_game(i + 1));
+            game.to_string()
+        });
+        writeln!(out, "{}", pgn).map_err(|e| e.to_string())?;
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+
+// review computes accuracy of both players in every game of PGN file and accuracy of every
+// player across all games.
+fn review(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let depth: usize = parse_option(args, "--depth", 2)?;
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let show_moves = args.iter().any(|a| a == "--moves");
+    let games = read_games(path, args)?;
+
+    let reviews: Vec<_> = games
+        .par_iter()
+        .map(|g| review_game(g, evaluator.as_ref(), depth))
+        .collect();
+
+    let mut players: BTreeMap<String, (usize, PlayerStats)> = BTreeMap::new();
+    for (i, (game, review)) in games.iter().zip(reviews).enumerate() {
+        let names = [
+            game.tag("White").unwrap_or("White").to_string(),
+            game.tag("Black").unwrap_or("Black").to_string(),
+        ];
+        println!("game {}: {} - {}", i + 1, names[0], names[1]);
+        let review = match review {
+            Ok(review) => review,
+            Err(e) => {
+                println!("  {}", e);
+                continue;
+            }
+        };
+        if show_moves {
+            for m in &review.moves {
+                println!(
+                    "  {:>3}. {:<8}{:<3} accuracy {:>5.1}%  cp loss {:>4}",
+                    m.ply.div_ceil(2),
+                    m.san,
+                    m.judgement.map(|j| j.symbol()).unwrap_or(""),
+                    m.accuracy,
+                    m.cp_loss
+                );
+            }
+        }
+        for (name, stats) in names.iter().zip([&review.white, &review.black]) {
+            print_player_stats(name, stats);
+            let player = players.entry(name.clone()).or_default();
+            player.0 += 1;
+            player.1.merge(stats);
+        }
+    }
+
+    if games.len() > 1 {
+        println!("\nplayers:");
+        for (name, (games, stats)) in &players {
+            print_player_stats(&format!("{} ({} games)", name, games), stats);
+        }
+    }
+    Ok(())
+}
+
+fn print_player_stats(name: &str, stats: &PlayerStats) {
+    println!(
+        "  {:<24} accuracy {:>5.1}%  acpl {:>4.0}  inaccuracies {}  mistakes {}  blunders {}",
+        name,
+        stats.accuracy(),
+        stats.acpl(),
+        stats.inaccuracies,
+        stats.mistakes,
+        stats.blunders
+    );
+}
+
+// stats prints results of the player by color, by ECO code and in the most played openings,
+// performance rating in games against rated opponents and with --analyze accuracy of the player's
+// moves.
+fn stats(args: &[String]) -> Result<(), String> {
+    let (path, player) = match (args.first(), option_value(args, "--player")) {
+        (Some(path), Some(player)) if !path.starts_with("--") => (path, player),
+        _ => return Err(USAGE.to_string()),
+    };
+    let depth: usize = parse_option(args, "--depth", 2)?;
+    let top: usize = parse_option(args, "--top", 5)?;
+    let evaluator =
+        evaluator_option(option_value(args, "--evaluator").unwrap_or("material-mobility"))?;
+    let analysis = args
+        .iter()
+        .any(|a| a == "--analyze")
+        .then_some((evaluator.as_ref(), depth));
+    let report = player_report(&read_games(path, args)?, player, analysis);
+    if report.games == 0 {
+        return Err(format!("there are no games of {} in {}", player, path));
+    }
+
+    let print_record = |name: &str, record: &Record| {
+        println!(
+            "  {:<32} {:>4} games  +{} ={} -{}  {:>5.1}%",
+            name,
+            record.games(),
+            record.wins,
+            record.draws,
+            record.losses,
+            record.score()
+        )
+    };
+    println!(
+        "{}: {} games, average length {:.1} moves",
+        player,
+        report.games,
+        report.average_moves()
+    );
+    if let (Some(performance), Some(opponents)) = (report.performance(), report.average_opponent())
+    {
+        println!(
+            "performance {:.0} +/- {:.0} ({:.0} - {:.0}) in {} rated games, average opponent {:.0}",
+            performance.elo,
+            performance.margin(),
+            performance.low,
+            performance.high,
+            report.rated.len(),
+            opponents
+        );
+    }
+    print_record("as white", &report.white);
+    print_record("as black", &report.black);
+    println!("by ECO:");
+    for (eco, record) in &report.by_eco {
+        print_record(eco, record);
+    }
+    println!("most common openings:");
+    for (opening, record) in report.most_common_openings(top) {
+        print_record(opening, record);
+    }
+    if let Some(analysis) = &report.analysis {
+        println!("engine analysis:");
+        print_player_stats(player, analysis);
+        if report.unanalyzed > 0 {
+            println!("  {} games could not be analyzed", report.unanalyzed);
+        }
+    }
+    Ok(())
+}
+
+// drill plays opponent side of repertoire read from PGN file and asks for repertoire moves.
+fn drill(args: &[String]) -> Result<(), String> {
+    let path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let color = match option_value(args, "--color").unwrap_or("white") {
+        "white" => Color::WHITE,
+        "black" => Color::BLACK,
+        c => return Err(format!("invalid color {}, must be white or black", c)),
+    };
+    let randomness: f32 = parse_option(args, "--randomness", 0.5)?;
+    let lines: usize = parse_option(args, "--lines", 5)?;
+    let rng = rng_option(args)?;
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let repertoire = Repertoire::from_pgn(&content)?;
+
+    let mut drill = Drill {
+        repertoire: &repertoire,
+        color,
+        randomness: randomness.clamp(0.0, 1.0),
+        rng,
+        score: DrillScore::default(),
+    };
+    drill
+        .run(&mut io::stdin().lock(), &mut io::stdout(), lines)
+        .map_err(|e| e.to_string())
+}
+
+// train runs coordinates training quiz.
+fn train(args: &[String]) -> Result<(), String> {
+    let kind = match args.first().map(|s| s.as_str()) {
+        Some("colors") => QuizKind::Colors,
+        Some("squares") => QuizKind::Squares,
+        Some("knight") => QuizKind::Knight,
+        _ => return Err(USAGE.to_string()),
+    };
+    let count: usize = parse_option(args, "--count", 10)?;
+    let questions = quiz(rng_option(args)?, kind).take(count);
+    run_quiz(questions, &mut io::stdin().lock(), &mut io::stdout())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// tune tunes evaluation weights by SPSA self-play and writes them after every iteration, so an
+// interrupted session keeps its progress.
+fn tune(args: &[String]) -> Result<(), String> {
+    let tunables = option_values(args, "--tune")
+        .into_iter()
+        .map(|t| t.parse())
+        .collect::<Result<Vec<Tunable>, _>>()?;
+    let output = match option_value(args, "--output") {
+        Some(output) if !tunables.is_empty() => output,
+        _ => return Err(USAGE.to_string()),
+    };
+    let params = match option_value(args, "--params") {
+        Some(path) => EvalParams::load(path)?,
+        None => EvalParams::default(),
+    };
+    let defaults = SpsaSettings::default();
+    let settings = SpsaSettings {
+        iterations: parse_option(args, "--iterations", defaults.iterations)?,
+        pairs: parse_option(args, "--pairs", defaults.pairs)?,
+        random_plies: parse_option(args, "--random-plies", defaults.random_plies)?,
+        search: option_value(args, "--search")
+            .unwrap_or(&defaults.search)
+            .to_string(),
+        games: MatchSettings {
+            depth: parse_option(args, "--depth", defaults.games.depth)?,
+            ..defaults.games
+        },
+    };
+    let mut spsa = Spsa::new(params, tunables, settings);
+    let mut error = None;
+    spsa.run(&mut rng_option(args)?, |k, result, params| {
+        let values: Vec<String> = spsa_names(args)
+            .iter()
+            .map(|name| format!("{} {:.4}", name, params.get(name).unwrap_or_default()))
+            .collect();
+        println!("iteration {}: {:+} {}", k, result, values.join(", "));
+        if let Err(e) = fs::write(output, params.to_string()) {
+            error.get_or_insert(format!("cannot write {}: {}", output, e));
+        }
+    })?;
+    error.map_or(Ok(()), Err)
+}
+
+// spsa_names returns names of tuned parameters, without bounds.
+fn spsa_names(args: &[String]) -> Vec<&str> {
+    option_values(args, "--tune")
+        .into_iter()
+        .map(|t| t.split(':').next().unwrap_or(t))
+        .collect()
 }
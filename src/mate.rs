@@ -0,0 +1,161 @@
+use crate::board::{Board, Transition};
+use crate::search::{is_set, Score, SearchLine, StopSignal, DEFAULT_POLL_INTERVAL};
+use crate::tablebase::{self, Dtm};
+
+// MateSolver looks only for forced mates, every move of the defending side is checked, so found
+// mate is proven. It doesn't need an evaluator.
+pub struct MateSolver {
+    pub nodes: u64,
+    pub stop: Option<StopSignal>, // stopped solver reports no mate
+    pub poll_interval: u64,       // nodes between checks of the stop signal
+    stopped: bool,
+}
+
+impl Default for MateSolver {
+    fn default() -> Self {
+        MateSolver::new()
+    }
+}
+
+impl MateSolver {
+    pub fn new() -> Self {
+        MateSolver {
+            nodes: 0,
+            stop: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            stopped: false,
+        }
+    }
+
+    // solve returns the shortest forced mate of color to move, up to max_moves moves.
+    // Positions of endings with generated tablebases are looked up instead.
+    pub fn solve(&mut self, board: &Board, max_moves: usize) -> Option<SearchLine> {
+        self.stopped = false;
+        if let Some(dtm) = tablebase::probe(board) {
+            let moves = match dtm {
+                Dtm::Win(plies) => plies.div_ceil(2) as usize,
+                _ => return None,
+            };
+            return match tablebase::mate_line(board) {
+                Some(pv) if moves <= max_moves => Some(SearchLine {
+                    score: Score::Mate(moves as i32),
+                    pv,
+                }),
+                _ => None,
+            };
+        }
+        for moves in 1..=max_moves {
+            let pv = self.attack(board, moves);
+            if self.stopped {
+                return None;
+            }
+            if let Some(pv) = pv {
+                return Some(SearchLine {
+                    score: Score::Mate(moves as i32),
+                    pv,
+                });
+            }
+        }
+        None
+    }
+
+    // should_stop checks the stop signal every poll_interval nodes.
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(self.poll_interval.max(1)) {
+            self.stopped = is_set(&self.stop);
+        }
+        self.stopped
+    }
+
+    // attack returns line in which color to move mates in given number of moves.
+    fn attack(&mut self, board: &Board, moves: usize) -> Option<Vec<Transition>> {
+        self.nodes += 1;
+        if self.should_stop() {
+            return None;
+        }
+        let mut candidates: Vec<(Transition, Board)> = board
+            .generate_transitions()
+            .into_iter()
+            .map(|t| {
+                let mut child = board.clone();
+                child.make_transition(t);
+                (t, child)
+            })
+            .collect();
+        // checks are tried first, they are the most likely to mate.
+        candidates.sort_by_key(|(_, child)| !child.is_in_check());
+
+        for (t, child) in &candidates {
+            if child.is_in_check() && child.generate_transitions().is_empty() {
+                return Some(vec![*t]);
+            }
+        }
+        if moves == 1 {
+            return None;
+        }
+        for (t, child) in &candidates {
+            if let Some(pv) = self.defend(child, moves - 1) {
+                let mut line = vec![*t];
+                line.extend(pv);
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    // defend returns the longest line of defence when every move of color to move loses to mate
+    // in given number of moves, None when there's a defence or it's stalemate.
+    fn defend(&mut self, board: &Board, moves: usize) -> Option<Vec<Transition>> {
+        self.nodes += 1;
+        if self.should_stop() {
+            return None;
+        }
+        let replies = board.generate_transitions();
+        if replies.is_empty() {
+            return None;
+        }
+        let mut longest: Vec<Transition> = Vec::new();
+        for t in replies {
+            let mut child = board.clone();
+            child.make_transition(t);
+            let pv = self.attack(&child, moves)?;
+            if pv.len() + 1 > longest.len() {
+                longest = vec![t];
+                longest.extend(pv);
+            }
+        }
+        Some(longest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::mate::MateSolver;
+    use crate::notation::line_to_san;
+    use crate::search::Score;
+
+    #[test]
+    fn test_solve() {
+        let mut b = Board::default();
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w").unwrap();
+        let line = MateSolver::new().solve(&b, 3).unwrap();
+        assert_eq!(line.score, Score::Mate(1));
+        assert_eq!(line_to_san(&b, &line.pv), "1. Ra8#");
+
+        // two rooks ladder, mate in 2.
+        b.read_fen("7k/8/8/8/8/8/R7/1R4K1 w").unwrap();
+        let line = MateSolver::new().solve(&b, 2).unwrap();
+        assert_eq!(line.score, Score::Mate(2));
+        assert_eq!(line.pv.len(), 3);
+
+        b.read_fen("4k3/8/8/8/8/8/8/4K3 w").unwrap();
+        assert!(MateSolver::new().solve(&b, 2).is_none());
+
+        // rook ending is looked up in tablebase, mate in 16 is too deep to search.
+        b.read_fen("8/8/8/8/3k4/8/8/R3K3 w").unwrap();
+        let line = MateSolver::new().solve(&b, 20).unwrap();
+        assert_eq!(line.score, Score::Mate((line.pv.len() as i32 + 1) / 2));
+        assert!(MateSolver::new().solve(&b, 2).is_none());
+    }
+}
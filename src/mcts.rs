@@ -0,0 +1,237 @@
+use crate::board::{Board, Transition};
+use crate::evaluation::Evaluator;
+use crate::piece::Color;
+use crate::search::{is_set, Score, SearchLine, StopSignal, WIN_PROBABILITY_SCALE};
+use std::time::Instant;
+
+// EXPLORATION is a default constant of PUCT formula, higher value makes the search wider.
+pub const EXPLORATION: f32 = 1.5;
+
+// Prior returns prior probabilities of moves, e.g. policy of a neural network. Probabilities are
+// returned in the same order as moves and should sum up to 1. Like evaluators, priors can be shared
+// by threads.
+pub trait Prior: Send + Sync {
+    fn priors(&self, board: &Board, moves: &[Transition]) -> Vec<f32>;
+}
+
+// UniformPrior gives every move the same probability, used when there's no policy available.
+pub struct UniformPrior;
+
+impl Prior for UniformPrior {
+    fn priors(&self, _board: &Board, moves: &[Transition]) -> Vec<f32> {
+        vec![1.0 / moves.len() as f32; moves.len()]
+    }
+}
+
+struct Node {
+    transition: Option<Transition>, // None for the root
+    prior: f32,
+    visits: u32,
+    value: f32, // sum of results from the point of view of color that made the move
+    children: Vec<usize>,
+    expanded: bool,
+    mate: bool, // move gives check mate
+}
+
+impl Node {
+    fn new(transition: Option<Transition>, prior: f32) -> Self {
+        Node {
+            transition,
+            prior,
+            visits: 0,
+            value: 0.0,
+            children: Vec::new(),
+            expanded: false,
+            mate: false,
+        }
+    }
+
+    // q returns average result of the node, unvisited nodes are treated as a draw.
+    fn q(&self) -> f32 {
+        if self.visits == 0 {
+            0.5
+        } else {
+            self.value / self.visits as f32
+        }
+    }
+}
+
+// Mcts is a Monte Carlo tree search guided by PUCT formula, as in AlphaZero. Leaves are not
+// played out, they are scored with evaluator instead and priors of moves come from Prior.
+pub struct Mcts<'a> {
+    evaluator: &'a dyn Evaluator,
+    prior: &'a dyn Prior,
+    pub exploration: f32,
+    pub nodes: u64,
+    pub deadline: Option<Instant>, // playouts stop at deadline even if not all were run
+    pub stop: Option<StopSignal>,  // checked before every playout, as the deadline
+    tree: Vec<Node>,
+}
+
+impl<'a> Mcts<'a> {
+    pub fn new(evaluator: &'a dyn Evaluator, prior: &'a dyn Prior) -> Self {
+        Mcts {
+            evaluator,
+            prior,
+            exploration: EXPLORATION,
+            nodes: 0,
+            deadline: None,
+            stop: None,
+            tree: Vec::new(),
+        }
+    }
+
+    // search runs given number of playouts from the position and returns 'multipv' most visited
+    // root moves with their lines.
+    pub fn search(&mut self, board: &Board, playouts: usize, multipv: usize) -> Vec<SearchLine> {
+        self.tree = vec![Node::new(None, 1.0)];
+        for i in 0..playouts.max(1) {
+            if i > 0 && (is_set(&self.stop) || self.deadline.is_some_and(|d| Instant::now() >= d)) {
+                break;
+            }
+            self.playout(board);
+        }
+
+        let mut root_children = self.tree[0].children.clone();
+        root_children.sort_by_key(|&c| std::cmp::Reverse(self.tree[c].visits));
+        root_children
+            .into_iter()
+            .take(multipv.max(1))
+            .map(|c| SearchLine {
+                score: self.score(c),
+                pv: self.principal_variation(c),
+            })
+            .collect()
+    }
+
+    // root_visits returns root moves with number of their visits.
+    pub fn root_visits(&self) -> Vec<(Transition, u32)> {
+        self.tree
+            .first()
+            .map(|root| {
+                root.children
+                    .iter()
+                    .filter_map(|&c| Some((self.tree[c].transition?, self.tree[c].visits)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn playout(&mut self, root: &Board) {
+        self.nodes += 1;
+        let mut board = root.clone();
+        let mut path = vec![0];
+        let mut node = 0;
+        while self.tree[node].expanded && !self.tree[node].children.is_empty() {
+            node = self.select(node);
+            board.make_transition(self.tree[node].transition.unwrap());
+            path.push(node);
+        }
+
+        // value is a result from the point of view of color to move in the leaf.
+        let mut value = self.expand(node, &board);
+        for &n in path.iter().rev() {
+            self.tree[n].visits += 1;
+            self.tree[n].value += 1.0 - value;
+            value = 1.0 - value;
+        }
+    }
+
+    fn select(&self, node: usize) -> usize {
+        let parent_visits = (self.tree[node].visits as f32).sqrt();
+        let puct = |c: usize| {
+            let child = &self.tree[c];
+            child.q() + self.exploration * child.prior * parent_visits / (1.0 + child.visits as f32)
+        };
+        *self.tree[node]
+            .children
+            .iter()
+            .max_by(|&&a, &&b| puct(a).total_cmp(&puct(b)))
+            .unwrap()
+    }
+
+    // expand adds children of the leaf and returns its value.
+    fn expand(&mut self, node: usize, board: &Board) -> f32 {
+        let moves = board.generate_transitions();
+        if moves.is_empty() {
+            self.tree[node].expanded = true;
+            if board.is_in_check() {
+                self.tree[node].mate = true;
+                return 0.0;
+            }
+            return 0.5;
+        }
+        if !self.tree[node].expanded {
+            let priors = self.prior.priors(board, &moves);
+            for (t, p) in moves.into_iter().zip(priors) {
+                self.tree.push(Node::new(Some(t), p));
+                let child = self.tree.len() - 1;
+                self.tree[node].children.push(child);
+            }
+            self.tree[node].expanded = true;
+        }
+        let eval = (self.evaluator.evaluate(board) * 100.0) as i32;
+        let eval = if board.color_to_move == Color::WHITE {
+            eval
+        } else {
+            -eval
+        };
+        Score::Centipawns(eval).win_probability()
+    }
+
+    // score converts average result of root move to centipawns with inverse of win probability.
+    fn score(&self, node: usize) -> Score {
+        if self.tree[node].mate {
+            return Score::Mate(1);
+        }
+        let q = self.tree[node].q().clamp(0.001, 0.999);
+        Score::Centipawns(((q / (1.0 - q)).ln() / WIN_PROBABILITY_SCALE) as i32)
+    }
+
+    // principal_variation follows the most visited children.
+    fn principal_variation(&self, node: usize) -> Vec<Transition> {
+        let mut pv = Vec::new();
+        let mut node = node;
+        loop {
+            pv.extend(self.tree[node].transition);
+            match self.tree[node]
+                .children
+                .iter()
+                .filter(|&&c| self.tree[c].visits > 0)
+                .max_by_key(|&&c| self.tree[c].visits)
+            {
+                Some(&child) => node = child,
+                None => return pv,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::SimpleEvaluator;
+    use crate::mcts::{Mcts, UniformPrior};
+    use crate::notation::to_san;
+    use crate::search::Score;
+
+    #[test]
+    fn test_mcts_finds_mate_and_capture() {
+        let e = SimpleEvaluator {};
+        let mut b = Board::default();
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w").unwrap();
+        let mut mcts = Mcts::new(&e, &UniformPrior);
+        let lines = mcts.search(&b, 300, 2);
+        assert_eq!(to_san(&b, &lines[0].pv[0]), "Ra8#");
+        assert_eq!(lines[0].score, Score::Mate(1));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(mcts.nodes, 300);
+        let visits: u32 = mcts.root_visits().iter().map(|(_, v)| v).sum();
+        assert_eq!(visits, 299); // the first playout expands the root
+
+        b.read_fen("4k3/8/8/3q4/8/8/3R4/4K3 w").unwrap();
+        let lines = Mcts::new(&e, &UniformPrior).search(&b, 300, 1);
+        assert_eq!(to_san(&b, &lines[0].pv[0]), "Rxd5");
+        assert!(matches!(lines[0].score, Score::Centipawns(cp) if cp > 300));
+    }
+}
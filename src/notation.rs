@@ -0,0 +1,782 @@
+use crate::board::{Board, Transition, TransitionFlag};
+use crate::piece::{Color, PieceType};
+use std::str::FromStr;
+
+// Squares are indexed from 0 (a1) to 63 (h8), rank by rank: index = rank * 8 + file, where
+// file 0 is 'a' and rank 0 is '1'.
+
+// square_name returns algebraic name of a square e.g. 28 -> e4.
+pub fn square_name(square: usize) -> String {
+    let (file, rank) = file_rank(square);
+    format!("{}{}", file_letter(file), (b'1' + rank as u8) as char)
+}
+
+// parse_square translates square written in algebraic notation e.g. 'e4' to its index.
+pub fn parse_square(name: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(file), Some(rank), None) if ('1'..='8').contains(&rank) => Some(square(
+            file_from_letter(file)?,
+            rank as usize - '1' as usize,
+        )),
+        _ => None,
+    }
+}
+
+// file_rank returns (file, rank) pair of a square e.g. 28 -> (4, 3).
+pub fn file_rank(square: usize) -> (usize, usize) {
+    (square % 8, square / 8)
+}
+
+// square returns index of a square for given file and rank, both in range 0-7.
+pub fn square(file: usize, rank: usize) -> usize {
+    rank * 8 + file
+}
+
+// square_at returns index of a square for given file and rank, None if it's outside of the board.
+// Useful for squares computed with offsets e.g. knight jumps.
+pub fn square_at(file: i32, rank: i32) -> Option<usize> {
+    if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+        return None;
+    }
+    Some(square(file as usize, rank as usize))
+}
+
+// file_letter returns letter of a file e.g. 4 -> 'e'.
+pub fn file_letter(file: usize) -> char {
+    (b'a' + file as u8) as char
+}
+
+// file_from_letter returns file of given letter e.g. 'e' -> 4, None if it's not in 'a'-'h'.
+pub fn file_from_letter(letter: char) -> Option<usize> {
+    if ('a'..='h').contains(&letter) {
+        Some(letter as usize - 'a' as usize)
+    } else {
+        None
+    }
+}
+
+// to_internal writes transition as 'from' and 'to' squares e.g. e2e4, e7e8q. Castle is written
+// as king move e.g. e1g1.
+pub fn to_internal(t: &Transition) -> String {
+    let mut m = format!("{}{}", square_name(t.from), square_name(t.to));
+    m.push_str(&t.promotion.sign().to_lowercase());
+    m
+}
+
+// to_uci writes transition as in UCI protocol, like to_internal but in Chess960 mode castle is
+// written as king takes own rook e.g. e1h1. Castling rooks always start in corners, see
+// CastlingNotation, so positions of Chess960 with other castling files can't be written.
+pub fn to_uci(t: &Transition, chess960: bool) -> String {
+    match t.flag {
+        TransitionFlag::ShortCastle if chess960 => {
+            format!("{}{}", square_name(t.from), square_name(t.from + 3))
+        }
+        TransitionFlag::LongCastle if chess960 => {
+            format!("{}{}", square_name(t.from), square_name(t.from - 4))
+        }
+        _ => to_internal(t),
+    }
+}
+
+// parse_uci finds legal move written as in UCI protocol, see to_uci. Error tells why the move
+// can't be made.
+pub fn parse_uci(board: &Board, m: &str, chess960: bool) -> Result<Transition, &'static str> {
+    let squares = (
+        m.get(0..2).and_then(parse_square),
+        m.get(2..4).and_then(parse_square),
+    );
+    let (from, to) = match squares {
+        (Some(from), Some(to)) if m.len() == 4 || m.len() == 5 => (from, to),
+        _ => return Err("invalid move notation"),
+    };
+    if let Some(t) = board
+        .generate_transitions()
+        .into_iter()
+        .find(|t| to_uci(t, chess960) == m)
+    {
+        return Ok(t);
+    }
+    let piece = board.piece_at(from);
+    if piece.p_type == PieceType::KING && piece.color == board.color_to_move {
+        let target = board.piece_at(to);
+        if chess960 && (to as i32 - from as i32).abs() == 2 && target.is_none() {
+            return Err("castle must be written as king takes rook in Chess960 mode");
+        }
+        if !chess960 && target.p_type == PieceType::ROOK && target.color == piece.color {
+            return Err("castle written as king takes rook needs Chess960 mode");
+        }
+    }
+    // the board explains why the move is illegal.
+    match board.clone().make_move_internal_notation(m) {
+        Err(e) => Err(e),
+        Ok(_) => Err("illegal move"),
+    }
+}
+
+// to_san writes transition in standard algebraic notation, board is a position before the move.
+pub fn to_san(board: &Board, t: &Transition) -> String {
+    let mut san = match t.flag {
+        TransitionFlag::ShortCastle => String::from("O-O"),
+        TransitionFlag::LongCastle => String::from("O-O-O"),
+        _ => {
+            let piece = board.piece_at(t.from);
+            let is_capture = is_capture(board, t);
+            let mut san = String::from(piece.p_type.sign());
+            if piece.p_type == PieceType::PAWN {
+                if is_capture {
+                    san.push_str(&square_name(t.from)[0..1]);
+                }
+            } else {
+                san.push_str(&disambiguation(board, t));
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&square_name(t.to));
+            if t.flag == TransitionFlag::Promotion {
+                san.push('=');
+                san.push_str(t.promotion.sign());
+            }
+            san
+        }
+    };
+    san.push_str(check_suffix(board, t));
+    san
+}
+
+// to_lan writes transition in long algebraic notation, with both squares given e.g. Ng1-f3,
+// e7xd8=Q+. Board is a position before the move.
+pub fn to_lan(board: &Board, t: &Transition) -> String {
+    let mut lan = match t.flag {
+        TransitionFlag::ShortCastle => String::from("O-O"),
+        TransitionFlag::LongCastle => String::from("O-O-O"),
+        _ => {
+            let separator = if is_capture(board, t) { 'x' } else { '-' };
+            let mut lan = format!(
+                "{}{}{}{}",
+                board.piece_at(t.from).p_type.sign(),
+                square_name(t.from),
+                separator,
+                square_name(t.to)
+            );
+            if t.flag == TransitionFlag::Promotion {
+                lan.push('=');
+                lan.push_str(t.promotion.sign());
+            }
+            lan
+        }
+    };
+    lan.push_str(check_suffix(board, t));
+    lan
+}
+
+// to_spoken describes transition in words for screen readers e.g. 'knight from g1 to f3,
+// capturing the pawn, check'. Board is a position before the move.
+pub fn to_spoken(board: &Board, t: &Transition) -> String {
+    let mut words = match t.flag {
+        TransitionFlag::ShortCastle => String::from("castles kingside"),
+        TransitionFlag::LongCastle => String::from("castles queenside"),
+        _ => {
+            let mut words = format!(
+                "{} from {} to {}",
+                piece_name(board.piece_at(t.from).p_type),
+                square_name(t.from),
+                square_name(t.to)
+            );
+            if t.flag == TransitionFlag::EnPassant {
+                words.push_str(", capturing the pawn en passant");
+            } else if is_capture(board, t) {
+                words.push_str(", capturing the ");
+                words.push_str(piece_name(board.piece_at(t.to).p_type));
+            }
+            if t.flag == TransitionFlag::Promotion {
+                words.push_str(", promoting to a ");
+                words.push_str(piece_name(t.promotion));
+            }
+            words
+        }
+    };
+    match check_suffix(board, t) {
+        "+" => words.push_str(", check"),
+        "#" => words.push_str(", checkmate"),
+        _ => {}
+    }
+    words
+}
+
+fn piece_name(p_type: PieceType) -> &'static str {
+    match p_type {
+        PieceType::KING => "king",
+        PieceType::QUEEN => "queen",
+        PieceType::ROOK => "rook",
+        PieceType::BISHOP => "bishop",
+        PieceType::KNIGHT => "knight",
+        PieceType::PAWN => "pawn",
+        PieceType::NONE => "piece",
+    }
+}
+
+// parse_san returns transition of a move written in SAN, or LAN, in given position.
+pub fn parse_san(board: &Board, m: &str) -> Result<Transition, &'static str> {
+    let mut after = board.clone();
+    after.make_pgn_move(m)?;
+    let fen = after.position_fen();
+    board
+        .generate_transitions()
+        .into_iter()
+        .find(|t| {
+            let mut b = board.clone();
+            b.make_transition(*t);
+            b.position_fen() == fen
+        })
+        .ok_or("invalid move")
+}
+
+// castle_flag recognizes castles written with letter O or zeros, joined by hyphens, en or em
+// dashes e.g. O-O, 0-0-0, O–O. Check and annotation symbols are ignored.
+pub fn castle_flag(m: &str) -> Option<TransitionFlag> {
+    let m = m.trim_end_matches(['+', '#', '!', '?']);
+    let parts: Vec<&str> = m.split(['-', '–', '—']).collect();
+    if !parts.iter().all(|p| *p == "O" || *p == "0") {
+        return None;
+    }
+    match parts.len() {
+        2 => Some(TransitionFlag::ShortCastle),
+        3 => Some(TransitionFlag::LongCastle),
+        _ => None,
+    }
+}
+
+// ParseMode tells how closely a SAN move has to follow the standard.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ParseMode {
+    #[default]
+    Normal, // capture and check symbols are ignored
+    Strict,  // move must be written exactly as to_san writes it, annotation symbols aside
+    Lenient, // sloppy moves from scraped data are accepted e.g. ngf3, e8Q, 0-0
+}
+
+impl FromStr for ParseMode {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(ParseMode::Normal),
+            "strict" => Ok(ParseMode::Strict),
+            "lenient" => Ok(ParseMode::Lenient),
+            _ => Err("mode must be 'normal', 'strict' or 'lenient'"),
+        }
+    }
+}
+
+// parse_san_mode parses move in SAN like parse_san, checking it as given mode requires.
+pub fn parse_san_mode(board: &Board, m: &str, mode: ParseMode) -> Result<Transition, &'static str> {
+    match mode {
+        ParseMode::Normal => parse_san(board, m),
+        ParseMode::Strict => {
+            let written = m.trim_end_matches(['!', '?']);
+            let t = parse_san(board, written)?;
+            check_strict(board, &t, written)?;
+            Ok(t)
+        }
+        ParseMode::Lenient => parse_san(board, m).or_else(|e| {
+            lenient_forms(m)
+                .iter()
+                .find_map(|f| parse_san(board, f).ok())
+                .ok_or(e)
+        }),
+    }
+}
+
+// check_strict compares written move with its standard form and tells what's wrong with it.
+fn check_strict(board: &Board, t: &Transition, written: &str) -> Result<(), &'static str> {
+    let san = to_san(board, t);
+    if written == san {
+        return Ok(());
+    }
+    if let Some(mismatch) = san_mismatch(board, t, written) {
+        Err(mismatch)
+    } else if written.len() != san.len()
+        && written.chars().next() == san.chars().next()
+        && !written.contains('-')
+    {
+        Err("illegal disambiguation")
+    } else {
+        Err("move is not in standard algebraic notation")
+    }
+}
+
+// san_mismatch checks capture marker and check symbol of written move against the transition it
+// was read as, board is a position before the move.
+pub fn san_mismatch(board: &Board, t: &Transition, written: &str) -> Option<&'static str> {
+    let written = written.trim_end_matches(['!', '?']);
+    let capture = is_capture(board, t);
+    if written.contains('x') != capture {
+        return Some(if capture {
+            "capture is not marked"
+        } else {
+            "capture marker on a move that doesn't capture"
+        });
+    }
+    let symbol = |s: &str| s.chars().last().filter(|c| *c == '+' || *c == '#');
+    match (symbol(written), symbol(check_suffix(board, t))) {
+        (w, actual) if w == actual => None,
+        (_, None) => Some("check symbol on a move that doesn't check"),
+        (None, Some('#')) => Some("mate is not marked"),
+        (None, _) => Some("check is not marked"),
+        _ => Some("wrong check symbol"),
+    }
+}
+
+// lenient_forms returns standard forms a sloppy move may stand for, in order of preference.
+// Lowercase 'b' may be a pawn file or a bishop, so both are tried.
+fn lenient_forms(m: &str) -> Vec<String> {
+    let m = m.trim_end_matches(|c| "!?+#".contains(c));
+    let m = m
+        .strip_suffix("e.p.")
+        .or_else(|| m.strip_suffix("ep"))
+        .unwrap_or(m);
+    if !m.is_empty() && m.chars().all(|c| "0Oo-".contains(c)) {
+        return vec![m.replace(['0', 'o'], "O")];
+    }
+    let mut body = m.to_string();
+    if let Some(i) = body.find('=') {
+        body = format!("{}{}", &body[..i + 1], body[i + 1..].to_uppercase());
+    } else {
+        let chars: Vec<char> = body.chars().collect();
+        if let [.., rank, piece] = chars[..] {
+            if rank.is_ascii_digit() && "qrbnQRBN".contains(piece) {
+                body.pop();
+                body.push('=');
+                body.push(piece.to_ascii_uppercase());
+            }
+        }
+    }
+    match body.chars().next() {
+        Some(c @ ('n' | 'r' | 'q' | 'k')) => {
+            vec![format!("{}{}", c.to_ascii_uppercase(), &body[1..])]
+        }
+        Some('b') => vec![body.clone(), format!("B{}", &body[1..])],
+        _ => vec![body],
+    }
+}
+
+// LanMove is a move written in long algebraic notation, piece is PAWN when no letter is given.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LanMove {
+    pub piece: PieceType,
+    pub from: usize,
+    pub to: usize,
+    pub promotion: PieceType,
+}
+
+// parse_lan parses move in long algebraic notation e.g. Ng1-f3, e2-e4, e7xd8=Q+. Check and
+// annotation symbols are ignored. Castles are not included, they are written like in SAN.
+pub fn parse_lan(m: &str) -> Option<LanMove> {
+    let m = m.trim_end_matches(|c| "+#!?".contains(c));
+    let (piece, rest) = match m.chars().next()? {
+        'K' => (PieceType::KING, &m[1..]),
+        'Q' | 'R' | 'B' | 'N' => (PieceType::from_sign(&m[0..1]), &m[1..]),
+        'P' => (PieceType::PAWN, &m[1..]),
+        _ => (PieceType::PAWN, m),
+    };
+    if rest.len() < 5 || !rest.is_char_boundary(2) || !rest.is_char_boundary(5) {
+        return None;
+    }
+    let from = parse_square(&rest[0..2])?;
+    if !matches!(rest.as_bytes()[2], b'-' | b'x') {
+        return None;
+    }
+    let to = parse_square(&rest[3..5])?;
+    let promotion = match rest[5..].trim_start_matches('=') {
+        "" => PieceType::NONE,
+        p => match PieceType::from_sign(p) {
+            PieceType::NONE => return None,
+            promotion => promotion,
+        },
+    };
+    Some(LanMove {
+        piece,
+        from,
+        to,
+        promotion,
+    })
+}
+
+fn is_capture(board: &Board, t: &Transition) -> bool {
+    !board.piece_at(t.to).is_none() || t.flag == TransitionFlag::EnPassant
+}
+
+// check_suffix returns '+' when transition gives check and '#' when it mates.
+fn check_suffix(board: &Board, t: &Transition) -> &'static str {
+    let mut after = board.clone();
+    after.make_transition(*t);
+    if !after.is_in_check() {
+        ""
+    } else if after.generate_transitions().is_empty() {
+        "#"
+    } else {
+        "+"
+    }
+}
+
+// disambiguation returns file, rank or whole square of moving piece when other piece of the same
+// type can move to the same square.
+fn disambiguation(board: &Board, t: &Transition) -> String {
+    let p_type = board.piece_at(t.from).p_type;
+    let others: Vec<usize> = board
+        .generate_transitions()
+        .iter()
+        .filter(|o| o.to == t.to && o.from != t.from && board.piece_at(o.from).p_type == p_type)
+        .map(|o| o.from)
+        .collect();
+    if others.is_empty() {
+        return String::new();
+    }
+    let name = square_name(t.from);
+    if others.iter().all(|o| o % 8 != t.from % 8) {
+        return name[0..1].to_string();
+    }
+    if others.iter().all(|o| o / 8 != t.from / 8) {
+        return name[1..2].to_string();
+    }
+    name
+}
+
+// MoveNotation selects notation used to write moves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveNotation {
+    San, // Nf3
+    Lan, // Ng1-f3
+}
+
+impl MoveNotation {
+    pub fn write(&self, board: &Board, t: &Transition) -> String {
+        match self {
+            MoveNotation::San => to_san(board, t),
+            MoveNotation::Lan => to_lan(board, t),
+        }
+    }
+}
+
+impl FromStr for MoveNotation {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "san" => Ok(MoveNotation::San),
+            "lan" => Ok(MoveNotation::Lan),
+            _ => Err("notation must be 'san' or 'lan'"),
+        }
+    }
+}
+
+// line_to_san writes sequence of transitions starting at given position with move numbers,
+// e.g. "1. e4 e5 2. Nf3" or "1... e5 2. Nf3" when black starts.
+pub fn line_to_san(board: &Board, line: &[Transition]) -> String {
+    write_line(board, line, MoveNotation::San)
+}
+
+// write_line writes sequence of transitions like line_to_san, in given notation.
+pub fn write_line(board: &Board, line: &[Transition], notation: MoveNotation) -> String {
+    let mut b = board.clone();
+    let mut move_number = 1;
+    let mut result = Vec::new();
+    for (i, t) in line.iter().enumerate() {
+        if b.color_to_move == Color::WHITE {
+            result.push(format!("{}.", move_number));
+        } else if i == 0 {
+            result.push(format!("{}...", move_number));
+        }
+        result.push(notation.write(&b, t));
+        if b.color_to_move == Color::BLACK {
+            move_number += 1;
+        }
+        b.make_transition(*t);
+    }
+    result.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Board, TransitionFlag};
+    use crate::notation::{
+        castle_flag, file_from_letter, file_letter, file_rank, line_to_san, parse_lan, parse_san,
+        parse_san_mode, parse_square, parse_uci, square, square_at, square_name, to_lan, to_san,
+        to_spoken, to_uci, write_line, LanMove, MoveNotation, ParseMode,
+    };
+    use crate::piece::PieceType;
+
+    #[test]
+    fn test_spoken() {
+        let mut b = Board::default();
+        let spoken = |b: &Board, m: &str| to_spoken(b, &parse_san(b, m).unwrap());
+        assert_eq!(spoken(&b, "Nf3"), "knight from g1 to f3");
+        b.read_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w").unwrap();
+        assert_eq!(spoken(&b, "O-O"), "castles kingside");
+        assert_eq!(spoken(&b, "O-O-O"), "castles queenside");
+        assert_eq!(
+            spoken(&b, "bxa8=N"),
+            "pawn from b7 to a8, capturing the rook, promoting to a knight"
+        );
+        assert_eq!(
+            spoken(&b, "Rxa8+"),
+            "rook from a1 to a8, capturing the rook, check"
+        );
+        let mut b = Board::default();
+        for m in ["e4", "a6", "e5", "d5"].iter() {
+            let t = parse_san(&b, m).unwrap();
+            b.make_transition(t);
+        }
+        assert_eq!(
+            spoken(&b, "exd6"),
+            "pawn from e5 to d6, capturing the pawn en passant"
+        );
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R3K3 w").unwrap();
+        assert_eq!(spoken(&b, "Ra8#"), "rook from a1 to a8, checkmate");
+    }
+
+    #[test]
+    fn test_uci_notation() {
+        let mut b = Board::default();
+        b.read_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq").unwrap();
+        let uci =
+            |m: &str, chess960: bool| parse_uci(&b, m, chess960).map(|t| to_uci(&t, chess960));
+        assert_eq!(uci("e1g1", false), Ok("e1g1".to_string()));
+        assert_eq!(uci("e1h1", true), Ok("e1h1".to_string()));
+        assert_eq!(uci("e1a1", true), Ok("e1a1".to_string()));
+        assert_eq!(uci("b7a8n", true), Ok("b7a8n".to_string()));
+        assert!(uci("e1h1", false).unwrap_err().contains("Chess960"));
+        assert!(uci("e1g1", true).unwrap_err().contains("Chess960"));
+        assert_eq!(uci("e1e", false), Err("invalid move notation"));
+        assert_eq!(uci("b7b8k", false), Err("invalid promotion piece"));
+        assert!(uci("a1b2", false).is_err());
+    }
+
+    #[test]
+    fn test_lan() {
+        let mut b = Board::default();
+        b.read_pgn(
+            "1. e2-e4 e7-e5 2. Ng1-f3 Nb8-c6 3. Bf1-b5 a7-a6 4. Bb5xc6 d7xc6 5. O-O",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            b.position_fen(),
+            "r1bqkbnr/1pp2ppp/p1p5/4p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b"
+        );
+        assert!(b.make_pgn_move("Nf6-d5").is_err()); // no knight on f6
+        assert!(b.make_pgn_move("Bc8-g4").is_ok());
+
+        assert_eq!(
+            parse_lan("e7xd8=Q+"),
+            Some(LanMove {
+                piece: PieceType::PAWN,
+                from: 52,
+                to: 59,
+                promotion: PieceType::QUEEN
+            })
+        );
+        assert_eq!(parse_lan("Ke1-e2").unwrap().piece, PieceType::KING);
+        for m in ["Nf3", "exd5", "e8=Q", "Ng1f3", "e2-e4=K", "é2-e4"] {
+            assert_eq!(parse_lan(m), None, "{}", m);
+        }
+
+        b.read_fen("3qk3/4P3/8/8/8/8/8/4K1N1 w").unwrap();
+        let lans: Vec<String> = b
+            .generate_transitions()
+            .iter()
+            .map(|t| to_lan(&b, t))
+            .collect();
+        for lan in ["e7xd8=Q+", "Ng1-f3", "Ke1-f2"] {
+            assert!(lans.contains(&lan.to_string()), "{} not in {:?}", lan, lans);
+        }
+        let line = b.generate_transitions()[..1].to_vec();
+        assert!(write_line(&b, &line, MoveNotation::Lan).starts_with("1. "));
+        assert_eq!("lan".parse(), Ok(MoveNotation::Lan));
+    }
+
+    #[test]
+    fn test_square_name() {
+        assert_eq!(square_name(0), "a1");
+        assert_eq!(square_name(28), "e4");
+        assert_eq!(square_name(63), "h8");
+    }
+
+    #[test]
+    fn test_square_conversions() {
+        let files = "abcdefgh";
+        let ranks = "12345678";
+        for (file, f) in files.chars().enumerate() {
+            for (rank, r) in ranks.chars().enumerate() {
+                let name = format!("{}{}", f, r);
+                let index = square(file, rank);
+                assert_eq!(index, rank * 8 + file);
+                assert_eq!(square_name(index), name);
+                assert_eq!(parse_square(&name), Some(index));
+                assert_eq!(file_rank(index), (file, rank));
+                assert_eq!(square_at(file as i32, rank as i32), Some(index));
+                assert_eq!(file_letter(file), f);
+                assert_eq!(file_from_letter(f), Some(file));
+            }
+        }
+        assert_eq!(parse_square("e4"), Some(28));
+
+        for name in ["", "e", "e0", "e9", "i1", "E4", "e44", "4e", " e4"] {
+            assert_eq!(parse_square(name), None, "{}", name);
+        }
+        for (file, rank) in [(-1, 0), (0, -1), (8, 0), (0, 8), (8, 8)] {
+            assert_eq!(square_at(file, rank), None);
+        }
+        assert_eq!(file_from_letter('i'), None);
+        assert_eq!(file_from_letter('A'), None);
+    }
+
+    #[test]
+    fn test_to_san() {
+        let sans = |fen: &str| -> Vec<String> {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            b.generate_transitions()
+                .iter()
+                .map(|t| to_san(&b, t))
+                .collect()
+        };
+        let castles = sans("r3k2r/8/8/8/8/8/8/R3K2R w");
+        for san in ["O-O", "O-O-O", "Rxa8+", "Rxh8+", "Rb1"] {
+            assert!(
+                castles.contains(&san.to_string()),
+                "{} not in {:?}",
+                san,
+                castles
+            );
+        }
+        let rooks = sans("r3k3/8/8/8/8/8/4K3/R6R w");
+        for san in ["Rad1", "Rhd1", "Rxa8+", "Rh8+"] {
+            assert!(
+                rooks.contains(&san.to_string()),
+                "{} not in {:?}",
+                san,
+                rooks
+            );
+        }
+        let pawns = sans("4k3/1P6/8/4P3/8/8/3P4/4K3 w");
+        for san in ["b8=Q+", "b8=N", "e6", "d4"] {
+            assert!(
+                pawns.contains(&san.to_string()),
+                "{} not in {:?}",
+                san,
+                pawns
+            );
+        }
+
+        let mut b = Board::default();
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w").unwrap();
+        let t = b
+            .generate_transitions()
+            .into_iter()
+            .find(|t| t.from == 0 && t.to == 56)
+            .unwrap();
+        assert_eq!(to_san(&b, &t), "Ra8#");
+        assert_eq!(line_to_san(&b, &[t]), "1. Ra8#");
+    }
+
+    #[test]
+    fn test_parse_san_disambiguation() {
+        let origin = |fen: &str, m: &str| -> Result<String, &'static str> {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            parse_san(&b, m).map(|t| square_name(t.from))
+        };
+        let queens = "4k3/8/8/8/8/8/8/Q2QK3 w";
+        assert_eq!(origin(queens, "Qad4"), Ok("a1".to_string()));
+        assert_eq!(origin(queens, "Qdd4"), Ok("d1".to_string()));
+        assert_eq!(origin(queens, "Qa1xd4"), Ok("a1".to_string()));
+        let file = "4k3/8/8/8/8/1Q6/8/1Q2K3 w";
+        assert_eq!(origin(file, "Q1b2"), Ok("b1".to_string()));
+        assert_eq!(origin(file, "Q3b2"), Ok("b3".to_string()));
+        let bishops = "4k3/8/2B5/8/4B3/2B5/8/4K1B1 w";
+        assert_eq!(origin(bishops, "Bcd5"), Ok("c6".to_string()));
+        assert_eq!(origin(bishops, "Bgd4"), Ok("g1".to_string()));
+        assert_eq!(origin(bishops, "Bc3d4"), Ok("c3".to_string()));
+
+        // too short moves are errors, not panics.
+        let b = Board::default();
+        for m in ["", "N", "Nx", "R+", "x", "e", "=Q", "Nf3=Q", "Qz1d4"] {
+            assert!(parse_san(&b, m).is_err(), "{}", m);
+        }
+        assert_eq!(parse_san(&b, "N").err(), Some("move is too short"));
+        assert_eq!(parse_san(&b, "exd4").err(), Some("invalid move"));
+        assert!(parse_san(&b, "Nxf3").is_ok());
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        let parse = |fen: &str, m: &str, mode: ParseMode| -> Result<String, &'static str> {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            parse_san_mode(&b, m, mode).map(|t| to_san(&b, &t))
+        };
+        let rooks = "r3k3/8/8/8/8/8/4K3/R6R w";
+        assert_eq!(
+            parse(rooks, "Rad1!?", ParseMode::Strict),
+            Ok("Rad1".to_string())
+        );
+        assert_eq!(
+            parse(rooks, "Rxa8", ParseMode::Normal),
+            Ok("Rxa8+".to_string())
+        );
+        assert_eq!(
+            parse(rooks, "Rxa8", ParseMode::Strict),
+            Err("check is not marked")
+        );
+        assert_eq!(
+            parse(rooks, "Ra8+", ParseMode::Strict),
+            Err("capture is not marked")
+        );
+        assert_eq!(
+            parse(rooks, "Rd1", ParseMode::Strict),
+            Err("illegal disambiguation")
+        );
+        assert_eq!(
+            parse(rooks, "rad1", ParseMode::Lenient),
+            Ok("Rad1".to_string())
+        );
+        assert!(parse(rooks, "rad1", ParseMode::Normal).is_err());
+
+        let pawns = "4k3/1P6/8/4P3/8/8/3P4/4K3 w";
+        assert_eq!(
+            parse(pawns, "b8q", ParseMode::Lenient),
+            Ok("b8=Q+".to_string())
+        );
+        assert_eq!(
+            parse(pawns, "b8=n", ParseMode::Lenient),
+            Ok("b8=N".to_string())
+        );
+        assert_eq!(
+            parse("r3k2r/8/8/8/8/8/8/R3K2R w", "0-0-0", ParseMode::Lenient),
+            Ok("O-O-O".to_string())
+        );
+        assert_eq!("strict".parse(), Ok(ParseMode::Strict));
+
+        let mate = "6k1/5ppp/8/8/8/8/8/R5K1 w";
+        assert_eq!(
+            parse(mate, "Ra8", ParseMode::Strict),
+            Err("mate is not marked")
+        );
+        assert_eq!(
+            parse(mate, "Ra8+", ParseMode::Strict),
+            Err("wrong check symbol")
+        );
+        assert_eq!(
+            parse(mate, "Rxa7", ParseMode::Strict),
+            Err("capture marker on a move that doesn't capture")
+        );
+
+        assert_eq!(castle_flag("0-0+"), Some(TransitionFlag::ShortCastle));
+        assert_eq!(castle_flag("O—O—O"), Some(TransitionFlag::LongCastle));
+        assert_eq!(castle_flag("0-0-0-0"), None);
+        assert_eq!(castle_flag("O-"), None);
+    }
+}
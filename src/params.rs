@@ -0,0 +1,292 @@
+use crate::board::Board;
+use crate::endgame::kpk;
+use crate::evaluation::{
+    king_center_eval, king_safety_eval, EvalTerm, Evaluator, MaterialMobilityEvaluator,
+    KING_CENTER_EVAL_MODIFIER, KING_SAFETY_EVAL_MODIFIER, MOBILITY_EVAL_MODIFIER,
+    PAWN_EVAL_MODIFIER,
+};
+use crate::piece::{Color, PieceType};
+use crate::position::Position;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// PIECES are piece types in order of values and tables of EvalParams, with names used in files.
+const PIECES: [(PieceType, &str); 6] = [
+    (PieceType::PAWN, "pawn"),
+    (PieceType::KNIGHT, "knight"),
+    (PieceType::BISHOP, "bishop"),
+    (PieceType::ROOK, "rook"),
+    (PieceType::QUEEN, "queen"),
+    (PieceType::KING, "king"),
+];
+
+fn piece_index(p_type: PieceType) -> Option<usize> {
+    PIECES.iter().position(|(p, _)| *p == p_type)
+}
+
+fn piece_by_name(name: &str) -> Option<usize> {
+    PIECES.iter().position(|(_, n)| *n == name)
+}
+
+// EvalParams are weights of ParamsEvaluator, kept in a text file so they can be tuned and
+// reloaded without rebuilding or restarting the engine. Every line is a name and values:
+//
+//   pawn 1            value of a piece, also knight, bishop, rook, queen and king
+//   pst knight 0 ...  bonus of a piece on every square from a1 to h8, from white's point of view
+//   mobility 0.1      bonus for every legal move
+//   bad-pawn 0.5      penalty for doubled, blocked and isolated pawns
+//   king-safety 1     weight of king safety penalties, see evaluation::king_safety
+//   king-center 0.1   weight of king centralization, see evaluation::king_center_eval
+//
+// Missing lines keep defaults, which evaluate like MaterialMobilityEvaluator. '#' starts a comment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalParams {
+    pub values: [f32; 6],
+    pub pst: [[f32; 64]; 6],
+    pub mobility: f32,
+    pub bad_pawn: f32,
+    pub king_safety: f32,
+    pub king_center: f32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        let mut values = [0.0; 6];
+        for (i, (p_type, _)) in PIECES.iter().enumerate() {
+            values[i] = p_type.points() as f32;
+        }
+        EvalParams {
+            values,
+            pst: [[0.0; 64]; 6],
+            mobility: MOBILITY_EVAL_MODIFIER,
+            bad_pawn: PAWN_EVAL_MODIFIER,
+            king_safety: KING_SAFETY_EVAL_MODIFIER,
+            king_center: KING_CENTER_EVAL_MODIFIER,
+        }
+    }
+}
+
+impl EvalParams {
+    // load reads parameters from file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?
+            .parse()
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // get returns value of a single weight by its name in files e.g. 'knight' or 'mobility'.
+    pub fn get(&self, name: &str) -> Option<f32> {
+        match name {
+            "mobility" => Some(self.mobility),
+            "bad-pawn" => Some(self.bad_pawn),
+            "king-safety" => Some(self.king_safety),
+            "king-center" => Some(self.king_center),
+            _ => piece_by_name(name).map(|i| self.values[i]),
+        }
+    }
+
+    // set changes a single weight by its name in files.
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), String> {
+        match name {
+            "mobility" => self.mobility = value,
+            "bad-pawn" => self.bad_pawn = value,
+            "king-safety" => self.king_safety = value,
+            "king-center" => self.king_center = value,
+            _ => match piece_by_name(name) {
+                Some(i) => self.values[i] = value,
+                None => return Err(format!("unknown parameter {}", name)),
+            },
+        }
+        Ok(())
+    }
+
+    // piece_square returns value of a piece on a square plus its table bonus, positive for white.
+    fn piece_square(&self, board: &Board, square: usize) -> (f32, f32) {
+        let piece = board.piece_at(square);
+        let i = match piece_index(piece.p_type) {
+            Some(i) => i,
+            None => return (0.0, 0.0),
+        };
+        // tables are written for white, black pieces use the square mirrored vertically.
+        let (sign, square) = match piece.color {
+            Color::WHITE => (1.0, square),
+            _ => (-1.0, square ^ 56),
+        };
+        (sign * self.values[i], sign * self.pst[i][square])
+    }
+}
+
+impl FromStr for EvalParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = EvalParams::default();
+        for (n, line) in s.lines().enumerate() {
+            let error = |e: &str| format!("line {}: {}", n + 1, e);
+            let words: Vec<&str> = line
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect();
+            let numbers = |words: &[&str]| -> Result<Vec<f32>, String> {
+                words
+                    .iter()
+                    .map(|w| {
+                        w.parse()
+                            .map_err(|_| error(&format!("invalid number {}", w)))
+                    })
+                    .collect()
+            };
+            let piece = |name: &str| {
+                piece_by_name(name).ok_or_else(|| error(&format!("unknown piece {}", name)))
+            };
+            match words.as_slice() {
+                [] => {}
+                ["pst", name, values @ ..] => {
+                    let values = numbers(values)?;
+                    if values.len() != 64 {
+                        return Err(error("piece-square table must have 64 values"));
+                    }
+                    params.pst[piece(name)?].copy_from_slice(&values);
+                }
+                [name, value] => {
+                    let value = numbers(&[value])?[0];
+                    params.set(name, value).map_err(|e| error(&e))?;
+                }
+                _ => return Err(error("expected name and value")),
+            }
+        }
+        Ok(params)
+    }
+}
+
+impl Display for EvalParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (_, name)) in PIECES.iter().enumerate() {
+            writeln!(f, "{} {}", name, self.values[i])?;
+        }
+        writeln!(f, "mobility {}", self.mobility)?;
+        writeln!(f, "bad-pawn {}", self.bad_pawn)?;
+        writeln!(f, "king-safety {}", self.king_safety)?;
+        writeln!(f, "king-center {}", self.king_center)?;
+        for (i, (_, name)) in PIECES.iter().enumerate() {
+            let values: Vec<String> = self.pst[i].iter().map(|v| v.to_string()).collect();
+            writeln!(f, "pst {} {}", name, values.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+// ParamsEvaluator evaluates material, piece-square tables, pawn structure, mobility, king safety
+// and centralization with weights of EvalParams. Parameters are shared, so reloading them doesn't
+// disturb searches that are running, they keep the parameters they started with.
+pub struct ParamsEvaluator {
+    pub params: Arc<EvalParams>,
+}
+
+impl ParamsEvaluator {
+    fn terms(&self, board: &Board) -> (f32, f32, f32, f32, f32, f32) {
+        let (mut material, mut pst) = (0.0, 0.0);
+        for square in 0..64 {
+            let (value, bonus) = self.params.piece_square(board, square);
+            material += value;
+            pst += bonus;
+        }
+        let m = MaterialMobilityEvaluator {};
+        let (d, b, i) = (
+            m.count_doubled_pawns(*board.squares()),
+            m.count_blocked_pawns(*board.squares()),
+            m.count_isolated_pawns(*board.squares()),
+        );
+        let bad_pawns = (d.0 + b.0 + i.0 - d.1 - b.1 - i.1) as f32 * self.params.bad_pawn;
+        let mobility = m.mobility(board) * self.params.mobility;
+        let king_safety = king_safety_eval(board.squares()) * self.params.king_safety;
+        let king_center = king_center_eval(board.squares()) * self.params.king_center;
+        (
+            material,
+            pst,
+            -bad_pawns,
+            mobility,
+            king_safety,
+            king_center,
+        )
+    }
+}
+
+impl Evaluator for ParamsEvaluator {
+    fn evaluate(&self, position: &dyn Position) -> f32 {
+        let board: &Board = &position.to_board();
+        let (material, pst, pawns, mobility, king, center) = self.terms(board);
+        kpk::scale(board, material + pst + pawns + mobility + king + center)
+    }
+
+    fn breakdown(&self, position: &dyn Position) -> Vec<EvalTerm> {
+        let board: &Board = &position.to_board();
+        let (material, pst, pawns, mobility, king, center) = self.terms(board);
+        let mut terms = vec![
+            EvalTerm::new("material", material),
+            EvalTerm::new("piece-square tables", pst),
+            EvalTerm::new("pawn structure", pawns),
+            EvalTerm::new("mobility", mobility),
+            EvalTerm::new("king safety", king),
+            EvalTerm::new("king centralization", center),
+        ];
+        let total = material + pst + pawns + mobility + king + center;
+        let scaled = kpk::scale(board, total);
+        if scaled != total {
+            terms.push(EvalTerm::new("kpk bitbase", scaled - total));
+        }
+        terms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::{Evaluator, MaterialMobilityEvaluator};
+    use crate::params::{EvalParams, ParamsEvaluator};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_params() {
+        let mut b = Board::default();
+        b.read_fen("r3k3/1p6/8/8/3N4/8/4K3/R6R w").unwrap();
+        let default = ParamsEvaluator {
+            params: Arc::new(EvalParams::default()),
+        };
+        assert!((default.evaluate(&b) - MaterialMobilityEvaluator {}.evaluate(&b)).abs() < 1e-4);
+
+        let mut center = vec!["0"; 64];
+        center[27] = "0.5"; // d4
+        let text = format!(
+            "# knights love the center\nknight 3.5\npst knight {}\nmobility 0\n",
+            center.join(" ")
+        );
+        let params: EvalParams = text.parse().unwrap();
+        assert_eq!(params.values[1], 3.5);
+        assert_eq!(params.to_string().parse::<EvalParams>().unwrap(), params);
+        let tuned = ParamsEvaluator {
+            params: Arc::new(params),
+        };
+        let terms = tuned.breakdown(&b);
+        assert_eq!(terms[0].value, 7.5);
+        assert_eq!(terms[1].value, 0.5);
+        assert_eq!(terms[3].value, 0.0);
+        assert_eq!(terms[4].name, "king safety");
+
+        assert_eq!(
+            "pst knight 1 2".parse::<EvalParams>(),
+            Err("line 1: piece-square table must have 64 values".to_string())
+        );
+        assert_eq!(
+            "\nelephant 3".parse::<EvalParams>(),
+            Err("line 2: unknown parameter elephant".to_string())
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::board::{attackers, Board, GameStatus, Square, Transition, KING_STEPS};
+use crate::notation::square_at;
+use crate::piece::{Color, PieceType};
+use std::fmt::{Display, Formatter};
+
+// MatePattern is a named checkmate pattern, used to tag puzzles and final positions of games.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MatePattern {
+    // Smothered is a knight mating a king surrounded by its own pieces.
+    Smothered,
+    // Arabian is a rook next to a cornered king, defended by a knight.
+    Arabian,
+    // Anastasia is a rook or a queen mating on an edge file, a knight covers escape squares and
+    // the king's own piece blocks the remaining one.
+    Anastasia,
+    // Boden is two bishops mating on crossing diagonals.
+    Boden,
+    // Epaulette is a queen mating from the king's file, own pieces on both sides block the king.
+    Epaulette,
+    // BackRank is a rook or a queen mating on the back rank, own pieces in front block the king.
+    BackRank,
+    // Ladder is a rook or a queen mating on an edge, another one covers the line next to it.
+    Ladder,
+}
+
+// PATTERNS are all patterns, from the most specific one.
+const PATTERNS: [MatePattern; 7] = [
+    MatePattern::Smothered,
+    MatePattern::Arabian,
+    MatePattern::Anastasia,
+    MatePattern::Boden,
+    MatePattern::Epaulette,
+    MatePattern::BackRank,
+    MatePattern::Ladder,
+];
+
+impl Display for MatePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MatePattern::Smothered => "smothered mate",
+            MatePattern::Arabian => "Arabian mate",
+            MatePattern::Anastasia => "Anastasia's mate",
+            MatePattern::Boden => "Boden's mate",
+            MatePattern::Epaulette => "epaulette mate",
+            MatePattern::BackRank => "back-rank mate",
+            MatePattern::Ladder => "ladder mate",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// mate_pattern recognizes the pattern of a checkmate in the position, None when the position isn't
+// a checkmate or the mate has no name. When more patterns fit, the most specific one is returned,
+// e.g. a smothered mate on the back rank is a smothered mate.
+pub fn mate_pattern(board: &Board) -> Option<MatePattern> {
+    if !matches!(board.status(), GameStatus::Checkmate(_)) {
+        return None;
+    }
+    let mated = board.color_to_move;
+    let king = board.king_square(mated)?;
+    let checker = match board.checkers(mated)[..] {
+        [checker] => checker,
+        _ => return None, // double checks have no names
+    };
+    let mate = Mate {
+        board,
+        mated,
+        king,
+        checker,
+    };
+    PATTERNS.iter().copied().find(|p| mate.fits(*p))
+}
+
+// line_mate_pattern plays a forced line of legal moves and recognizes the mate it ends with.
+pub fn line_mate_pattern(board: &Board, line: &[Transition]) -> Option<MatePattern> {
+    let mut board = board.clone();
+    for t in line {
+        let legal = board
+            .generate_transitions()
+            .into_iter()
+            .any(|l| l.from == t.from && l.to == t.to && l.promotion == t.promotion);
+        if !legal {
+            return None;
+        }
+        board.make_transition(*t);
+    }
+    mate_pattern(&board)
+}
+
+// Mate is a checkmate given by a single checker.
+struct Mate<'a> {
+    board: &'a Board,
+    mated: Color,
+    king: Square,
+    checker: Square,
+}
+
+impl Mate<'_> {
+    fn fits(&self, pattern: MatePattern) -> bool {
+        match pattern {
+            MatePattern::Smothered => self.smothered(),
+            MatePattern::Arabian => self.arabian(),
+            MatePattern::Anastasia => self.anastasia(),
+            MatePattern::Boden => self.boden(),
+            MatePattern::Epaulette => self.epaulette(),
+            MatePattern::BackRank => self.back_rank(),
+            MatePattern::Ladder => self.ladder(),
+        }
+    }
+
+    fn p_type(&self, square: Square) -> PieceType {
+        self.board.piece_at(square).p_type
+    }
+
+    fn checker_is(&self, types: &[PieceType]) -> bool {
+        types.contains(&self.p_type(self.checker))
+    }
+
+    fn is_own(&self, square: Square) -> bool {
+        self.board.piece_at(square).color == self.mated
+    }
+
+    // attacked_by checks if a piece of mating side of given type attacks the square.
+    fn attacked_by(&self, square: Square, p_type: PieceType, except: Option<Square>) -> bool {
+        attackers(self.board.squares(), square, self.mated.opposite())
+            .into_iter()
+            .any(|a| Some(a) != except && self.p_type(a) == p_type)
+    }
+
+    fn neighbours(&self) -> Vec<Square> {
+        let (file, rank) = coordinates(self.king);
+        KING_STEPS
+            .iter()
+            .filter_map(|(df, dr)| square_at(file + df, rank + dr))
+            .collect()
+    }
+
+    fn smothered(&self) -> bool {
+        self.checker_is(&[PieceType::KNIGHT]) && self.neighbours().iter().all(|s| self.is_own(*s))
+    }
+
+    fn arabian(&self) -> bool {
+        let (file, rank) = coordinates(self.king);
+        let (cf, cr) = coordinates(self.checker);
+        let corner = (file == 0 || file == 7) && (rank == 0 || rank == 7);
+        corner
+            && self.checker_is(&[PieceType::ROOK])
+            && (file - cf).abs() + (rank - cr).abs() == 1
+            && self.attacked_by(self.checker, PieceType::KNIGHT, None)
+    }
+
+    fn anastasia(&self) -> bool {
+        let (file, rank) = coordinates(self.king);
+        let inner = if file == 0 { 1 } else { 6 };
+        (file == 0 || file == 7)
+            && self.checker_is(&[PieceType::ROOK, PieceType::QUEEN])
+            && coordinates(self.checker).0 == file
+            && square_at(inner, rank).is_some_and(|s| self.is_own(s))
+            && self
+                .neighbours()
+                .iter()
+                .any(|s| self.attacked_by(*s, PieceType::KNIGHT, None))
+    }
+
+    fn boden(&self) -> bool {
+        self.checker_is(&[PieceType::BISHOP])
+            && self
+                .neighbours()
+                .iter()
+                .any(|s| self.attacked_by(*s, PieceType::BISHOP, Some(self.checker)))
+    }
+
+    fn epaulette(&self) -> bool {
+        let (file, rank) = coordinates(self.king);
+        let beside = [square_at(file - 1, rank), square_at(file + 1, rank)];
+        self.checker_is(&[PieceType::QUEEN])
+            && coordinates(self.checker).0 == file
+            && beside.iter().all(|s| s.is_some_and(|s| self.is_own(s)))
+    }
+
+    fn back_rank(&self) -> bool {
+        let (file, rank) = coordinates(self.king);
+        let (home, forward) = if self.mated == Color::WHITE {
+            (0, 1)
+        } else {
+            (7, -1)
+        };
+        rank == home
+            && self.checker_is(&[PieceType::ROOK, PieceType::QUEEN])
+            && coordinates(self.checker).1 == rank
+            && (-1..=1)
+                .filter_map(|df| square_at(file + df, rank + forward))
+                .all(|s| self.is_own(s))
+    }
+
+    fn ladder(&self) -> bool {
+        let (file, rank) = coordinates(self.king);
+        let (cf, cr) = coordinates(self.checker);
+        // the edge the king stands on and the direction away from it.
+        let inward = if (rank == 0 || rank == 7) && cr == rank {
+            (0, if rank == 0 { 1 } else { -1 })
+        } else if (file == 0 || file == 7) && cf == file {
+            (if file == 0 { 1 } else { -1 }, 0)
+        } else {
+            return false;
+        };
+        let (df, dr): (i32, i32) = inward;
+        let next_line: Vec<Square> = (-1..=1)
+            .filter_map(|i| square_at(file + df + i * dr.abs(), rank + dr + i * df.abs()))
+            .collect();
+        self.checker_is(&[PieceType::ROOK, PieceType::QUEEN])
+            && next_line.iter().all(|s| {
+                self.attacked_by(*s, PieceType::ROOK, Some(self.checker))
+                    || self.attacked_by(*s, PieceType::QUEEN, Some(self.checker))
+            })
+    }
+}
+
+fn coordinates(square: Square) -> (i32, i32) {
+    ((square % 8) as i32, (square / 8) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::notation::parse_san;
+    use crate::patterns::{line_mate_pattern, mate_pattern, MatePattern};
+
+    fn pattern(fen: &str) -> Option<MatePattern> {
+        let mut b = Board::default();
+        b.read_fen(fen).unwrap();
+        mate_pattern(&b)
+    }
+
+    #[test]
+    fn test_mate_pattern() {
+        assert_eq!(
+            pattern("6rk/5Npp/8/8/8/8/8/6K1 b"),
+            Some(MatePattern::Smothered)
+        );
+        assert_eq!(
+            pattern("7k/7R/5N2/8/8/8/8/6K1 b"),
+            Some(MatePattern::Arabian)
+        );
+        assert_eq!(
+            pattern("8/4N1pk/8/7R/8/8/8/6K1 b"),
+            Some(MatePattern::Anastasia)
+        );
+        assert_eq!(
+            pattern("2kr4/3p4/B7/8/5B2/8/8/4K3 b"),
+            Some(MatePattern::Boden)
+        );
+        assert_eq!(
+            pattern("3rkr2/8/4Q3/8/8/8/8/4K3 b"),
+            Some(MatePattern::Epaulette)
+        );
+        assert_eq!(
+            pattern("R5k1/5ppp/8/8/8/8/8/6K1 b"),
+            Some(MatePattern::BackRank)
+        );
+        assert_eq!(
+            pattern("R6k/1R6/8/8/8/8/8/6K1 b"),
+            Some(MatePattern::Ladder)
+        );
+        // the fool's mate has no name here, the second position isn't a mate.
+        assert_eq!(
+            pattern("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w"),
+            None
+        );
+        assert_eq!(pattern("6k1/5ppp/8/8/8/8/8/R5K1 w"), None);
+
+        let mut b = Board::default();
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w").unwrap();
+        let mate = parse_san(&b, "Ra8").unwrap();
+        assert_eq!(line_mate_pattern(&b, &[mate]), Some(MatePattern::BackRank));
+        assert_eq!(line_mate_pattern(&b, &[]), None);
+        assert_eq!(MatePattern::Anastasia.to_string(), "Anastasia's mate");
+    }
+}
@@ -0,0 +1,995 @@
+use crate::board::{Board, Transition};
+use crate::game::Game;
+use crate::notation::{parse_san, parse_san_mode, san_mismatch, MoveNotation, ParseMode};
+use crate::piece::Color;
+use crate::score::Score;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+pub(crate) const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// PgnGame is a single game from PGN database: tag pairs and movetext.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub movetext: String,
+}
+
+// ReplayError describes the move that could not be played while replaying a game, with enough
+// context to find it in a large database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayError {
+    pub game: Option<usize>, // number of the game in database from 1, when known
+    pub ply: usize,          // 0 when the start position is invalid
+    pub move_number: usize,
+    pub side: Color, // color to move
+    pub token: String,
+    pub fen: String, // position before the move
+    pub error: &'static str,
+}
+
+impl ReplayError {
+    // with_game sets number of the game the error comes from.
+    pub fn with_game(mut self, game: usize) -> Self {
+        self.game = Some(game);
+        self
+    }
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(game) = self.game {
+            write!(f, "game {}: ", game)?;
+        }
+        if self.ply == 0 {
+            return write!(f, "start position: {} ({})", self.error, self.fen);
+        }
+        let (dots, side) = if self.side == Color::WHITE {
+            (".", "white")
+        } else {
+            ("...", "black")
+        };
+        write!(
+            f,
+            "move {}{} {} (ply {}, {} to move): {} ({})",
+            self.move_number, dots, self.token, self.ply, side, self.error, self.fen
+        )
+    }
+}
+
+impl PgnGame {
+    // tag returns value of a tag pair e.g. tag("White").
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // set_tag changes value of a tag pair, or adds it after the others when the game has none.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // moves returns SAN moves of the game.
+    pub fn moves(&self) -> Vec<String> {
+        movetext_tokens(&self.movetext)
+    }
+
+    // start_position returns board with position the game starts from, FEN tag is respected.
+    pub fn start_position(&self) -> Result<Board, &'static str> {
+        let mut board = Board::default();
+        if let Some(fen) = self.tag("FEN") {
+            board.read_fen(fen)?;
+        }
+        Ok(board)
+    }
+
+    // start_error describes invalid start position of the game.
+    pub fn start_error(&self, error: &'static str) -> ReplayError {
+        let fen = self.tag("FEN").unwrap_or_default();
+        ReplayError {
+            game: None,
+            ply: 0,
+            move_number: first_move_number(fen),
+            side: first_color(fen),
+            token: String::new(),
+            fen: fen.to_string(),
+            error,
+        }
+    }
+
+    // replay_error describes error of the move played at given ply, board is the position before
+    // the move.
+    pub fn replay_error(
+        &self,
+        board: &Board,
+        ply: usize,
+        token: &str,
+        error: &'static str,
+    ) -> ReplayError {
+        let fen = self.tag("FEN").unwrap_or_default();
+        let skipped = (first_color(fen) == Color::BLACK) as usize;
+        ReplayError {
+            game: None,
+            ply,
+            move_number: first_move_number(fen) + (ply - 1 + skipped) / 2,
+            side: board.color_to_move,
+            token: token.to_string(),
+            fen: board.to_fen(),
+            error,
+        }
+    }
+
+    // replay plays all moves of the game, on_move is called with position after every ply.
+    pub fn replay<F>(&self, on_move: F) -> Result<Board, ReplayError>
+    where
+        F: FnMut(&Board),
+    {
+        self.replay_with(&ReplayOptions::default(), on_move)
+            .map(|replay| replay.board)
+    }
+
+    // to_game replays the game into Game, with its positions and moves.
+    pub fn to_game(&self) -> Result<Game, ReplayError> {
+        let mut game = Game::new(self.start_position().map_err(|e| self.start_error(e))?);
+        for (i, token) in self.moves().into_iter().enumerate() {
+            let board = game.board().clone();
+            parse_san_mode(&board, &token, ParseMode::Normal)
+                .and_then(|t| game.make_move(t))
+                .map_err(|e| self.replay_error(&board, i + 1, &token, e))?;
+        }
+        Ok(game)
+    }
+
+    // replay_with plays all moves of the game like replay, with given options.
+    pub fn replay_with<F>(
+        &self,
+        options: &ReplayOptions,
+        mut on_move: F,
+    ) -> Result<Replay, ReplayError>
+    where
+        F: FnMut(&Board),
+    {
+        let mut board = self.start_position().map_err(|e| self.start_error(e))?;
+        let mut warnings = Vec::new();
+        for (i, token) in self.moves().into_iter().enumerate() {
+            let before = board.clone();
+            let played = match options.mode {
+                ParseMode::Normal if !options.cross_check => board.make_pgn_move(&token),
+                mode => parse_san_mode(&board, &token, mode).map(|t| {
+                    if options.cross_check {
+                        if let Some(warning) = san_mismatch(&before, &t, &token) {
+                            warnings.push(self.replay_error(&before, i + 1, &token, warning));
+                        }
+                    }
+                    board.make_transition(t)
+                }),
+            };
+            if let Err(error) = played {
+                return Err(self.replay_error(&before, i + 1, &token, error));
+            }
+            on_move(&board);
+        }
+        Ok(Replay { board, warnings })
+    }
+}
+
+// ReplayOptions controls how PgnGame::replay_with reads moves.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReplayOptions {
+    pub mode: ParseMode,
+    pub cross_check: bool, // capture markers and check symbols are compared with played moves
+}
+
+// Replay is a replayed game: its final position and moves of which capture markers or check
+// symbols don't match the position, described like errors. Warnings are found only with
+// cross_check option.
+#[derive(Clone)]
+pub struct Replay {
+    pub board: Board,
+    pub warnings: Vec<ReplayError>,
+}
+
+// first_color returns color to move in the start position given by FEN tag, empty for standard one.
+fn first_color(fen: &str) -> Color {
+    match fen.split_whitespace().nth(1) {
+        Some("b") => Color::BLACK,
+        _ => Color::WHITE,
+    }
+}
+
+// first_move_number returns number of the first move of the game, from fullmove field of FEN tag.
+fn first_move_number(fen: &str) -> usize {
+    fen.split_whitespace()
+        .nth(5)
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(1)
+}
+
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('"', "\\\""))?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f)?;
+        }
+        writeln!(f, "{}", self.movetext)
+    }
+}
+
+// split_games splits content of PGN database into separate games. Comments in braces may span
+// many lines, blank lines and lines looking like tag pairs inside them don't end the game. Lines
+// starting with '%' are escaped, they are skipped.
+pub fn split_games(content: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut tags = Vec::new();
+    let mut movetext: Vec<&str> = Vec::new();
+    let mut in_comment = false;
+
+    for line in content.lines() {
+        if in_comment {
+            movetext.push(line.trim());
+            in_comment = ends_in_comment(line, true);
+            continue;
+        }
+        let line = line.trim();
+        if line.starts_with('%') {
+            continue;
+        }
+        let is_tag = line.starts_with('[') && line.ends_with(']');
+        // blank line or new tag pairs after movetext ends the game.
+        if !movetext.is_empty() && (line.is_empty() || is_tag) {
+            games.push(PgnGame {
+                tags,
+                movetext: movetext.join("\n"),
+            });
+            tags = Vec::new();
+            movetext = Vec::new();
+        }
+        if is_tag {
+            if let Some(tag) = parse_tag(line) {
+                tags.push(tag);
+            }
+        } else if !line.is_empty() {
+            movetext.push(line);
+            in_comment = ends_in_comment(line, false);
+        }
+    }
+    if !movetext.is_empty() || !tags.is_empty() {
+        games.push(PgnGame {
+            tags,
+            movetext: movetext.join("\n"),
+        });
+    }
+    games
+}
+
+// ends_in_comment tells if a line of movetext ends inside a comment in braces, in_comment tells
+// if it starts inside one. Braces of ';' comments don't count, they end with the line.
+fn ends_in_comment(line: &str, mut in_comment: bool) -> bool {
+    for c in line.chars() {
+        match c {
+            '}' if in_comment => in_comment = false,
+            '{' if !in_comment => in_comment = true,
+            ';' if !in_comment => break,
+            _ => {}
+        }
+    }
+    in_comment
+}
+
+// parse_tag parses tag pair line e.g. [White "Kasparov, Garry"].
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = &line[1..line.len() - 1];
+    let (name, value) = inner.split_once(' ')?;
+    let value = value.trim();
+    if !value.starts_with('"') || !value.ends_with('"') || value.len() < 2 {
+        return None;
+    }
+    Some((
+        name.to_string(),
+        value[1..value.len() - 1].replace("\\\"", "\""),
+    ))
+}
+
+// movetext_tokens splits movetext into SAN moves of the main line. Move numbers, comments,
+// NAGs, nested variations and game result are skipped.
+pub fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut depth: usize = 0;
+    movetext_elements(movetext)
+        .into_iter()
+        .filter_map(|e| match e {
+            MovetextElement::Start => {
+                depth += 1;
+                None
+            }
+            // stray ')' doesn't hide the rest of the main line.
+            MovetextElement::End => {
+                depth = depth.saturating_sub(1);
+                None
+            }
+            MovetextElement::Move(m) if depth == 0 => Some(m),
+            _ => None,
+        })
+        .collect()
+}
+
+// strip_move_number removes move number from the start of a word e.g. '12.Nf3' or '3...a6'.
+// Castles written with zeros e.g. '0-0' are kept.
+fn strip_move_number(word: &str) -> &str {
+    let rest = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.is_empty() || rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else {
+        word
+    }
+}
+
+// VariationToken is a move or bracket of a variation, used to read trees of moves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VariationToken {
+    Move(String),
+    Start, // '(' - following moves are alternative to the last move
+    End,   // ')'
+}
+
+// variation_tokens splits movetext with variations into moves and brackets. Comments, NAGs,
+// move numbers and game result are skipped.
+pub fn variation_tokens(movetext: &str) -> Vec<VariationToken> {
+    movetext_elements(movetext)
+        .into_iter()
+        .filter_map(|e| match e {
+            MovetextElement::Move(m) => Some(VariationToken::Move(m)),
+            MovetextElement::Start => Some(VariationToken::Start),
+            MovetextElement::End => Some(VariationToken::End),
+            _ => None,
+        })
+        .collect()
+}
+
+// MovetextElement is a single part of movetext, move numbers are not kept.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MovetextElement {
+    Move(String),
+    Comment(String), // text of {} or ; comment, without brackets
+    Nag(String),     // e.g. $1
+    Start,           // '('
+    End,             // ')'
+    Result(String),
+}
+
+// movetext_elements splits movetext into moves, comments, NAGs, variation brackets and result.
+// Symbols after moves, e.g. 'Nf6??', are separate NAGs. Quirks of old databases are handled: '(=)'
+// draw offers are skipped, evaluation symbols like '+-' are read as NAGs, 'e.p.' suffixes are
+// dropped and moves broken by a line break e.g. 'Nxe<newline>5' are joined.
+pub fn movetext_elements(movetext: &str) -> Vec<MovetextElement> {
+    let mut elements = Vec::new();
+    let mut word = String::new();
+    let mut joining = false; // word is a move broken by a line break
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next() {
+        if (c == '\n' || c == '\r') && is_broken_move(&word) {
+            joining = true;
+        }
+        if c.is_whitespace() && joining {
+            continue;
+        }
+        joining = false;
+        if c == '{' || c == ';' || c == '(' || c == ')' || c.is_whitespace() {
+            elements.extend(word_element(&word));
+            word.clear();
+        }
+        match c {
+            '{' | ';' => {
+                let end = if c == '{' { '}' } else { '\n' };
+                let text: String = chars.by_ref().take_while(|&c| c != end).collect();
+                elements.push(MovetextElement::Comment(text.trim().to_string()));
+            }
+            '(' if chars.as_str().starts_with("=)") => {
+                chars.nth(1);
+            }
+            '(' => elements.push(MovetextElement::Start),
+            ')' => elements.push(MovetextElement::End),
+            c if c.is_whitespace() => {}
+            c => word.push(c),
+        }
+    }
+    elements.extend(word_element(&word));
+    elements
+}
+
+// EVALUATION_SYMBOLS are symbols written instead of NAGs, with their numbers.
+const EVALUATION_SYMBOLS: [(&str, u8); 20] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+    ("=", 10),
+    ("+=", 14),
+    ("⩲", 14),
+    ("=+", 15),
+    ("⩱", 15),
+    ("+/-", 16),
+    ("±", 16),
+    ("-/+", 17),
+    ("∓", 17),
+    ("+-", 18),
+    ("+--", 18),
+    ("-+", 19),
+    ("--+", 19),
+    ("∞", 13),
+];
+
+// is_broken_move tells if the word is a beginning of a move rather than a whole one, e.g. 'Nxe'.
+// Evaluation symbols, results and draw offers are whole words even if they end like a move.
+fn is_broken_move(word: &str) -> bool {
+    if evaluation_nag(word).is_some() || RESULTS.contains(&word) || word == "(=)" {
+        return false;
+    }
+    let m = strip_move_number(word);
+    match m.chars().last() {
+        Some(c) if m.chars().count() == 1 => "KQRBNOabcdefgh".contains(c),
+        Some(c) => "abcdefghx=-".contains(c),
+        None => false,
+    }
+}
+
+fn word_element(word: &str) -> Vec<MovetextElement> {
+    if RESULTS.contains(&word) {
+        return vec![MovetextElement::Result(word.to_string())];
+    }
+    if word.starts_with('$') {
+        return vec![MovetextElement::Nag(word.to_string())];
+    }
+    if let Some(nag) = evaluation_nag(word) {
+        return vec![nag];
+    }
+    let m = strip_move_number(word);
+    let m = m.strip_suffix("e.p.").unwrap_or(m);
+    if m.is_empty() || m == "ep" {
+        return Vec::new();
+    }
+    // symbols written just after the move, e.g. 'Nf6??', are its NAG.
+    let written = m.trim_end_matches(['!', '?']);
+    match evaluation_nag(&m[written.len()..]) {
+        Some(nag) if !written.is_empty() => {
+            vec![MovetextElement::Move(written.to_string()), nag]
+        }
+        _ => vec![MovetextElement::Move(m.to_string())],
+    }
+}
+
+fn evaluation_nag(symbol: &str) -> Option<MovetextElement> {
+    EVALUATION_SYMBOLS
+        .iter()
+        .find(|(s, _)| *s == symbol)
+        .map(|(_, nag)| MovetextElement::Nag(format!("${}", nag)))
+}
+
+// PgnExportOptions controls how PgnGame::export writes a game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnExportOptions {
+    pub line_width: usize, // movetext is wrapped at this column, 0 disables wrapping
+    pub comments: bool,
+    pub variations: bool,
+    pub notation: MoveNotation,
+}
+
+impl Default for PgnExportOptions {
+    fn default() -> Self {
+        PgnExportOptions {
+            line_width: 80,
+            comments: true,
+            variations: true,
+            notation: MoveNotation::San,
+        }
+    }
+}
+
+// MoveAnnotation is analysis of a main line move, written as %eval and %clk comment commands and
+// a note after them, NAG after the move and a variation that could be played instead of it.
+#[derive(Clone, Debug, Default)]
+pub struct MoveAnnotation {
+    pub eval: Option<Score>, // from white point of view
+    pub clock: Option<Duration>,
+    pub nag: Option<u8>,
+    pub variation: Vec<Transition>, // starts in position before the move
+    pub note: Option<String>,
+}
+
+impl MoveAnnotation {
+    // comment returns e.g. '{[%eval 0.25] [%clk 0:05:00] note}', None when there's nothing to
+    // write.
+    fn comment(&self) -> Option<String> {
+        let mut commands = Vec::new();
+        match self.eval {
+            Some(Score::Centipawns(cp)) => {
+                commands.push(format!("[%eval {:.2}]", cp as f32 / 100.0))
+            }
+            Some(Score::Mate(moves)) => commands.push(format!("[%eval #{}]", moves)),
+            None => {}
+        }
+        if let Some(clock) = self.clock {
+            let seconds = clock.as_secs();
+            commands.push(format!(
+                "[%clk {}:{:02}:{:02}]",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ));
+        }
+        commands.extend(self.note.clone());
+        if commands.is_empty() {
+            None
+        } else {
+            Some(format!("{{{}}}", commands.join(" ")))
+        }
+    }
+}
+
+// parse_clock reads %clk command of a comment, e.g. '[%eval 0.25] [%clk 0:04:59.5]'.
+pub fn parse_clock(comment: &str) -> Option<Duration> {
+    let (_, rest) = comment.split_once("[%clk ")?;
+    let (clock, _) = rest.split_once(']')?;
+    let mut seconds = 0.0;
+    for part in clock.trim().split(':') {
+        let value: f64 = part
+            .parse()
+            .ok()
+            .filter(|v: &f64| v.is_finite() && *v >= 0.0)?;
+        seconds = seconds * 60.0 + value;
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+// Position is a board with number of the move of color that has the move.
+#[derive(Clone)]
+struct Position {
+    board: Board,
+    number: usize,
+}
+
+impl PgnGame {
+    // export writes the game with given options, moves are rewritten in chosen notation.
+    // annotations are given for main line moves, in order of plies.
+    pub fn export(
+        &self,
+        options: &PgnExportOptions,
+        annotations: &[MoveAnnotation],
+    ) -> Result<String, ReplayError> {
+        let board = self.start_position().map_err(|e| self.start_error(e))?;
+        let start = Position {
+            number: board.fullmove_number(),
+            board,
+        };
+        let (mut current, mut previous) = (start.clone(), start);
+        let mut stack = Vec::new();
+        let mut tokens = Vec::new();
+        let mut need_number = true; // black move needs number e.g. '3...' after a comment
+        let mut ply = 0; // plies of the main line
+
+        for element in movetext_elements(&self.movetext) {
+            let emit = options.variations || stack.is_empty();
+            match element {
+                MovetextElement::Start => {
+                    stack.push((current.clone(), previous.clone()));
+                    current = previous.clone();
+                    if options.variations {
+                        tokens.push("(".to_string());
+                        need_number = true;
+                    }
+                }
+                MovetextElement::End => {
+                    let (c, p) = stack.pop().ok_or_else(|| {
+                        self.replay_error(&current.board, ply + 1, ")", "unexpected ')'")
+                    })?;
+                    current = c;
+                    previous = p;
+                    if options.variations {
+                        tokens.push(")".to_string());
+                        need_number = true;
+                    }
+                }
+                MovetextElement::Move(m) if emit => {
+                    let t = parse_san(&current.board, &m)
+                        .map_err(|e| self.replay_error(&current.board, ply + 1, &m, e))?;
+                    let color = current.board.color_to_move;
+                    if color == Color::WHITE {
+                        tokens.push(format!("{}.", current.number));
+                    } else if need_number {
+                        tokens.push(format!("{}...", current.number));
+                    }
+                    need_number = false;
+                    tokens.push(options.notation.write(&current.board, &t));
+
+                    previous = current.clone();
+                    current.board.make_transition(t);
+                    if color == Color::BLACK {
+                        current.number += 1;
+                    }
+                    if let Some(annotation) = annotations.get(ply).filter(|_| stack.is_empty()) {
+                        if let Some(nag) = annotation.nag {
+                            tokens.push(format!("${}", nag));
+                        }
+                        if let Some(comment) = annotation.comment() {
+                            tokens.push(comment);
+                            need_number = true;
+                        }
+                        if options.variations && !annotation.variation.is_empty() {
+                            tokens.push("(".to_string());
+                            tokens.extend(line_tokens(&previous, &annotation.variation, options));
+                            tokens.push(")".to_string());
+                            need_number = true;
+                        }
+                    }
+                    if stack.is_empty() {
+                        ply += 1;
+                    }
+                }
+                MovetextElement::Comment(text) if emit && options.comments => {
+                    tokens.push(format!("{{{}}}", text));
+                    need_number = true;
+                }
+                MovetextElement::Nag(nag) if emit => tokens.push(nag),
+                MovetextElement::Result(result) if stack.is_empty() => tokens.push(result),
+                _ => {}
+            }
+        }
+
+        let game = PgnGame {
+            tags: self.tags.clone(),
+            movetext: wrap(&join_tokens(&tokens), options.line_width),
+        };
+        Ok(game.to_string())
+    }
+}
+
+// line_tokens returns numbered moves of a line played from the position.
+fn line_tokens(start: &Position, line: &[Transition], options: &PgnExportOptions) -> Vec<String> {
+    let mut position = start.clone();
+    let mut tokens = Vec::new();
+    for (i, t) in line.iter().enumerate() {
+        if position.board.color_to_move == Color::WHITE {
+            tokens.push(format!("{}.", position.number));
+        } else {
+            if i == 0 {
+                tokens.push(format!("{}...", position.number));
+            }
+            position.number += 1;
+        }
+        tokens.push(options.notation.write(&position.board, t));
+        position.board.make_transition(*t);
+    }
+    tokens
+}
+
+// join_tokens joins movetext tokens with spaces, variation brackets stick to the moves inside them
+// e.g. '(1... c5 2. Nf3)'.
+pub(crate) fn join_tokens(tokens: &[String]) -> String {
+    let mut text = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && token != ")" && tokens[i - 1] != "(" {
+            text.push(' ');
+        }
+        text.push_str(token);
+    }
+    text
+}
+
+// wrap breaks text into lines not longer than width, unless a single word is longer. Move numbers
+// stay on the line of their moves.
+pub(crate) fn wrap(text: &str, width: usize) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+    let mut words: Vec<String> = Vec::new();
+    for word in text.split(' ') {
+        match words.last_mut() {
+            Some(last) if is_move_number(last) => {
+                last.push(' ');
+                last.push_str(word);
+            }
+            _ => words.push(word.to_string()),
+        }
+    }
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in &words {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines.join("\n")
+}
+
+// is_move_number checks if the word is only a move number e.g. '12.', '12...' or '(12...'.
+fn is_move_number(word: &str) -> bool {
+    let number = word.trim_start_matches('(');
+    number.starts_with(|c: char| c.is_ascii_digit())
+        && number.ends_with('.')
+        && strip_move_number(number).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notation::MoveNotation;
+    use crate::pgn::{
+        movetext_elements, movetext_tokens, parse_clock, split_games, variation_tokens,
+        MoveAnnotation, MovetextElement, PgnExportOptions, PgnGame, VariationToken,
+    };
+    use crate::piece::Color;
+    use crate::score::Score;
+    use std::time::Duration;
+
+    const DATABASE: &str = r#"[Event "Casual"]
+[White "Fool"]
+[Black "Mate"]
+[Result "0-1"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+
+[Event "Casual"]
+[White "Scholar"]
+[Result "1-0"]
+
+1.e4 e5 2.Bc4 Nc6 3.Qh5 Nf6
+4.Qxf7# 1-0
+"#;
+
+    #[test]
+    fn test_split_games() {
+        let games = split_games(DATABASE);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("Fool"));
+        assert_eq!(games[1].tag("Black"), None);
+        assert_eq!(games[1].moves().len(), 7);
+        assert_eq!(games[0].to_string(), "[Event \"Casual\"]\n[White \"Fool\"]\n[Black \"Mate\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n");
+    }
+
+    #[test]
+    fn test_split_games_comments() {
+        let pgn = "[Event \"Study\"]\n\n\
+                   1. e4 { The king's pawn.\n\n[%cal Ge2e4]\nBest by test. } 1... e5 $1\n\
+                   % escaped line\n\
+                   2. Nf3 ; a comment with { brace\n\
+                   2... Nc6 (2... d6 { Philidor } (2... Nf6 3. Nxe5) 3. d4) 3. Bb5 a6 ) 4. Ba4 *\n\n\
+                   [Event \"Next\"]\n\n1. d4 *";
+        let games = split_games(pgn);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tags.len(), 1);
+        assert_eq!(
+            games[0].moves(),
+            vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]
+        );
+        assert!(games[0].movetext.contains("[%cal Ge2e4]"));
+        assert!(!games[0].movetext.contains("escaped"));
+        assert!(games[0].replay(|_| {}).is_ok());
+        assert_eq!(games[1].moves(), vec!["d4"]);
+    }
+
+    #[test]
+    fn test_replay() {
+        let games = split_games(DATABASE);
+        let mut plies = 0;
+        let board = games[1].replay(|_| plies += 1).unwrap();
+        assert_eq!(plies, 7);
+        assert!(board.is_check_mate());
+
+        let broken = split_games("1. e4 e5 2. Ke3");
+        let err = broken[0].replay(|_| {}).err().unwrap();
+        assert_eq!(err.ply, 3);
+        assert_eq!(err.token, "Ke3");
+        assert_eq!((err.move_number, err.side), (2, Color::WHITE));
+        assert_eq!(
+            err.fen,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+
+        // numbers of moves continue from FEN tag.
+        let from_fen = split_games(
+            "[FEN \"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 7\"]\n\n7... e5 8. Nf3 Nf3",
+        );
+        let err = from_fen[0].replay(|_| {}).err().unwrap().with_game(12);
+        assert_eq!((err.ply, err.move_number, err.side), (3, 8, Color::BLACK));
+        assert_eq!(
+            err.to_string(),
+            "game 12: move 8... Nf3 (ply 3, black to move): invalid move \
+             (rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 8)"
+        );
+
+        let invalid = split_games("[FEN \"8/8/8 w - - 0 1\"]\n\n1. e4");
+        assert_eq!(invalid[0].replay(|_| {}).err().unwrap().ply, 0);
+
+        let short = split_games("1. N *");
+        let err = short[0].replay(|_| {}).err().unwrap();
+        assert_eq!((err.token.as_str(), err.error), ("N", "move is too short"));
+    }
+
+    #[test]
+    fn test_movetext_tokens() {
+        assert_eq!(
+            movetext_tokens("1.e4 e5 2. Nf3 Nc6 3...a6 1/2-1/2"),
+            vec!["e4", "e5", "Nf3", "Nc6", "a6"]
+        );
+        assert_eq!(
+            movetext_tokens("1. e4 {best} e5 (1... c5) 2. Nf3 $1 *"),
+            vec!["e4", "e5", "Nf3"]
+        );
+    }
+
+    #[test]
+    fn test_evaluation_at_line_end() {
+        for symbol in ["+-", "-+", "+="] {
+            let movetext = format!("1. e4 e5 2. Nf3 {}\nNc6 *", symbol);
+            assert_eq!(movetext_tokens(&movetext), vec!["e4", "e5", "Nf3", "Nc6"]);
+            let game = &split_games(&format!("{}\n", movetext))[0];
+            assert!(game.replay(|_| {}).is_ok(), "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn test_movetext_quirks() {
+        let movetext = "1. e4 d5 2. e5 f5 3. exf6e.p. Nxf6 (=) 4. Nf3 +- Nc6 5. Bb\n5 a6 e.p.\r\n6. Bxc6+\n!? bxc6 1-\n0";
+        assert_eq!(
+            movetext_elements(movetext)
+                .into_iter()
+                .filter(|e| !matches!(e, MovetextElement::Move(_)))
+                .collect::<Vec<_>>(),
+            vec![
+                MovetextElement::Nag("$18".to_string()),
+                MovetextElement::Nag("$5".to_string()),
+                MovetextElement::Result("1-0".to_string()),
+            ]
+        );
+        let game = PgnGame {
+            tags: Vec::new(),
+            movetext: movetext.to_string(),
+        };
+        assert_eq!(
+            game.moves(),
+            vec![
+                "e4", "d5", "e5", "f5", "exf6", "Nxf6", "Nf3", "Nc6", "Bb5", "a6", "Bxc6+", "bxc6"
+            ]
+        );
+        assert!(game.replay(|_| {}).is_ok());
+    }
+
+    #[test]
+    fn test_variation_tokens() {
+        let m = |s: &str| VariationToken::Move(s.to_string());
+        assert_eq!(
+            variation_tokens("1. e4 {best by test} e5 (1...c5 $1 2.Nf3) 2. Nf3 ; main line\n*"),
+            vec![
+                m("e4"),
+                m("e5"),
+                VariationToken::Start,
+                m("c5"),
+                m("Nf3"),
+                VariationToken::End,
+                m("Nf3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_movetext_elements() {
+        assert_eq!(
+            movetext_elements("1.e4 {good} $1 (1. d4) 1-0"),
+            vec![
+                MovetextElement::Move("e4".to_string()),
+                MovetextElement::Comment("good".to_string()),
+                MovetextElement::Nag("$1".to_string()),
+                MovetextElement::Start,
+                MovetextElement::Move("d4".to_string()),
+                MovetextElement::End,
+                MovetextElement::Result("1-0".to_string()),
+            ]
+        );
+        assert_eq!(
+            movetext_elements("3. Bc4 Nf6?? 4. Qxf7#!"),
+            vec![
+                MovetextElement::Move("Bc4".to_string()),
+                MovetextElement::Move("Nf6".to_string()),
+                MovetextElement::Nag("$4".to_string()),
+                MovetextElement::Move("Qxf7#".to_string()),
+                MovetextElement::Nag("$1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let games = split_games(
+            "[White \"A\"]\n\n1. e4 {best by test} e5 (1... c5 2. Nf3) 2. Nf3 $1 Nc6 3. Bb5 a6 1-0",
+        );
+        let export = |options: &PgnExportOptions| games[0].export(options, &[]).unwrap();
+
+        let default = PgnExportOptions::default();
+        assert_eq!(
+            export(&default),
+            "[White \"A\"]\n\n1. e4 {best by test} 1... e5 (1... c5 2. Nf3) 2. Nf3 $1 Nc6 3. Bb5 a6 1-0\n"
+        );
+        let short = PgnExportOptions {
+            line_width: 20,
+            comments: false,
+            variations: false,
+            notation: MoveNotation::Lan,
+        };
+        let pgn = export(&short);
+        assert_eq!(
+            pgn,
+            "[White \"A\"]\n\n1. e2-e4 e7-e5\n2. Ng1-f3 $1 Nb8-c6\n3. Bf1-b5 a7-a6 1-0\n"
+        );
+        assert!(pgn.lines().all(|l| l.len() <= 20));
+        // numbers continue from FEN.
+        let endgame = &split_games(
+            "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 40\"]\n\n40... Kd7 41. e4 (41. e3 Ke6) *",
+        )[0];
+        assert_eq!(
+            endgame.export(&default, &[]).unwrap(),
+            "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 40\"]\n\n40... Kd7 41. e4 (41. e3 Ke6) *\n"
+        );
+        // exported game can be read back.
+        let elements = movetext_elements(&split_games(&pgn)[0].movetext);
+        assert_eq!(
+            elements
+                .iter()
+                .filter(|e| matches!(e, MovetextElement::Move(_)))
+                .count(),
+            6
+        );
+
+        let annotations = vec![
+            MoveAnnotation {
+                eval: Some(Score::Centipawns(25)),
+                clock: Some(Duration::from_secs(3725)),
+                ..Default::default()
+            },
+            MoveAnnotation {
+                eval: Some(Score::Mate(-3)),
+                clock: None,
+                ..Default::default()
+            },
+        ];
+        let options = PgnExportOptions {
+            line_width: 0,
+            ..short
+        };
+        assert!(games[0]
+            .export(&options, &annotations)
+            .unwrap()
+            .ends_with("1. e2-e4 {[%eval 0.25] [%clk 1:02:05]} 1... e7-e5 {[%eval #-3]} 2. Ng1-f3 $1 Nb8-c6 3. Bf1-b5 a7-a6 1-0\n"));
+        assert_eq!(
+            parse_clock("[%eval 0.25] [%clk 1:02:05]"),
+            Some(Duration::from_secs(3725))
+        );
+        assert_eq!(
+            parse_clock("[%clk 0:00:04.5]"),
+            Some(Duration::from_millis(4500))
+        );
+        assert_eq!(parse_clock("[%clk -1:00]"), None);
+        assert_eq!(parse_clock("[%eval 0.25]"), None);
+
+        let broken = split_games("1. e4 e5 2. Ke3");
+        assert_eq!(broken[0].export(&default, &[]).unwrap_err().ply, 3);
+
+        // castles written with zeros or dashes are normalized.
+        let zeros = split_games("1. e4 e5 2. Nf3 Nf6 3. Bc4 Bc5 4. 0-0 O–O+ *");
+        assert_eq!(
+            zeros[0].export(&default, &[]).unwrap(),
+            "1. e4 e5 2. Nf3 Nf6 3. Bc4 Bc5 4. O-O O-O *\n"
+        );
+    }
+}
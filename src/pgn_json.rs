@@ -0,0 +1,227 @@
+use crate::board::Board;
+use crate::notation::{parse_san, to_internal, to_san};
+use crate::pgn::{
+    join_tokens, movetext_elements, wrap, MovetextElement, PgnExportOptions, PgnGame, ReplayError,
+};
+use crate::piece::Color;
+use serde::{Deserialize, Serialize};
+
+// JsonGame is a game as JSON for web apps, which then don't need their own PGN parser. Tags are
+// pairs in order of the PGN. start_fen is written for convenience, when read back the start
+// position comes from the FEN tag.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JsonGame {
+    pub tags: Vec<(String, String)>,
+    pub start_fen: String,
+    pub moves: Vec<JsonMove>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+}
+
+// JsonMove is a move with position after it, e.g. {"san": "e4", "uci": "e2e4", "fen": ...}.
+// Comments are the ones written before and after the move, variations are alternatives to the
+// move, played from the position before it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JsonMove {
+    pub san: String,
+    pub uci: String,
+    pub fen: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments_before: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nags: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variations: Vec<Vec<JsonMove>>,
+}
+
+// to_json returns the game with its comments, NAGs and variations. Moves are checked, the first
+// illegal one, also in a variation, is an error.
+pub fn to_json(game: &PgnGame) -> Result<JsonGame, ReplayError> {
+    let start = game.start_position().map_err(|e| game.start_error(e))?;
+    let mut reader = LineReader {
+        game,
+        elements: movetext_elements(&game.movetext),
+        next: 0,
+        result: None,
+    };
+    let moves = reader.line(&start, 1)?;
+    Ok(JsonGame {
+        tags: game.tags.clone(),
+        start_fen: start.to_fen(),
+        moves,
+        result: reader.result,
+    })
+}
+
+// LineReader builds trees of moves from movetext elements.
+struct LineReader<'a> {
+    game: &'a PgnGame,
+    elements: Vec<MovetextElement>,
+    next: usize,
+    result: Option<String>,
+}
+
+impl LineReader<'_> {
+    // line reads moves played from the board up to the end of the variation or movetext, ply is
+    // the ply of the first move counted from the start of the game.
+    fn line(&mut self, board: &Board, ply: usize) -> Result<Vec<JsonMove>, ReplayError> {
+        let mut line: Vec<JsonMove> = Vec::new();
+        let (mut before, mut current) = (board.clone(), board.clone());
+        let mut comments = Vec::new(); // comments before the next move
+        while let Some(element) = self.elements.get(self.next).cloned() {
+            self.next += 1;
+            match element {
+                MovetextElement::Move(m) => {
+                    let t = parse_san(&current, &m)
+                        .map_err(|e| self.game.replay_error(&current, ply + line.len(), &m, e))?;
+                    before = current.clone();
+                    current.make_transition(t);
+                    line.push(JsonMove {
+                        san: to_san(&before, &t),
+                        uci: to_internal(&t),
+                        fen: current.to_fen(),
+                        comments_before: std::mem::take(&mut comments),
+                        comments: Vec::new(),
+                        nags: Vec::new(),
+                        variations: Vec::new(),
+                    });
+                }
+                MovetextElement::Comment(text) => match line.last_mut() {
+                    Some(last) if comments.is_empty() => last.comments.push(text),
+                    _ => comments.push(text),
+                },
+                MovetextElement::Nag(nag) => {
+                    if let (Some(last), Ok(nag)) = (line.last_mut(), nag[1..].parse()) {
+                        last.nags.push(nag);
+                    }
+                }
+                MovetextElement::Start => {
+                    let ply = ply + line.len().max(1) - 1;
+                    let variation = self.line(&before, ply)?;
+                    match line.last_mut() {
+                        Some(last) => last.variations.push(variation),
+                        None => {
+                            let e = "variation before the first move";
+                            return Err(self.game.replay_error(&current, ply, "(", e));
+                        }
+                    }
+                }
+                MovetextElement::End => return Ok(line),
+                MovetextElement::Result(result) => self.result = Some(result),
+            }
+        }
+        Ok(line)
+    }
+}
+
+// from_json returns the game as PGN with moves checked and written in SAN. Move numbers come from
+// the start position, the FEN tag, and movetext is wrapped as by PgnGame::export.
+pub fn from_json(json: &JsonGame) -> Result<PgnGame, ReplayError> {
+    let mut game = PgnGame {
+        tags: json.tags.clone(),
+        movetext: String::new(),
+    };
+    let start = game.start_position().map_err(|e| game.start_error(e))?;
+    let mut tokens = Vec::new();
+    line_tokens(&game, &start, 1, &json.moves, &mut tokens)?;
+    tokens.extend(json.result.clone());
+    game.movetext = wrap(
+        &join_tokens(&tokens),
+        PgnExportOptions::default().line_width,
+    );
+    Ok(game)
+}
+
+fn line_tokens(
+    game: &PgnGame,
+    board: &Board,
+    ply: usize,
+    moves: &[JsonMove],
+    tokens: &mut Vec<String>,
+) -> Result<(), ReplayError> {
+    let mut board = board.clone();
+    let mut need_number = true; // black move needs number e.g. '3...' after a comment
+    for (i, m) in moves.iter().enumerate() {
+        let t =
+            parse_san(&board, &m.san).map_err(|e| game.replay_error(&board, ply + i, &m.san, e))?;
+        for comment in &m.comments_before {
+            tokens.push(format!("{{{}}}", comment));
+            need_number = true;
+        }
+        if board.color_to_move == Color::WHITE {
+            tokens.push(format!("{}.", board.fullmove_number()));
+        } else if need_number {
+            tokens.push(format!("{}...", board.fullmove_number()));
+        }
+        need_number = false;
+        tokens.push(to_san(&board, &t));
+        tokens.extend(m.nags.iter().map(|n| format!("${}", n)));
+        for comment in &m.comments {
+            tokens.push(format!("{{{}}}", comment));
+            need_number = true;
+        }
+        for variation in &m.variations {
+            tokens.push("(".to_string());
+            line_tokens(game, &board, ply + i, variation, tokens)?;
+            tokens.push(")".to_string());
+            need_number = true;
+        }
+        board.make_transition(t);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pgn::split_games;
+    use crate::pgn_json::{from_json, to_json, JsonGame};
+
+    #[test]
+    fn test_pgn_json() {
+        let pgn = "[Event \"Casual\"]\n[Result \"1-0\"]\n\n{Opening} 1. e4 {best by test} e5 \
+            (1... c5 $1 2. Nf3 (2. c3) d6) 2. Qh5 $2 Nc6 3. Bc4 Nf6?? 4. Qxf7# 1-0";
+        let game = &split_games(pgn)[0];
+        let json = to_json(game).unwrap();
+        assert_eq!(json.tags[0], ("Event".to_string(), "Casual".to_string()));
+        assert_eq!(json.result.as_deref(), Some("1-0"));
+        assert_eq!(json.moves.len(), 7);
+        let e4 = &json.moves[0];
+        assert_eq!((e4.san.as_str(), e4.uci.as_str()), ("e4", "e2e4"));
+        assert_eq!(
+            e4.fen,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        assert_eq!(e4.comments_before, vec!["Opening"]);
+        assert_eq!(e4.comments, vec!["best by test"]);
+        let sicilian = &json.moves[1].variations[0];
+        assert_eq!(sicilian[0].san, "c5");
+        assert_eq!(sicilian[0].nags, vec![1]);
+        assert_eq!(sicilian[1].variations[0][0].san, "c3");
+        assert_eq!(json.moves[2].nags, vec![2]);
+        assert_eq!(json.moves[5].nags, vec![4]);
+        assert_eq!(json.moves[6].san, "Qxf7#");
+
+        let text = serde_json::to_string(&json).unwrap();
+        assert!(text.contains(r#""tags":[["Event","Casual"],["Result","1-0"]]"#));
+        assert!(!text.contains("variations\":[]"));
+        let read: JsonGame = serde_json::from_str(&text).unwrap();
+        assert_eq!(read, json);
+
+        let back = from_json(&read).unwrap();
+        assert_eq!(back.tags, game.tags);
+        assert_eq!(
+            back.movetext,
+            "{Opening} 1. e4 {best by test} 1... e5 (1... c5 $1 2. Nf3 (2. c3) 2... d6)\n\
+             2. Qh5 $2 Nc6 3. Bc4 Nf6 $4 4. Qxf7# 1-0"
+        );
+        assert_eq!(to_json(&back).unwrap(), json);
+
+        let illegal = &split_games("1. e4 (1. e5) e5")[0];
+        assert_eq!(to_json(illegal).unwrap_err().token, "e5");
+        let mut wrong = json;
+        wrong.moves[1].san = "e4".to_string();
+        assert_eq!(from_json(&wrong).unwrap_err().ply, 2);
+    }
+}
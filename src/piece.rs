@@ -1,7 +1,7 @@
 use std::cmp::min;
 use std::fmt::{Display, Formatter};
 
-#[derive(Clone, Copy, PartialEq, Hash, Eq)]
+#[derive(Clone, Copy, PartialEq, Hash, Eq, Debug)]
 pub enum Color {
     NONE,
     BLACK,
@@ -11,9 +11,9 @@ pub enum Color {
 impl Display for Color {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Color::NONE => write!(f, "{}", "NONE"),
-            Color::BLACK => write!(f, "{}", "BLACK"),
-            Color::WHITE => write!(f, "{}", "WHITE"),
+            Color::NONE => write!(f, "NONE"),
+            Color::BLACK => write!(f, "BLACK"),
+            Color::WHITE => write!(f, "WHITE"),
         }
     }
 }
@@ -28,7 +28,7 @@ impl Color {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum PieceType {
     NONE,
     KING,
@@ -61,24 +61,39 @@ impl PieceType {
             _ => Self::NONE,
         };
     }
+
+    // sign returns letter used for piece in algebraic notation, pawn has no letter.
+    pub fn sign(&self) -> &'static str {
+        return match self {
+            PieceType::KING => "K",
+            PieceType::QUEEN => "Q",
+            PieceType::KNIGHT => "N",
+            PieceType::BISHOP => "B",
+            PieceType::ROOK => "R",
+            _ => "",
+        };
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct Piece {
     pub p_type: PieceType,
     pub color: Color,
     pub has_moved: bool,
 }
 
-impl Piece {
-    pub(crate) fn default() -> Self {
+// Default piece is an empty square.
+impl Default for Piece {
+    fn default() -> Self {
         Piece {
             p_type: PieceType::NONE,
             color: Color::NONE,
             has_moved: false,
         }
     }
+}
 
+impl Piece {
     pub fn new(p_type: PieceType, color: Color) -> Self {
         Piece {
             p_type,
@@ -110,10 +125,10 @@ impl Piece {
     }
 
     pub fn is_sliding(&self) -> bool {
-        return match self.p_type {
-            PieceType::BISHOP | PieceType::ROOK | PieceType::QUEEN => true,
-            _ => false,
-        };
+        return matches!(
+            self.p_type,
+            PieceType::BISHOP | PieceType::ROOK | PieceType::QUEEN
+        );
     }
 
     pub fn get_moves(&self, position: usize) -> Vec<i32> {
@@ -161,7 +176,7 @@ impl Piece {
             rook_moves.push(i as i32 * 8);
         }
         for i in 1..col {
-            rook_moves.push(-1 * i as i32); // to left
+            rook_moves.push(-(i as i32)); // to left
         }
         for i in 1..(9 - col) {
             rook_moves.push(i as i32);
@@ -219,7 +234,7 @@ impl Piece {
                 pawn_moves.push(7 * modifier)
             }
         } else {
-            pawn_moves.extend_from_slice(&*vec![7 * modifier, 9 * modifier]);
+            pawn_moves.extend_from_slice(&[7 * modifier, 9 * modifier]);
         }
 
         if !self.has_moved {
@@ -227,24 +242,15 @@ impl Piece {
         }
         return pawn_moves;
     }
-
-    pub fn get_sliding_moves(&self) -> Vec<i32> {
-        return match self.p_type {
-            PieceType::BISHOP => vec![9, 7, -9, -7],
-            PieceType::ROOK => vec![8, 1, -8, -1],
-            PieceType::QUEEN => vec![9, 7, -9, -7, 8, 1, -8, -1],
-            _ => Vec::new(),
-        };
-    }
 }
 
 fn position_to_row_col(position: usize) -> Option<(usize, usize)> {
     for i in 0..8 {
         if position >= 8 * i && position < 8 * (i + 1) {
-            if (position + 1) % 8 == 0 {
+            if position % 8 == 7 {
                 return Some((i + 1, 8));
             } else {
-                return Some((i + 1, (position + 1) % 8 as usize));
+                return Some((i + 1, (position + 1) % 8));
             }
         }
     }
@@ -0,0 +1,125 @@
+use crate::board::{signed_points, Board, CastlingRights, Square};
+use crate::piece::{Color, Piece};
+use crate::pst;
+use std::borrow::Cow;
+
+// Position is a read-only view of pieces on the board and color to move, which is all evaluators
+// need. Terms that generate moves, e.g. mobility, build a Board with to_board.
+pub trait Position {
+    fn piece(&self, square: Square) -> Piece;
+
+    fn color_to_move(&self) -> Color;
+
+    fn occupied(&self, square: Square) -> bool {
+        !self.piece(square).is_none()
+    }
+
+    // material returns points of white pieces minus black ones, see Board::material.
+    fn material(&self) -> i32 {
+        self.squares().iter().map(signed_points).sum()
+    }
+
+    // piece_square returns middlegame and endgame piece-square bonuses, see Board::piece_square.
+    fn piece_square(&self) -> (i32, i32) {
+        pst::sum(&self.squares())
+    }
+
+    // squares returns pieces of all squares, 0 is left lower corner as in Board.
+    fn squares(&self) -> [Piece; 64] {
+        let mut squares = [Piece::default(); 64];
+        for (square, piece) in squares.iter_mut().enumerate() {
+            *piece = self.piece(square);
+        }
+        squares
+    }
+
+    // to_board returns the position as a Board, borrowed when it's a Board already.
+    fn to_board(&self) -> Cow<'_, Board>;
+}
+
+impl Position for Board {
+    fn piece(&self, square: Square) -> Piece {
+        self.piece_at(square)
+    }
+
+    fn color_to_move(&self) -> Color {
+        self.color_to_move
+    }
+
+    fn squares(&self) -> [Piece; 64] {
+        *Board::squares(self)
+    }
+
+    fn material(&self) -> i32 {
+        Board::material(self)
+    }
+
+    fn piece_square(&self) -> (i32, i32) {
+        Board::piece_square(self)
+    }
+
+    fn to_board(&self) -> Cow<'_, Board> {
+        Cow::Borrowed(self)
+    }
+}
+
+// Placement is a position decoded from data that has only pieces and color to move, e.g. rows
+// of training files. Its board has no castling rights and no en passant square.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub squares: [Piece; 64],
+    pub color_to_move: Color,
+}
+
+impl Position for Placement {
+    fn piece(&self, square: Square) -> Piece {
+        self.squares[square]
+    }
+
+    fn color_to_move(&self) -> Color {
+        self.color_to_move
+    }
+
+    fn squares(&self) -> [Piece; 64] {
+        self.squares
+    }
+
+    fn to_board(&self) -> Cow<'_, Board> {
+        let pieces = self.squares.iter().copied().enumerate();
+        let board = Board::from_pieces(pieces, self.color_to_move, CastlingRights::default(), None)
+            .expect("position without castling rights and en passant square is valid");
+        Cow::Owned(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::piece::{Color, Piece, PieceType};
+    use crate::position::{Placement, Position};
+
+    #[test]
+    fn test_placement() {
+        let mut squares = [Piece::default(); 64];
+        squares[4] = Piece::new(PieceType::KING, Color::WHITE);
+        squares[0] = Piece::new(PieceType::ROOK, Color::WHITE);
+        squares[60] = Piece::new(PieceType::KING, Color::BLACK);
+        let placement = Placement {
+            squares,
+            color_to_move: Color::BLACK,
+        };
+        assert!(placement.occupied(0));
+        assert!(!placement.occupied(1));
+        assert_eq!(placement.color_to_move(), Color::BLACK);
+
+        let board = placement.to_board();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/R3K3 b - - 0 1");
+        assert_eq!(board.king_square(Color::WHITE), Some(4));
+        assert_eq!(Position::squares(board.as_ref())[0].p_type, PieceType::ROOK);
+
+        let start = Board::default();
+        assert_eq!(start.piece(3).p_type, PieceType::QUEEN);
+        assert_eq!(Position::color_to_move(&start), Color::WHITE);
+        assert_eq!(placement.piece_square(), board.piece_square());
+    }
+}
@@ -0,0 +1,39 @@
+// prelude re-exports types needed by most users of chust as a library: `use chust::prelude::*;`.
+//
+// Stability: items of the prelude are the public API of the crate. Until 1.0 their breaking
+// changes are released only with a new minor version (0.x), patch versions only add to them.
+// Other public items are reachable through their modules, but may change in any release.
+
+pub use crate::board::{
+    Board, CastlingRights, DrawReason, GameState, GameStatus, Move, Side, Square,
+};
+#[cfg(feature = "search")]
+pub use crate::evaluation::Evaluator;
+pub use crate::notation::{parse_san, parse_square, square_name, to_san};
+pub use crate::piece::{Color, Piece, PieceType};
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_prelude() {
+        let mut board = Board::default();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            let m: Move = parse_san(&board, san).unwrap();
+            board.make_transition(m);
+        }
+        assert_eq!(board.status(), GameStatus::Checkmate(Color::BLACK));
+        assert!(board.legal_moves().is_empty());
+        let king: Square = board.king_square(Color::WHITE).unwrap();
+        assert_eq!(square_name(king), "e1");
+        assert_eq!(
+            board.checkers(Color::WHITE),
+            vec![parse_square("h4").unwrap()]
+        );
+        assert_eq!(board.piece_at(king).p_type, PieceType::KING);
+
+        board.read_fen("7k/5Q2/6K1/8/8/8/8/8 b").unwrap();
+        assert_eq!(board.status(), GameStatus::Stalemate);
+    }
+}
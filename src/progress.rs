@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Progress receives updates from long operations, e.g. processing PGN database. Methods can be
+// called from many threads at once.
+pub trait Progress: Sync {
+    // start is called once before the work begins with number of items to process.
+    fn start(&self, _label: &str, _total: u64) {}
+    // advance is called after 'n' items are processed.
+    fn advance(&self, n: u64);
+    // finish is called once when all items are processed.
+    fn finish(&self) {}
+}
+
+// NoProgress ignores all updates.
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn advance(&self, _n: u64) {}
+}
+
+// Counter counts processed items, useful when caller polls progress from other thread.
+#[derive(Default)]
+pub struct Counter {
+    pub done: AtomicU64,
+    pub total: AtomicU64,
+}
+
+impl Progress for Counter {
+    fn start(&self, _label: &str, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+    }
+
+    fn advance(&self, n: u64) {
+        self.done.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+// Bar draws progress bar with ETA on stderr, it's hidden when stderr is not a terminal.
+#[cfg(feature = "progress")]
+pub struct Bar {
+    bar: indicatif::ProgressBar,
+}
+
+#[cfg(feature = "progress")]
+impl Bar {
+    pub fn new() -> Self {
+        let bar = indicatif::ProgressBar::new(0);
+        let style = indicatif::ProgressStyle::with_template(
+            "{msg} [{bar:40}] {pos}/{len} ({per_sec}, eta {eta})",
+        )
+        .unwrap()
+        .progress_chars("=> ");
+        bar.set_style(style);
+        Bar { bar }
+    }
+}
+
+#[cfg(feature = "progress")]
+impl Default for Bar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "progress")]
+impl Progress for Bar {
+    fn start(&self, label: &str, total: u64) {
+        self.bar.set_message(label.to_string());
+        self.bar.set_length(total);
+        self.bar.set_position(0);
+    }
+
+    fn advance(&self, n: u64) {
+        self.bar.inc(n);
+    }
+
+    fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
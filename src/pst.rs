@@ -0,0 +1,169 @@
+use crate::board::Square;
+use crate::piece::{Color, Piece, PieceType};
+
+// Piece-square tables give bonuses in centipawns for pieces standing on squares, separately in
+// the middlegame and in the endgame. Tables are written for white with rank 8 first, as the board
+// is seen by white, black pieces use them mirrored vertically.
+
+#[rustfmt::skip]
+const PAWN_MIDGAME: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+// PAWN_ENDGAME rewards passers running to promotion.
+#[rustfmt::skip]
+const PAWN_ENDGAME: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    80, 80, 80, 80, 80, 80, 80, 80,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    30, 30, 30, 30, 30, 30, 30, 30,
+    15, 15, 15, 15, 15, 15, 15, 15,
+     5,  5,  5,  5,  5,  5,  5,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+// KING_MIDGAME keeps the king behind its pawns.
+#[rustfmt::skip]
+const KING_MIDGAME: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+// KING_ENDGAME brings the king to the center.
+#[rustfmt::skip]
+const KING_ENDGAME: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+// MIDGAME and ENDGAME are tables of pawns, knights, bishops, rooks, queens and kings.
+pub const MIDGAME: [[i32; 64]; 6] = [PAWN_MIDGAME, KNIGHT, BISHOP, ROOK, QUEEN, KING_MIDGAME];
+pub const ENDGAME: [[i32; 64]; 6] = [PAWN_ENDGAME, KNIGHT, BISHOP, ROOK, QUEEN, KING_ENDGAME];
+
+fn table_index(p_type: PieceType) -> Option<usize> {
+    match p_type {
+        PieceType::PAWN => Some(0),
+        PieceType::KNIGHT => Some(1),
+        PieceType::BISHOP => Some(2),
+        PieceType::ROOK => Some(3),
+        PieceType::QUEEN => Some(4),
+        PieceType::KING => Some(5),
+        PieceType::NONE => None,
+    }
+}
+
+// bonus returns middlegame and endgame bonuses of a piece on a square, positive for white and
+// negative for black.
+pub fn bonus(piece: &Piece, square: Square) -> (i32, i32) {
+    let i = match table_index(piece.p_type) {
+        Some(i) => i,
+        None => return (0, 0),
+    };
+    // tables start from a8, so white squares are mirrored and black ones are read as they are.
+    let (sign, row) = match piece.color {
+        Color::WHITE => (1, square ^ 56),
+        Color::BLACK => (-1, square),
+        Color::NONE => return (0, 0),
+    };
+    (sign * MIDGAME[i][row], sign * ENDGAME[i][row])
+}
+
+// sum returns middlegame and endgame bonuses of all pieces.
+pub fn sum(squares: &[Piece; 64]) -> (i32, i32) {
+    squares
+        .iter()
+        .enumerate()
+        .map(|(square, piece)| bonus(piece, square))
+        .fold((0, 0), |(mg, eg), (m, e)| (mg + m, eg + e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::piece::{Color, Piece, PieceType};
+    use crate::pst::{bonus, sum};
+
+    #[test]
+    fn test_bonus() {
+        let white_king = Piece::new(PieceType::KING, Color::WHITE);
+        let black_king = Piece::new(PieceType::KING, Color::BLACK);
+        assert_eq!(bonus(&white_king, 6), (30, -30)); // g1
+        assert_eq!(bonus(&black_king, 62), (-30, 30)); // g8 mirrors g1
+        assert_eq!(bonus(&white_king, 27), (-40, 40)); // d4
+        let pawn = Piece::new(PieceType::PAWN, Color::WHITE);
+        assert_eq!(bonus(&pawn, 52), (50, 80)); // e7
+        assert_eq!(bonus(&Piece::default(), 0), (0, 0));
+
+        let mut squares = [Piece::default(); 64];
+        squares[6] = white_king;
+        squares[62] = black_king;
+        assert_eq!(sum(&squares), (0, 0));
+    }
+}
@@ -0,0 +1,736 @@
+use crate::board::{Board, Transition};
+use crate::notation::parse_san;
+use crate::pgn::{PgnGame, ReplayError};
+use crate::piece::{Color, PieceType};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+// Rgb is a color written as '#rrggbb'.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').ok_or("color must start with #")?;
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("color must be #rrggbb");
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or_default();
+        Ok(Rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+// Theme is a set of colors of the board and pieces. It's read from lines 'name = #rrggbb', every
+// color not given keeps its value from the brown theme, '#' starts a comment line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub light: Rgb,
+    pub dark: Rgb,
+    pub border: Rgb,      // background of coordinates
+    pub coordinates: Rgb, // color of coordinate labels
+    pub highlight: Rgb,   // squares of the last move
+    pub white_fill: Rgb,
+    pub white_outline: Rgb,
+    pub black_fill: Rgb,
+    pub black_outline: Rgb,
+}
+
+pub const THEME_NAMES: [&str; 3] = ["brown", "blue", "green"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            light: Rgb(0xf0, 0xd9, 0xb5),
+            dark: Rgb(0xb5, 0x88, 0x63),
+            border: Rgb(0x40, 0x2e, 0x22),
+            coordinates: Rgb(0xf0, 0xd9, 0xb5),
+            highlight: Rgb(0xcd, 0xd2, 0x6a),
+            white_fill: Rgb(0xff, 0xff, 0xff),
+            white_outline: Rgb(0x20, 0x20, 0x20),
+            black_fill: Rgb(0x20, 0x20, 0x20),
+            black_outline: Rgb(0x00, 0x00, 0x00),
+        }
+    }
+}
+
+impl Theme {
+    // by_name returns one of built-in themes.
+    pub fn by_name(name: &str) -> Option<Self> {
+        let theme = match name {
+            "brown" => Theme::default(),
+            "blue" => Theme {
+                light: Rgb(0xde, 0xe3, 0xe6),
+                dark: Rgb(0x8c, 0xa2, 0xad),
+                border: Rgb(0x2c, 0x3e, 0x50),
+                coordinates: Rgb(0xde, 0xe3, 0xe6),
+                ..Theme::default()
+            },
+            "green" => Theme {
+                light: Rgb(0xee, 0xee, 0xd2),
+                dark: Rgb(0x76, 0x96, 0x56),
+                border: Rgb(0x30, 0x40, 0x25),
+                coordinates: Rgb(0xee, 0xee, 0xd2),
+                highlight: Rgb(0xf6, 0xf6, 0x69),
+                ..Theme::default()
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut theme = Theme::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or(format!("line {}: expected 'name = #rrggbb'", i + 1))?;
+            let color: Rgb = value
+                .trim()
+                .parse()
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            let field = match name.trim() {
+                "light" => &mut theme.light,
+                "dark" => &mut theme.dark,
+                "border" => &mut theme.border,
+                "coordinates" => &mut theme.coordinates,
+                "highlight" => &mut theme.highlight,
+                "white-fill" => &mut theme.white_fill,
+                "white-outline" => &mut theme.white_outline,
+                "black-fill" => &mut theme.black_fill,
+                "black-outline" => &mut theme.black_outline,
+                name => return Err(format!("line {}: unknown color {}", i + 1, name)),
+            };
+            *field = color;
+        }
+        Ok(theme)
+    }
+}
+
+// PIXEL_PIECES is the built-in piece set.
+const PIXEL_PIECES: &str = "
+[K]
+.......++.......
+......+##+......
+.......++.......
+....+++##+++....
+...+########+...
+...+###++###+...
+....+######+....
+.....+####+.....
+.....+####+.....
+.....+####+.....
+....+######+....
+...+########+...
+..+##########+..
+..++++++++++++..
+................
+................
+[Q]
+................
+..+...+..+...+..
+.+#+.+#++#+.+#+.
+.+##+##++##+##+.
+..+##########+..
+..+##########+..
+...+########+...
+....+######+....
+....+######+....
+.....+####+.....
+....+######+....
+...+########+...
+..+##########+..
+..++++++++++++..
+................
+................
+[R]
+................
+................
+..++..++++..++..
+..+#++####++#+..
+..+##########+..
+...+########+...
+....+######+....
+.....+####+.....
+.....+####+.....
+.....+####+.....
+....+######+....
+...+########+...
+..+##########+..
+..++++++++++++..
+................
+................
+[B]
+................
+.......++.......
+......+##+......
+.....+####+.....
+....+##++##+....
+....+###+##+....
+....+######+....
+.....+####+.....
+......+##+......
+.....+####+.....
+......+##+......
+.....+####+.....
+...+########+...
+...++++++++++...
+................
+................
+[N]
+................
+.......++.......
+......+#+++.....
+.....+######+...
+....+###+####+..
+...+#########+..
+...+####++###+..
+....++++.+###+..
+........+####+..
+.......+#####+..
+......+######+..
+.....+#######+..
+....+#########+.
+....+++++++++++.
+................
+................
+[P]
+................
+................
+................
+......++++......
+.....+####+.....
+.....+####+.....
+......+##+......
+.....+####+.....
+......+##+......
+......+##+......
+.....+####+.....
+....+######+....
+...+########+...
+...++++++++++...
+................
+................
+";
+
+// PieceSet is a set of pixel masks of pieces, drawn scaled to the size of a square. It's read
+// from sections '[K]', '[Q]', '[R]', '[B]', '[N]' and '[P]', each followed by rows of the mask of
+// the same width: '#' is filled with piece color, '+' with its outline and '.' is transparent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PieceSet {
+    masks: HashMap<&'static str, Vec<Vec<u8>>>,
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        PIXEL_PIECES.parse().expect("built-in piece set is valid")
+    }
+}
+
+impl FromStr for PieceSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut masks: HashMap<&'static str, Vec<Vec<u8>>> = HashMap::new();
+        let mut current = None;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let sign = ["K", "Q", "R", "B", "N", "P"]
+                    .iter()
+                    .find(|s| **s == name)
+                    .copied()
+                    .ok_or(format!("line {}: unknown piece {}", i + 1, name))?;
+                masks.insert(sign, Vec::new());
+                current = Some(sign);
+                continue;
+            }
+            let sign = current.ok_or(format!("line {}: mask without piece", i + 1))?;
+            if !line.bytes().all(|b| b == b'#' || b == b'+' || b == b'.') {
+                return Err(format!("line {}: mask may contain only # + .", i + 1));
+            }
+            let mask = masks.get_mut(sign).expect("section is inserted");
+            if mask.first().is_some_and(|row| row.len() != line.len()) {
+                return Err(format!("line {}: rows of {} differ in width", i + 1, sign));
+            }
+            mask.push(line.bytes().collect());
+        }
+        if masks.len() != 6 || masks.values().any(|m| m.is_empty()) {
+            return Err("piece set must have masks of K, Q, R, B, N and P".to_string());
+        }
+        Ok(PieceSet { masks })
+    }
+}
+
+impl PieceSet {
+    fn mask(&self, p_type: PieceType) -> &[Vec<u8>] {
+        match p_type.sign() {
+            "" => &self.masks["P"],
+            sign => &self.masks[sign],
+        }
+    }
+}
+
+// Image is a raster image, pixels are stored row by row from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize, color: Rgb) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![color; width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + column] = color;
+            }
+        }
+    }
+}
+
+// GLYPHS are 3x5 bitmaps of coordinate labels, rows from the top, the highest bit on the left.
+const GLYPHS: [(char, [u8; 5]); 16] = [
+    ('a', [0b000, 0b011, 0b101, 0b101, 0b011]),
+    ('b', [0b100, 0b110, 0b101, 0b101, 0b110]),
+    ('c', [0b000, 0b011, 0b100, 0b100, 0b011]),
+    ('d', [0b001, 0b011, 0b101, 0b101, 0b011]),
+    ('e', [0b010, 0b101, 0b111, 0b100, 0b011]),
+    ('f', [0b011, 0b100, 0b110, 0b100, 0b100]),
+    ('g', [0b011, 0b101, 0b011, 0b001, 0b110]),
+    ('h', [0b100, 0b100, 0b110, 0b101, 0b101]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b110, 0b101, 0b010]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b010, 0b101, 0b010, 0b101, 0b010]),
+];
+
+// Renderer draws diagrams of positions as SVG or raster images, with its theme and piece set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Renderer {
+    pub theme: Theme,
+    pub pieces: PieceSet,
+    pub square: usize, // size of a square in pixels
+    pub coordinates: bool,
+    pub flipped: bool, // black at the bottom
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            theme: Theme::default(),
+            pieces: PieceSet::default(),
+            square: 48,
+            coordinates: true,
+            flipped: false,
+        }
+    }
+}
+
+impl Renderer {
+    // margin returns width of the border with coordinates, left and below the board.
+    fn margin(&self) -> usize {
+        if self.coordinates {
+            self.square / 2
+        } else {
+            0
+        }
+    }
+
+    // size returns width and height of the diagram.
+    pub fn size(&self) -> (usize, usize) {
+        let side = 8 * self.square + self.margin();
+        (side, side)
+    }
+
+    // square_origin returns top left corner of a square in the diagram.
+    fn square_origin(&self, index: usize) -> (usize, usize) {
+        let (file, rank) = (index % 8, index / 8);
+        let (column, row) = if self.flipped {
+            (7 - file, rank)
+        } else {
+            (file, 7 - rank)
+        };
+        (self.margin() + column * self.square, row * self.square)
+    }
+
+    // square_color returns color of the square, highlighted when the last move was played on it.
+    fn square_color(&self, index: usize, last_move: Option<&Transition>) -> Rgb {
+        if last_move.is_some_and(|t| t.from == index || t.to == index) {
+            self.theme.highlight
+        } else if (index % 8 + index / 8).is_multiple_of(2) {
+            self.theme.dark
+        } else {
+            self.theme.light
+        }
+    }
+
+    // labels returns coordinate labels with their centers.
+    fn labels(&self) -> Vec<(char, usize, usize)> {
+        if !self.coordinates {
+            return Vec::new();
+        }
+        let (margin, square) = (self.margin(), self.square);
+        let mut labels = Vec::new();
+        for i in 0..8 {
+            let file = (b'a' + i as u8) as char;
+            let rank = (b'1' + i as u8) as char;
+            let (x, y) = self.square_origin(i * 9);
+            labels.push((file, x + square / 2, 8 * square + margin / 2));
+            labels.push((rank, margin / 2, y + square / 2));
+        }
+        labels
+    }
+
+    // piece_colors returns fill and outline colors of pieces of the color.
+    fn piece_colors(&self, color: Color) -> (Rgb, Rgb) {
+        if color == Color::WHITE {
+            (self.theme.white_fill, self.theme.white_outline)
+        } else {
+            (self.theme.black_fill, self.theme.black_outline)
+        }
+    }
+
+    // svg returns diagram of the position as SVG document.
+    pub fn svg(&self, board: &Board, last_move: Option<&Transition>) -> String {
+        let (width, height) = self.size();
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+             viewBox=\"0 0 {0} {1}\" shape-rendering=\"crispEdges\">\n",
+            width, height
+        );
+        let mut rect = |x: f32, y: f32, w: f32, h: f32, color: Rgb| {
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                x, y, w, h, color
+            );
+        };
+        rect(0.0, 0.0, width as f32, height as f32, self.theme.border);
+        for index in 0..64 {
+            let (x, y) = self.square_origin(index);
+            let s = self.square as f32;
+            rect(
+                x as f32,
+                y as f32,
+                s,
+                s,
+                self.square_color(index, last_move),
+            );
+            let piece = board.piece_at(index);
+            if piece.p_type == PieceType::NONE {
+                continue;
+            }
+            let (fill, outline) = self.piece_colors(piece.color);
+            let mask = self.pieces.mask(piece.p_type);
+            let (pw, ph) = (s / mask[0].len() as f32, s / mask.len() as f32);
+            for (row, line) in mask.iter().enumerate() {
+                // neighbouring pixels of the same color are written as one rectangle.
+                let mut column = 0;
+                while column < line.len() {
+                    let run = line[column..]
+                        .iter()
+                        .take_while(|&&b| b == line[column])
+                        .count();
+                    let color = match line[column] {
+                        b'#' => Some(fill),
+                        b'+' => Some(outline),
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        rect(
+                            x as f32 + column as f32 * pw,
+                            y as f32 + row as f32 * ph,
+                            run as f32 * pw,
+                            ph,
+                            color,
+                        );
+                    }
+                    column += run;
+                }
+            }
+        }
+        for (label, x, y) in self.labels() {
+            let _ = writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" fill=\"{}\" \
+                 text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                x,
+                y,
+                self.margin() * 2 / 3,
+                self.theme.coordinates,
+                label
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // image returns diagram of the position as raster image, coordinates are drawn with a small
+    // built-in font.
+    pub fn image(&self, board: &Board, last_move: Option<&Transition>) -> Image {
+        let (width, height) = self.size();
+        let mut image = Image::new(width, height, self.theme.border);
+        for index in 0..64 {
+            let (x, y) = self.square_origin(index);
+            let color = self.square_color(index, last_move);
+            image.fill(x, y, self.square, self.square, color);
+            let piece = board.piece_at(index);
+            if piece.p_type == PieceType::NONE {
+                continue;
+            }
+            let (fill, outline) = self.piece_colors(piece.color);
+            let mask = self.pieces.mask(piece.p_type);
+            for py in 0..self.square {
+                for px in 0..self.square {
+                    let row = &mask[py * mask.len() / self.square];
+                    let color = match row[px * row.len() / self.square] {
+                        b'#' => fill,
+                        b'+' => outline,
+                        _ => continue,
+                    };
+                    image.pixels[(y + py) * width + x + px] = color;
+                }
+            }
+        }
+        let scale = (self.margin() / 8).max(1);
+        for (label, x, y) in self.labels() {
+            let glyph = match GLYPHS.iter().find(|(c, _)| *c == label) {
+                Some((_, glyph)) => glyph,
+                None => continue,
+            };
+            let (left, top) = (
+                x.saturating_sub(3 * scale / 2),
+                y.saturating_sub(5 * scale / 2),
+            );
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        let (gx, gy) = (left + column * scale, top + row * scale);
+                        image.fill(gx, gy, scale, scale, self.theme.coordinates);
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    // game_frames returns diagrams of every position of the game, from the start position, with
+    // squares of the last move highlighted.
+    pub fn game_frames(&self, game: &PgnGame) -> Result<Vec<Image>, ReplayError> {
+        let mut board = game.start_position().map_err(|e| game.start_error(e))?;
+        let mut frames = vec![self.image(&board, None)];
+        for (i, token) in game.moves().into_iter().enumerate() {
+            let t = parse_san(&board, &token)
+                .map_err(|e| game.replay_error(&board, i + 1, &token, e))?;
+            board.make_transition(t);
+            frames.push(self.image(&board, Some(&t)));
+        }
+        Ok(frames)
+    }
+}
+
+// Animation is timing of animated diagrams of a game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Animation {
+    pub delay: Duration, // how long every position is shown
+    pub hold: Duration,  // how long the final position is shown before the animation repeats
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            delay: Duration::from_millis(1000),
+            hold: Duration::from_millis(5000),
+        }
+    }
+}
+
+// write_gif writes frames as animated GIF repeated forever. Diagrams have few colors, so all
+// frames share one palette and no quantization is needed.
+#[cfg(feature = "gif")]
+pub fn write_gif<W: std::io::Write>(
+    frames: &[Image],
+    animation: &Animation,
+    out: W,
+) -> std::io::Result<()> {
+    use std::convert::TryFrom;
+    use std::io::{Error, ErrorKind};
+
+    let first = frames
+        .first()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no frames"))?;
+    let size = |v: usize| {
+        u16::try_from(v).map_err(|_| Error::new(ErrorKind::InvalidInput, "image is too large"))
+    };
+    let (width, height) = (size(first.width)?, size(first.height)?);
+    let mut palette: Vec<Rgb> = Vec::new();
+    let mut indexed = Vec::new();
+    for frame in frames {
+        let mut pixels = Vec::with_capacity(frame.pixels.len());
+        for color in &frame.pixels {
+            let index = match palette.iter().position(|c| c == color) {
+                Some(index) => index,
+                None => {
+                    palette.push(*color);
+                    palette.len() - 1
+                }
+            };
+            pixels.push(index);
+        }
+        indexed.push(pixels);
+    }
+    if palette.len() > 256 {
+        return Err(Error::new(ErrorKind::InvalidInput, "more than 256 colors"));
+    }
+    let rgb: Vec<u8> = palette.iter().flat_map(|c| [c.0, c.1, c.2]).collect();
+    let mut encoder = gif::Encoder::new(out, width, height, &rgb).map_err(Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(Error::other)?;
+    let centiseconds = |d: Duration| (d.as_millis() / 10).min(u16::MAX as u128) as u16;
+    for (i, pixels) in indexed.into_iter().enumerate() {
+        let pixels: Vec<u8> = pixels.into_iter().map(|p| p as u8).collect();
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, pixels, None);
+        frame.delay = if i + 1 == frames.len() {
+            centiseconds(animation.hold)
+        } else {
+            centiseconds(animation.delay)
+        };
+        encoder.write_frame(&frame).map_err(Error::other)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::pgn::split_games;
+    use crate::render::{PieceSet, Renderer, Rgb, Theme, THEME_NAMES};
+
+    #[test]
+    fn test_assets() {
+        assert_eq!("#f0d9B5".parse(), Ok(Rgb(0xf0, 0xd9, 0xb5)));
+        assert_eq!(Rgb(1, 2, 255).to_string(), "#0102ff");
+        assert!("f0d9b5".parse::<Rgb>().is_err());
+        assert!(THEME_NAMES.iter().all(|n| Theme::by_name(n).is_some()));
+
+        let theme: Theme = "# brand\nlight = #ffffff\ndark = #000000\n"
+            .parse()
+            .unwrap();
+        assert_eq!(theme.light, Rgb(255, 255, 255));
+        assert_eq!(theme.border, Theme::default().border);
+        assert!("shade = #000000".parse::<Theme>().is_err());
+
+        let set = "[K]\n#\n[Q]\n+\n[R]\n#\n[B]\n#\n[N]\n#\n[P]\n.#.\n";
+        assert!(set.parse::<PieceSet>().is_ok());
+        assert!("[K]\n#\n".parse::<PieceSet>().is_err());
+        assert!(set.replace(".#.", ".#x").parse::<PieceSet>().is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let board = Board::default();
+        let renderer = Renderer {
+            square: 16,
+            ..Default::default()
+        };
+        assert_eq!(renderer.size(), (136, 136));
+        let svg = renderer.svg(&board, None);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("</text>").count(), 16);
+
+        let image = renderer.image(&board, None);
+        assert_eq!(image.pixels.len(), 136 * 136);
+        // a1 is dark, its bottom left corner is empty, h1 is light.
+        let theme = Theme::default();
+        assert_eq!(image.pixel(8, 127), theme.dark);
+        assert_eq!(image.pixel(135, 127), theme.light);
+        assert_eq!(image.pixel(0, 0), theme.border);
+
+        let plain = Renderer {
+            square: 16,
+            coordinates: false,
+            flipped: true,
+            theme: Theme::by_name("blue").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(plain.size(), (128, 128));
+        assert!(!plain.svg(&board, None).contains("<text"));
+        // flipped board has h8 in bottom left corner, black rook is drawn there.
+        let image = plain.image(&board, None);
+        assert_eq!(image.pixel(8, 122), plain.theme.black_fill);
+    }
+
+    #[test]
+    fn test_game_frames() {
+        let renderer = Renderer {
+            square: 8,
+            coordinates: false,
+            ..Default::default()
+        };
+        let games = split_games("1. e4 e5 2. Nf3 *");
+        let frames = renderer.game_frames(&games[0]).unwrap();
+        assert_eq!(frames.len(), 4);
+        // e2 and e4 are highlighted after the first move.
+        let theme = &renderer.theme;
+        assert_eq!(frames[1].pixel(4 * 8, 6 * 8 + 7), theme.highlight);
+        assert_eq!(frames[1].pixel(4 * 8, 4 * 8 + 7), theme.highlight);
+        assert_ne!(frames[0].pixel(4 * 8, 6 * 8 + 7), theme.highlight);
+        let broken = split_games("1. e4 e5 2. Ke3");
+        assert_eq!(renderer.game_frames(&broken[0]).unwrap_err().ply, 3);
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn test_write_gif() {
+        use crate::render::{write_gif, Animation};
+
+        let renderer = Renderer {
+            square: 8,
+            ..Default::default()
+        };
+        let frames = renderer.game_frames(&split_games("1. e4 e5 *")[0]).unwrap();
+        let mut gif = Vec::new();
+        write_gif(&frames, &Animation::default(), &mut gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        assert!(write_gif(&[], &Animation::default(), &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::board::Board;
+use crate::pgn::{split_games, variation_tokens, VariationToken};
+use crate::piece::Color;
+use rand::Rng;
+use std::io::{self, BufRead, Write};
+
+// Node is a position in repertoire tree, reached by playing 'san' move from parent node.
+pub struct Node {
+    pub san: String,
+    pub board: Board, // position after the move
+    pub children: Vec<usize>,
+}
+
+// Repertoire is a tree of opening moves, the first child of every node is the main line.
+pub struct Repertoire {
+    pub nodes: Vec<Node>,
+}
+
+impl Repertoire {
+    // from_pgn reads all games of PGN file with their variations into a single tree starting
+    // from the initial position.
+    pub fn from_pgn(content: &str) -> Result<Self, String> {
+        let mut repertoire = Repertoire {
+            nodes: vec![Node {
+                san: String::new(),
+                board: Board::default(),
+                children: Vec::new(),
+            }],
+        };
+        for game in split_games(content) {
+            // current is the node after the last move, previous is its parent.
+            let (mut current, mut previous) = (0, 0);
+            let mut stack = Vec::new();
+            for token in variation_tokens(&game.movetext) {
+                match token {
+                    VariationToken::Start => {
+                        stack.push((current, previous));
+                        current = previous;
+                    }
+                    VariationToken::End => {
+                        let (c, p) = stack.pop().ok_or("unexpected ')' in movetext")?;
+                        current = c;
+                        previous = p;
+                    }
+                    VariationToken::Move(san) => {
+                        previous = current;
+                        current = repertoire.add_move(current, &san)?;
+                    }
+                }
+            }
+            if !stack.is_empty() {
+                return Err("variation is not closed with ')'".to_string());
+            }
+        }
+        Ok(repertoire)
+    }
+
+    // add_move returns child of the node reached with given move, it's created when necessary.
+    fn add_move(&mut self, parent: usize, san: &str) -> Result<usize, String> {
+        let mut board = self.nodes[parent].board.clone();
+        board
+            .make_pgn_move(san)
+            .map_err(|e| format!("{}: {}", san, e))?;
+        let fen = board.position_fen();
+        if let Some(&child) = self.nodes[parent]
+            .children
+            .iter()
+            .find(|&&c| self.nodes[c].board.position_fen() == fen)
+        {
+            return Ok(child);
+        }
+        self.nodes.push(Node {
+            san: san.to_string(),
+            board,
+            children: Vec::new(),
+        });
+        let child = self.nodes.len() - 1;
+        self.nodes[parent].children.push(child);
+        Ok(child)
+    }
+
+    // find_move returns child of the node reached with given SAN move, different spellings of
+    // the same move e.g. 'Nf3' and 'Ngf3' are accepted.
+    pub fn find_move(&self, node: usize, san: &str) -> Option<usize> {
+        let mut board = self.nodes[node].board.clone();
+        board.make_pgn_move(san.trim()).ok()?;
+        let fen = board.position_fen();
+        self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.nodes[c].board.position_fen() == fen)
+    }
+}
+
+// DrillScore counts moves recalled by the player.
+#[derive(Debug, Default, PartialEq)]
+pub struct DrillScore {
+    pub correct: usize,
+    pub wrong: usize,
+    pub lines: usize,
+}
+
+// Drill plays opponent side of repertoire and asks player for repertoire moves.
+pub struct Drill<'a, R: Rng> {
+    pub repertoire: &'a Repertoire,
+    pub color: Color,    // color of the player
+    pub randomness: f32, // 0 - opponent always plays main line, 1 - uniformly random variation
+    pub rng: R,
+    pub score: DrillScore,
+}
+
+impl<'a, R: Rng> Drill<'a, R> {
+    // opponent_move picks opponent move, main line or random variation depending on randomness.
+    fn opponent_move(&mut self, node: usize) -> usize {
+        let children = &self.repertoire.nodes[node].children;
+        if children.len() > 1 && self.rng.gen::<f32>() < self.randomness {
+            return children[self.rng.gen_range(0..children.len())];
+        }
+        children[0]
+    }
+
+    // run drills given number of lines, every line is played until repertoire ends. Player can
+    // type 'quit' to stop.
+    pub fn run<I: BufRead, W: Write>(
+        &mut self,
+        input: &mut I,
+        output: &mut W,
+        lines: usize,
+    ) -> io::Result<()> {
+        for _ in 0..lines {
+            writeln!(output, "new line")?;
+            if !self.drill_line(input, output)? {
+                break;
+            }
+            self.score.lines += 1;
+        }
+        writeln!(
+            output,
+            "score: {}/{} moves recalled in {} lines",
+            self.score.correct,
+            self.score.correct + self.score.wrong,
+            self.score.lines
+        )
+    }
+
+    // drill_line returns false when player wants to quit.
+    fn drill_line<I: BufRead, W: Write>(
+        &mut self,
+        input: &mut I,
+        output: &mut W,
+    ) -> io::Result<bool> {
+        let repertoire = self.repertoire;
+        let nodes = &repertoire.nodes;
+        let mut node = 0;
+        while !nodes[node].children.is_empty() {
+            if nodes[node].board.color_to_move != self.color {
+                node = self.opponent_move(node);
+                writeln!(output, "opponent plays {}", nodes[node].san)?;
+                continue;
+            }
+
+            write!(output, "your move: ")?;
+            output.flush()?;
+            let mut answer = String::new();
+            if input.read_line(&mut answer)? == 0 || answer.trim() == "quit" {
+                return Ok(false);
+            }
+            match repertoire.find_move(node, &answer) {
+                Some(child) => {
+                    self.score.correct += 1;
+                    node = child;
+                }
+                None => {
+                    self.score.wrong += 1;
+                    let expected: Vec<&str> = nodes[node]
+                        .children
+                        .iter()
+                        .map(|&c| nodes[c].san.as_str())
+                        .collect();
+                    writeln!(output, "wrong, repertoire move: {}", expected.join(" or "))?;
+                    node = nodes[node].children[0];
+                }
+            }
+        }
+        writeln!(output, "end of line")?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::piece::Color;
+    use crate::repertoire::{Drill, DrillScore, Repertoire};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const REPERTOIRE: &str = "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 3. Bb5
+
+1. e4 e5 2. Ngf3 Nf6 3. Nxe5";
+
+    #[test]
+    fn test_repertoire_tree() {
+        let r = Repertoire::from_pgn(REPERTOIRE).unwrap();
+        // root, e4, e5, c5, Nf3 (after c5), Nf3, Nc6, Bb5, Nf6, Nxe5
+        assert_eq!(r.nodes.len(), 10);
+        let e4 = r.nodes[0].children[0];
+        assert_eq!(r.nodes[e4].children.len(), 2);
+        assert_eq!(r.find_move(e4, "c5"), Some(r.nodes[e4].children[1]));
+        assert_eq!(r.find_move(e4, "d5"), None);
+
+        assert!(Repertoire::from_pgn("1. e4 (1. d4").is_err());
+        assert!(Repertoire::from_pgn("1. e5").is_err());
+    }
+
+    #[test]
+    fn test_drill() {
+        let r = Repertoire::from_pgn(REPERTOIRE).unwrap();
+        let mut drill = Drill {
+            repertoire: &r,
+            color: Color::WHITE,
+            randomness: 0.0,
+            rng: StdRng::seed_from_u64(1),
+            score: DrillScore::default(),
+        };
+        let mut input = "e4\nNf3\nBc4\n".as_bytes();
+        let mut output = Vec::new();
+        drill.run(&mut input, &mut output, 1).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("opponent plays e5"));
+        assert!(output.contains("wrong, repertoire move: Bb5"));
+        assert_eq!(
+            drill.score,
+            DrillScore {
+                correct: 2,
+                wrong: 1,
+                lines: 1
+            }
+        );
+    }
+}
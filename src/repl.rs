@@ -0,0 +1,307 @@
+use crate::board::{Board, Transition};
+use crate::coach::explain;
+use crate::evaluation::Evaluator;
+use crate::notation::{line_to_san, parse_san, to_internal, to_san};
+use crate::search::{Search, SearchProfile};
+use crate::tactics::scan;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+pub const HELP: &str = "commands:
+    position startpos|fen FEN [moves m1 m2 ...]   set position, moves are written like e2e4, e7e8q
+    move m1 [m2 ...]                              make moves
+    undo                                          take back last move
+    d                                             draw board
+    moves                                         list legal moves
+    perft N                                       count leaf nodes of legal moves tree
+    eval                                          static evaluation
+    explain MOVE                                  why move changes evaluation, e.g. explain g4
+    tactics                                       hanging pieces, forks, pins, skewers, discovered attacks
+    compare MOVE MOVE [depth N]                   search both moves, e.g. why Nxe5 is worse than Kd2
+    go depth N                                    search position
+    estimate MS                                   depth 'go' would reach in MS milliseconds
+    quit";
+
+// COMPARE_DEPTH is depth of compare command without depth.
+const COMPARE_DEPTH: usize = 4;
+
+// Repl is a debugging console working on a single position, modeled after Stockfish's one.
+pub struct Repl {
+    board: Board,
+    history: Vec<Board>,
+    evaluator: Box<dyn Evaluator>,
+    profile: SearchProfile, // of the last 'go', speed of the search used by estimate
+}
+
+impl Repl {
+    pub fn new(evaluator: Box<dyn Evaluator>) -> Self {
+        Repl {
+            board: Board::default(),
+            history: Vec::new(),
+            evaluator,
+            profile: SearchProfile::default(),
+        }
+    }
+
+    // run reads commands line by line until 'quit' or end of input.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim() == "quit" {
+                break;
+            }
+            match self.execute(&line) {
+                Ok(out) if out.is_empty() => {}
+                Ok(out) => writeln!(output, "{}", out)?,
+                Err(e) => writeln!(output, "error: {}", e)?,
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    // execute runs single command and returns its output.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            ["position", args @ ..] => self.position(args),
+            ["move", moves @ ..] => self.make_moves(moves).map(|_| String::new()),
+            ["undo"] => match self.history.pop() {
+                Some(board) => {
+                    self.board = board;
+                    Ok(String::new())
+                }
+                None => Err("there is no move to take back".to_string()),
+            },
+            ["d"] => Ok(format!(
+                "{}\n\nfen: {}",
+                self.board.draw(),
+                self.board.to_fen()
+            )),
+            ["moves"] => Ok(self.moves()),
+            ["perft", depth] => self.perft(parse_number(depth)?),
+            ["eval"] => Ok(self.eval()),
+            ["explain", m] => self.explain(m),
+            ["tactics"] => Ok(self.tactics()),
+            ["compare", a, b] => self.compare(a, b, COMPARE_DEPTH),
+            ["compare", a, b, "depth", depth] => self.compare(a, b, parse_number(depth)?),
+            ["go", "depth", depth] => Ok(self.go(parse_number(depth)?)),
+            ["estimate", millis] => self.estimate(parse_number(millis)?),
+            ["help"] => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command '{}', type 'help'", line.trim())),
+        }
+    }
+
+    fn position(&mut self, args: &[&str]) -> Result<String, String> {
+        let moves_at = args
+            .iter()
+            .position(|a| *a == "moves")
+            .unwrap_or(args.len());
+        let mut board = Board::default();
+        match &args[..moves_at] {
+            ["startpos"] => {}
+            ["fen", fen @ ..] => {
+                let fen = fen.join(" ");
+                board.read_fen(&fen)?;
+            }
+            _ => return Err("position must be 'startpos' or 'fen FEN'".to_string()),
+        }
+        self.board = board;
+        self.history.clear();
+        self.make_moves(args.get(moves_at + 1..).unwrap_or(&[]))?;
+        Ok(String::new())
+    }
+
+    // make_moves makes all moves or none of them when any move is illegal.
+    fn make_moves(&mut self, moves: &[&str]) -> Result<(), String> {
+        let mut board = self.board.clone();
+        let mut history = Vec::new();
+        for m in moves {
+            history.push(board.clone());
+            board
+                .make_move_internal_notation(m)
+                .map_err(|e| format!("{}: {}", m, e))?;
+        }
+        self.history.extend(history);
+        self.board = board;
+        Ok(())
+    }
+
+    fn moves(&self) -> String {
+        let transitions = self.board.generate_transitions();
+        let moves: Vec<String> = transitions
+            .iter()
+            .map(|t| format!("{} ({})", to_internal(t), to_san(&self.board, t)))
+            .collect();
+        format!("{} legal moves: {}", moves.len(), moves.join(", "))
+    }
+
+    // perft prints number of nodes after every move, as Stockfish's 'go perft'.
+    fn perft(&self, depth: usize) -> Result<String, String> {
+        if depth == 0 {
+            return Err("perft depth must be at least 1".to_string());
+        }
+        let mut lines = Vec::new();
+        let mut total = 0;
+        for t in self.board.generate_transitions() {
+            let mut child = self.board.clone();
+            child.make_transition(t);
+            let nodes = child.perft(depth - 1);
+            total += nodes;
+            lines.push(format!("{}: {}", to_internal(&t), nodes));
+        }
+        lines.push(format!("\nnodes searched: {}", total));
+        Ok(lines.join("\n"))
+    }
+
+    fn eval(&self) -> String {
+        let terms = self.evaluator.breakdown(&self.board);
+        let mut lines: Vec<String> = terms
+            .iter()
+            .map(|t| format!("{:<16}{:>7.2}", t.name, t.value))
+            .collect();
+        lines.push(format!(
+            "{:<16}{:>7.2}",
+            "total",
+            terms.iter().map(|t| t.value).sum::<f32>()
+        ));
+        lines.join("\n")
+    }
+
+    fn tactics(&self) -> String {
+        let tactics: Vec<String> = scan(&self.board).iter().map(|t| t.to_string()).collect();
+        if tactics.is_empty() {
+            return "no tactics found".to_string();
+        }
+        tactics.join("\n")
+    }
+
+    // parse_move reads legal move given in SAN or internal notation.
+    fn parse_move(&self, m: &str) -> Result<Transition, String> {
+        parse_san(&self.board, m)
+            .ok()
+            .or_else(|| {
+                self.board
+                    .generate_transitions()
+                    .into_iter()
+                    .find(|t| to_internal(t) == m)
+            })
+            .ok_or_else(|| format!("illegal move: {}", m))
+    }
+
+    // explain lists reasons of evaluation changes caused by move given in SAN or internal notation.
+    fn explain(&self, m: &str) -> Result<String, String> {
+        let t = self.parse_move(m)?;
+        let explanations = explain(self.evaluator.as_ref(), &self.board, &t);
+        if explanations.is_empty() {
+            return Ok("nothing important changes".to_string());
+        }
+        let lines: Vec<String> = explanations.iter().map(|e| e.to_string()).collect();
+        Ok(lines.join("\n"))
+    }
+
+    // compare searches two moves to the same depth and prints their scores and lines, the line of
+    // the worse move is its refutation.
+    fn compare(&self, a: &str, b: &str, depth: usize) -> Result<String, String> {
+        let (a, b) = (self.parse_move(a)?, self.parse_move(b)?);
+        let mut search = Search::new(self.evaluator.as_ref());
+        let lines = search.compare(&self.board, a, b, depth)?;
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|l| format!("{} {}", l.score, line_to_san(&self.board, &l.pv)))
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    fn go(&mut self, depth: usize) -> String {
+        let mut search = Search::new(self.evaluator.as_ref());
+        let mut lines = Vec::new();
+        let best = search.iterate(&self.board, depth, 1, |d, best| {
+            lines.push(format!(
+                "info depth {} score {} pv {}",
+                d,
+                best[0].score,
+                line_to_san(&self.board, &best[0].pv)
+            ));
+        });
+        match best.first().and_then(|l| l.pv.first()) {
+            Some(t) => lines.push(format!("bestmove {}", to_internal(t))),
+            None => lines.push("bestmove (none)".to_string()),
+        }
+        self.profile = search.profile;
+        lines.join("\n")
+    }
+
+    // estimate tells what depth a search would reach in given time, judging by speed and
+    // branching factor of the last 'go'.
+    fn estimate(&self, millis: usize) -> Result<String, String> {
+        let budget = Duration::from_millis(millis as u64);
+        match (
+            self.profile.expected_depth(budget),
+            self.profile.nps(),
+            self.profile.branching_factor(),
+        ) {
+            (Some(depth), Some(nps), Some(branching)) => Ok(format!(
+                "expected depth {} in {} ms ({:.0} nodes/s, branching factor {:.1})",
+                depth, millis, nps, branching
+            )),
+            _ => Err("estimate needs 'go' of depth 2 or more first".to_string()),
+        }
+    }
+}
+
+fn parse_number(s: &str) -> Result<usize, String> {
+    s.parse().map_err(|_| format!("invalid number: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::SimpleEvaluator;
+    use crate::repl::Repl;
+
+    #[test]
+    fn test_repl() {
+        let mut repl = Repl::new(Box::new(SimpleEvaluator {}));
+        let input =
+            "position startpos moves e2e4 e7e5\nd\nundo\nd\nperft 2\nmove e2e4 e1e2\nquit\nd\n";
+        let mut output = Vec::new();
+        repl.run(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("fen: rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w"));
+        assert!(output.contains("fen: rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b"));
+        assert!(output.contains("nodes searched: 600"));
+        assert!(output.contains("error: e2e4:"));
+        assert_eq!(output.matches("fen:").count(), 2);
+
+        assert!(repl
+            .execute("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w")
+            .is_ok());
+        assert!(repl
+            .execute("go depth 2")
+            .unwrap()
+            .ends_with("bestmove a1a8"));
+        assert!(repl
+            .execute("estimate 1000")
+            .unwrap()
+            .starts_with("expected depth "));
+        assert!(repl.execute("moves").unwrap().starts_with("17 legal moves"));
+        assert_eq!(repl.execute("tactics").unwrap(), "no tactics found");
+        assert_eq!(
+            repl.execute("explain Ra8").unwrap(),
+            "nothing important changes"
+        );
+        assert!(repl.execute("explain Rb2").is_err());
+        assert!(repl
+            .execute("compare Ra8 a1a2 depth 2")
+            .unwrap()
+            .starts_with("#1 1. Ra8#\n+2.00 1. Ra2"));
+        assert!(repl.execute("compare Ra8 Rb9").is_err());
+        assert!(repl.execute("undo").is_err());
+        assert!(Repl::new(Box::new(SimpleEvaluator {}))
+            .execute("estimate 1000")
+            .is_err());
+        assert!(repl.execute("position fen 8/8").is_err());
+    }
+}
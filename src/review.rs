@@ -0,0 +1,334 @@
+use crate::board::{Board, Transition};
+use crate::evaluation::Evaluator;
+use crate::pgn::{MoveAnnotation, PgnGame, ReplayError};
+use crate::piece::Color;
+use crate::search::{is_zugzwang_risk, Score, Search};
+use rayon::prelude::*;
+
+// MAX_CP limits evaluation used for centipawn loss, so mate scores don't dominate the average.
+const MAX_CP: i32 = 1000;
+
+// Judgement classifies a move by the drop of win probability it caused.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    // from_drop returns judgement for given drop of win probability, thresholds as on Lichess.
+    pub fn from_drop(drop: f32) -> Option<Self> {
+        if drop >= 0.15 {
+            Some(Judgement::Blunder)
+        } else if drop >= 0.10 {
+            Some(Judgement::Mistake)
+        } else if drop >= 0.05 {
+            Some(Judgement::Inaccuracy)
+        } else {
+            None
+        }
+    }
+
+    // symbol returns annotation symbol used in PGN, e.g. '??' for blunder.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+
+    // nag returns Numeric Annotation Glyph of the symbol, e.g. 4 for '??'.
+    pub fn nag(&self) -> u8 {
+        match self {
+            Judgement::Inaccuracy => 6,
+            Judgement::Mistake => 2,
+            Judgement::Blunder => 4,
+        }
+    }
+}
+
+// MoveReview describes quality of a single move.
+#[derive(Clone, Debug)]
+pub struct MoveReview {
+    pub ply: usize,
+    pub san: String,
+    pub color: Color,
+    pub cp_loss: i32,
+    pub accuracy: f32, // 0-100
+    pub judgement: Option<Judgement>,
+}
+
+// PlayerStats sums up moves of one player, can be merged across many games.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerStats {
+    pub moves: usize,
+    pub cp_loss: i64,
+    pub accuracy_sum: f32,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+impl PlayerStats {
+    fn add_move(&mut self, m: &MoveReview) {
+        self.moves += 1;
+        self.cp_loss += m.cp_loss as i64;
+        self.accuracy_sum += m.accuracy;
+        match m.judgement {
+            Some(Judgement::Inaccuracy) => self.inaccuracies += 1,
+            Some(Judgement::Mistake) => self.mistakes += 1,
+            Some(Judgement::Blunder) => self.blunders += 1,
+            None => {}
+        }
+    }
+
+    pub fn merge(&mut self, other: &PlayerStats) {
+        self.moves += other.moves;
+        self.cp_loss += other.cp_loss;
+        self.accuracy_sum += other.accuracy_sum;
+        self.inaccuracies += other.inaccuracies;
+        self.mistakes += other.mistakes;
+        self.blunders += other.blunders;
+    }
+
+    // accuracy returns average accuracy of all moves in percents.
+    pub fn accuracy(&self) -> f32 {
+        if self.moves == 0 {
+            return 100.0;
+        }
+        self.accuracy_sum / self.moves as f32
+    }
+
+    // acpl returns average centipawn loss.
+    pub fn acpl(&self) -> f32 {
+        if self.moves == 0 {
+            return 0.0;
+        }
+        self.cp_loss as f32 / self.moves as f32
+    }
+}
+
+// GameReview is a review of every move of the game.
+pub struct GameReview {
+    pub moves: Vec<MoveReview>,
+    pub white: PlayerStats,
+    pub black: PlayerStats,
+}
+
+// move_accuracy converts drop of win probability (0-1) into accuracy of the move, using formula
+// fitted by Lichess.
+pub fn move_accuracy(drop: f32) -> f32 {
+    let accuracy = 103.1668 * (-0.04354 * drop.max(0.0) * 100.0).exp() - 3.1669;
+    accuracy.clamp(0.0, 100.0)
+}
+
+// PositionEval is evaluation of a position from the point of view of color to move, score and
+// best line are missing when the game is over.
+#[derive(Clone, Debug)]
+struct PositionEval {
+    cp: i32,
+    win: f32,
+    score: Option<Score>,
+    pv: Vec<Transition>,
+}
+
+fn evaluate(board: &Board, evaluator: &dyn Evaluator, depth: usize) -> PositionEval {
+    if board.generate_transitions().is_empty() {
+        return if board.is_in_check() {
+            PositionEval {
+                cp: -MAX_CP,
+                win: 0.0,
+                score: None,
+                pv: Vec::new(),
+            }
+        } else {
+            PositionEval {
+                cp: 0,
+                win: 0.5,
+                score: None,
+                pv: Vec::new(),
+            }
+        };
+    }
+    let line = Search::new(evaluator).analyze(board, depth, 1).remove(0);
+    let score = line.score;
+    let cp = match score {
+        Score::Centipawns(cp) => cp.clamp(-MAX_CP, MAX_CP),
+        Score::Mate(moves) if moves > 0 => MAX_CP,
+        Score::Mate(_) => -MAX_CP,
+    };
+    PositionEval {
+        cp,
+        win: score.win_probability(),
+        score: Some(score),
+        pv: line.pv,
+    }
+}
+
+// GamePositions are positions of the game with their evaluations, the start position first.
+struct GamePositions {
+    boards: Vec<Board>,
+    evals: Vec<PositionEval>,
+}
+
+// evaluate_game replays the game and searches every position in parallel.
+fn evaluate_game(
+    game: &PgnGame,
+    evaluator: &dyn Evaluator,
+    depth: usize,
+) -> Result<GamePositions, ReplayError> {
+    let mut boards = vec![game.start_position().map_err(|e| game.start_error(e))?];
+    game.replay(|board| boards.push(board.clone()))?;
+    let evals = boards
+        .par_iter()
+        .map(|board| evaluate(board, evaluator, depth))
+        .collect();
+    Ok(GamePositions { boards, evals })
+}
+
+// review_game searches every position of the game to given depth and compares played moves with
+// the best ones. Positions are searched in parallel.
+pub fn review_game(
+    game: &PgnGame,
+    evaluator: &dyn Evaluator,
+    depth: usize,
+) -> Result<GameReview, ReplayError> {
+    let GamePositions {
+        boards: positions,
+        evals,
+    } = evaluate_game(game, evaluator, depth)?;
+
+    let mut review = GameReview {
+        moves: Vec::new(),
+        white: PlayerStats::default(),
+        black: PlayerStats::default(),
+    };
+    for (i, san) in game.moves().into_iter().enumerate() {
+        // evaluation after the move is from the opponent point of view.
+        let (before, after) = (&evals[i], &evals[i + 1]);
+        let drop = before.win - (1.0 - after.win);
+        let m = MoveReview {
+            ply: i + 1,
+            san,
+            color: positions[i].color_to_move,
+            cp_loss: (before.cp + after.cp).max(0),
+            accuracy: move_accuracy(drop),
+            judgement: Judgement::from_drop(drop),
+        };
+        if m.color == Color::WHITE {
+            review.white.add_move(&m);
+        } else {
+            review.black.add_move(&m);
+        }
+        review.moves.push(m);
+    }
+    Ok(review)
+}
+
+// annotate_game searches every position of the game and returns annotations of its moves, as in
+// computer analysis of Lichess: evaluation after every move, NAG of inaccuracies, mistakes and
+// blunders, and the best line instead of them. Moves after which the opponent gets at risk of
+// zugzwang, see search::is_zugzwang_risk, are noted, so evaluations of such endings are taken
+// with care.
+pub fn annotate_game(
+    game: &PgnGame,
+    evaluator: &dyn Evaluator,
+    depth: usize,
+) -> Result<Vec<MoveAnnotation>, ReplayError> {
+    let GamePositions { boards, evals } = evaluate_game(game, evaluator, depth)?;
+    Ok(evals
+        .windows(2)
+        .zip(&boards[1..])
+        .enumerate()
+        .map(|(i, (pair, after_move))| {
+            let (before, after) = (&pair[0], &pair[1]);
+            // the opponent was at risk already after the previous move of the same player.
+            let zugzwang = is_zugzwang_risk(after_move)
+                && !i
+                    .checked_sub(1)
+                    .is_some_and(|j| is_zugzwang_risk(&boards[j]));
+            let judgement = Judgement::from_drop(before.win - (1.0 - after.win));
+            MoveAnnotation {
+                eval: after.score.map(|s| s.white_view(after_move.color_to_move)),
+                nag: judgement.map(|j| j.nag()),
+                variation: if judgement.is_some() {
+                    before.pv.clone()
+                } else {
+                    Vec::new()
+                },
+                note: zugzwang.then(|| "zugzwang risk".to_string()),
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::SimpleEvaluator;
+    use crate::pgn::split_games;
+    use crate::pgn::PgnExportOptions;
+    use crate::piece::Color;
+    use crate::review::{annotate_game, move_accuracy, review_game, Judgement, PlayerStats};
+    use crate::search::Score;
+
+    #[test]
+    fn test_move_accuracy() {
+        assert!(move_accuracy(0.0) > 99.9);
+        assert_eq!(move_accuracy(-0.1), move_accuracy(0.0));
+        assert!(move_accuracy(0.1) < 70.0 && move_accuracy(0.1) > 60.0);
+        assert_eq!(move_accuracy(1.0), 0.0);
+        assert_eq!(Judgement::from_drop(0.2), Some(Judgement::Blunder));
+        assert_eq!(Judgement::from_drop(0.01), None);
+    }
+
+    #[test]
+    fn test_review_game() {
+        let games = split_games("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7#");
+        let review = review_game(&games[0], &SimpleEvaluator {}, 2).unwrap();
+        assert_eq!(review.moves.len(), 7);
+        assert_eq!(review.white.moves, 4);
+
+        // 3... Nf6 allows mate in one
+        let nf6 = &review.moves[5];
+        assert_eq!((nf6.san.as_str(), nf6.color), ("Nf6", Color::BLACK));
+        assert_eq!(nf6.judgement, Some(Judgement::Blunder));
+        assert_eq!(review.black.blunders, 1);
+        assert!(review.white.accuracy() > review.black.accuracy());
+
+        let mut total = PlayerStats::default();
+        total.merge(&review.white);
+        total.merge(&review.black);
+        assert_eq!(total.moves, 7);
+    }
+
+    #[test]
+    fn test_annotate_game() {
+        let games = split_games("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0");
+        let annotations = annotate_game(&games[0], &SimpleEvaluator {}, 2).unwrap();
+        assert_eq!(annotations.len(), 7);
+        assert_eq!(annotations[5].nag, Some(4));
+        assert_eq!(annotations[5].eval, Some(Score::Mate(1)));
+        assert!(!annotations[5].variation.is_empty());
+        assert!(annotations[6].eval.is_none());
+
+        let options = PgnExportOptions {
+            line_width: 0,
+            ..Default::default()
+        };
+        let pgn = games[0].export(&options, &annotations).unwrap();
+        assert!(pgn.contains("3... Nf6 $4 {[%eval #1]} (3... "));
+        assert!(pgn.ends_with("4. Qxf7# 1-0\n"));
+        assert!(annotations.iter().all(|a| a.note.is_none()));
+
+        // black is left with the king alone after the trade, the risk is noted once.
+        let games = split_games(
+            "[FEN \"3r4/8/3k4/8/3P4/3K4/8/3R4 w - - 0 1\"]\n\n1. Rb1 Rb8 2. Rxb8 Kc6 3. Rc8+ Kd7 *",
+        );
+        let annotations = annotate_game(&games[0], &SimpleEvaluator {}, 1).unwrap();
+        let notes: Vec<_> = annotations.iter().map(|a| a.note.is_some()).collect();
+        assert_eq!(notes, vec![false, false, true, false, false, false]);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::piece::Color;
+use std::fmt::{Display, Formatter};
+
+// WIN_PROBABILITY_SCALE is a slope of logistic curve mapping centipawns to expected score, the
+// same value as used by Lichess.
+pub const WIN_PROBABILITY_SCALE: f32 = 0.003_682_08;
+
+// Score is a search result from the point of view of color that has the move.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Score {
+    Centipawns(i32),
+    Mate(i32), // number of moves to mate, negative when color that has the move is getting mated.
+}
+
+impl Score {
+    // flip returns the same score from the point of view of the other color.
+    pub fn flip(&self) -> Self {
+        match self {
+            Score::Centipawns(cp) => Score::Centipawns(-cp),
+            Score::Mate(moves) => Score::Mate(-moves),
+        }
+    }
+
+    // win_probability returns expected score of color that has the move, from 0 (loss) to 1 (win).
+    pub fn win_probability(&self) -> f32 {
+        self.win_probability_with_scale(WIN_PROBABILITY_SCALE)
+    }
+
+    // win_probability_with_scale works like win_probability but with custom slope of logistic
+    // curve, higher scale means that the same advantage gives better winning chances.
+    pub fn win_probability_with_scale(&self, scale: f32) -> f32 {
+        match self {
+            Score::Centipawns(cp) => 1.0 / (1.0 + (-scale * *cp as f32).exp()),
+            Score::Mate(moves) if *moves > 0 => 1.0,
+            Score::Mate(_) => 0.0,
+        }
+    }
+
+    // white_view returns score from white point of view, as it's usually presented.
+    pub fn white_view(&self, color_to_move: Color) -> Self {
+        if color_to_move == Color::WHITE {
+            *self
+        } else {
+            self.flip()
+        }
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(cp) => write!(f, "{:+.2}", *cp as f32 / 100.0),
+            Score::Mate(moves) => write!(f, "#{}", moves),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::score::Score;
+
+    #[test]
+    fn test_win_probability() {
+        assert_eq!(Score::Centipawns(0).win_probability(), 0.5);
+        assert_eq!(Score::Mate(3).win_probability(), 1.0);
+        assert_eq!(Score::Mate(-1).win_probability(), 0.0);
+        let p = Score::Centipawns(300).win_probability();
+        assert!(p > 0.74 && p < 0.76, "{}", p);
+        assert!((p + Score::Centipawns(-300).win_probability() - 1.0).abs() < 1e-6);
+        assert!(Score::Centipawns(300).win_probability_with_scale(0.01) > p);
+    }
+}
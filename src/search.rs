@@ -0,0 +1,980 @@
+use crate::board::{Board, Transition, TransitionFlag};
+use crate::checkpoint::Checkpoint;
+use crate::evaluation::Evaluator;
+use crate::piece::{Color, PieceType};
+pub use crate::score::{Score, WIN_PROBABILITY_SCALE};
+use crate::tactics::see;
+use crate::tt::{Bound, TranspositionTable};
+use crate::zobrist::hash;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const MATE: i32 = 100_000;
+const INFINITY: i32 = 1_000_000;
+const MAX_PLY: i32 = 256;
+// DEFAULT_POLL_INTERVAL is a number of nodes between checks of the deadline and stop signal.
+pub const DEFAULT_POLL_INTERVAL: u64 = 1024;
+// NO_PROGRESS_PLIES is a number of plies without pawn moves and captures after which a stable
+// evaluation is suspected to be a fortress and is scaled towards a draw.
+pub const NO_PROGRESS_PLIES: usize = 20;
+// FIFTY_MOVE_PLIES is a number of plies without progress that ends the game in a draw.
+const FIFTY_MOVE_PLIES: usize = 100;
+// DEFAULT_SEE_PRUNING_DEPTH is the largest remaining depth at which quiet moves losing material in
+// exchanges are skipped.
+pub const DEFAULT_SEE_PRUNING_DEPTH: usize = 2;
+// DEFAULT_NULL_MOVE_REDUCTION is a number of plies by which the search after a null move is
+// shallower than after real moves.
+pub const DEFAULT_NULL_MOVE_REDUCTION: usize = 2;
+// ZUGZWANG_MOBILITY is the largest number of legal moves at which color to move may be in zugzwang
+// with any material.
+const ZUGZWANG_MOBILITY: usize = 3;
+// STABLE_DEPTHS is a number of the last depths that must agree on the best move and score.
+const STABLE_DEPTHS: usize = 3;
+// STABLE_MARGIN is the largest difference of scores, in centipawns, still considered stable.
+const STABLE_MARGIN: i32 = 30;
+// BRANCHING_DEPTHS is the largest number of the last depths the effective branching factor is
+// measured over, an even number evens out odd and even depths of alpha-beta.
+const BRANCHING_DEPTHS: usize = 4;
+
+// StopSignal is set from another thread to stop a running search, e.g. on UCI 'stop'.
+pub type StopSignal = Arc<AtomicBool>;
+
+// is_set checks if stop was requested.
+pub fn is_set(stop: &Option<StopSignal>) -> bool {
+    stop.as_ref().is_some_and(|s| s.load(Ordering::Relaxed))
+}
+
+// SearchLine is one of the best lines found by the search.
+#[derive(Clone, Debug)]
+pub struct SearchLine {
+    pub score: Score,
+    pub pv: Vec<Transition>,
+}
+
+// SearchProfile records nodes searched in every completed depth of iterative deepening and time it
+// took, so depth reachable in another search of a similar position can be estimated.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchProfile {
+    pub depth_nodes: Vec<(usize, u64)>, // depth and nodes of its iteration
+    pub elapsed: Duration,              // time of completed depths
+}
+
+impl SearchProfile {
+    // depth returns the last completed depth, 0 before the first one.
+    pub fn depth(&self) -> usize {
+        self.depth_nodes.last().map_or(0, |(depth, _)| *depth)
+    }
+
+    // nps returns nodes searched per second.
+    pub fn nps(&self) -> Option<f64> {
+        let nodes: u64 = self.depth_nodes.iter().map(|(_, nodes)| nodes).sum();
+        let seconds = self.elapsed.as_secs_f64();
+        (nodes > 0 && seconds > 0.0).then(|| nodes as f64 / seconds)
+    }
+
+    // branching_factor returns how many times every depth grows the tree, measured over the last
+    // BRANCHING_DEPTHS depths. It needs at least two completed depths.
+    pub fn branching_factor(&self) -> Option<f64> {
+        let n = self.depth_nodes.len();
+        let k = (n.max(1) - 1).min(BRANCHING_DEPTHS);
+        if k == 0 {
+            return None;
+        }
+        let (first, last) = (self.depth_nodes[n - 1 - k].1, self.depth_nodes[n - 1].1);
+        Some(
+            (last.max(1) as f64 / first.max(1) as f64)
+                .powf(1.0 / k as f64)
+                .max(1.0),
+        )
+    }
+
+    // depth_time returns time of the iteration of given depth: measured for completed depths,
+    // extrapolated from the last one by the branching factor for others.
+    pub fn depth_time(&self, depth: usize) -> Option<Duration> {
+        let nps = self.nps()?;
+        let nodes = match self.depth_nodes.iter().find(|(d, _)| *d == depth) {
+            Some((_, nodes)) => *nodes as f64,
+            None => {
+                let (last, nodes) = *self.depth_nodes.last()?;
+                let plies = depth as f64 - last as f64;
+                nodes as f64 * self.branching_factor()?.powf(plies)
+            }
+        };
+        Some(Duration::from_secs_f64((nodes / nps).min(1e9)))
+    }
+
+    // expected_depth returns the deepest depth iterative deepening completes in the time budget,
+    // at least 1, as the first depth is always completed.
+    pub fn expected_depth(&self, budget: Duration) -> Option<usize> {
+        let mut total = Duration::ZERO;
+        for depth in 1..=MAX_PLY as usize {
+            total += self.depth_time(depth)?;
+            if total > budget {
+                return Some((depth - 1).max(1));
+            }
+        }
+        Some(MAX_PLY as usize)
+    }
+}
+
+// Search is a negamax search with alpha-beta pruning and quiescence search at the leaves.
+pub struct Search<'a> {
+    evaluator: &'a dyn Evaluator,
+    pub nodes: u64,
+    // deadline stops iterative deepening, lines of the last completed depth are returned.
+    pub deadline: Option<Instant>,
+    // stop ends the search immediately, even in the first depth, so there may be no lines.
+    pub stop: Option<StopSignal>,
+    // max_nodes stops iterative deepening like deadline, when nodes reaches it.
+    pub max_nodes: Option<u64>,
+    pub poll_interval: u64, // nodes between checks of the deadline and stop signal
+    // quiet_plies is a number of plies without pawn moves and captures before the searched
+    // position, used to detect lack of progress. Halfmove clock of the position is used when it's
+    // larger. Positions in the tree whose clock reaches fifty-move rule are draws.
+    pub quiet_plies: usize,
+    // history has hashes of positions played in the game before the searched one, see
+    // zobrist::hash. Positions in the tree repeating them, or positions earlier in the line, are
+    // draws.
+    pub history: Vec<u64>,
+    pub tt: TranspositionTable,
+    // see_pruning_depth is the largest remaining depth at which quiet moves that don't give check
+    // and lose material in exchanges on their destination, see tactics::see, are skipped. Moves
+    // of the piece onto squares defended by cheaper pieces are rarely good so close to the
+    // leaves. 0 turns the pruning off.
+    pub see_pruning_depth: usize,
+    // null_move_reduction is a reduction of null move pruning: when color to move passes the move
+    // and a search shallower by the reduction still fails high, the node is cut off without
+    // searching its moves. It's not tried in positions at risk of zugzwang, see
+    // is_zugzwang_risk, where passing would be better than any move. 0 turns the pruning off.
+    pub null_move_reduction: usize,
+    // contempt is a score in centipawns color to move at the root loses with a draw, and its
+    // opponent gains. Positive contempt avoids draws with weaker opponents, negative one accepts
+    // them against stronger ones.
+    pub contempt: i32,
+    // checkpoint is a file where the search is saved after every completed depth, so it can be
+    // resumed later. The first error of saving is kept in checkpoint_error and stops saving.
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_error: Option<io::Error>,
+    // profile of the last iterative deepening, an iteration that wouldn't complete before the
+    // deadline is not started, as its lines would be lost.
+    pub profile: SearchProfile,
+    stopped: bool,
+    stoppable: bool,
+    next_poll: u64, // nodes count at which the deadline and stop signal are checked
+    line: Vec<u64>, // hashes of positions from the root to the searched node
+}
+
+impl<'a> Search<'a> {
+    pub fn new(evaluator: &'a dyn Evaluator) -> Self {
+        Search {
+            evaluator,
+            nodes: 0,
+            deadline: None,
+            stop: None,
+            max_nodes: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            quiet_plies: 0,
+            history: Vec::new(),
+            tt: TranspositionTable::default(),
+            see_pruning_depth: DEFAULT_SEE_PRUNING_DEPTH,
+            null_move_reduction: DEFAULT_NULL_MOVE_REDUCTION,
+            contempt: 0,
+            checkpoint: None,
+            checkpoint_error: None,
+            profile: SearchProfile::default(),
+            stopped: false,
+            stoppable: false,
+            next_poll: 0,
+            line: Vec::new(),
+        }
+    }
+
+    // analyze searches position up to given depth and returns 'multipv' best lines.
+    pub fn analyze(&mut self, board: &Board, depth: usize, multipv: usize) -> Vec<SearchLine> {
+        self.iterate(board, depth, multipv, |_, _| {})
+    }
+
+    // best_line returns the best move with its principal variation and score found at given depth,
+    // None when there are no legal moves.
+    pub fn best_line(&mut self, board: &Board, depth: usize) -> Option<SearchLine> {
+        self.analyze(board, depth, 1).into_iter().next()
+    }
+
+    // iterate runs iterative deepening, on_depth is called with best lines after every depth.
+    // Scores of lines that make no progress are scaled towards a draw, see no_progress_scale.
+    pub fn iterate<F>(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        multipv: usize,
+        on_depth: F,
+    ) -> Vec<SearchLine>
+    where
+        F: FnMut(usize, &[SearchLine]),
+    {
+        self.deepen(board, 0, Vec::new(), depth, multipv, on_depth)
+    }
+
+    // compare searches two moves to the same depth and returns their lines in the given order, so
+    // the line of a rejected move shows its refutation. Both scores are exact, not just bounds of
+    // the worse move. A deadline stops it like iterate, with lines of the last completed depth.
+    pub fn compare(
+        &mut self,
+        board: &Board,
+        a: Transition,
+        b: Transition,
+        depth: usize,
+    ) -> Result<[SearchLine; 2], &'static str> {
+        let legal = board.generate_transitions();
+        if ![a, b].iter().all(|t| legal.iter().any(|l| same(l, t))) {
+            return Err("move is not legal");
+        }
+        let mut root_moves = vec![a];
+        if !same(&a, &b) {
+            root_moves.push(b);
+        }
+        self.stopped = false;
+        self.next_poll = self.nodes;
+        let mut lines = Vec::new();
+        for d in 1..=depth.max(1) {
+            self.stoppable = d > 1;
+            let iteration = self.search_root(board, &root_moves, d, root_moves.len());
+            if self.stopped {
+                break;
+            }
+            lines = iteration;
+        }
+        // stop signal may interrupt even the first depth.
+        let line = |t: &Transition| lines.iter().find(|l| same(&l.pv[0], t)).cloned();
+        match (line(&a), line(&b)) {
+            (Some(a), Some(b)) => Ok([a, b]),
+            _ => Err("search was stopped"),
+        }
+    }
+
+    // resume continues iterative deepening of the checkpoint from the next depth, with its
+    // transposition table. on_depth is called with lines of the checkpoint first.
+    pub fn resume<F>(
+        &mut self,
+        board: &Board,
+        checkpoint: Checkpoint,
+        depth: usize,
+        multipv: usize,
+        mut on_depth: F,
+    ) -> Vec<SearchLine>
+    where
+        F: FnMut(usize, &[SearchLine]),
+    {
+        self.tt = checkpoint.tt;
+        self.nodes = checkpoint.nodes;
+        if !checkpoint.lines.is_empty() {
+            on_depth(checkpoint.depth, &checkpoint.lines);
+        }
+        self.deepen(
+            board,
+            checkpoint.depth,
+            checkpoint.lines,
+            depth,
+            multipv,
+            on_depth,
+        )
+    }
+
+    // deepen searches depths after the completed one, whose lines are given.
+    fn deepen<F>(
+        &mut self,
+        board: &Board,
+        completed: usize,
+        lines: Vec<SearchLine>,
+        depth: usize,
+        multipv: usize,
+        mut on_depth: F,
+    ) -> Vec<SearchLine>
+    where
+        F: FnMut(usize, &[SearchLine]),
+    {
+        let mut root_moves = board.generate_transitions();
+        if root_moves.is_empty() {
+            return Vec::new(); // the game is over, there's nothing to choose from.
+        }
+        order_transitions(&mut root_moves);
+        put_first(&mut root_moves, &lines);
+        let mut scaled = lines;
+        let mut history = Vec::new(); // best move and score of every completed depth
+        self.stopped = false;
+        self.next_poll = self.nodes;
+        self.profile = SearchProfile::default();
+
+        for d in completed + 1..=depth.max(1) {
+            let started = Instant::now();
+            if is_set(&self.stop)
+                || d > 1
+                    && (self.deadline.is_some_and(|deadline| {
+                        started >= deadline
+                            || self
+                                .profile
+                                .depth_time(d)
+                                .is_some_and(|time| started + time > deadline)
+                    }) || self.max_nodes.is_some_and(|m| self.nodes >= m))
+            {
+                break;
+            }
+            // the first depth is always completed, so there's a move to play.
+            self.stoppable = d > 1;
+            let nodes = self.nodes;
+            let iteration = self.search_root(board, &root_moves, d, multipv.max(1));
+            if self.stopped {
+                break;
+            }
+            self.profile.depth_nodes.push((d, self.nodes - nodes));
+            self.profile.elapsed += started.elapsed();
+            let lines = iteration;
+            // best lines of this iteration are searched first in the next one.
+            put_first(&mut root_moves, &lines);
+            self.save_checkpoint(board, d, &lines);
+            history.push((lines[0].pv[0], lines[0].score));
+            let scale = self.no_progress_scale(board, &history, &lines[0].pv);
+            scaled = lines
+                .iter()
+                .map(|line| SearchLine {
+                    score: match line.score {
+                        Score::Centipawns(cp) => Score::Centipawns((cp as f32 * scale) as i32),
+                        mate => mate,
+                    },
+                    pv: line.pv.clone(),
+                })
+                .collect();
+            on_depth(d, &scaled);
+        }
+        scaled
+    }
+
+    // save_checkpoint writes the search to checkpoint file, if there's one.
+    fn save_checkpoint(&mut self, board: &Board, depth: usize, lines: &[SearchLine]) {
+        let path = match &self.checkpoint {
+            Some(path) if self.checkpoint_error.is_none() => path,
+            _ => return,
+        };
+        // the table is moved to checkpoint for a while rather than copied, it may be large.
+        let checkpoint = Checkpoint {
+            hash: hash(board),
+            depth,
+            nodes: self.nodes,
+            lines: lines.to_vec(),
+            tt: std::mem::take(&mut self.tt),
+        };
+        let saved = checkpoint.save(path, board);
+        self.tt = checkpoint.tt;
+        self.checkpoint_error = saved.err();
+    }
+
+    // no_progress_scale returns factor of scores of the best line, lower than 1 when nothing
+    // changes: there were at least NO_PROGRESS_PLIES quiet plies, the last STABLE_DEPTHS depths
+    // agree on the best move and score, and the best line doesn't move a pawn nor capture. The
+    // factor drops to 0 when the line reaches fifty-move rule.
+    fn no_progress_scale(
+        &self,
+        board: &Board,
+        history: &[(Transition, Score)],
+        pv: &[Transition],
+    ) -> f32 {
+        let quiet_plies = self.quiet_plies.max(board.halfmove_clock());
+        if quiet_plies < NO_PROGRESS_PLIES || history.len() < STABLE_DEPTHS {
+            return 1.0;
+        }
+        let recent = &history[history.len() - STABLE_DEPTHS..];
+        let stable = recent.iter().all(|(t, score)| {
+            same(t, &recent[0].0)
+                && match (score, recent[0].1) {
+                    (Score::Centipawns(a), Score::Centipawns(b)) => (a - b).abs() <= STABLE_MARGIN,
+                    _ => false,
+                }
+        });
+        let mut b = board.clone();
+        let quiet_line = pv.iter().all(|t| {
+            let progress = makes_progress(&b, t);
+            b.make_transition(*t);
+            !progress
+        });
+        if !stable || !quiet_line {
+            return 1.0;
+        }
+        let left = FIFTY_MOVE_PLIES.saturating_sub(quiet_plies + pv.len());
+        let range = FIFTY_MOVE_PLIES - NO_PROGRESS_PLIES;
+        (left as f32 / range as f32).min(1.0)
+    }
+
+    fn search_root(
+        &mut self,
+        board: &Board,
+        root_moves: &[Transition],
+        depth: usize,
+        multipv: usize,
+    ) -> Vec<SearchLine> {
+        let mut lines: Vec<SearchLine> = Vec::new();
+        self.line = vec![hash(board)];
+        for _ in 0..multipv.min(root_moves.len()) {
+            let mut alpha = -INFINITY;
+            let mut best_pv = Vec::new();
+            for t in root_moves {
+                if lines.iter().any(|l| same(&l.pv[0], t)) {
+                    continue; // already reported in better line.
+                }
+                let mut child = board.clone();
+                child.make_transition(*t);
+                let mut pv = Vec::new();
+                let score = -self.negamax(&child, depth - 1, -INFINITY, -alpha, 1, &mut pv);
+                if score > alpha || best_pv.is_empty() {
+                    alpha = score;
+                    best_pv = vec![*t];
+                    best_pv.extend(pv);
+                }
+            }
+            lines.push(SearchLine {
+                score: score_from_internal(alpha),
+                pv: best_pv,
+            });
+        }
+        lines
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        ply: i32,
+        pv: &mut Vec<Transition>,
+    ) -> i32 {
+        if self.is_repetition(board) {
+            return self.draw_score(ply);
+        }
+        if depth == 0 {
+            return self.quiesce(board, alpha, beta);
+        }
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let key = hash(board);
+        let entry = self.tt.get(key);
+        if let Some(entry) = entry.filter(|e| e.depth() >= depth) {
+            let score = from_tt(entry.score(), ply);
+            let cutoff = match entry.bound() {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                pv.clear();
+                pv.extend(entry.best_move(board));
+                return score;
+            }
+        }
+
+        let mut moves = board.generate_transitions();
+        if moves.is_empty() {
+            if board.is_in_check() {
+                return -(MATE - ply); // check mate, the sooner the worse.
+            }
+            return self.draw_score(ply); // stalemate
+        }
+        // mate on the last move before fifty-move rule still counts.
+        if board.halfmove_clock() >= FIFTY_MOVE_PLIES {
+            return self.draw_score(ply);
+        }
+        // after a null move, the other color moves, so null moves never follow each other.
+        let reduction = self.null_move_reduction;
+        if reduction > 0
+            && depth > reduction
+            && beta.abs() < MATE - MAX_PLY
+            && board.last_move().is_some()
+            && !board.is_in_check()
+            && !zugzwang_risk(board, moves.len())
+            && self.evaluate(board) >= beta
+        {
+            let mut passed = board.clone();
+            passed.make_null_move();
+            let mut null_pv = Vec::new();
+            let score = -self.negamax(
+                &passed,
+                depth - 1 - reduction,
+                -beta,
+                -beta + 1,
+                ply + 1,
+                &mut null_pv,
+            );
+            if score >= beta && !self.stopped {
+                pv.clear();
+                return beta;
+            }
+        }
+        order_transitions(&mut moves);
+        // best move of a shallower search is still the most likely to cut off.
+        if let Some(pos) = entry.and_then(|e| moves.iter().position(|t| e.is_best_move(t))) {
+            moves[..=pos].rotate_right(1);
+        }
+
+        let original_alpha = alpha;
+        let prune = depth <= self.see_pruning_depth && !board.is_in_check();
+        self.line.push(key);
+        for (i, t) in moves.iter().enumerate() {
+            let mut child = board.clone();
+            child.make_transition(*t);
+            // the first move is always searched, so there's a score to return.
+            if prune && i > 0 && !is_noisy(t) && !child.is_in_check() && see(board, t) < 0 {
+                continue;
+            }
+            let mut child_pv = Vec::new();
+            let score = -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1, &mut child_pv);
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(*t);
+                pv.extend(child_pv);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        self.line.pop();
+        // results of interrupted search are not reliable.
+        if !self.stopped {
+            let bound = if alpha >= beta {
+                Bound::Lower
+            } else if alpha > original_alpha {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            self.tt
+                .store(key, depth, to_tt(alpha, ply), bound, pv.first());
+        }
+        alpha
+    }
+
+    // is_repetition checks if the position was played in the game or earlier in the searched line.
+    // A position can repeat only after four plies without pawn moves and captures.
+    fn is_repetition(&self, board: &Board) -> bool {
+        if board.halfmove_clock() < 4 {
+            return false;
+        }
+        let key = hash(board);
+        self.history.iter().chain(&self.line).any(|h| *h == key)
+    }
+
+    // draw_score returns score of a draw for color to move at given ply, colors to move at the root
+    // are at even plies.
+    fn draw_score(&self, ply: i32) -> i32 {
+        if ply % 2 == 0 {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    // should_stop checks the stop signal, the deadline and max_nodes every poll_interval nodes, in
+    // every node of the main search and quiescence, so the search returns soon after any of them.
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes >= self.next_poll {
+            self.next_poll = self.nodes + self.poll_interval.max(1);
+            self.stopped = is_set(&self.stop)
+                || self.stoppable
+                    && (self.deadline.is_some_and(|d| Instant::now() >= d)
+                        || self.max_nodes.is_some_and(|m| self.nodes >= m));
+        }
+        self.stopped
+    }
+
+    // quiesce searches only captures and promotions to avoid evaluating unstable positions.
+    fn quiesce(&mut self, board: &Board, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        let stand_pat = self.evaluate(board);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Transition> = board
+            .generate_transitions()
+            .into_iter()
+            .filter(is_noisy)
+            .collect();
+        order_transitions(&mut moves);
+        for t in &moves {
+            let mut child = board.clone();
+            child.make_transition(*t);
+            let score = -self.quiesce(&child, -beta, -alpha);
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    // evaluate returns static evaluation in centipawns from the point of view of color to move.
+    fn evaluate(&self, board: &Board) -> i32 {
+        let eval = (self.evaluator.evaluate(board) * 100.0) as i32;
+        if board.color_to_move == Color::WHITE {
+            eval
+        } else {
+            -eval
+        }
+    }
+}
+
+// is_zugzwang_risk checks if color to move may be in zugzwang, when any move makes its position
+// worse: it has only the king and pawns, or hardly any legal moves. Checkmate and stalemate are
+// not.
+pub fn is_zugzwang_risk(board: &Board) -> bool {
+    zugzwang_risk(board, board.generate_transitions().len())
+}
+
+fn zugzwang_risk(board: &Board, mobility: usize) -> bool {
+    let color = board.color_to_move;
+    let pieces = board
+        .squares()
+        .iter()
+        .any(|p| p.color == color && p.p_type != PieceType::KING && p.p_type != PieceType::PAWN);
+    mobility > 0 && (!pieces || mobility <= ZUGZWANG_MOBILITY)
+}
+
+// makes_progress checks if transition moves a pawn or captures, which resets fifty-move rule.
+pub fn makes_progress(board: &Board, t: &Transition) -> bool {
+    board.piece_at(t.from).p_type == PieceType::PAWN
+        || !board.piece_at(t.to).is_none()
+        || t.flag == TransitionFlag::EnPassant
+}
+
+// to_tt converts mate scores to distance from the stored position rather than from the root, so
+// they stay correct when the position is reached at another ply. from_tt converts them back.
+fn to_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE - MAX_PLY {
+        score + ply
+    } else if score <= -(MATE - MAX_PLY) {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn from_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE - MAX_PLY {
+        score - ply
+    } else if score <= -(MATE - MAX_PLY) {
+        score + ply
+    } else {
+        score
+    }
+}
+
+// score_from_internal converts search score to Score, scores close to MATE are mates.
+fn score_from_internal(value: i32) -> Score {
+    if value.abs() >= MATE - MAX_PLY {
+        let moves = (MATE - value.abs() + 1) / 2;
+        return if value > 0 {
+            Score::Mate(moves)
+        } else {
+            Score::Mate(-moves)
+        };
+    }
+    Score::Centipawns(value)
+}
+
+// put_first moves first moves of lines to the front of root moves, in order of lines.
+fn put_first(root_moves: &mut Vec<Transition>, lines: &[SearchLine]) {
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(pos) = root_moves.iter().position(|t| same(t, &line.pv[0])) {
+            let t = root_moves.remove(pos);
+            root_moves.insert(i, t);
+        }
+    }
+}
+
+fn same(a: &Transition, b: &Transition) -> bool {
+    a.from == b.from && a.to == b.to && a.promotion == b.promotion
+}
+
+// is_noisy checks if transition changes material: capture, en passant or promotion.
+fn is_noisy(t: &Transition) -> bool {
+    !t.to_piece.is_none()
+        || t.flag == TransitionFlag::EnPassant
+        || t.flag == TransitionFlag::Promotion
+}
+
+// order_transitions puts captures and promotions before quiet moves. They are sorted by the most
+// valuable victim and then by the least valuable attacker (MVV-LVA), so e.g. pawn takes queen is
+// tried before queen takes queen and both before pawn takes rook.
+fn order_transitions(moves: &mut [Transition]) {
+    moves.sort_by_key(|t| {
+        if !is_noisy(t) {
+            return 0;
+        }
+        let victim = match t.flag {
+            TransitionFlag::EnPassant => PieceType::PAWN.points(),
+            _ => t.to_piece.p_type.points(),
+        };
+        // king captures last, it has 200 points.
+        let attacker = t.from_piece.p_type.points().min(10);
+        -((victim + t.promotion.points()) * 16 - attacker) - 16
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::SimpleEvaluator;
+    use crate::notation::{parse_san, to_san};
+    use crate::search::{is_zugzwang_risk, order_transitions, Score, Search, SearchProfile};
+    use crate::zobrist::hash;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let mut b = Board::default();
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w").unwrap();
+        let e = SimpleEvaluator {};
+        let lines = Search::new(&e).analyze(&b, 2, 1);
+        assert_eq!(lines[0].score, Score::Mate(1));
+        assert_eq!(to_san(&b, &lines[0].pv[0]), "Ra8#");
+    }
+
+    #[test]
+    fn test_best_line() {
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/3q4/8/8/3R4/4K3 w").unwrap();
+        let e = SimpleEvaluator {};
+        let mut search = Search::new(&e);
+        let line = search.best_line(&b, 3).unwrap();
+        assert_eq!(to_san(&b, &line.pv[0]), "Rxd5");
+        assert_eq!(line.pv.len(), 3);
+        // mated side has nothing to choose from.
+        b.read_fen("R5k1/5ppp/8/8/8/8/8/6K1 b").unwrap();
+        assert!(search.best_line(&b, 2).is_none());
+        assert!(search.analyze(&b, 2, 1).is_empty());
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut b = Board::default();
+        b.read_fen("4k3/8/3p4/4p3/8/5N2/8/4K3 w").unwrap();
+        let e = SimpleEvaluator {};
+        let mut search = Search::new(&e);
+        let moves = b.generate_transitions();
+        let find = |san: &str| *moves.iter().find(|t| to_san(&b, t) == san).unwrap();
+        let [capture, quiet] = search.compare(&b, find("Nxe5"), find("Kd2"), 3).unwrap();
+        assert_eq!(to_san(&b, &quiet.pv[0]), "Kd2");
+        assert_eq!(quiet.score, Score::Centipawns(100));
+        // the pawn is defended, the refutation is the recapture.
+        assert_eq!(capture.score, Score::Centipawns(-100));
+        let mut after = b.clone();
+        after.make_transition(capture.pv[0]);
+        assert_eq!(to_san(&after, &capture.pv[1]), "dxe5");
+        let [same, _] = search.compare(&b, find("Kd2"), find("Kd2"), 1).unwrap();
+        assert_eq!(to_san(&b, &same.pv[0]), "Kd2");
+        let mut illegal = find("Kd2");
+        illegal.to = 63;
+        assert_eq!(
+            search.compare(&b, illegal, find("Kd2"), 1).err(),
+            Some("move is not legal")
+        );
+    }
+
+    #[test]
+    fn test_no_progress_scale() {
+        let mut b = Board::default();
+        b.read_fen("8/8/8/4k3/8/8/8/KR6 w").unwrap();
+        let e = SimpleEvaluator {};
+        let lines = Search::new(&e).analyze(&b, 3, 1);
+        assert_eq!(lines[0].score, Score::Centipawns(500));
+
+        let mut search = Search::new(&e);
+        search.quiet_plies = 60;
+        let lines = search.analyze(&b, 3, 1);
+        match lines[0].score {
+            Score::Centipawns(cp) => assert!(cp > 0 && cp < 500, "score {}", cp),
+            score => panic!("unexpected score {:?}", score),
+        }
+
+        // every move reaches fifty-move rule.
+        b.read_fen("8/8/8/4k3/8/8/8/KR6 w - - 99 80").unwrap();
+        let lines = Search::new(&e).analyze(&b, 3, 1);
+        assert_eq!(lines[0].score, Score::Centipawns(0));
+    }
+
+    #[test]
+    fn test_multipv_and_capture() {
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/3q4/8/8/3R4/4K3 w").unwrap();
+        let e = SimpleEvaluator {};
+        let lines = Search::new(&e).analyze(&b, 2, 3);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(to_san(&b, &lines[0].pv[0]), "Rxd5");
+        assert_eq!(lines[0].score, Score::Centipawns(500));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let e = SimpleEvaluator {};
+        let mut search = Search::new(&e);
+        search.deadline = Some(Instant::now());
+        let mut depths = Vec::new();
+        let lines = search.iterate(&Board::default(), 20, 1, |d, _| depths.push(d));
+        // depth 1 is completed even when there's no time left.
+        assert_eq!(depths, vec![1]);
+        assert_eq!(lines.len(), 1);
+
+        // stop ends the search even in the first depth.
+        let mut search = Search::new(&e);
+        search.stop = Some(Arc::new(AtomicBool::new(true)));
+        search.poll_interval = 1;
+        let lines = search.iterate(&Board::default(), 20, 1, |_, _| {});
+        assert!(lines.is_empty());
+        assert!(search.nodes <= 1);
+    }
+
+    #[test]
+    fn test_search_profile() {
+        let profile = SearchProfile {
+            depth_nodes: vec![(1, 25), (2, 100), (3, 400), (4, 2000), (5, 6400)],
+            elapsed: Duration::from_millis(8925),
+        };
+        assert_eq!(profile.depth(), 5);
+        assert!((profile.nps().unwrap() - 1000.0).abs() < 1e-6);
+        assert!((profile.branching_factor().unwrap() - 4.0).abs() < 1e-9);
+        let millis = |depth| profile.depth_time(depth).unwrap().as_secs_f64() * 1000.0;
+        assert!((millis(4) - 2000.0).abs() < 1e-3);
+        assert!((millis(6) - 25_600.0).abs() < 1e-3);
+        assert_eq!(profile.expected_depth(Duration::from_secs(30)), Some(5));
+        assert_eq!(profile.expected_depth(Duration::from_secs(40)), Some(6));
+        assert_eq!(profile.expected_depth(Duration::ZERO), Some(1));
+        assert_eq!(SearchProfile::default().expected_depth(Duration::MAX), None);
+
+        let e = SimpleEvaluator {};
+        let mut search = Search::new(&e);
+        search.analyze(&Board::default(), 4, 1);
+        let depths: Vec<usize> = search.profile.depth_nodes.iter().map(|(d, _)| *d).collect();
+        assert_eq!(depths, vec![1, 2, 3, 4]);
+        let nodes: u64 = search.profile.depth_nodes.iter().map(|(_, n)| n).sum();
+        assert_eq!(nodes, search.nodes);
+        // an iteration that can't complete in time is not started.
+        let deadline = Instant::now() + search.profile.depth_time(5).unwrap() / 100;
+        let mut search = Search::new(&e);
+        search.deadline = Some(deadline);
+        let mut depths = Vec::new();
+        search.iterate(&Board::default(), 20, 1, |d, _| depths.push(d));
+        assert!(depths.len() < 5);
+        assert_eq!(search.profile.depth_nodes.len(), depths.len());
+    }
+
+    #[test]
+    fn test_getting_mated() {
+        let mut b = Board::default();
+        b.read_fen("r5k1/5ppp/8/8/8/8/5PPP/6K1 b").unwrap();
+        let e = SimpleEvaluator {};
+        let lines = Search::new(&e).analyze(&b, 3, 1);
+        assert_eq!(lines[0].score, Score::Mate(1));
+        assert_eq!(lines[0].score.flip(), Score::Mate(-1));
+        assert_eq!(format!("{}", Score::Centipawns(-35)), "-0.35");
+    }
+
+    #[test]
+    fn test_see_pruning() {
+        let mut b = Board::default();
+        b.read_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+            .unwrap();
+        let e = SimpleEvaluator {};
+        let mut pruned = Search::new(&e);
+        let lines = pruned.analyze(&b, 4, 1);
+        let mut full = Search::new(&e);
+        full.see_pruning_depth = 0;
+        let full_lines = full.analyze(&b, 4, 1);
+        assert_eq!(lines[0].score, full_lines[0].score);
+        assert!(pruned.nodes < full.nodes);
+    }
+
+    #[test]
+    fn test_null_move_pruning() {
+        let mut b = Board::default();
+        assert!(!is_zugzwang_risk(&b));
+        let e = SimpleEvaluator {};
+        let mut pruned = Search::new(&e);
+        pruned.analyze(&b, 4, 1);
+        let mut full = Search::new(&e);
+        full.null_move_reduction = 0;
+        full.analyze(&b, 4, 1);
+        assert!(pruned.nodes < full.nodes);
+
+        // black's rook is locked in, only Kc8, bxa6 and axb6 are legal.
+        b.read_fen("rk6/pp6/PP6/8/8/8/8/7K b - - 0 1").unwrap();
+        assert!(is_zugzwang_risk(&b));
+        // no null moves in pawn endings, the search is the same as without the pruning.
+        b.read_fen("8/8/3k4/3p4/3P4/3K4/8/8 w - - 0 1").unwrap();
+        assert!(is_zugzwang_risk(&b));
+        let mut pruned = Search::new(&e);
+        let lines = pruned.analyze(&b, 5, 1);
+        let mut full = Search::new(&e);
+        full.null_move_reduction = 0;
+        let full_lines = full.analyze(&b, 5, 1);
+        assert_eq!(lines[0].score, full_lines[0].score);
+        assert_eq!(pruned.nodes, full.nodes);
+    }
+
+    #[test]
+    fn test_contempt() {
+        // every move reaches fifty-move rule, so the position is a draw.
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        let e = SimpleEvaluator {};
+        let mut search = Search::new(&e);
+        assert_eq!(search.analyze(&b, 2, 1)[0].score, Score::Centipawns(0));
+        search.contempt = 30;
+        assert_eq!(search.analyze(&b, 2, 1)[0].score, Score::Centipawns(-30));
+        search.contempt = -30;
+        assert_eq!(search.analyze(&b, 2, 1)[0].score, Score::Centipawns(30));
+    }
+
+    #[test]
+    fn test_repetition() {
+        // black is a queen down, but the knight can go back to the position played before.
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/8/8/5n2/8/K2Q4 b - - 4 10").unwrap();
+        let back = parse_san(&b, "Ng1").unwrap();
+        let other = parse_san(&b, "Ne5").unwrap();
+        let mut played = b.clone();
+        played.make_transition(back);
+        let e = SimpleEvaluator {};
+        let mut search = Search::new(&e);
+        let losing = |score| matches!(score, Score::Centipawns(cp) if cp < -300);
+        let [back_line, other_line] = search.compare(&b, back, other, 2).unwrap();
+        assert!(losing(back_line.score) && losing(other_line.score));
+
+        search.history = vec![hash(&played)];
+        search.contempt = 20;
+        let [back_line, other_line] = search.compare(&b, back, other, 2).unwrap();
+        assert_eq!(back_line.score, Score::Centipawns(-20)); // a draw, with contempt
+        assert!(losing(other_line.score));
+    }
+
+    #[test]
+    fn test_order_transitions() {
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/1r1q4/2P5/8/8/3QK3 w - - 0 1").unwrap();
+        let mut moves = b.generate_transitions();
+        order_transitions(&mut moves);
+        let sans: Vec<_> = moves.iter().map(|t| to_san(&b, t)).collect();
+        assert_eq!(sans[..3], ["cxd5", "Qxd5", "cxb5"]);
+        assert!(sans[3..].iter().all(|san| !san.contains('x')));
+    }
+}
@@ -0,0 +1,891 @@
+use crate::board::Board;
+use crate::evaluation::Evaluator;
+use crate::notation::{line_to_san, to_internal, to_san};
+use crate::search::{self, Search, SearchLine, StopSignal};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::WebSocket;
+
+const DEFAULT_DEPTH: usize = 4;
+const MAX_DEPTH: usize = 10;
+// ACCEPT_POLL is a time between checks of the shutdown signal while waiting for connections and
+// for searches to finish.
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+// CACHE_ENTRIES is a number of analyses kept by the server, a few opening books of positions.
+const CACHE_ENTRIES: usize = 4096;
+
+// Score is written from white point of view, e.g. {"cp": 35} or {"mate": -2}, so it can be
+// shown directly on evaluation bar.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Score {
+    Cp(i32),
+    Mate(i32),
+}
+
+impl Score {
+    fn new(board: &Board, score: search::Score) -> Self {
+        match score.white_view(board.color_to_move) {
+            search::Score::Centipawns(cp) => Score::Cp(cp),
+            search::Score::Mate(moves) => Score::Mate(moves),
+        }
+    }
+}
+
+// Info is a single line found by the search at given depth.
+#[derive(Serialize, Debug)]
+pub struct Info {
+    pub depth: usize,
+    pub multipv: usize,
+    pub score: Score,
+    pub win_probability: f32, // white expected score, e.g. for evaluation bar
+    pub pv: Vec<String>,
+    pub san: String,
+}
+
+impl Info {
+    fn new(board: &Board, depth: usize, multipv: usize, line: &SearchLine) -> Self {
+        Info {
+            depth,
+            multipv,
+            score: Score::new(board, line.score),
+            win_probability: line.score.white_view(board.color_to_move).win_probability(),
+            pv: line.pv.iter().map(to_internal).collect(),
+            san: line_to_san(board, &line.pv),
+        }
+    }
+}
+
+// Event is a message sent over WebSocket.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Event {
+    Info(Info),
+    BestMove {
+        fen: String,
+        r#move: String,
+        san: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+// Command is a message received over WebSocket. When fen is given ('startpos' or FEN) the
+// position is set up from it, otherwise moves are made in the current position, so client can
+// send game moves one by one. Position is analysed after every command.
+#[derive(Deserialize, Debug, Default)]
+pub struct Command {
+    pub fen: Option<String>,
+    #[serde(default)]
+    pub moves: Vec<String>,
+    pub depth: Option<usize>,
+    pub multipv: Option<usize>,
+}
+
+impl Command {
+    // apply returns position after the command, board is not changed when command is invalid.
+    pub fn apply(&self, board: &Board) -> Result<Board, String> {
+        let mut board = match self.fen.as_deref() {
+            None => board.clone(),
+            Some("startpos") => Board::default(),
+            Some(fen) => {
+                let mut board = Board::default();
+                board.read_fen(fen)?;
+                board
+            }
+        };
+        for m in &self.moves {
+            board
+                .make_move_internal_notation(m)
+                .map_err(|e| format!("{}: {}", m, e))?;
+        }
+        Ok(board)
+    }
+
+    fn depth(&self) -> usize {
+        self.depth.unwrap_or(DEFAULT_DEPTH).clamp(1, MAX_DEPTH)
+    }
+
+    fn multipv(&self) -> usize {
+        self.multipv.unwrap_or(1).max(1)
+    }
+}
+
+// ServeLimits keep a single client from taking all threads of a shared server: it may have only
+// a few connections open at once, idle ones too, every connection is closed when its request
+// doesn't come in request_timeout, and every search, of a request or a WebSocket command, stops
+// after its time or nodes budget with lines of the last completed depth.
+#[derive(Clone, Copy, Debug)]
+pub struct ServeLimits {
+    pub connections_per_client: usize,
+    pub request_timeout: Duration,
+    pub search_time: Duration,
+    pub search_nodes: u64,
+}
+
+impl Default for ServeLimits {
+    fn default() -> Self {
+        ServeLimits {
+            connections_per_client: 4,
+            request_timeout: Duration::from_secs(10),
+            search_time: Duration::from_secs(5),
+            search_nodes: 5_000_000,
+        }
+    }
+}
+
+// Clients counts open connections of every client address.
+type Clients = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+// ClientSlot is an open connection of a client, it's given back when dropped.
+struct ClientSlot {
+    clients: Clients,
+    addr: IpAddr,
+}
+
+impl ClientSlot {
+    // take returns a slot of the client, None when all its slots are taken.
+    fn take(clients: &Clients, addr: IpAddr, limit: usize) -> Option<Self> {
+        let mut open = clients.lock().unwrap();
+        let count = open.entry(addr).or_default();
+        if *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(ClientSlot {
+            clients: clients.clone(),
+            addr,
+        })
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        let mut open = self.clients.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.addr) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.addr);
+            }
+        }
+    }
+}
+
+// Status is a response of health endpoints, e.g. {"status": "ready"}.
+#[derive(Serialize, Debug)]
+struct Status {
+    status: &'static str,
+}
+
+// Server is state shared by connections.
+struct Server {
+    evaluator: Arc<dyn Evaluator>,
+    cache: Mutex<AnalysisCache>,
+    clients: Clients,
+    limits: ServeLimits,
+    shutdown: StopSignal,
+    searching: AtomicUsize, // searches in progress, shutdown waits for them
+}
+
+// Searching counts a search in progress as long as it lives.
+struct Searching<'a>(&'a AtomicUsize);
+
+impl<'a> Searching<'a> {
+    fn start(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Searching(count)
+    }
+}
+
+impl Drop for Searching<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Server {
+    // search returns a search within limits of a single request.
+    fn search(&self) -> Search<'_> {
+        let mut search = Search::new(self.evaluator.as_ref());
+        search.deadline = Some(Instant::now() + self.limits.search_time);
+        search.max_nodes = Some(self.limits.search_nodes);
+        search
+    }
+}
+
+// CacheKey is a position without move counters, which rarely change the best lines, with depth
+// and number of lines.
+type CacheKey = (String, usize, usize);
+
+// AnalysisCache keeps lines of the recently analysed positions, so popular ones, e.g. openings,
+// are answered without searching. The least recently used analysis is dropped when it's full.
+pub struct AnalysisCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, (u64, Vec<SearchLine>)>, // last use and lines
+    uses: u64,
+}
+
+impl AnalysisCache {
+    pub fn new(capacity: usize) -> Self {
+        AnalysisCache {
+            capacity,
+            entries: HashMap::new(),
+            uses: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn key(board: &Board, depth: usize, multipv: usize) -> CacheKey {
+        let fen: Vec<String> = board
+            .to_fen()
+            .split(' ')
+            .take(4)
+            .map(String::from)
+            .collect();
+        (fen.join(" "), depth, multipv)
+    }
+
+    pub fn get(&mut self, board: &Board, depth: usize, multipv: usize) -> Option<Vec<SearchLine>> {
+        self.uses += 1;
+        let uses = self.uses;
+        let (used, lines) = self.entries.get_mut(&Self::key(board, depth, multipv))?;
+        *used = uses;
+        Some(lines.clone())
+    }
+
+    pub fn insert(&mut self, board: &Board, depth: usize, multipv: usize, lines: Vec<SearchLine>) {
+        let key = Self::key(board, depth, multipv);
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        if self.capacity > 0 {
+            self.uses += 1;
+            self.entries.insert(key, (self.uses, lines));
+        }
+    }
+}
+
+// serve accepts connections until listener fails or shutdown is set, every connection is handled
+// in its own thread. On shutdown searches in progress are finished, they're bounded by limits,
+// and then serve returns, new WebSocket commands are rejected meanwhile.
+//
+// GET /healthz answers 200 while the server runs.
+// GET /readyz answers 200 when the server takes requests, 503 once it's shutting down.
+// GET /analyze?fen=FEN&depth=N&multipv=N returns best lines as JSON, repeated requests are
+// answered from AnalysisCache.
+// GET /ws upgrades to WebSocket and streams analysis of positions sent by client.
+// Clients over their connections limit get 429 Too Many Requests, see ServeLimits.
+pub fn serve(
+    listener: TcpListener,
+    evaluator: Arc<dyn Evaluator>,
+    limits: ServeLimits,
+    shutdown: StopSignal,
+) -> io::Result<()> {
+    let server = Arc::new(Server {
+        evaluator,
+        cache: Mutex::new(AnalysisCache::new(CACHE_ENTRIES)),
+        clients: Arc::new(Mutex::new(HashMap::new())),
+        limits,
+        shutdown,
+        searching: AtomicUsize::new(0),
+    });
+    // listener doesn't block, so the shutdown signal is noticed without new connections.
+    listener.set_nonblocking(true)?;
+    while !server.shutdown.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_POLL);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let addr = match stream.peer_addr() {
+            Ok(addr) => addr.ip(),
+            Err(_) => continue, // the client is gone already
+        };
+        // the slot is taken before the request is read, so idle connections count too.
+        let slot = ClientSlot::take(&server.clients, addr, server.limits.connections_per_client);
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(server.limits.request_timeout))?;
+        let server = server.clone();
+        thread::spawn(move || {
+            // connection errors concern only a single client.
+            let _ = handle_connection(stream, slot, &server);
+        });
+    }
+    while server.searching.load(Ordering::SeqCst) > 0 {
+        thread::sleep(ACCEPT_POLL);
+    }
+    Ok(())
+}
+
+// Request is a head of HTTP request.
+struct Request {
+    path: String,
+    query: Vec<(String, String)>,
+    upgrade: bool,
+    head: Vec<u8>, // raw head, WebSocket handshake reads it once again
+}
+
+impl Request {
+    fn read<R: BufRead>(reader: &mut R) -> io::Result<Request> {
+        let mut head = Vec::new();
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            head.extend_from_slice(line.as_bytes());
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            lines.push(line);
+        }
+
+        let target = lines
+            .first()
+            .and_then(|l| l.split_whitespace().nth(1))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid request line"))?;
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let upgrade = lines.iter().skip(1).any(|l| {
+            let l = l.to_lowercase();
+            l.starts_with("upgrade:") && l.contains("websocket")
+        });
+        Ok(Request {
+            path: path.to_string(),
+            query: parse_query(query),
+            upgrade,
+            head,
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// Replay is a stream that returns already read request head before the rest of the stream.
+struct Replay<S> {
+    head: io::Cursor<Vec<u8>>,
+    stream: S,
+}
+
+impl<S: Read> Read for Replay<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.head.read(buf)?;
+        if n > 0 {
+            return Ok(n);
+        }
+        self.stream.read(buf)
+    }
+}
+
+impl<S: Write> Write for Replay<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+// handle_connection answers a request of the client holding the slot, clients without one are
+// over their connections limit. Reading the request is bounded by the read timeout of the stream.
+fn handle_connection(
+    stream: TcpStream,
+    slot: Option<ClientSlot>,
+    server: &Server,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = Request::read(&mut reader)?;
+    let _slot = match slot {
+        Some(slot) => slot,
+        None => {
+            let message = "too many connections from the client".to_string();
+            let mut stream = stream;
+            return respond(
+                &mut stream,
+                "429 Too Many Requests",
+                &json(&Event::Error { message }),
+            );
+        }
+    };
+    // WebSocket clients may wait between commands as long as they want.
+    stream.set_read_timeout(None)?;
+
+    if request.path == "/ws" && request.upgrade {
+        let mut head = request.head;
+        head.extend_from_slice(reader.buffer());
+        let replay = Replay {
+            head: io::Cursor::new(head),
+            stream,
+        };
+        let mut ws = tungstenite::accept(replay).map_err(|e| io::Error::other(e.to_string()))?;
+        return live_analysis(&mut ws, server);
+    }
+
+    let mut stream = stream;
+    match request.path.as_str() {
+        "/healthz" => respond(&mut stream, "200 OK", &json(&Status { status: "ok" })),
+        "/readyz" if server.shutdown.load(Ordering::Relaxed) => respond(
+            &mut stream,
+            "503 Service Unavailable",
+            &json(&Status {
+                status: "shutting down",
+            }),
+        ),
+        "/readyz" => respond(&mut stream, "200 OK", &json(&Status { status: "ready" })),
+        "/analyze" => {
+            let command = Command {
+                fen: request.param("fen").map(String::from),
+                moves: Vec::new(),
+                depth: request.param("depth").and_then(|d| d.parse().ok()),
+                multipv: request.param("multipv").and_then(|m| m.parse().ok()),
+            };
+            match analyze(&command, &Board::default(), server) {
+                Ok(lines) => respond(&mut stream, "200 OK", &json(&lines)),
+                Err(e) => respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    &json(&Event::Error { message: e }),
+                ),
+            }
+        }
+        _ => respond(
+            &mut stream,
+            "404 Not Found",
+            &json(&Event::Error {
+                message: format!("unknown path {}", request.path),
+            }),
+        ),
+    }
+}
+
+// analyze returns the best lines found at the last completed depth, from the cache when the
+// position was already analysed. The cache isn't locked during the search, so other requests go
+// on, and searches stopped by limits aren't cached.
+fn analyze(command: &Command, board: &Board, server: &Server) -> Result<Vec<Info>, String> {
+    if command.fen.is_none() {
+        return Err("fen parameter is required".to_string());
+    }
+    let board = command.apply(board)?;
+    if board.generate_transitions().is_empty() {
+        return Err("there are no legal moves in given position".to_string());
+    }
+    let (depth, multipv) = (command.depth(), command.multipv());
+    let cached = server.cache.lock().unwrap().get(&board, depth, multipv);
+    let (lines, reached) = match cached {
+        Some(lines) => (lines, depth),
+        None => {
+            let _searching = Searching::start(&server.searching);
+            let mut reached = 0;
+            let lines = server
+                .search()
+                .iterate(&board, depth, multipv, |d, _| reached = d);
+            if reached == depth {
+                let mut cache = server.cache.lock().unwrap();
+                cache.insert(&board, depth, multipv, lines.clone());
+            }
+            (lines, reached)
+        }
+    };
+    Ok(lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| Info::new(&board, reached, i + 1, line))
+        .collect())
+}
+
+// live_analysis reads commands from client and sends analysis after every searched depth,
+// finished with the best move.
+fn live_analysis<S: Read + Write>(ws: &mut WebSocket<S>, server: &Server) -> io::Result<()> {
+    let mut board = Board::default();
+    loop {
+        let text = match ws.read().map_err(ws_error)? {
+            tungstenite::Message::Text(text) => text,
+            tungstenite::Message::Close(_) => return Ok(()),
+            _ => continue,
+        };
+        let position = serde_json::from_str::<Command>(&text)
+            .map_err(|e| e.to_string())
+            .and_then(|command| Ok((command.apply(&board)?, command)));
+        let (position, command) = match position {
+            Ok(p) => p,
+            Err(message) => {
+                send(ws, &Event::Error { message })?;
+                continue;
+            }
+        };
+        if server.shutdown.load(Ordering::Relaxed) {
+            let message = "server is shutting down".to_string();
+            return send(ws, &Event::Error { message });
+        }
+        board = position;
+        let transitions = board.generate_transitions();
+        if transitions.is_empty() {
+            let message = "there are no legal moves in given position".to_string();
+            send(ws, &Event::Error { message })?;
+            continue;
+        }
+
+        let _searching = Searching::start(&server.searching);
+        let mut result = Ok(());
+        let lines =
+            server
+                .search()
+                .iterate(&board, command.depth(), command.multipv(), |d, lines| {
+                    for (i, line) in lines.iter().enumerate() {
+                        if result.is_ok() {
+                            result = send(ws, &Event::Info(Info::new(&board, d, i + 1, line)));
+                        }
+                    }
+                });
+        result?;
+        let best = &lines[0].pv[0];
+        send(
+            ws,
+            &Event::BestMove {
+                fen: board.to_fen(),
+                r#move: to_internal(best),
+                san: to_san(&board, best),
+            },
+        )?;
+    }
+}
+
+fn send<S: Read + Write>(ws: &mut WebSocket<S>, event: &Event) -> io::Result<()> {
+    ws.send(tungstenite::Message::text(json(event)))
+        .map_err(ws_error)
+}
+
+fn ws_error(e: tungstenite::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+fn json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+// parse_query parses URL query e.g. 'fen=8%2F8...&depth=5'.
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (name, value) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match s.get(i + 1..i + 3).map(|h| u8::from_str_radix(h, 16)) {
+                Some(Ok(b)) => {
+                    decoded.push(b);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::SimpleEvaluator;
+    use crate::serve::{
+        analyze, handle_connection, parse_query, serve, AnalysisCache, ClientSlot, Command,
+        ServeLimits, Server,
+    };
+    use std::collections::HashMap;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let e = Arc::new(SimpleEvaluator {});
+        let shutdown = Arc::new(AtomicBool::new(false));
+        thread::spawn(move || serve(listener, e, ServeLimits::default(), shutdown));
+        addr
+    }
+
+    fn get(addr: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, addr).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn server(cache_entries: usize, limits: ServeLimits) -> Server {
+        Server {
+            evaluator: Arc::new(SimpleEvaluator {}),
+            cache: Mutex::new(AnalysisCache::new(cache_entries)),
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            limits,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            searching: AtomicUsize::new(0),
+        }
+    }
+
+    #[test]
+    fn test_command() {
+        let command: Command =
+            serde_json::from_str(r#"{"fen": "startpos", "moves": ["e2e4"]}"#).unwrap();
+        let board = command.apply(&Board::default()).unwrap();
+        let command: Command = serde_json::from_str(r#"{"moves": ["e7e5"]}"#).unwrap();
+        assert_eq!(
+            command.apply(&board).unwrap().position_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w"
+        );
+        let command: Command = serde_json::from_str(r#"{"moves": ["e2e4"]}"#).unwrap();
+        assert!(command.apply(&board).is_err());
+
+        assert_eq!(
+            parse_query("fen=6k1%2F8+w&depth=2&x"),
+            vec![
+                ("fen".to_string(), "6k1/8 w".to_string()),
+                ("depth".to_string(), "2".to_string()),
+                ("x".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analysis_cache() {
+        let server = server(2, ServeLimits::default());
+        let cache = &server.cache;
+        let command = |fen: &str| Command {
+            fen: Some(fen.to_string()),
+            depth: Some(2),
+            ..Command::default()
+        };
+        let mate = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let lines = analyze(&command(mate), &Board::default(), &server).unwrap();
+        assert_eq!(lines[0].san, "1. Ra8#");
+        // move counters don't matter, the cached analysis is returned.
+        let mut b = Board::default();
+        b.read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 12 40").unwrap();
+        assert!(cache.lock().unwrap().get(&b, 2, 1).is_some());
+        assert!(cache.lock().unwrap().get(&b, 3, 1).is_none());
+        let lines = analyze(&command(&b.to_fen()), &Board::default(), &server).unwrap();
+        assert_eq!(lines[0].pv, vec!["a1a8".to_string()]);
+        assert_eq!(cache.lock().unwrap().len(), 1);
+
+        // the least recently used position is dropped.
+        analyze(&command("startpos"), &Board::default(), &server).unwrap();
+        assert!(cache.lock().unwrap().get(&b, 2, 1).is_some());
+        analyze(
+            &command("4k3/8/8/8/8/8/8/R3K3 w"),
+            &Board::default(),
+            &server,
+        )
+        .unwrap();
+        let mut cache = cache.lock().unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&b, 2, 1).is_some());
+        assert!(cache.get(&Board::default(), 2, 1).is_none());
+    }
+
+    #[test]
+    fn test_serve_limits() {
+        let server = server(
+            16,
+            ServeLimits {
+                connections_per_client: 2,
+                search_nodes: 1,
+                ..ServeLimits::default()
+            },
+        );
+        let addr = "127.0.0.1".parse().unwrap();
+        let first = ClientSlot::take(&server.clients, addr, 2).unwrap();
+        let second = ClientSlot::take(&server.clients, addr, 2).unwrap();
+        assert!(ClientSlot::take(&server.clients, addr, 2).is_none());
+        // other clients have their own slots.
+        assert!(ClientSlot::take(&server.clients, "10.0.0.1".parse().unwrap(), 2).is_some());
+        drop(first);
+        assert!(ClientSlot::take(&server.clients, addr, 2).is_some());
+        drop(second);
+        assert!(server.clients.lock().unwrap().is_empty());
+
+        // the nodes budget stops the search after the first depth, which isn't cached.
+        let command = Command {
+            fen: Some("startpos".to_string()),
+            depth: Some(4),
+            ..Command::default()
+        };
+        let lines = analyze(&command, &Board::default(), &server).unwrap();
+        assert_eq!(lines[0].depth, 1);
+        assert!(server.cache.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_idle_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let limits = ServeLimits {
+            connections_per_client: 1,
+            request_timeout: Duration::from_millis(200),
+            ..ServeLimits::default()
+        };
+        let e = Arc::new(SimpleEvaluator {});
+        let shutdown = Arc::new(AtomicBool::new(false));
+        thread::spawn(move || serve(listener, e, limits, shutdown));
+
+        // a connection without request takes the only slot until it times out.
+        let mut idle = TcpStream::connect(&addr).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(get(&addr, "/healthz").starts_with("HTTP/1.1 429 Too Many Requests"));
+        let mut response = String::new();
+        idle.read_to_string(&mut response).unwrap();
+        assert!(response.is_empty());
+        assert!(get(&addr, "/healthz").starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn test_health_and_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let stop = shutdown.clone();
+        let e = Arc::new(SimpleEvaluator {});
+        let serving = thread::spawn(move || serve(listener, e, ServeLimits::default(), stop));
+
+        let health = get(&addr, "/healthz");
+        assert!(health.starts_with("HTTP/1.1 200 OK"));
+        assert!(health.ends_with(r#"{"status":"ok"}"#));
+        assert!(get(&addr, "/readyz").ends_with(r#"{"status":"ready"}"#));
+
+        shutdown.store(true, Ordering::Relaxed);
+        serving.join().unwrap().unwrap();
+        assert!(TcpStream::connect(&addr).is_err());
+
+        // connections accepted before shutdown are told the server isn't ready.
+        let server = server(1, ServeLimits::default());
+        server.shutdown.store(true, Ordering::Relaxed);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        write!(client, "GET /readyz HTTP/1.1\r\n\r\n").unwrap();
+        let slot = ClientSlot::take(&server.clients, "127.0.0.1".parse().unwrap(), 1);
+        handle_connection(listener.accept().unwrap().0, slot, &server).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(response.ends_with(r#"{"status":"shutting down"}"#));
+    }
+
+    #[test]
+    fn test_http_analyze() {
+        let addr = start_server();
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        write!(
+            stream,
+            "GET /analyze?fen=6k1%2F5ppp%2F8%2F8%2F8%2F8%2F8%2FR5K1+w&depth=2 HTTP/1.1\r\nHost: {}\r\n\r\n",
+            addr
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with(
+            r#"[{"depth":2,"multipv":1,"score":{"mate":1},"win_probability":1.0,"pv":["a1a8"],"san":"1. Ra8#"}]"#
+        ));
+    }
+
+    #[test]
+    fn test_live_analysis() {
+        let addr = start_server();
+        let stream = TcpStream::connect(&addr).unwrap();
+        let (mut ws, _) = tungstenite::client(format!("ws://{}/ws", addr), stream).unwrap();
+
+        let read_until_best_move = |ws: &mut tungstenite::WebSocket<TcpStream>| {
+            let mut events = Vec::new();
+            loop {
+                let text = ws.read().unwrap().into_text().unwrap();
+                let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let done = event["type"] != "info";
+                events.push(event);
+                if done {
+                    return events;
+                }
+            }
+        };
+
+        ws.send(tungstenite::Message::text(
+            r#"{"fen": "6k1/5ppp/8/8/8/8/8/R5K1 w", "depth": 2}"#,
+        ))
+        .unwrap();
+        let events = read_until_best_move(&mut ws);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[1]["score"]["mate"], 1);
+        assert_eq!(events[2]["move"], "a1a8");
+
+        ws.send(tungstenite::Message::text(
+            r#"{"moves": ["a1a7"], "depth": 1}"#,
+        ))
+        .unwrap();
+        let events = read_until_best_move(&mut ws);
+        assert_eq!(events[0]["depth"], 1);
+        assert_eq!(events[1]["fen"], "6k1/R4ppp/8/8/8/8/8/6K1 b - - 1 1");
+
+        // it's black turn now
+        ws.send(tungstenite::Message::text(r#"{"moves": ["a7a8"]}"#))
+            .unwrap();
+        let events = read_until_best_move(&mut ws);
+        assert_eq!(events[0]["type"], "error");
+    }
+}
@@ -0,0 +1,258 @@
+use crate::backend::{SearchBackend, SearchLimits, SearchResult};
+use crate::board::Board;
+use crate::piece::{Color, PieceType};
+use crate::search::{Score, SearchLine, StopSignal};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// DEFAULT_MARGIN is how many centipawns worse than the best move a steering move may be.
+pub const DEFAULT_MARGIN: i32 = 50;
+// CANDIDATES is a number of best root moves searched to choose the steering move from.
+const CANDIDATES: usize = 5;
+
+// PositionType is a kind of position the engine can steer games into for practice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionType {
+    IsolatedQueenPawn,
+    OppositeCastling,
+    RookEndgame,
+}
+
+pub const POSITION_TYPE_NAMES: [&str; 3] = ["iqp", "opposite-castling", "rook-endgame"];
+
+impl FromStr for PositionType {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iqp" => Ok(PositionType::IsolatedQueenPawn),
+            "opposite-castling" => Ok(PositionType::OppositeCastling),
+            "rook-endgame" => Ok(PositionType::RookEndgame),
+            _ => Err("unknown position type"),
+        }
+    }
+}
+
+impl Display for PositionType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PositionType::IsolatedQueenPawn => "iqp",
+            PositionType::OppositeCastling => "opposite-castling",
+            PositionType::RookEndgame => "rook-endgame",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl PositionType {
+    // matches checks if position is of this type, for either color.
+    pub fn matches(&self, board: &Board) -> bool {
+        match self {
+            PositionType::IsolatedQueenPawn => [Color::WHITE, Color::BLACK]
+                .iter()
+                .any(|c| has_isolated_queen_pawn(board, *c)),
+            PositionType::OppositeCastling => opposite_castling(board),
+            PositionType::RookEndgame => rook_endgame(board),
+        }
+    }
+}
+
+// pieces returns squares with pieces of given type and color.
+fn pieces(board: &Board, p_type: PieceType, color: Color) -> Vec<usize> {
+    (0..64)
+        .filter(|&i| board.piece_at(i).p_type == p_type && board.piece_at(i).color == color)
+        .collect()
+}
+
+// has_isolated_queen_pawn checks if color has a pawn on d-file and no pawns on c and e files.
+fn has_isolated_queen_pawn(board: &Board, color: Color) -> bool {
+    let files: Vec<usize> = pieces(board, PieceType::PAWN, color)
+        .iter()
+        .map(|i| i % 8)
+        .collect();
+    files.contains(&3) && !files.contains(&2) && !files.contains(&4)
+}
+
+// opposite_castling checks if kings stand on their first ranks on different wings.
+fn opposite_castling(board: &Board) -> bool {
+    let king = |color, rank| {
+        pieces(board, PieceType::KING, color)
+            .first()
+            .filter(|&&i| i / 8 == rank)
+            .map(|i| i % 8)
+    };
+    match (king(Color::WHITE, 0), king(Color::BLACK, 7)) {
+        (Some(white), Some(black)) => white >= 6 && black <= 2 || white <= 2 && black >= 6,
+        _ => false,
+    }
+}
+
+// rook_endgame checks if both colors have rooks and there are no other pieces than kings and
+// pawns.
+fn rook_endgame(board: &Board) -> bool {
+    let only_rooks = board.squares().iter().all(|p| {
+        matches!(
+            p.p_type,
+            PieceType::NONE | PieceType::KING | PieceType::PAWN | PieceType::ROOK
+        )
+    });
+    only_rooks
+        && !pieces(board, PieceType::ROOK, Color::WHITE).is_empty()
+        && !pieces(board, PieceType::ROOK, Color::BLACK).is_empty()
+}
+
+// steering_score counts positions of the line that are of the target type, the sooner the
+// target is reached the more it counts.
+fn steering_score(board: &Board, line: &SearchLine, target: PositionType) -> usize {
+    let mut board = board.clone();
+    let mut score = 0;
+    for (i, t) in line.pv.iter().enumerate() {
+        board.make_transition(*t);
+        if target.matches(&board) {
+            score += line.pv.len() - i;
+        }
+    }
+    score
+}
+
+// steer returns index of the line that leads the most to target positions among lines not worse
+// than the best one by more than margin centipawns. Lines with mate scores are never traded.
+pub fn steer(board: &Board, lines: &[SearchLine], target: PositionType, margin: i32) -> usize {
+    let best = match lines.first().map(|l| l.score) {
+        Some(Score::Centipawns(cp)) => cp,
+        _ => return 0,
+    };
+    let mut chosen = (0, 0);
+    for (i, line) in lines.iter().enumerate() {
+        let close = matches!(line.score, Score::Centipawns(cp) if best - cp <= margin);
+        if close {
+            let score = steering_score(board, line, target);
+            if score > chosen.1 {
+                chosen = (i, score);
+            }
+        }
+    }
+    chosen.0
+}
+
+// Sparring is a search backend for practice, it steers games toward positions of the target type
+// by choosing among root moves that are almost as good as the best one.
+pub struct Sparring<'a> {
+    backend: Box<dyn SearchBackend + 'a>,
+    pub target: PositionType,
+    pub margin: i32, // in centipawns
+}
+
+impl<'a> Sparring<'a> {
+    pub fn new(backend: Box<dyn SearchBackend + 'a>, target: PositionType) -> Self {
+        Sparring {
+            backend,
+            target,
+            margin: DEFAULT_MARGIN,
+        }
+    }
+}
+
+impl SearchBackend for Sparring<'_> {
+    fn name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    fn set_stop(&mut self, stop: StopSignal, poll_interval: u64) {
+        self.backend.set_stop(stop, poll_interval);
+    }
+
+    fn set_history(&mut self, history: &[u64]) {
+        self.backend.set_history(history);
+    }
+
+    // search looks for more best lines than asked, the steering one is returned first.
+    fn search(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(usize, &[SearchLine]),
+    ) -> SearchResult {
+        let multipv = limits.multipv.max(1);
+        let candidates = SearchLimits {
+            multipv: multipv.max(CANDIDATES),
+            ..*limits
+        };
+        let mut result = self
+            .backend
+            .search(board, &candidates, &mut |depth, lines| {
+                on_info(depth, &lines[..multipv.min(lines.len())])
+            });
+        let chosen = steer(board, &result.lines, self.target, self.margin);
+        let line = result.lines.remove(chosen);
+        result.lines.insert(0, line);
+        result.lines.truncate(multipv);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{SearchBackend, SearchLimits};
+    use crate::board::Board;
+    use crate::evaluation::SimpleEvaluator;
+    use crate::notation::to_internal;
+    use crate::search::{Score, Search, SearchLine};
+    use crate::sparring::{steer, PositionType, Sparring};
+
+    #[test]
+    fn test_position_types() {
+        let mut b = Board::default();
+        let types = [
+            PositionType::IsolatedQueenPawn,
+            PositionType::OppositeCastling,
+            PositionType::RookEndgame,
+        ];
+        assert!(types.iter().all(|t| !t.matches(&b)));
+        assert!(types.iter().all(|t| t.to_string().parse() == Ok(*t)));
+
+        b.read_fen("r1bqk2r/pp3ppp/2n1pn2/8/3P4/2N2N2/PP3PPP/R1BQKB1R w")
+            .unwrap();
+        assert!(PositionType::IsolatedQueenPawn.matches(&b));
+        b.read_fen("2kr3r/ppp2ppp/8/8/8/8/PPP2PPP/R4RK1 w").unwrap();
+        assert!(PositionType::OppositeCastling.matches(&b));
+        b.read_fen("8/5pk1/8/8/8/8/r4PPP/3R2K1 w").unwrap();
+        assert!(PositionType::RookEndgame.matches(&b));
+        b.read_fen("8/5pk1/8/8/8/8/r4PPP/3B2K1 w").unwrap();
+        assert!(!PositionType::RookEndgame.matches(&b));
+    }
+
+    #[test]
+    fn test_steer() {
+        let mut b = Board::default();
+        b.read_fen("3r2k1/3b1ppp/8/1B6/8/8/5PPP/3R2K1 w").unwrap();
+        let moves = b.generate_transitions();
+        let find = |m: &str| *moves.iter().find(|t| to_internal(t) == m).unwrap();
+        let line = |cp, pv| SearchLine {
+            score: Score::Centipawns(cp),
+            pv,
+        };
+        // exchanging bishops leads to rook endgame.
+        let mut after_exchange = b.clone();
+        after_exchange.make_transition(find("b5d7"));
+        let recapture = *after_exchange
+            .generate_transitions()
+            .iter()
+            .find(|t| to_internal(t) == "d8d7")
+            .unwrap();
+        let lines = vec![
+            line(30, vec![find("h2h3")]),
+            line(10, vec![find("b5d7"), recapture]),
+        ];
+        assert_eq!(steer(&b, &lines, PositionType::RookEndgame, 50), 1);
+        assert_eq!(steer(&b, &lines, PositionType::RookEndgame, 10), 0);
+        assert_eq!(steer(&b, &lines, PositionType::IsolatedQueenPawn, 50), 0);
+
+        let mut sparring = Sparring::new(
+            Box::new(Search::new(&SimpleEvaluator {})),
+            PositionType::RookEndgame,
+        );
+        let result = sparring.search(&b, &SearchLimits::depth(1), &mut |_, _| {});
+        assert_eq!(result.lines.len(), 1);
+    }
+}
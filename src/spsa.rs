@@ -0,0 +1,271 @@
+use crate::arena::{play_match, MatchSettings, Player};
+use crate::backend::backend_by_name;
+use crate::board::Board;
+use crate::params::{EvalParams, ParamsEvaluator};
+use rand::Rng;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// Exponents of SPSA gain sequences recommended by Spall, as used by Fishtest.
+const ALPHA: f64 = 0.602;
+const GAMMA: f64 = 0.101;
+
+// Tunable is a weight of EvalParams tuned by SPSA. step is a perturbation at the end of tuning
+// and rate scales moves of the value, both as c_end and r_end of Fishtest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tunable {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+    pub rate: f64,
+}
+
+impl Tunable {
+    // new creates tunable with bounds and step derived from the current value: from 0 to twice
+    // the value, stepping by a twentieth of the range.
+    pub fn new(name: &str, value: f64) -> Self {
+        let max = if value > 0.0 { value * 2.0 } else { 1.0 };
+        Tunable {
+            name: name.to_string(),
+            min: 0.0,
+            max,
+            step: max / 20.0,
+            rate: 0.002,
+        }
+    }
+}
+
+impl FromStr for Tunable {
+    type Err = String;
+
+    // from_str reads 'NAME:MIN:MAX:STEP', bounds and step are given together or not at all and
+    // then are set by Tunable::new from default parameters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let name = parts[0];
+        let value = EvalParams::default()
+            .get(name)
+            .ok_or_else(|| format!("unknown parameter {}", name))?;
+        let mut tunable = Tunable::new(name, value as f64);
+        match parts[1..] {
+            [] => {}
+            [min, max, step] => {
+                let number = |s: &str| {
+                    s.parse::<f64>()
+                        .map_err(|_| format!("invalid number {} of {}", s, name))
+                };
+                tunable.min = number(min)?;
+                tunable.max = number(max)?;
+                tunable.step = number(step)?;
+                if tunable.min > tunable.max || tunable.step <= 0.0 {
+                    return Err(format!("invalid bounds of {}", name));
+                }
+            }
+            _ => return Err(format!("expected NAME or NAME:MIN:MAX:STEP, got {}", s)),
+        }
+        Ok(tunable)
+    }
+}
+
+// SpsaSettings controls tuning. Every iteration plays 'pairs' pairs of games between two
+// perturbed parameter sets, each pair from a position after random opening plies, with colors
+// reversed in the second game.
+#[derive(Clone, Debug)]
+pub struct SpsaSettings {
+    pub iterations: usize,
+    pub pairs: usize,
+    pub random_plies: usize,
+    pub search: String, // name of search backend, see BACKEND_NAMES
+    pub games: MatchSettings,
+}
+
+impl Default for SpsaSettings {
+    fn default() -> Self {
+        SpsaSettings {
+            iterations: 100,
+            pairs: 1,
+            random_plies: 4,
+            search: "alphabeta".to_string(),
+            games: MatchSettings {
+                depth: 2,
+                max_plies: 160,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+// Spsa tunes weights of evaluation by simultaneous perturbation stochastic approximation: every
+// iteration all weights are shifted by +step or -step at random, the two resulting sets play each
+// other and weights move toward the set that scored better. Search has no reduction or pruning
+// margins yet, so only evaluation weights are tuned.
+pub struct Spsa {
+    pub params: EvalParams,
+    pub tunables: Vec<Tunable>,
+    pub settings: SpsaSettings,
+    values: Vec<f64>, // current values of tunables, kept in f64 so small steps add up
+}
+
+impl Spsa {
+    pub fn new(params: EvalParams, tunables: Vec<Tunable>, settings: SpsaSettings) -> Self {
+        let values = tunables
+            .iter()
+            .map(|t| params.get(&t.name).unwrap_or_default() as f64)
+            .collect();
+        Spsa {
+            params,
+            tunables,
+            settings,
+            values,
+        }
+    }
+
+    // with_values returns parameters with tunables set to given values.
+    fn with_values(&self, values: &[f64]) -> EvalParams {
+        let mut params = self.params.clone();
+        for (t, v) in self.tunables.iter().zip(values) {
+            params
+                .set(&t.name, *v as f32)
+                .expect("tunables are checked when parsed");
+        }
+        params
+    }
+
+    // tuned returns parameters with current values of tunables.
+    pub fn tuned(&self) -> EvalParams {
+        self.with_values(&self.values)
+    }
+
+    // run tunes for all iterations, on_iteration gets iteration number from 1, result of the plus
+    // set against the minus one in points and tuned parameters.
+    pub fn run<R, F>(&mut self, rng: &mut R, mut on_iteration: F) -> Result<EvalParams, String>
+    where
+        R: Rng,
+        F: FnMut(usize, f64, &EvalParams),
+    {
+        let n = self.settings.iterations as f64;
+        let big_a = 0.1 * n;
+        for k in 1..=self.settings.iterations {
+            let mut plus = self.values.clone();
+            let mut minus = self.values.clone();
+            let mut gains = Vec::new();
+            for (i, t) in self.tunables.iter().enumerate() {
+                // gains decrease to step and rate at the last iteration.
+                let c = t.step * n.powf(GAMMA) / (k as f64).powf(GAMMA);
+                let a = t.rate * t.step * t.step * (big_a + n).powf(ALPHA);
+                let r = a / (big_a + k as f64).powf(ALPHA) / (c * c);
+                let delta = if rng.gen::<bool>() { 1.0 } else { -1.0 };
+                plus[i] = (self.values[i] + c * delta).clamp(t.min, t.max);
+                minus[i] = (self.values[i] - c * delta).clamp(t.min, t.max);
+                gains.push(r * c * delta);
+            }
+            let result = self.play(rng, &self.with_values(&plus), &self.with_values(&minus))?;
+            for (i, t) in self.tunables.iter().enumerate() {
+                self.values[i] = (self.values[i] + gains[i] * result).clamp(t.min, t.max);
+            }
+            on_iteration(k, result, &self.tuned());
+        }
+        Ok(self.tuned())
+    }
+
+    // play plays pairs of games between two parameter sets and returns points of the first one
+    // minus points of the second one.
+    fn play<R: Rng>(
+        &self,
+        rng: &mut R,
+        plus: &EvalParams,
+        minus: &EvalParams,
+    ) -> Result<f64, String> {
+        let evaluators = [plus, minus].map(|p| ParamsEvaluator {
+            params: Arc::new(p.clone()),
+        });
+        let player = |evaluator, name: &str| -> Result<Player, String> {
+            let backend = backend_by_name(&self.settings.search, evaluator)
+                .ok_or_else(|| format!("unknown search {}", self.settings.search))?;
+            Ok(Player {
+                name: name.to_string(),
+                backend,
+            })
+        };
+        let mut first = player(&evaluators[0], "plus")?;
+        let mut second = player(&evaluators[1], "minus")?;
+        let mut result = 0.0;
+        for _ in 0..self.settings.pairs {
+            let start = random_opening(rng, self.settings.random_plies);
+            let score = play_match(
+                &mut first,
+                &mut second,
+                2,
+                &start,
+                &self.settings.games,
+                |_, _| {},
+            );
+            result += score.wins as f64 - score.losses as f64;
+        }
+        Ok(result)
+    }
+}
+
+// random_opening plays given number of random moves from the start position, stopping early when
+// the game ends.
+fn random_opening<R: Rng>(rng: &mut R, plies: usize) -> Board {
+    let mut board = Board::default();
+    for _ in 0..plies {
+        let moves = board.generate_transitions();
+        if moves.is_empty() {
+            break;
+        }
+        board.make_transition(moves[rng.gen_range(0..moves.len())]);
+    }
+    board
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::params::EvalParams;
+    use crate::spsa::{Spsa, SpsaSettings, Tunable};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_tunable() {
+        let knight: Tunable = "knight".parse().unwrap();
+        assert_eq!((knight.min, knight.max, knight.step), (0.0, 6.0, 0.3));
+        let mobility: Tunable = "mobility:0:0.5:0.02".parse().unwrap();
+        assert_eq!(
+            (mobility.min, mobility.max, mobility.step),
+            (0.0, 0.5, 0.02)
+        );
+        assert!("elephant".parse::<Tunable>().is_err());
+        assert!("knight:1:2".parse::<Tunable>().is_err());
+        assert!("knight:4:2:1".parse::<Tunable>().is_err());
+    }
+
+    #[test]
+    fn test_spsa() {
+        let mut params = EvalParams::default();
+        params.set("knight", 1.0).unwrap(); // knights are clearly undervalued
+        let tunables = vec!["knight:0:6:0.5".parse().unwrap()];
+        let mut settings = SpsaSettings {
+            iterations: 2,
+            random_plies: 2,
+            ..Default::default()
+        };
+        settings.games.depth = 1;
+        settings.games.max_plies = 24;
+        let mut spsa = Spsa::new(params, tunables, settings);
+        let mut results = Vec::new();
+        let tuned = spsa
+            .run(&mut StdRng::seed_from_u64(7), |k, result, params| {
+                results.push((k, result, params.get("knight").unwrap()));
+            })
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, r, _)| (-2.0..=2.0).contains(r)));
+        let knight = tuned.get("knight").unwrap();
+        assert!((0.0..=6.0).contains(&knight));
+        assert_eq!(results[1].2, knight);
+        assert_eq!(tuned.get("bishop"), Some(3.0));
+    }
+}
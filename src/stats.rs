@@ -0,0 +1,218 @@
+use crate::elo::{self, Estimate, RatedResult};
+use crate::evaluation::Evaluator;
+use crate::pgn::PgnGame;
+use crate::piece::Color;
+use crate::review::{review_game, PlayerStats};
+use std::collections::BTreeMap;
+
+// OPENING_PLIES is a number of moves naming the opening of a game without Opening tag.
+const OPENING_PLIES: usize = 6;
+
+// Record counts results of games from the point of view of one player.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Record {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Record {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    // score returns points of the player in percent of games, a draw is half a point.
+    pub fn score(&self) -> f32 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        (self.wins as f32 + self.draws as f32 / 2.0) * 100.0 / self.games() as f32
+    }
+
+    // add counts result of a game played with the color, unfinished games are not counted.
+    fn add(&mut self, result: &str, color: Color) {
+        match (result, color) {
+            ("1-0", Color::WHITE) | ("0-1", Color::BLACK) => self.wins += 1,
+            ("1-0", _) | ("0-1", _) => self.losses += 1,
+            ("1/2-1/2", _) => self.draws += 1,
+            _ => {}
+        }
+    }
+}
+
+// PlayerReport sums up games of one player in a database: results by color, by ECO code and by
+// opening, length of games and, when games were analyzed, quality of the player's moves.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerReport {
+    pub games: usize,
+    pub white: Record,
+    pub black: Record,
+    pub by_eco: BTreeMap<String, Record>,
+    pub openings: BTreeMap<String, Record>,
+    pub plies: usize,
+    pub rated: Vec<RatedResult>, // finished games against opponents with WhiteElo or BlackElo tag
+    pub analysis: Option<PlayerStats>,
+    pub unanalyzed: usize, // games that could not be replayed by the analysis
+}
+
+impl PlayerReport {
+    // average_moves returns average length of games in full moves.
+    pub fn average_moves(&self) -> f32 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        self.plies as f32 / 2.0 / self.games as f32
+    }
+
+    // performance returns performance rating of the player in rated games.
+    pub fn performance(&self) -> Option<Estimate> {
+        elo::performance(&self.rated)
+    }
+
+    // average_opponent returns average rating of opponents in rated games.
+    pub fn average_opponent(&self) -> Option<f64> {
+        if self.rated.is_empty() {
+            return None;
+        }
+        Some(self.rated.iter().map(|r| r.opponent).sum::<f64>() / self.rated.len() as f64)
+    }
+
+    // most_common_openings returns at most n openings played the most, ties by name.
+    pub fn most_common_openings(&self, n: usize) -> Vec<(&str, &Record)> {
+        let mut openings: Vec<_> = self
+            .openings
+            .iter()
+            .map(|(name, record)| (name.as_str(), record))
+            .collect();
+        openings.sort_by_key(|(_, record)| std::cmp::Reverse(record.games()));
+        openings.truncate(n);
+        openings
+    }
+}
+
+// player_color returns color of the player in the game, names are matched ignoring case, so
+// 'carlsen' is found in 'Carlsen, Magnus'.
+pub fn player_color(game: &PgnGame, player: &str) -> Option<Color> {
+    let player = player.to_lowercase();
+    let plays = |tag| {
+        game.tag(tag)
+            .is_some_and(|name| name.to_lowercase().contains(&player))
+    };
+    if plays("White") {
+        Some(Color::WHITE)
+    } else if plays("Black") {
+        Some(Color::BLACK)
+    } else {
+        None
+    }
+}
+
+// player_report sums up games of the player, other games are skipped. With an evaluator games are
+// reviewed by search of given depth, as by review_game, and moves of the player are summed up.
+pub fn player_report(
+    games: &[PgnGame],
+    player: &str,
+    analysis: Option<(&dyn Evaluator, usize)>,
+) -> PlayerReport {
+    let mut report = PlayerReport {
+        analysis: analysis.map(|_| PlayerStats::default()),
+        ..PlayerReport::default()
+    };
+    for game in games {
+        let color = match player_color(game, player) {
+            Some(color) => color,
+            None => continue,
+        };
+        let result = game.tag("Result").unwrap_or("*");
+        let moves = game.moves();
+        report.games += 1;
+        report.plies += moves.len();
+        match color {
+            Color::WHITE => report.white.add(result, color),
+            _ => report.black.add(result, color),
+        }
+        let (opponent, points) = match color {
+            Color::WHITE => (game.tag("BlackElo"), ["0-1", "1/2-1/2", "1-0"]),
+            _ => (game.tag("WhiteElo"), ["1-0", "1/2-1/2", "0-1"]),
+        };
+        let points = points.iter().position(|r| *r == result);
+        if let (Some(Ok(opponent)), Some(points)) = (opponent.map(str::parse), points) {
+            report.rated.push(RatedResult {
+                opponent,
+                points: points as f64 / 2.0,
+            });
+        }
+        let eco = game.tag("ECO").unwrap_or("?").to_string();
+        report.by_eco.entry(eco).or_default().add(result, color);
+        let opening = match game.tag("Opening") {
+            Some(name) if name != "?" => name.to_string(),
+            _ => moves[..moves.len().min(OPENING_PLIES)].join(" "),
+        };
+        report
+            .openings
+            .entry(opening)
+            .or_default()
+            .add(result, color);
+
+        if let (Some((evaluator, depth)), Some(stats)) = (analysis, report.analysis.as_mut()) {
+            match review_game(game, evaluator, depth) {
+                Ok(review) if color == Color::WHITE => stats.merge(&review.white),
+                Ok(review) => stats.merge(&review.black),
+                Err(_) => report.unanalyzed += 1,
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::SimpleEvaluator;
+    use crate::pgn::split_games;
+    use crate::piece::Color;
+    use crate::stats::{player_color, player_report, Record};
+
+    #[test]
+    fn test_player_report() {
+        let pgn =
+            "[White \"Carlsen, Magnus\"]\n[Black \"Nepo\"]\n[Result \"1-0\"]\n[ECO \"C65\"]\n\
+            [Opening \"Ruy Lopez\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 1-0\n\n\
+            [White \"Caruana\"]\n[Black \"Carlsen, Magnus\"]\n[Result \"1/2-1/2\"]\n\
+            [WhiteElo \"2820\"]\n[ECO \"C65\"]\n\
+            [Opening \"Ruy Lopez\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. O-O 1/2-1/2\n\n\
+            [White \"Ding\"]\n[Black \"Carlsen, Magnus\"]\n[Result \"1-0\"]\n[WhiteElo \"2780\"]\n\n\
+            1. d4 d5 1-0\n\n\
+            [White \"Ding\"]\n[Black \"Nepo\"]\n[Result \"0-1\"]\n\n1. c4 0-1\n";
+        let games = split_games(pgn);
+        assert_eq!(player_color(&games[1], "CARLSEN"), Some(Color::BLACK));
+        assert_eq!(player_color(&games[3], "carlsen"), None);
+
+        let report = player_report(&games, "carlsen", None);
+        assert_eq!(report.games, 3);
+        let record = |wins, draws, losses| Record {
+            wins,
+            draws,
+            losses,
+        };
+        assert_eq!(report.white, record(1, 0, 0));
+        assert_eq!(report.black, record(0, 1, 1));
+        assert_eq!(report.black.score(), 25.0);
+        assert_eq!(report.by_eco["C65"], record(1, 1, 0));
+        assert_eq!(report.by_eco["?"], record(0, 0, 1));
+        assert_eq!(report.average_moves(), 15.0 / 2.0 / 3.0);
+        let openings = report.most_common_openings(5);
+        assert_eq!(openings[0], ("Ruy Lopez", &record(1, 1, 0)));
+        assert_eq!(openings[1], ("d4 d5", &record(0, 0, 1)));
+        assert_eq!(report.analysis, None);
+        assert_eq!(report.rated.len(), 2);
+        assert_eq!(report.rated[0].points, 0.5);
+        assert_eq!(report.average_opponent(), Some(2800.0));
+        let performance = report.performance().unwrap();
+        assert!((performance.elo - 2608.6).abs() < 0.1);
+
+        let e = SimpleEvaluator {};
+        let analyzed = player_report(&games[..1], "Magnus", Some((&e, 1)));
+        assert_eq!(analyzed.analysis.unwrap().moves, 3);
+        assert_eq!(analyzed.unanalyzed, 0);
+    }
+}
@@ -0,0 +1,558 @@
+use crate::board::{Board, Transition};
+use crate::evaluation::Evaluator;
+use crate::piece::{Color, PieceType};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+// Tables are generated with retrograde analysis: starting from checkmates, positions are resolved
+// one ply further at a time by taking moves back. They are built in memory when first probed and
+// kept for the lifetime of the process, so no tablebase files are needed.
+
+// Dtm is distance to mate in plies from the point of view of color that has the move, Loss(0) is
+// a checkmate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dtm {
+    Win(u16),
+    Loss(u16),
+    Draw,
+}
+
+impl Dtm {
+    // after_move returns result of position before the move that leads to position with this
+    // result.
+    fn after_move(&self) -> Dtm {
+        match self {
+            Dtm::Win(plies) => Dtm::Loss(plies + 1),
+            Dtm::Loss(plies) => Dtm::Win(plies + 1),
+            Dtm::Draw => Dtm::Draw,
+        }
+    }
+}
+
+impl Display for Dtm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dtm::Win(plies) => write!(f, "win, mate in {}", plies.div_ceil(2)),
+            Dtm::Loss(0) => write!(f, "checkmated"),
+            Dtm::Loss(plies) => write!(f, "loss, mated in {}", plies / 2),
+            Dtm::Draw => write!(f, "draw"),
+        }
+    }
+}
+
+// Ending is material of kings and a single piece of the stronger side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ending {
+    KQK,
+    KRK,
+    KPK,
+}
+
+static KQK_TABLE: OnceLock<Table> = OnceLock::new();
+static KRK_TABLE: OnceLock<Table> = OnceLock::new();
+static KPK_TABLE: OnceLock<Table> = OnceLock::new();
+
+impl Ending {
+    fn of(p_type: PieceType) -> Option<Ending> {
+        match p_type {
+            PieceType::QUEEN => Some(Ending::KQK),
+            PieceType::ROOK => Some(Ending::KRK),
+            PieceType::PAWN => Some(Ending::KPK),
+            _ => None,
+        }
+    }
+
+    fn piece(&self) -> PieceType {
+        match self {
+            Ending::KQK => PieceType::QUEEN,
+            Ending::KRK => PieceType::ROOK,
+            Ending::KPK => PieceType::PAWN,
+        }
+    }
+
+    // table returns table of the ending, generating it on first use.
+    pub fn table(&self) -> &'static Table {
+        let cell = match self {
+            Ending::KQK => &KQK_TABLE,
+            Ending::KRK => &KRK_TABLE,
+            Ending::KPK => &KPK_TABLE,
+        };
+        cell.get_or_init(|| Table::generate(*self))
+    }
+}
+
+const SIZE: usize = 2 * 64 * 64 * 64;
+const KING_STEPS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+// Position is a position of an ending with the stronger side playing white, pawn goes up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub strong_to_move: bool,
+    pub strong_king: usize,
+    pub piece: usize,
+    pub weak_king: usize,
+}
+
+fn step(square: usize, (files, ranks): (i32, i32)) -> Option<usize> {
+    let file = (square % 8) as i32 + files;
+    let rank = (square / 8) as i32 + ranks;
+    if (0..8).contains(&file) && (0..8).contains(&rank) {
+        Some((rank * 8 + file) as usize)
+    } else {
+        None
+    }
+}
+
+fn adjacent(a: usize, b: usize) -> bool {
+    let (fa, ra) = ((a % 8) as i32, (a / 8) as i32);
+    let (fb, rb) = ((b % 8) as i32, (b / 8) as i32);
+    a != b && (fa - fb).abs() <= 1 && (ra - rb).abs() <= 1
+}
+
+fn directions(p_type: PieceType) -> &'static [(i32, i32)] {
+    match p_type {
+        PieceType::ROOK => &ROOK_DIRECTIONS,
+        PieceType::QUEEN => &KING_STEPS,
+        _ => &[],
+    }
+}
+
+// slide visits squares reachable by sliding piece, stopping before any of blockers.
+fn slide(p_type: PieceType, from: usize, blockers: &[usize], mut visit: impl FnMut(usize)) {
+    for direction in directions(p_type) {
+        let mut square = from;
+        while let Some(next) = step(square, *direction) {
+            if blockers.contains(&next) {
+                break;
+            }
+            visit(next);
+            square = next;
+        }
+    }
+}
+
+// attacks checks if piece of the stronger side attacks target, blocker can stand in the way.
+fn attacks(p_type: PieceType, from: usize, target: usize, blocker: usize) -> bool {
+    let offset = |s: usize| {
+        (
+            (s % 8) as i32 - (from % 8) as i32,
+            (s / 8) as i32 - (from / 8) as i32,
+        )
+    };
+    let (files, ranks) = offset(target);
+    let aligned = match p_type {
+        PieceType::PAWN => return ranks == 1 && files.abs() == 1,
+        PieceType::ROOK => files == 0 || ranks == 0,
+        PieceType::QUEEN => files == 0 || ranks == 0 || files.abs() == ranks.abs(),
+        _ => false,
+    };
+    let distance = files.abs().max(ranks.abs());
+    if !aligned || distance == 0 {
+        return false;
+    }
+    // blocker is in the way when it is on the same ray closer than target.
+    let (blocker_files, blocker_ranks) = offset(blocker);
+    let k = blocker_files.abs().max(blocker_ranks.abs());
+    let between = k > 0
+        && k < distance
+        && blocker_files == files.signum() * k
+        && blocker_ranks == ranks.signum() * k;
+    !between
+}
+
+impl Position {
+    fn index(&self) -> usize {
+        ((self.strong_to_move as usize * 64 + self.strong_king) * 64 + self.piece) * 64
+            + self.weak_king
+    }
+
+    fn from_index(index: usize) -> Self {
+        Position {
+            strong_to_move: index / (64 * 64 * 64) == 1,
+            strong_king: index / (64 * 64) % 64,
+            piece: index / 64 % 64,
+            weak_king: index % 64,
+        }
+    }
+
+    // is_legal checks if pieces stand on different squares, pawn is not on first or last rank and
+    // the side that doesn't have the move is not in check.
+    fn is_legal(&self, p_type: PieceType) -> bool {
+        let distinct = self.strong_king != self.piece
+            && self.piece != self.weak_king
+            && self.strong_king != self.weak_king;
+        let pawn_rank = p_type != PieceType::PAWN || (1..7).contains(&(self.piece / 8));
+        distinct
+            && pawn_rank
+            && !adjacent(self.strong_king, self.weak_king)
+            && !(self.strong_to_move && self.in_check(p_type))
+    }
+
+    // in_check checks if weak king is attacked, the stronger king can never be.
+    fn in_check(&self, p_type: PieceType) -> bool {
+        attacks(p_type, self.piece, self.weak_king, self.strong_king)
+    }
+
+    fn with(&self, strong_king: usize, piece: usize, weak_king: usize) -> Position {
+        Position {
+            strong_to_move: !self.strong_to_move,
+            strong_king,
+            piece,
+            weak_king,
+        }
+    }
+
+    // moves counts legal moves of the side to move that stay in the ending and returns results of
+    // the ones leaving it by capture or promotion, for the side that makes them.
+    fn moves(&self, ending: Ending) -> (u8, Vec<Dtm>) {
+        let p_type = ending.piece();
+        let mut inside = 0;
+        let mut exits = Vec::new();
+        if !self.strong_to_move {
+            for to in KING_STEPS.iter().filter_map(|d| step(self.weak_king, *d)) {
+                if adjacent(to, self.strong_king) {
+                    continue;
+                }
+                if to == self.piece {
+                    // two kings can't mate.
+                    exits.push(Dtm::Draw);
+                } else if !attacks(p_type, self.piece, to, self.strong_king) {
+                    inside += 1;
+                }
+            }
+            return (inside, exits);
+        }
+
+        for to in KING_STEPS.iter().filter_map(|d| step(self.strong_king, *d)) {
+            if to != self.piece && to != self.weak_king && !adjacent(to, self.weak_king) {
+                inside += 1;
+            }
+        }
+        if p_type != PieceType::PAWN {
+            slide(
+                p_type,
+                self.piece,
+                &[self.strong_king, self.weak_king],
+                |_| inside += 1,
+            );
+            return (inside, exits);
+        }
+        let free = |s: usize| s != self.strong_king && s != self.weak_king;
+        let push = self.piece + 8;
+        if !free(push) {
+            return (inside, exits);
+        }
+        if push / 8 == 7 {
+            let child = self.with(self.strong_king, push, self.weak_king);
+            for promotion in [Ending::KQK, Ending::KRK].iter() {
+                exits.push(promotion.table().get(&child).after_move());
+            }
+            // knight or bishop can't mate alone.
+            exits.push(Dtm::Draw);
+        } else {
+            inside += 1;
+            if self.piece / 8 == 1 && free(push + 8) {
+                inside += 1;
+            }
+        }
+        (inside, exits)
+    }
+
+    // for_each_predecessor visits positions from which a move leads to this one inside the ending.
+    fn for_each_predecessor(&self, p_type: PieceType, mut visit: impl FnMut(Position)) {
+        let occupied = [self.strong_king, self.piece, self.weak_king];
+        let empty = |s: usize| !occupied.contains(&s);
+        if self.strong_to_move {
+            for from in KING_STEPS.iter().filter_map(|d| step(self.weak_king, *d)) {
+                if empty(from) && !adjacent(from, self.strong_king) {
+                    visit(self.with(self.strong_king, self.piece, from));
+                }
+            }
+            return;
+        }
+        // the stronger side moved, weak king must not have been in check before.
+        let mut visit_legal = |previous: Position| {
+            if !previous.in_check(p_type) {
+                visit(previous);
+            }
+        };
+        for from in KING_STEPS.iter().filter_map(|d| step(self.strong_king, *d)) {
+            if empty(from) && !adjacent(from, self.weak_king) {
+                visit_legal(self.with(from, self.piece, self.weak_king));
+            }
+        }
+        if p_type != PieceType::PAWN {
+            slide(p_type, self.piece, &occupied, |from| {
+                visit_legal(self.with(self.strong_king, from, self.weak_king))
+            });
+        } else if self.piece / 8 >= 2 && empty(self.piece - 8) {
+            visit_legal(self.with(self.strong_king, self.piece - 8, self.weak_king));
+            if self.piece / 8 == 3 && empty(self.piece - 16) {
+                visit_legal(self.with(self.strong_king, self.piece - 16, self.weak_king));
+            }
+        }
+    }
+}
+
+// Table holds distance to mate of every position of an ending.
+pub struct Table {
+    ending: Ending,
+    dtm: Vec<Option<Dtm>>, // None for illegal positions
+}
+
+impl Table {
+    pub fn ending(&self) -> Ending {
+        self.ending
+    }
+
+    pub fn get(&self, position: &Position) -> Dtm {
+        self.dtm[position.index()].unwrap_or(Dtm::Draw)
+    }
+
+    // generate resolves positions ply by ply. A position is won when any move leads to a lost
+    // one, lost when all moves lead to won ones and drawn when it is never resolved.
+    fn generate(ending: Ending) -> Table {
+        let p_type = ending.piece();
+        let mut dtm: Vec<Option<Dtm>> = vec![None; SIZE];
+        let mut unresolved_moves = vec![0u8; SIZE];
+        let mut can_draw = vec![false; SIZE];
+        // results of moves leaving the ending, resolved at ply of their distance to mate.
+        let mut exits: Vec<Vec<(usize, Dtm)>> = Vec::new();
+        let mut frontier = Vec::new();
+
+        let mut legal = vec![false; SIZE];
+        for (index, legal) in legal.iter_mut().enumerate() {
+            *legal = Position::from_index(index).is_legal(p_type);
+        }
+        for index in (0..SIZE).filter(|i| legal[*i]) {
+            let position = Position::from_index(index);
+            let (inside, exit_results) = position.moves(ending);
+            if inside == 0 && exit_results.is_empty() {
+                dtm[index] = Some(if position.in_check(p_type) {
+                    frontier.push(index);
+                    Dtm::Loss(0)
+                } else {
+                    Dtm::Draw
+                });
+                continue;
+            }
+            unresolved_moves[index] = inside;
+            for result in exit_results {
+                let plies = match result {
+                    Dtm::Draw => {
+                        can_draw[index] = true;
+                        continue;
+                    }
+                    Dtm::Loss(plies) => {
+                        unresolved_moves[index] += 1;
+                        plies
+                    }
+                    Dtm::Win(plies) => plies,
+                } as usize;
+                if exits.len() <= plies {
+                    exits.resize(plies + 1, Vec::new());
+                }
+                exits[plies].push((index, result));
+            }
+        }
+
+        let mut plies = 0;
+        while !frontier.is_empty() || plies + 1 < exits.len() {
+            plies += 1;
+            let mut next = Vec::new();
+            let mut resolve = |index: usize, win: bool, dtm: &mut Vec<Option<Dtm>>| {
+                if dtm[index].is_some() {
+                    return;
+                }
+                if win {
+                    dtm[index] = Some(Dtm::Win(plies as u16));
+                    next.push(index);
+                } else {
+                    unresolved_moves[index] -= 1;
+                    if unresolved_moves[index] == 0 && !can_draw[index] {
+                        dtm[index] = Some(Dtm::Loss(plies as u16));
+                        next.push(index);
+                    }
+                }
+            };
+            for index in frontier {
+                let lost = matches!(dtm[index], Some(Dtm::Loss(_)));
+                Position::from_index(index).for_each_predecessor(p_type, |previous| {
+                    resolve(previous.index(), lost, &mut dtm)
+                });
+            }
+            for (index, result) in exits.get(plies).cloned().unwrap_or_default() {
+                resolve(index, matches!(result, Dtm::Win(_)), &mut dtm);
+            }
+            frontier = next;
+        }
+
+        for (index, legal) in legal.iter().enumerate() {
+            if *legal && dtm[index].is_none() {
+                dtm[index] = Some(Dtm::Draw);
+            }
+        }
+        Table { ending, dtm }
+    }
+}
+
+// position returns ending and its position of the board, None when board has other material.
+// Board with black as stronger side is mirrored.
+pub fn position(board: &Board) -> Option<(Ending, Position)> {
+    let mut kings = [None, None];
+    let mut piece = None;
+    for (square, p) in board.squares().iter().enumerate() {
+        match p.p_type {
+            PieceType::NONE => {}
+            PieceType::KING => kings[(p.color == Color::BLACK) as usize] = Some(square),
+            _ if piece.is_none() => piece = Some((square, *p)),
+            _ => return None,
+        }
+    }
+    let (square, p) = piece?;
+    let ending = Ending::of(p.p_type)?;
+    let mirror = |s: usize| if p.color == Color::BLACK { s ^ 56 } else { s };
+    let strong = (p.color == Color::BLACK) as usize;
+    Some((
+        ending,
+        Position {
+            strong_to_move: board.color_to_move == p.color,
+            strong_king: mirror(kings[strong]?),
+            piece: mirror(square),
+            weak_king: mirror(kings[1 - strong]?),
+        },
+    ))
+}
+
+// probe returns distance to mate of KQK, KRK or KPK position, None for other material.
+pub fn probe(board: &Board) -> Option<Dtm> {
+    let (ending, position) = position(board)?;
+    Some(ending.table().get(&position))
+}
+
+// best_move returns the move that wins the fastest, holds the draw or loses the slowest.
+pub fn best_move(board: &Board) -> Option<Transition> {
+    probe(board)?;
+    let rank = |dtm: Dtm| match dtm {
+        Dtm::Loss(plies) => (2, -(plies as i32)),
+        Dtm::Draw => (1, 0),
+        Dtm::Win(plies) => (0, plies as i32),
+    };
+    board.generate_transitions().into_iter().max_by_key(|t| {
+        let mut child = board.clone();
+        child.make_transition(*t);
+        // captures and minor piece promotions leave material that can't mate.
+        rank(probe(&child).unwrap_or(Dtm::Draw))
+    })
+}
+
+// mate_line returns moves to mate from won position, following the fastest win for the
+// stronger side and the slowest loss for the weaker one.
+pub fn mate_line(board: &Board) -> Option<Vec<Transition>> {
+    if !matches!(probe(board)?, Dtm::Win(_)) {
+        return None;
+    }
+    let mut board = board.clone();
+    let mut line = Vec::new();
+    while let Some(Dtm::Win(_)) | Some(Dtm::Loss(1..)) = probe(&board) {
+        let t = best_move(&board)?;
+        board.make_transition(t);
+        line.push(t);
+    }
+    Some(line)
+}
+
+// TablebaseEvaluator evaluates positions of generated endings by their distance to mate, a win
+// is worth more than any material and faster wins more than slower ones. Other positions are
+// evaluated by the fallback evaluator.
+pub struct TablebaseEvaluator {
+    pub fallback: Box<dyn Evaluator>,
+}
+
+// WIN is evaluation of position won according to tablebase, in pawns.
+const WIN: f32 = 200.0;
+
+impl Evaluator for TablebaseEvaluator {
+    fn evaluate(&self, position: &dyn crate::position::Position) -> f32 {
+        let board: &Board = &position.to_board();
+        let eval = match probe(board) {
+            Some(Dtm::Win(plies)) => WIN - plies as f32 / 100.0,
+            Some(Dtm::Loss(plies)) => -WIN + plies as f32 / 100.0,
+            Some(Dtm::Draw) => 0.0,
+            None => return self.fallback.evaluate(board),
+        };
+        if board.color_to_move == Color::WHITE {
+            eval
+        } else {
+            -eval
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::evaluation::{Evaluator, SimpleEvaluator};
+    use crate::notation::line_to_san;
+    use crate::tablebase::{best_move, mate_line, probe, Dtm, Ending, TablebaseEvaluator};
+
+    fn dtm(fen: &str) -> Option<Dtm> {
+        let mut b = Board::default();
+        b.read_fen(fen).unwrap();
+        probe(&b)
+    }
+
+    #[test]
+    fn test_tablebase() {
+        // the longest mates are 10 moves with queen and 16 with rook.
+        let longest = |ending: Ending| {
+            (0..super::SIZE)
+                .filter_map(|i| match ending.table().dtm[i] {
+                    Some(Dtm::Win(plies)) => Some(plies),
+                    _ => None,
+                })
+                .max()
+        };
+        assert_eq!(longest(Ending::KQK), Some(19));
+        assert_eq!(longest(Ending::KRK), Some(31));
+
+        assert_eq!(dtm("7k/8/6K1/8/8/8/8/R7 w"), Some(Dtm::Win(1)));
+        assert_eq!(dtm("R6k/8/6K1/8/8/8/8/8 b"), Some(Dtm::Loss(0)));
+        assert_eq!(dtm("7k/5Q2/6K1/8/8/8/8/8 b"), Some(Dtm::Draw));
+        // king in front of its pawn on the sixth rank wins, opposition decides on the third.
+        assert!(matches!(dtm("4k3/8/4K3/4P3/8/8/8/8 b"), Some(Dtm::Loss(_))));
+        assert!(matches!(dtm("4k3/8/4K3/4P3/8/8/8/8 w"), Some(Dtm::Win(_))));
+        assert_eq!(dtm("8/8/8/8/8/4k3/4P3/4K3 w"), Some(Dtm::Draw));
+        // rook pawn can't win against king in the corner, black as stronger side is mirrored.
+        assert_eq!(dtm("k7/8/8/P7/8/8/8/K7 w"), Some(Dtm::Draw));
+        assert!(matches!(dtm("8/8/8/8/4p3/4k3/8/4K3 b"), Some(Dtm::Win(_))));
+        assert_eq!(dtm("8/8/8/8/8/8/8/K1k5 w"), None);
+
+        let mut b = Board::default();
+        b.read_fen("7k/8/6K1/8/8/8/8/R7 w").unwrap();
+        let t = best_move(&b).unwrap();
+        assert_eq!((t.from, t.to), (0, 56));
+
+        // promotion leads from KPK table to KQK one.
+        b.read_fen("8/4P2k/8/5K2/8/8/8/8 w").unwrap();
+        let line = mate_line(&b).unwrap();
+        assert!(line_to_san(&b, &line).starts_with("1. e8=Q"));
+        assert!(line_to_san(&b, &line).ends_with('#'));
+        let evaluator = TablebaseEvaluator {
+            fallback: Box::new(SimpleEvaluator {}),
+        };
+        assert!(evaluator.evaluate(&b) > 100.0);
+        b.read_fen("4k3/8/8/8/8/8/4P3/4KQ2 w").unwrap();
+        assert_eq!(evaluator.evaluate(&b), 10.0);
+    }
+}
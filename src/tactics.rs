@@ -0,0 +1,357 @@
+use crate::board::{attackers, Board, Square, Transition, TransitionFlag, KING_STEPS};
+use crate::notation::{square_at, square_name};
+use crate::piece::{Color, PieceType};
+use std::fmt::{Display, Formatter};
+
+// Tactic is a tactical motif found on the board without search, with squares of pieces involved.
+// Motifs are threats: the side that has the move may escape them, and the other side may carry
+// them out only when it has the move.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tactic {
+    // Hanging piece is attacked and not defended, or attacked by a cheaper piece.
+    Hanging {
+        piece: Square,
+        attackers: Vec<Square>,
+    },
+    // Fork is a piece attacking two or more targets at once.
+    Fork {
+        attacker: Square,
+        targets: Vec<Square>,
+    },
+    // Pin is a slider attacking a piece that can't move away without exposing a more valuable one.
+    Pin {
+        pinner: Square,
+        pinned: Square,
+        behind: Square,
+    },
+    // Skewer is a slider attacking a valuable piece which exposes a cheaper one when it moves away.
+    Skewer {
+        attacker: Square,
+        front: Square,
+        behind: Square,
+    },
+    // DiscoveredAttack is a piece standing in the way of its own slider towards a target.
+    DiscoveredAttack {
+        piece: Square,
+        slider: Square,
+        target: Square,
+    },
+}
+
+// Tactic is written with squares, e.g. 'fork by e5 of d7, f7' or 'pin of c6 by b5 to e8'.
+impl Display for Tactic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let list = |squares: &[Square]| {
+            let names: Vec<String> = squares.iter().map(|s| square_name(*s)).collect();
+            names.join(", ")
+        };
+        match self {
+            Tactic::Hanging { piece, attackers } => write!(
+                f,
+                "hanging {} attacked by {}",
+                square_name(*piece),
+                list(attackers)
+            ),
+            Tactic::Fork { attacker, targets } => {
+                write!(f, "fork by {} of {}", square_name(*attacker), list(targets))
+            }
+            Tactic::Pin {
+                pinner,
+                pinned,
+                behind,
+            } => write!(
+                f,
+                "pin of {} by {} to {}",
+                square_name(*pinned),
+                square_name(*pinner),
+                square_name(*behind)
+            ),
+            Tactic::Skewer {
+                attacker,
+                front,
+                behind,
+            } => write!(
+                f,
+                "skewer by {} of {} to {}",
+                square_name(*attacker),
+                square_name(*front),
+                square_name(*behind)
+            ),
+            Tactic::DiscoveredAttack {
+                piece,
+                slider,
+                target,
+            } => write!(
+                f,
+                "discovered attack by {} moving {} on {}",
+                square_name(*slider),
+                square_name(*piece),
+                square_name(*target)
+            ),
+        }
+    }
+}
+
+// scan finds tactical motifs of both colors, looking at attacks only: it doesn't check whether
+// attacking pieces are pinned themselves or whether the motif wins material after exchanges.
+pub fn scan(board: &Board) -> Vec<Tactic> {
+    let mut tactics = Vec::new();
+    for square in 0..64 {
+        let piece = board.piece_at(square);
+        if piece.is_none() {
+            continue;
+        }
+        if let Some(hanging) = hanging(board, square) {
+            tactics.push(hanging);
+        }
+        let targets: Vec<Square> = (0..64)
+            .filter(|t| {
+                board.piece_at(*t).color == piece.color.opposite()
+                    && attackers(board.squares(), *t, piece.color).contains(&square)
+                    && is_target(board, *t, value(piece.p_type))
+            })
+            .collect();
+        if targets.len() >= 2 {
+            tactics.push(Tactic::Fork {
+                attacker: square,
+                targets,
+            });
+        }
+        tactics.extend(lines(board, square));
+    }
+    tactics
+}
+
+// see is a static exchange evaluation of the transition: material in pawns won by the moving side
+// when both sides keep recapturing on the destination with their cheapest pieces, each side may
+// stop when recapturing doesn't pay. Pins and checks are not taken into account.
+pub fn see(board: &Board, t: &Transition) -> i32 {
+    let mut squares = *board.squares();
+    let mut gains = vec![value(squares[t.to].p_type)];
+    if t.flag == TransitionFlag::EnPassant {
+        gains[0] = value(PieceType::PAWN);
+        // the captured pawn stands next to the moving one, on the file of the destination.
+        squares[t.from - t.from % 8 + t.to % 8] = Default::default();
+    }
+    let mut moving = squares[t.from];
+    if t.flag == TransitionFlag::Promotion {
+        gains[0] += value(t.promotion) - value(PieceType::PAWN);
+        moving.p_type = t.promotion;
+    }
+    squares[t.to] = moving;
+    squares[t.from] = Default::default();
+    let mut side = moving.color.opposite();
+    while let Some(attacker) = attackers(&squares, t.to, side)
+        .into_iter()
+        .min_by_key(|a| value(squares[*a].p_type))
+    {
+        // victim is the piece that has just moved to the destination.
+        let gain = value(squares[t.to].p_type) - gains[gains.len() - 1];
+        gains.push(gain);
+        squares[t.to] = squares[attacker];
+        squares[attacker] = Default::default();
+        side = side.opposite();
+    }
+    for i in (1..gains.len()).rev() {
+        gains[i - 1] = -(-gains[i - 1]).max(gains[i]);
+    }
+    gains[0]
+}
+
+// value is a value of a piece in pawns, king is worth more than anything else.
+fn value(p_type: PieceType) -> i32 {
+    p_type.points()
+}
+
+fn defended(board: &Board, square: Square) -> bool {
+    let color = board.piece_at(square).color;
+    !attackers(board.squares(), square, color).is_empty()
+}
+
+// is_target checks if piece on a square is worth attacking with a piece of given value: it's the
+// king, it's more valuable or it's not defended.
+fn is_target(board: &Board, square: Square, attacker_value: i32) -> bool {
+    let p_type = board.piece_at(square).p_type;
+    p_type == PieceType::KING || value(p_type) > attacker_value || !defended(board, square)
+}
+
+fn hanging(board: &Board, square: Square) -> Option<Tactic> {
+    let piece = board.piece_at(square);
+    if piece.p_type == PieceType::KING {
+        return None;
+    }
+    let attackers = attackers(board.squares(), square, piece.color.opposite());
+    let cheapest = attackers
+        .iter()
+        .map(|a| value(board.piece_at(*a).p_type))
+        .min()?;
+    if defended(board, square) && cheapest >= value(piece.p_type) {
+        return None;
+    }
+    Some(Tactic::Hanging {
+        piece: square,
+        attackers,
+    })
+}
+
+// lines finds pins, skewers and discovered attacks along lines of a slider: the first two pieces
+// met in every direction.
+fn lines(board: &Board, square: Square) -> Vec<Tactic> {
+    let slider = board.piece_at(square);
+    let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+    let mut tactics = Vec::new();
+    for (df, dr) in KING_STEPS {
+        let straight = df == 0 || dr == 0;
+        match slider.p_type {
+            PieceType::QUEEN => {}
+            PieceType::ROOK if straight => {}
+            PieceType::BISHOP if !straight => {}
+            _ => continue,
+        }
+        let mut met = Vec::new();
+        let mut distance = 1;
+        while let Some(s) = square_at(file + df * distance, rank + dr * distance) {
+            if !board.piece_at(s).is_none() {
+                met.push(s);
+                if met.len() == 2 {
+                    break;
+                }
+            }
+            distance += 1;
+        }
+        let (front, behind) = match met[..] {
+            [front, behind] => (front, behind),
+            _ => continue,
+        };
+        let (f, b) = (board.piece_at(front), board.piece_at(behind));
+        let enemy = slider.color.opposite();
+        let tactic = if f.color == enemy && b.color == enemy {
+            if f.p_type != PieceType::KING && value(b.p_type) > value(f.p_type) {
+                Tactic::Pin {
+                    pinner: square,
+                    pinned: front,
+                    behind,
+                }
+            } else if value(f.p_type) > value(b.p_type) {
+                Tactic::Skewer {
+                    attacker: square,
+                    front,
+                    behind,
+                }
+            } else {
+                continue;
+            }
+        } else if f.color == slider.color
+            && b.color == enemy
+            && is_target(board, behind, value(slider.p_type))
+        {
+            Tactic::DiscoveredAttack {
+                piece: front,
+                slider: square,
+                target: behind,
+            }
+        } else {
+            continue;
+        };
+        tactics.push(tactic);
+    }
+    tactics
+}
+
+// color_of returns color of pieces that carry out the tactic.
+pub fn color_of(board: &Board, tactic: &Tactic) -> Color {
+    let square = match tactic {
+        Tactic::Hanging { piece, .. } => return board.piece_at(*piece).color.opposite(),
+        Tactic::Fork { attacker, .. } => attacker,
+        Tactic::Pin { pinner, .. } => pinner,
+        Tactic::Skewer { attacker, .. } => attacker,
+        Tactic::DiscoveredAttack { slider, .. } => slider,
+    };
+    board.piece_at(*square).color
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::notation::parse_square;
+    use crate::piece::Color;
+    use crate::tactics::{color_of, scan, see, Tactic};
+
+    fn scan_fen(fen: &str) -> Vec<String> {
+        let mut b = Board::default();
+        b.read_fen(fen).unwrap();
+        scan(&b).iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_scan() {
+        // the knight forks the king and the rook.
+        assert_eq!(
+            scan_fen("r3k3/2N5/8/8/8/8/8/4K3 b"),
+            vec!["fork by c7 of a8, e8", "hanging a8 attacked by c7"]
+        );
+        // the king and the rook are forked, the rook would be a target even if it was defended.
+        assert_eq!(
+            scan_fen("4k3/8/8/8/8/8/P1n5/R3K3 w"),
+            vec!["hanging a1 attacked by c2", "fork by c2 of a1, e1"]
+        );
+        assert_eq!(
+            scan_fen("4k3/8/2n5/1B6/8/8/8/4K3 w"),
+            vec!["pin of c6 by b5 to e8", "hanging c6 attacked by b5"]
+        );
+        assert_eq!(
+            scan_fen("r3k2R/8/8/8/8/8/8/4K3 b"),
+            vec![
+                "discovered attack by a8 moving e8 on h8",
+                "skewer by h8 of e8 to a8"
+            ]
+        );
+        // the queen is attacked by a cheaper piece, defended or not.
+        assert_eq!(
+            scan_fen("4k3/8/8/b7/8/8/3Q4/4K3 w"),
+            vec![
+                "hanging d2 attacked by a5",
+                "hanging a5 attacked by d2",
+                "pin of d2 by a5 to e1"
+            ]
+        );
+        assert!(scan_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w").is_empty());
+
+        let mut b = Board::default();
+        b.read_fen("4k3/8/8/8/8/8/4N3/4R1K1 w").unwrap();
+        let tactics = scan(&b);
+        assert_eq!(
+            tactics,
+            vec![Tactic::DiscoveredAttack {
+                piece: parse_square("e2").unwrap(),
+                slider: parse_square("e1").unwrap(),
+                target: parse_square("e8").unwrap(),
+            }]
+        );
+        assert_eq!(color_of(&b, &tactics[0]), Color::WHITE);
+    }
+
+    #[test]
+    fn test_see() {
+        let see_san = |fen: &str, san: &str| {
+            let mut b = Board::default();
+            b.read_fen(fen).unwrap();
+            let t = b
+                .generate_transitions()
+                .into_iter()
+                .find(|t| crate::notation::to_san(&b, t) == san)
+                .unwrap();
+            see(&b, &t)
+        };
+        let knight = "4k3/8/3p4/4p3/8/5N2/8/4K3 w";
+        assert_eq!(see_san(knight, "Nxe5"), 1 - 3);
+        assert_eq!(see_san(knight, "Nd4"), -3);
+        assert_eq!(see_san(knight, "Nh4"), 0);
+        assert_eq!(see_san("4k3/8/3p4/8/8/5N2/8/4K3 w", "Ne5"), -3);
+        // the rook behind takes back, so black doesn't recapture.
+        assert_eq!(see_san("4k3/4r3/8/4p3/8/8/4R3/4R1K1 w", "Rxe5"), 1);
+        assert_eq!(see_san("4k3/4r3/8/4p3/8/8/4R3/6K1 w", "Rxe5"), 1 - 5);
+        assert_eq!(see_san("4k3/8/8/3pP3/8/8/8/4K3 w - d6", "exd6"), 1);
+        assert_eq!(see_san("3rk3/2P5/8/8/8/8/8/4K3 w", "cxd8=Q+"), 5 + 8 - 9);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::board::KNIGHT_JUMPS;
+use crate::notation::{parse_square, square_at, square_name};
+use crate::piece::Color;
+use rand::Rng;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+// square_color returns color of a square, a1 is dark.
+pub fn square_color(square: usize) -> Color {
+    if (square / 8 + square % 8).is_multiple_of(2) {
+        Color::BLACK
+    } else {
+        Color::WHITE
+    }
+}
+
+// knight_path returns the shortest path of a knight between two squares, both ends included,
+// e.g. a1 -> h8 takes 6 moves.
+pub fn knight_path(from: usize, to: usize) -> Vec<usize> {
+    let mut previous = [None; 64];
+    let mut queue = VecDeque::from(vec![from]);
+    previous[from] = Some(from);
+    while let Some(square) = queue.pop_front() {
+        if square == to {
+            break;
+        }
+        let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+        for (df, dr) in &KNIGHT_JUMPS {
+            if let Some(next) = square_at(file + df, rank + dr) {
+                if previous[next].is_none() {
+                    previous[next] = Some(square);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    let mut path = vec![to];
+    while *path.last().unwrap() != from {
+        path.push(previous[*path.last().unwrap()].unwrap());
+    }
+    path.reverse();
+    path
+}
+
+// Question is a single task of coordinates training.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Question {
+    SquareColor(usize),       // answer 'light' or 'dark'
+    SquareName(usize),        // square is marked on a diagram, answer its name
+    KnightPath(usize, usize), // answer any shortest path, e.g. 'b3 c5'
+}
+
+impl Question {
+    pub fn prompt(&self) -> String {
+        match self {
+            Question::SquareColor(s) => format!("color of {}? (light/dark)", square_name(*s)),
+            Question::SquareName(s) => format!("{}\nwhich square is marked?", diagram(*s)),
+            Question::KnightPath(from, to) => format!(
+                "knight path from {} to {}? (squares after {}, separated with spaces)",
+                square_name(*from),
+                square_name(*to),
+                square_name(*from)
+            ),
+        }
+    }
+
+    // solution returns correct answer, for knight path one of the shortest ones.
+    pub fn solution(&self) -> String {
+        match self {
+            Question::SquareColor(s) if square_color(*s) == Color::WHITE => "light".to_string(),
+            Question::SquareColor(_) => "dark".to_string(),
+            Question::SquareName(s) => square_name(*s),
+            Question::KnightPath(from, to) => knight_path(*from, *to)[1..]
+                .iter()
+                .map(|s| square_name(*s))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    pub fn check(&self, answer: &str) -> bool {
+        let answer = answer.trim().to_lowercase();
+        match self {
+            Question::KnightPath(from, to) => {
+                let squares: Option<Vec<usize>> =
+                    answer.split_whitespace().map(parse_square).collect();
+                match squares {
+                    Some(squares) => {
+                        squares.len() == knight_path(*from, *to).len() - 1
+                            && squares.last() == Some(to)
+                            && is_knight_path(*from, &squares)
+                    }
+                    None => false,
+                }
+            }
+            _ => answer == self.solution(),
+        }
+    }
+}
+
+fn is_knight_path(from: usize, squares: &[usize]) -> bool {
+    let mut current = from;
+    for &next in squares {
+        let (df, dr) = (
+            next as i32 % 8 - current as i32 % 8,
+            next as i32 / 8 - current as i32 / 8,
+        );
+        if !KNIGHT_JUMPS.contains(&(df, dr)) {
+            return false;
+        }
+        current = next;
+    }
+    true
+}
+
+// diagram draws empty board from white side with given square marked with '*'.
+fn diagram(marked: usize) -> String {
+    let mut lines = Vec::new();
+    for rank in (0..8).rev() {
+        let row: String = (0..8)
+            .map(|file| if rank * 8 + file == marked { '*' } else { '.' })
+            .collect();
+        lines.push(row);
+    }
+    lines.join("\n")
+}
+
+// QuizKind selects questions generated by quiz.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuizKind {
+    Colors,
+    Squares,
+    Knight,
+}
+
+// quiz returns endless stream of random questions of given kind.
+pub fn quiz<R: Rng>(mut rng: R, kind: QuizKind) -> impl Iterator<Item = Question> {
+    std::iter::repeat_with(move || match kind {
+        QuizKind::Colors => Question::SquareColor(rng.gen_range(0..64)),
+        QuizKind::Squares => Question::SquareName(rng.gen_range(0..64)),
+        QuizKind::Knight => loop {
+            let (from, to) = (rng.gen_range(0..64), rng.gen_range(0..64));
+            if from != to {
+                break Question::KnightPath(from, to);
+            }
+        },
+    })
+}
+
+// run_quiz asks questions until they end or player types 'quit', returns number of correct
+// answers and number of asked questions.
+pub fn run_quiz<I, W, Q>(questions: Q, input: &mut I, output: &mut W) -> io::Result<(usize, usize)>
+where
+    I: BufRead,
+    W: Write,
+    Q: IntoIterator<Item = Question>,
+{
+    let (mut correct, mut asked) = (0, 0);
+    for question in questions {
+        writeln!(output, "{}", question.prompt())?;
+        output.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 || answer.trim() == "quit" {
+            break;
+        }
+        asked += 1;
+        if question.check(&answer) {
+            correct += 1;
+            writeln!(output, "correct")?;
+        } else {
+            writeln!(output, "wrong, it's {}", question.solution())?;
+        }
+    }
+    writeln!(output, "score: {}/{}", correct, asked)?;
+    Ok((correct, asked))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notation::parse_square;
+    use crate::piece::Color;
+    use crate::training::{knight_path, quiz, run_quiz, square_color, Question, QuizKind};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn sq(name: &str) -> usize {
+        parse_square(name).unwrap()
+    }
+
+    #[test]
+    fn test_square_color() {
+        assert_eq!(square_color(sq("a1")), Color::BLACK);
+        assert_eq!(square_color(sq("h1")), Color::WHITE);
+        assert_eq!(square_color(sq("d1")), Color::WHITE);
+        assert_eq!(square_color(sq("e4")), Color::WHITE);
+    }
+
+    #[test]
+    fn test_knight_path() {
+        assert_eq!(knight_path(sq("a1"), sq("h8")).len(), 7);
+        assert_eq!(knight_path(sq("a1"), sq("b2")).len(), 5);
+        assert_eq!(knight_path(sq("g1"), sq("f3")), vec![sq("g1"), sq("f3")]);
+        assert_eq!(knight_path(sq("e4"), sq("e4")), vec![sq("e4")]);
+
+        let q = Question::KnightPath(sq("g1"), sq("e5"));
+        assert!(q.check("f3 e5"));
+        assert!(q.check("F3 E5"));
+        assert!(!q.check("f3 d4"));
+        assert!(!q.check("h3 f4 e5"));
+    }
+
+    #[test]
+    fn test_run_quiz() {
+        let questions: Vec<Question> = quiz(StdRng::seed_from_u64(7), QuizKind::Colors)
+            .take(2)
+            .collect();
+        let answers = format!("{}\nwrong\n", questions[0].solution());
+        let mut output = Vec::new();
+        let score = run_quiz(questions, &mut answers.as_bytes(), &mut output).unwrap();
+        assert_eq!(score, (1, 2));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::board::{Board, Transition, PROMOTION_PIECES};
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+// ENTRY_SIZE is a number of bytes of a single entry, in memory and in files.
+pub const ENTRY_SIZE: usize = 16;
+// DEFAULT_ENTRIES is a size of the table of a new search, 1 MB.
+pub const DEFAULT_ENTRIES: usize = 1 << 16;
+
+// Bound tells how stored score relates to the real score of the position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower, // search failed high, real score is at least the stored one
+    Upper, // search failed low, real score is at most the stored one
+}
+
+// Entry is a search result of a position. Best move is packed into 16 bits: 6 bits of from and to
+// squares and 4 bits of promotion piece. In bytes the key is stored xored with the rest of the
+// entry, so an entry torn by concurrent writes of two processes doesn't match any key.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Entry {
+    key: u64,
+    score: i32,
+    depth: u8,
+    bound: u8, // 0 is an empty entry
+    best: u16,
+}
+
+impl Entry {
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth as usize
+    }
+
+    pub fn bound(&self) -> Bound {
+        match self.bound {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        }
+    }
+
+    // is_best_move checks if the transition is the stored best move, without generating moves.
+    pub fn is_best_move(&self, t: &Transition) -> bool {
+        self.best != 0 && pack_move(t) == self.best
+    }
+
+    // best_move finds stored best move among legal moves of the position.
+    pub fn best_move(&self, board: &Board) -> Option<Transition> {
+        if self.best == 0 {
+            return None;
+        }
+        board
+            .generate_transitions()
+            .into_iter()
+            .find(|t| pack_move(t) == self.best)
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[8..12].copy_from_slice(&self.score.to_le_bytes());
+        bytes[12] = self.depth;
+        bytes[13] = self.bound;
+        bytes[14..].copy_from_slice(&self.best.to_le_bytes());
+        let data = u64::from_le_bytes(bytes[8..].try_into().unwrap());
+        bytes[..8].copy_from_slice(&(self.key ^ data).to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let data = u64::from_le_bytes(bytes[8..].try_into().unwrap());
+        Entry {
+            key: u64::from_le_bytes(bytes[..8].try_into().unwrap()) ^ data,
+            score: i32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            depth: bytes[12],
+            bound: bytes[13],
+            best: u16::from_le_bytes(bytes[14..].try_into().unwrap()),
+        }
+    }
+}
+
+fn pack_move(t: &Transition) -> u16 {
+    let promotion = PROMOTION_PIECES
+        .iter()
+        .position(|p| *p == t.promotion)
+        .map_or(0, |i| i + 1);
+    (t.from | t.to << 6 | promotion << 12) as u16
+}
+
+// Storage keeps entries in memory or, with mmap feature, in a file mapped to memory.
+#[derive(Debug)]
+enum Storage {
+    Memory(Vec<Entry>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::MmapMut),
+}
+
+// TranspositionTable remembers search results of positions by their Zobrist hash, so positions
+// reached by different move orders and in previous iterations are not searched again. Entries are
+// replaced when the same position is searched again or a deeper search of another one is stored.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    storage: Storage,
+    len: usize,
+}
+
+impl PartialEq for TranspositionTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && (0..self.len).all(|i| self.entry(i) == other.entry(i))
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_ENTRIES)
+    }
+}
+
+impl TranspositionTable {
+    // new creates a table with given number of entries, rounded up to a power of two.
+    pub fn new(entries: usize) -> Self {
+        let len = entries.max(1).next_power_of_two();
+        TranspositionTable {
+            storage: Storage::Memory(vec![Entry::default(); len]),
+            len,
+        }
+    }
+
+    // open_mapped maps the table to file, so it's shared by all processes that open it and kept
+    // after they exit. New file gets given size in megabytes, existing one keeps its size.
+    #[cfg(feature = "mmap")]
+    pub fn open_mapped<P: AsRef<std::path::Path>>(path: P, megabytes: usize) -> io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        if file.metadata()?.len() == 0 {
+            let len = TranspositionTable::with_megabytes(megabytes).len();
+            file.set_len((len * ENTRY_SIZE) as u64)?;
+        }
+        let len = file.metadata()?.len() as usize / ENTRY_SIZE;
+        if !len.is_power_of_two() || len * ENTRY_SIZE != file.metadata()?.len() as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "size of transposition table file is not a power of two of entries",
+            ));
+        }
+        // the file may be changed by other processes at any time, torn entries are detected by
+        // their keys.
+        let map = unsafe { memmap2::MmapMut::map_mut(&file)? };
+        Ok(TranspositionTable {
+            storage: Storage::Mapped(map),
+            len,
+        })
+    }
+
+    // flush writes changes of mapped table to its file, it's a no-op for table in memory.
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.storage {
+            Storage::Memory(_) => Ok(()),
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(map) => map.flush(),
+        }
+    }
+
+    fn entry(&self, index: usize) -> Entry {
+        match &self.storage {
+            Storage::Memory(entries) => entries[index],
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(map) => Entry::from_bytes(&map[index * ENTRY_SIZE..][..ENTRY_SIZE]),
+        }
+    }
+
+    fn set_entry(&mut self, index: usize, entry: Entry) {
+        match &mut self.storage {
+            Storage::Memory(entries) => entries[index] = entry,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(map) => {
+                map[index * ENTRY_SIZE..][..ENTRY_SIZE].copy_from_slice(&entry.to_bytes())
+            }
+        }
+    }
+
+    // with_megabytes creates the largest table that fits in given size.
+    pub fn with_megabytes(megabytes: usize) -> Self {
+        let entries = (megabytes << 20) / ENTRY_SIZE;
+        TranspositionTable::new((entries.max(2) / 2 + 1).next_power_of_two())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        for i in 0..self.len {
+            self.set_entry(i, Entry::default());
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.len - 1)
+    }
+
+    // get returns entry of the position with given hash.
+    pub fn get(&self, key: u64) -> Option<Entry> {
+        let entry = self.entry(self.index(key));
+        (entry.bound != 0 && entry.key == key).then_some(entry)
+    }
+
+    // store saves search result of the position, score is from the point of view of color that
+    // has the move.
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: usize,
+        score: i32,
+        bound: Bound,
+        best: Option<&Transition>,
+    ) {
+        let index = self.index(key);
+        let old = self.entry(index);
+        if old.bound != 0 && old.key != key && old.depth as usize > depth {
+            return;
+        }
+        let entry = Entry {
+            key,
+            score,
+            depth: depth.min(u8::MAX as usize) as u8,
+            bound: match bound {
+                Bound::Exact => 1,
+                Bound::Lower => 2,
+                Bound::Upper => 3,
+            },
+            best: best.map_or(0, pack_move),
+        };
+        self.set_entry(index, entry);
+    }
+
+    // write stores the table as little endian entries.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for i in 0..self.len {
+            out.write_all(&self.entry(i).to_bytes())?;
+        }
+        Ok(())
+    }
+
+    // read reads table of given number of entries written by write.
+    pub fn read<R: Read>(input: &mut R, entries: usize) -> io::Result<Self> {
+        if !entries.is_power_of_two() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "size of transposition table must be a power of two",
+            ));
+        }
+        let mut bytes = vec![0; entries * ENTRY_SIZE];
+        input.read_exact(&mut bytes)?;
+        Ok(TranspositionTable {
+            storage: Storage::Memory(bytes.chunks(ENTRY_SIZE).map(Entry::from_bytes).collect()),
+            len: entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::notation::parse_san;
+    use crate::tt::{Bound, TranspositionTable, ENTRY_SIZE};
+
+    #[test]
+    fn test_store_and_read() {
+        let mut b = Board::default();
+        b.read_fen("8/4P1k1/8/8/8/8/8/4K3 w").unwrap();
+        let promotion = parse_san(&b, "e8=N+").unwrap();
+
+        let mut tt = TranspositionTable::new(1000);
+        assert_eq!(tt.len(), 1024);
+        tt.store(42, 5, -120, Bound::Lower, Some(&promotion));
+        tt.store(42 + 1024, 3, 50, Bound::Exact, None); // shallower search of another position
+        let entry = tt.get(42).unwrap();
+        assert_eq!((entry.depth(), entry.score()), (5, -120));
+        assert_eq!(entry.bound(), Bound::Lower);
+        assert_eq!(entry.best_move(&b).unwrap().promotion, promotion.promotion);
+        assert!(entry.is_best_move(&promotion));
+        assert!(!entry.is_best_move(&parse_san(&b, "e8=Q").unwrap()));
+        assert_eq!(tt.get(43), None);
+
+        let mut bytes = Vec::new();
+        tt.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 1024 * ENTRY_SIZE);
+        assert_eq!(TranspositionTable::read(&mut &bytes[..], 1024).unwrap(), tt);
+        assert_eq!(TranspositionTable::with_megabytes(1).len(), 1 << 16);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mapped() {
+        let path = std::env::temp_dir().join(format!("chust-tt-{}", std::process::id()));
+        let mut tt = TranspositionTable::open_mapped(&path, 1).unwrap();
+        assert_eq!(tt.len(), 1 << 16);
+        tt.store(42, 5, -120, Bound::Upper, None);
+        tt.flush().unwrap();
+
+        // another process sees the entry and size of the file.
+        let other = TranspositionTable::open_mapped(&path, 4).unwrap();
+        assert_eq!(other.len(), 1 << 16);
+        assert_eq!(other.get(42).unwrap().score(), -120);
+        assert!(other == tt);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,753 @@
+use crate::arena::{Clock, TimeControl};
+use crate::board::{Board, Transition};
+use crate::engine::ENGINE_INFO;
+use crate::evaluation::Evaluator;
+use crate::notation::{line_to_san, parse_san, to_internal, to_san, to_spoken};
+use crate::pgn::{
+    movetext_elements, parse_clock, split_games, MoveAnnotation, MovetextElement, PgnExportOptions,
+    PgnGame,
+};
+use crate::piece::{Color, PieceType};
+use crate::search::{Score, Search};
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    MouseButton, MouseEventKind,
+};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color as TermColor, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const HELP: &str =
+    "arrows + space or mouse: pick piece and square | type move (e4, g1f3) + Enter | 'undo' | Esc: quit";
+
+// SQUARE_WIDTH is a number of terminal columns of a single square.
+const SQUARE_WIDTH: u16 = 3;
+// LABEL_WIDTH is a number of terminal columns of rank labels left to the board.
+const LABEL_WIDTH: u16 = 2;
+// PLAYER is the name of the player in PGN of the game, the engine is named by ENGINE_INFO.
+const PLAYER: &str = "player";
+// TICK is how often the screen is redrawn while clocks run.
+const TICK: Duration = Duration::from_millis(200);
+
+// side returns index of the clock of the color.
+fn side(color: Color) -> usize {
+    if color == Color::WHITE {
+        0
+    } else {
+        1
+    }
+}
+
+// Play is a game against the engine, kept apart from the terminal so moves can be made by tests.
+// With a time control both sides play on clocks, the engine searches for a part of its time, up to
+// the depth.
+pub struct Play<'a> {
+    evaluator: &'a dyn Evaluator,
+    pub board: Board,
+    history: Vec<(Board, Transition, Option<[Clock; 2]>)>, // clocks before the move
+    pub player: Color,
+    pub depth: usize,
+    pub cursor: usize,
+    pub selected: Option<usize>,
+    score: Option<Score>, // from the point of view of white
+    time_control: Option<TimeControl>,
+    clocks: Option<[Clock; 2]>,
+    turn_started: Instant,
+    flag: Option<Color>, // color that ran out of time
+}
+
+impl<'a> Play<'a> {
+    pub fn new(evaluator: &'a dyn Evaluator, player: Color, depth: usize) -> Self {
+        Play {
+            evaluator,
+            board: Board::default(),
+            history: Vec::new(),
+            player,
+            depth,
+            cursor: if player == Color::BLACK { 52 } else { 12 },
+            selected: None,
+            score: None,
+            time_control: None,
+            clocks: None,
+            turn_started: Instant::now(),
+            flag: None,
+        }
+    }
+
+    // with_time_control starts clocks of both sides.
+    pub fn with_time_control(mut self, time_control: TimeControl) -> Self {
+        self.time_control = Some(time_control);
+        self.clocks = Some([Clock::new(&time_control); 2]);
+        self.turn_started = Instant::now();
+        self
+    }
+
+    // resume continues a game saved by to_pgn, the player plays the color named PLAYER, clocks
+    // are set from %clk comments and start again from the position after the last move.
+    pub fn resume(
+        evaluator: &'a dyn Evaluator,
+        depth: usize,
+        game: &PgnGame,
+    ) -> Result<Self, String> {
+        let player = if game.tag("White") == Some(PLAYER) {
+            Color::WHITE
+        } else {
+            Color::BLACK
+        };
+        let mut play = Play::new(evaluator, player, depth);
+        if let Some(tc) = game.tag("TimeControl") {
+            play = play.with_time_control(tc.parse().map_err(|e: &str| e.to_string())?);
+        }
+        play.board = game.start_position().map_err(|e| e.to_string())?;
+        let mut depth = 0; // of variations, the saved game has none
+        for element in movetext_elements(&game.movetext) {
+            match element {
+                MovetextElement::Start => depth += 1,
+                MovetextElement::End => depth -= 1,
+                MovetextElement::Move(m) if depth == 0 => {
+                    let t = parse_san(&play.board, &m).map_err(|e| format!("{}: {}", m, e))?;
+                    play.history.push((play.board.clone(), t, play.clocks));
+                    play.board.make_transition(t);
+                }
+                MovetextElement::Comment(c) if depth == 0 => {
+                    let mover = side(play.board.color_to_move.opposite());
+                    if let (Some(clocks), Some(remaining)) = (play.clocks.as_mut(), parse_clock(&c))
+                    {
+                        clocks[mover].remaining = remaining;
+                    }
+                }
+                _ => {}
+            }
+        }
+        play.cursor = if player == Color::BLACK { 52 } else { 12 };
+        Ok(play)
+    }
+
+    pub fn last_move(&self) -> Option<&Transition> {
+        self.history.last().map(|(_, t, _)| t)
+    }
+
+    // narrate_last_move describes the last move in words, for screen readers.
+    pub fn narrate_last_move(&self) -> Option<String> {
+        self.history.last().map(|(b, t, _)| to_spoken(b, t))
+    }
+
+    // remaining returns time left on the clock of the color, running down while it's to move.
+    pub fn remaining(&self, color: Color) -> Option<Duration> {
+        let clock = self.clocks?[side(color)];
+        if color == self.board.color_to_move && self.result().is_none() {
+            Some(clock.remaining.saturating_sub(self.turn_started.elapsed()))
+        } else {
+            Some(clock.remaining)
+        }
+    }
+
+    // check_flag ends the game when the color to move runs out of time.
+    pub fn check_flag(&mut self) {
+        let color = self.board.color_to_move;
+        if self.result().is_none() && self.remaining(color) == Some(Duration::ZERO) {
+            self.flag = Some(color);
+        }
+    }
+
+    // result returns description of finished game, None while it lasts.
+    pub fn result(&self) -> Option<String> {
+        if let Some(color) = self.flag {
+            return Some(format!("{} lost on time", color.to_string().to_lowercase()));
+        }
+        if !self.board.generate_transitions().is_empty() {
+            return None;
+        }
+        Some(if self.board.is_in_check() {
+            format!(
+                "checkmate, {} wins",
+                self.board
+                    .color_to_move
+                    .opposite()
+                    .to_string()
+                    .to_lowercase()
+            )
+        } else {
+            "stalemate".to_string()
+        })
+    }
+
+    // result_tag returns result of the game as written in PGN, '*' while it lasts.
+    fn result_tag(&self) -> &'static str {
+        match self.flag {
+            Some(Color::WHITE) => return "0-1",
+            Some(_) => return "1-0",
+            None => {}
+        }
+        match self.result() {
+            None => "*",
+            Some(_) if !self.board.is_in_check() => "1/2-1/2",
+            Some(_) if self.board.color_to_move == Color::WHITE => "0-1",
+            Some(_) => "1-0",
+        }
+    }
+
+    pub fn engine_to_move(&self) -> bool {
+        self.board.color_to_move != self.player && self.result().is_none()
+    }
+
+    // select picks square clicked or chosen with cursor: first a piece of the player, then its
+    // destination. Pawns reaching last rank become queens, other promotions can be typed.
+    pub fn select(&mut self, square: usize) -> Result<(), String> {
+        if self.engine_to_move() || self.result().is_some() {
+            return Err("it is not your move".to_string());
+        }
+        if let Some(from) = self.selected.take() {
+            let moves = self.board.generate_transitions();
+            let mut candidates = moves.iter().filter(|t| t.from == from && t.to == square);
+            let first = candidates.clone().next();
+            if let Some(t) = candidates
+                .find(|t| t.promotion == PieceType::QUEEN)
+                .or(first)
+            {
+                self.play(*t);
+                return Ok(());
+            }
+        }
+        if self.board.piece_at(square).color == self.player {
+            self.selected = Some(square);
+        }
+        Ok(())
+    }
+
+    // enter_move makes move typed in SAN or internal notation, or takes back moves on 'undo'.
+    pub fn enter_move(&mut self, text: &str) -> Result<(), String> {
+        let text = text.trim();
+        if text == "undo" {
+            return self.undo();
+        }
+        if self.engine_to_move() || self.result().is_some() {
+            return Err("it is not your move".to_string());
+        }
+        let t = parse_san(&self.board, text)
+            .ok()
+            .or_else(|| {
+                self.board
+                    .generate_transitions()
+                    .into_iter()
+                    .find(|t| to_internal(t) == text)
+            })
+            .ok_or_else(|| format!("illegal move: {}", text))?;
+        self.play(t);
+        Ok(())
+    }
+
+    // undo takes back moves up to the previous move of the player, with time they took.
+    pub fn undo(&mut self) -> Result<(), String> {
+        loop {
+            let (board, _, clocks) = self
+                .history
+                .pop()
+                .ok_or_else(|| "there is no move to take back".to_string())?;
+            self.board = board;
+            self.clocks = clocks;
+            self.flag = None;
+            if self.board.color_to_move == self.player {
+                self.selected = None;
+                self.turn_started = Instant::now();
+                return Ok(());
+            }
+        }
+    }
+
+    // engine_move searches the position and plays the best move when it is engine's turn.
+    pub fn engine_move(&mut self) {
+        if !self.engine_to_move() {
+            return;
+        }
+        let mut search = Search::new(self.evaluator);
+        if let Some(clocks) = &self.clocks {
+            let budget = clocks[side(self.board.color_to_move)].budget();
+            search.deadline = Some(self.turn_started + budget);
+        }
+        let lines = search.iterate(&self.board, self.depth, 1, |_, _| {});
+        if let Some(line) = lines.first() {
+            self.score = Some(match self.board.color_to_move {
+                Color::BLACK => line.score.flip(),
+                _ => line.score,
+            });
+            if let Some(t) = line.pv.first() {
+                self.play(*t);
+            }
+        }
+    }
+
+    // play makes the move and stops the clock of the color, the move is not made when its time
+    // is up.
+    fn play(&mut self, t: Transition) {
+        let before = self.clocks;
+        if let Some(clocks) = self.clocks.as_mut() {
+            let color = self.board.color_to_move;
+            if !clocks[side(color)].punch(self.turn_started.elapsed()) {
+                self.flag = Some(color);
+                return;
+            }
+        }
+        self.history.push((self.board.clone(), t, before));
+        self.board.make_transition(t);
+        self.selected = None;
+        self.turn_started = Instant::now();
+    }
+
+    // moves returns moves of the game in SAN.
+    pub fn moves(&self) -> Vec<String> {
+        self.history.iter().map(|(b, t, _)| to_san(b, t)).collect()
+    }
+
+    // to_pgn returns the game with time left after every move in %clk comments.
+    pub fn to_pgn(&self) -> PgnGame {
+        let start = self.history.first().map_or(&self.board, |(b, _, _)| b);
+        let engine = ENGINE_INFO.to_string();
+        let (white, black) = match self.player {
+            Color::WHITE => (PLAYER.to_string(), engine),
+            _ => (engine, PLAYER.to_string()),
+        };
+        let mut tags = vec![
+            ("Event".to_string(), "chust tui".to_string()),
+            ("White".to_string(), white),
+            ("Black".to_string(), black),
+            ("Result".to_string(), self.result_tag().to_string()),
+        ];
+        if start.to_fen() != Board::default().to_fen() {
+            tags.push(("FEN".to_string(), start.to_fen()));
+            tags.push(("SetUp".to_string(), "1".to_string()));
+        }
+        if let Some(tc) = self.time_control {
+            tags.push(("TimeControl".to_string(), tc.to_string()));
+        }
+        let moves: Vec<Transition> = self.history.iter().map(|(_, t, _)| *t).collect();
+        let game = PgnGame {
+            tags,
+            movetext: format!("{} {}", line_to_san(start, &moves), self.result_tag()),
+        };
+        // clocks after a move are the clocks before the next one.
+        let after = self.history.iter().skip(1).map(|(_, _, c)| *c);
+        let annotations: Vec<MoveAnnotation> = self
+            .history
+            .iter()
+            .zip(after.chain(std::iter::once(self.clocks)))
+            .map(|((b, _, _), clocks)| MoveAnnotation {
+                clock: clocks.map(|c| c[side(b.color_to_move)].remaining),
+                ..Default::default()
+            })
+            .collect();
+        match game.export(&PgnExportOptions::default(), &annotations) {
+            Ok(pgn) => split_games(&pgn).remove(0),
+            Err(_) => game,
+        }
+    }
+
+    // autosave writes the game to recovery file through a temporary file, so a crash while
+    // writing keeps the previous save. A finished game is removed, there's nothing to resume.
+    pub fn autosave(&self, path: &Path) -> io::Result<()> {
+        if self.result().is_some() {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, self.to_pgn().to_string())?;
+        fs::rename(&temp, path)
+    }
+
+    // flipped checks if board is shown from black side.
+    fn flipped(&self) -> bool {
+        self.player == Color::BLACK
+    }
+
+    // move_cursor moves cursor by files and ranks as seen on the screen, not past board edge.
+    fn move_cursor(&mut self, files: i32, ranks: i32) {
+        let (files, ranks) = if self.flipped() {
+            (-files, -ranks)
+        } else {
+            (files, ranks)
+        };
+        let file = (self.cursor % 8) as i32 + files;
+        let rank = (self.cursor / 8) as i32 + ranks;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            self.cursor = (rank * 8 + file) as usize;
+        }
+    }
+}
+
+// Tui is terminal user interface of a game against the engine.
+struct Tui<'a> {
+    play: Play<'a>,
+    input: String,
+    message: String,
+    board_area: Rect, // where squares were drawn last time, used to find clicked square
+    recovery: Option<PathBuf>, // the game is saved there after every move
+}
+
+// run plays the game against the engine in terminal until Esc is pressed. With recovery file the
+// game is saved after every move, so it can be resumed after a crash, see Play::resume.
+pub fn run(play: Play, recovery: Option<PathBuf>) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    execute!(io::stdout(), EnableMouseCapture)?;
+    let mut tui = Tui {
+        play,
+        input: String::new(),
+        message: HELP.to_string(),
+        board_area: Rect::default(),
+        recovery,
+    };
+    let result = tui.run(&mut terminal);
+    execute!(io::stdout(), DisableMouseCapture)?;
+    ratatui::restore();
+    result
+}
+
+impl Tui<'_> {
+    // autosave saves the game to recovery file, failures are shown in the status line.
+    fn autosave(&mut self) {
+        if let Some(path) = &self.recovery {
+            if let Err(e) = self.play.autosave(path) {
+                self.message = format!("can't save {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            if self.play.engine_to_move() {
+                self.message = "thinking...".to_string();
+                terminal.draw(|f| self.draw(f))?;
+                self.play.engine_move();
+                self.message = self.play.result().unwrap_or_else(|| {
+                    let narration = self.play.narrate_last_move().unwrap_or_default();
+                    format!("engine played {} | {}", narration, HELP)
+                });
+                self.autosave();
+            }
+            terminal.draw(|f| self.draw(f))?;
+            // running clocks are redrawn until there's an event.
+            if self.play.clocks.is_some() && !event::poll(TICK)? {
+                if self.play.result().is_none() {
+                    self.play.check_flag();
+                    if let Some(result) = self.play.result() {
+                        self.message = result;
+                        self.autosave();
+                    }
+                }
+                continue;
+            }
+            let moves = self.play.history.len();
+            let result = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Left => {
+                        self.play.move_cursor(-1, 0);
+                        Ok(())
+                    }
+                    KeyCode::Right => {
+                        self.play.move_cursor(1, 0);
+                        Ok(())
+                    }
+                    KeyCode::Up => {
+                        self.play.move_cursor(0, 1);
+                        Ok(())
+                    }
+                    KeyCode::Down => {
+                        self.play.move_cursor(0, -1);
+                        Ok(())
+                    }
+                    KeyCode::Char(' ') => self.play.select(self.play.cursor),
+                    KeyCode::Char(c) => {
+                        self.input.push(c);
+                        Ok(())
+                    }
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                        Ok(())
+                    }
+                    KeyCode::Enter if self.input.is_empty() => self.play.select(self.play.cursor),
+                    KeyCode::Enter => {
+                        let input = std::mem::take(&mut self.input);
+                        self.play.enter_move(&input)
+                    }
+                    _ => Ok(()),
+                },
+                Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    match self.clicked_square(mouse.column, mouse.row) {
+                        Some(square) => {
+                            self.play.cursor = square;
+                            self.play.select(square)
+                        }
+                        None => Ok(()),
+                    }
+                }
+                _ => Ok(()),
+            };
+            self.message = match result {
+                Err(e) => e,
+                Ok(()) => self.play.result().unwrap_or_else(|| HELP.to_string()),
+            };
+            if self.play.history.len() != moves || self.play.flag.is_some() {
+                self.autosave();
+            }
+        }
+    }
+
+    // clicked_square returns square under terminal cell, if any.
+    fn clicked_square(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.board_area;
+        if column < area.x || row < area.y {
+            return None;
+        }
+        let file = ((column - area.x) / SQUARE_WIDTH) as usize;
+        let row = (row - area.y) as usize;
+        if file >= 8 || row >= 8 {
+            return None;
+        }
+        Some(match self.play.flipped() {
+            false => (7 - row) * 8 + file,
+            true => row * 8 + 7 - file,
+        })
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, input, status] = Layout::vertical([
+            Constraint::Min(10),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [board, bar, moves] = Layout::horizontal([
+            Constraint::Length(LABEL_WIDTH + 8 * SQUARE_WIDTH + 2),
+            Constraint::Length(5),
+            Constraint::Min(20),
+        ])
+        .areas(main);
+
+        let block = Block::default().borders(Borders::ALL).title(" chust ");
+        let inner = block.inner(board);
+        self.board_area = Rect {
+            x: inner.x + LABEL_WIDTH,
+            y: inner.y,
+            width: 8 * SQUARE_WIDTH,
+            height: 8,
+        };
+        frame.render_widget(Paragraph::new(self.board_lines()).block(block), board);
+        frame.render_widget(self.eval_bar(), bar);
+        frame.render_widget(self.move_list(), moves);
+        frame.render_widget(
+            Paragraph::new(format!("{}_", self.input))
+                .block(Block::default().borders(Borders::ALL).title(" move ")),
+            input,
+        );
+        frame.render_widget(Paragraph::new(self.message.as_str()), status);
+    }
+
+    fn board_lines(&self) -> Vec<Line<'static>> {
+        let play = &self.play;
+        let last = play.last_move().map(|t| [t.from, t.to]);
+        let mut lines = Vec::new();
+        for row in 0..8 {
+            let rank = if play.flipped() { row } else { 7 - row };
+            let mut spans = vec![Span::raw(format!("{} ", rank + 1))];
+            for column in 0..8 {
+                let file = if play.flipped() { 7 - column } else { column };
+                let square = rank * 8 + file;
+                let background = if play.selected == Some(square) {
+                    TermColor::Rgb(106, 168, 79)
+                } else if square == play.cursor {
+                    TermColor::Rgb(100, 149, 237)
+                } else if last.is_some_and(|l| l.contains(&square)) {
+                    TermColor::Rgb(205, 210, 106)
+                } else if (rank + file) % 2 == 0 {
+                    TermColor::Rgb(181, 136, 99)
+                } else {
+                    TermColor::Rgb(240, 217, 181)
+                };
+                let piece = play.board.piece_at(square);
+                let foreground = match piece.color {
+                    Color::WHITE => TermColor::White,
+                    _ => TermColor::Black,
+                };
+                let letter = match piece.p_type {
+                    PieceType::NONE => " ".to_string(),
+                    PieceType::PAWN => "P".to_string(),
+                    p_type => p_type.sign().to_string(),
+                };
+                spans.push(Span::styled(
+                    format!(" {} ", letter),
+                    Style::default()
+                        .fg(foreground)
+                        .bg(background)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+        let files: String = (0..8)
+            .map(|i| {
+                let file = if play.flipped() { 7 - i } else { i };
+                format!(" {} ", (b'a' + file as u8) as char)
+            })
+            .collect();
+        lines.push(Line::from(format!("  {}", files)));
+        lines
+    }
+
+    // eval_bar shows winning chances of white as white part of a bar next to the board.
+    fn eval_bar(&self) -> Paragraph<'static> {
+        let chances = self.play.score.map_or(0.5, |s| s.win_probability());
+        let white_rows = (chances * 8.0).round() as usize;
+        let mut lines: Vec<Line> = (0..8)
+            .map(|row| {
+                // white is at the bottom of the bar as on the board shown from white side.
+                let white = if self.play.flipped() {
+                    row < white_rows
+                } else {
+                    row >= 8 - white_rows
+                };
+                let color = if white {
+                    TermColor::White
+                } else {
+                    TermColor::DarkGray
+                };
+                Line::from(Span::styled("   ", Style::default().bg(color)))
+            })
+            .collect();
+        lines.push(Line::from(
+            self.play.score.map_or("".to_string(), |s| s.to_string()),
+        ));
+        Paragraph::new(lines).block(Block::default().borders(Borders::TOP | Borders::BOTTOM))
+    }
+
+    fn move_list(&self) -> Paragraph<'static> {
+        let clock = |color| {
+            self.play.remaining(color).map_or(String::new(), |t| {
+                let seconds = t.as_secs();
+                format!("{}:{:02}", seconds / 60, seconds % 60)
+            })
+        };
+        let title = match self.play.clocks {
+            Some(_) => format!(
+                " moves | white {} | black {} ",
+                clock(Color::WHITE),
+                clock(Color::BLACK)
+            ),
+            None => " moves ".to_string(),
+        };
+        let moves = self.play.moves();
+        let lines: Vec<Line> = moves
+            .chunks(2)
+            .enumerate()
+            .map(|(i, pair)| Line::from(format!("{:>3}. {}", i + 1, pair.join(" "))))
+            .collect();
+        // the latest moves are kept visible when list is longer than the panel.
+        let scroll = lines.len().saturating_sub(8) as u16;
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0))
+            .block(Block::default().borders(Borders::ALL).title(title))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::SimpleEvaluator;
+    use crate::notation::parse_square;
+    use crate::pgn::split_games;
+    use crate::piece::Color;
+    use crate::tui::{side, Play};
+    use std::time::Duration;
+
+    #[test]
+    fn test_play() {
+        let evaluator = SimpleEvaluator {};
+        let mut play = Play::new(&evaluator, Color::WHITE, 1);
+        assert!(!play.engine_to_move());
+        let square = |name| parse_square(name).unwrap();
+
+        // empty square and opponent's piece are not selected.
+        play.select(square("e4")).unwrap();
+        play.select(square("e7")).unwrap();
+        assert_eq!(play.selected, None);
+        play.select(square("e2")).unwrap();
+        assert_eq!(play.selected, Some(square("e2")));
+        play.select(square("e4")).unwrap();
+        assert_eq!(play.moves(), vec!["e4"]);
+
+        assert!(play.engine_to_move());
+        assert!(play.enter_move("e5").is_err());
+        play.engine_move();
+        assert_eq!(play.moves().len(), 2);
+        assert!(play.score.is_some());
+        assert!(play.narrate_last_move().unwrap().contains(" from "));
+
+        assert!(play.enter_move("Ke3").is_err());
+        play.enter_move("g1f3").unwrap();
+        assert_eq!(play.moves()[2], "Nf3");
+        play.undo().unwrap();
+        assert_eq!(play.moves().len(), 2);
+        play.enter_move("undo").unwrap();
+        assert!(play.moves().is_empty());
+        assert!(play.undo().is_err());
+
+        play.board.read_fen("7k/5Q2/6K1/8/8/8/8/8 b").unwrap();
+        assert_eq!(play.result().unwrap(), "stalemate");
+        play.board.read_fen("5Q1k/8/6K1/8/8/8/8/8 b").unwrap();
+        assert_eq!(play.result().unwrap(), "checkmate, white wins");
+    }
+
+    #[test]
+    fn test_autosave() {
+        let evaluator = SimpleEvaluator {};
+        let path = std::env::temp_dir().join(format!("chust-tui-{}.pgn", std::process::id()));
+        let mut play =
+            Play::new(&evaluator, Color::BLACK, 1).with_time_control("60+1".parse().unwrap());
+        play.engine_move();
+        play.enter_move("e5").unwrap();
+        play.engine_move();
+        play.autosave(&path).unwrap();
+
+        let pgn = std::fs::read_to_string(&path).unwrap();
+        let game = &split_games(&pgn)[0];
+        assert_eq!(game.tag("Black"), Some("player"));
+        assert_eq!(game.tag("TimeControl"), Some("60+1"));
+        assert_eq!(game.tag("Result"), Some("*"));
+        assert_eq!(pgn.matches("[%clk 0:01:0").count(), 3);
+        let resumed = Play::resume(&evaluator, 1, game).unwrap();
+        assert_eq!(resumed.player, Color::BLACK);
+        assert_eq!(resumed.moves(), play.moves());
+        assert_eq!(resumed.board.to_fen(), play.board.to_fen());
+        let clock = |p: &Play, color| p.clocks.unwrap()[side(color)].remaining.as_secs();
+        assert_eq!(clock(&resumed, Color::WHITE), clock(&play, Color::WHITE));
+        assert_eq!(clock(&resumed, Color::BLACK), clock(&play, Color::BLACK));
+
+        // undo gives back time of the moves.
+        play.undo().unwrap();
+        assert_eq!(
+            play.clocks.unwrap()[side(Color::BLACK)].remaining.as_secs(),
+            60
+        );
+
+        // running out of time ends the game, and a finished game is not kept.
+        play.clocks.as_mut().unwrap()[side(Color::BLACK)].remaining = Duration::ZERO;
+        play.check_flag();
+        assert_eq!(play.result().unwrap(), "black lost on time");
+        assert!(play.enter_move("e5").is_err());
+        assert_eq!(play.to_pgn().tag("Result"), Some("1-0"));
+        play.autosave(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,1135 @@
+use crate::arena::Clock;
+use crate::backend::{backend_by_name, SearchLimits, BACKEND_NAMES, DEFAULT_PLAYOUTS};
+use crate::board::Board;
+use crate::book::OpeningBook;
+use crate::engine::{instant_move, InstantSource, ENGINE_INFO};
+use crate::evaluation::{evaluator_by_name, Evaluator, EVALUATOR_NAMES};
+use crate::experience::ExperienceBook;
+use crate::notation::{parse_uci, to_internal};
+use crate::params::{EvalParams, ParamsEvaluator};
+use crate::piece::Color;
+use crate::search::{Score, SearchLine, StopSignal, DEFAULT_POLL_INTERVAL};
+use crate::sparring::{PositionType, Sparring, POSITION_TYPE_NAMES};
+use crate::zobrist::hash;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// DEFAULT_DEPTH is a depth of 'go' without limits.
+const DEFAULT_DEPTH: usize = 6;
+const MAX_DEPTH: usize = 64;
+const MAX_MULTIPV: i64 = 64;
+// CONTEMPT_ELO is a rating difference, in Elo, that changes contempt by one centipawn.
+const CONTEMPT_ELO: i32 = 10;
+const MAX_CONTEMPT: i32 = 200;
+// TIME_ELO is a rating difference at which twice as much time is used against the stronger
+// player, or half as much against the weaker one.
+const TIME_ELO: f32 = 400.0;
+// CASUAL_TIME_SCALE is a part of time used in casual games, nothing is lost with them.
+const CASUAL_TIME_SCALE: f32 = 0.75;
+
+// COMMANDS are all commands sent by GUIs.
+const COMMANDS: [&str; 11] = [
+    "uci",
+    "debug",
+    "isready",
+    "setoption",
+    "register",
+    "ucinewgame",
+    "position",
+    "go",
+    "stop",
+    "ponderhit",
+    "quit",
+];
+
+// GO_LIMITS are parameters of 'go' followed by a number.
+const GO_LIMITS: [&str; 9] = [
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+];
+
+// command returns words of a command, starting from the first known command. Unknown words in
+// front of it are skipped, as required by UCI, e.g. 'joho debug on' is 'debug on'.
+fn command(line: &str) -> Vec<&str> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.iter().position(|w| COMMANDS.contains(w)) {
+        Some(i) => words[i..].to_vec(),
+        None => Vec::new(),
+    }
+}
+
+// OptionKind is a type of UCI option with its default value and bounds.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionKind {
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Combo {
+        default: &'static str,
+        values: Vec<&'static str>,
+    },
+    Check {
+        default: bool,
+    },
+    String {
+        default: &'static str, // '<empty>' is an empty string
+    },
+    Button,
+}
+
+// UciOption is an option reported after 'uci' command and changed with 'setoption'.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UciOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+}
+
+impl Display for UciOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match &self.kind {
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin default {} min {} max {}", default, min, max)
+            }
+            OptionKind::Combo { default, values } => {
+                write!(f, "combo default {}", default)?;
+                values.iter().try_for_each(|v| write!(f, " var {}", v))
+            }
+            OptionKind::Check { default } => write!(f, "check default {}", default),
+            OptionKind::String { default } => write!(f, "string default {}", default),
+            OptionKind::Button => write!(f, "button"),
+        }
+    }
+}
+
+// options returns all options supported by the engine.
+pub fn options() -> Vec<UciOption> {
+    vec![
+        UciOption {
+            name: "Search",
+            kind: OptionKind::Combo {
+                default: "alphabeta",
+                values: BACKEND_NAMES.to_vec(),
+            },
+        },
+        UciOption {
+            name: "Evaluator",
+            kind: OptionKind::Combo {
+                default: "material-mobility",
+                values: EVALUATOR_NAMES.to_vec(),
+            },
+        },
+        // parameters of ParamsEvaluator, which replaces Evaluator when the file is set.
+        UciOption {
+            name: "EvalFile",
+            kind: OptionKind::String { default: "<empty>" },
+        },
+        // ReloadEval reads EvalFile again, so do SIGHUP signals with 'sighup' feature.
+        UciOption {
+            name: "ReloadEval",
+            kind: OptionKind::Button,
+        },
+        UciOption {
+            name: "MultiPV",
+            kind: OptionKind::Spin {
+                default: 1,
+                min: 1,
+                max: MAX_MULTIPV,
+            },
+        },
+        UciOption {
+            name: "Playouts",
+            kind: OptionKind::Spin {
+                default: DEFAULT_PLAYOUTS as i64,
+                min: 1,
+                max: 100_000_000,
+            },
+        },
+        UciOption {
+            name: "Sparring",
+            kind: OptionKind::Combo {
+                default: "none",
+                values: [&["none"], &POSITION_TYPE_NAMES[..]].concat(),
+            },
+        },
+        UciOption {
+            name: "Experience",
+            kind: OptionKind::String { default: "<empty>" },
+        },
+        // PGN games, whose openings are played without a search.
+        UciOption {
+            name: "BookFile",
+            kind: OptionKind::String { default: "<empty>" },
+        },
+        // InstantMoves plays moves of BookFile and won or drawn tablebase positions at once.
+        UciOption {
+            name: "InstantMoves",
+            kind: OptionKind::Check { default: true },
+        },
+        // Contempt is centipawns lost with a draw against an equally rated opponent. When Rating
+        // and rating of UCI_Opponent are known, it grows by a centipawn for every 10 Elo the
+        // opponent is weaker, and time usage grows with strength of the opponent. Casual games
+        // are played with less time and never for a draw, bot bridges set RatedGame every game.
+        UciOption {
+            name: "Contempt",
+            kind: OptionKind::Spin {
+                default: 0,
+                min: -MAX_CONTEMPT as i64,
+                max: MAX_CONTEMPT as i64,
+            },
+        },
+        UciOption {
+            name: "Rating",
+            kind: OptionKind::Spin {
+                default: 0, // unknown
+                min: 0,
+                max: 4000,
+            },
+        },
+        UciOption {
+            name: "RatedGame",
+            kind: OptionKind::Check { default: true },
+        },
+        // UCI_Opponent is e.g. 'GM 2800 human Magnus Carlsen', title and rating may be 'none'.
+        UciOption {
+            name: "UCI_Opponent",
+            kind: OptionKind::String { default: "<empty>" },
+        },
+        UciOption {
+            name: "PollInterval",
+            kind: OptionKind::Spin {
+                default: DEFAULT_POLL_INTERVAL as i64,
+                min: 1,
+                max: 1_000_000,
+            },
+        },
+    ]
+}
+
+// Uci is an engine speaking Universal Chess Interface, the protocol used by chess GUIs.
+#[derive(Clone)]
+pub struct Uci {
+    board: Board,
+    history: Vec<u64>, // hashes of positions played before board, see Search::history
+    search: String,
+    evaluator: String,
+    multipv: usize,
+    playouts: u64,
+    poll_interval: u64,             // nodes between checks of 'stop' and the clock
+    sparring: Option<PositionType>, // type of positions games are steered into
+    // experience is a learning file shared with the search thread, it's saved with every new game.
+    experience: Option<(PathBuf, Arc<Mutex<ExperienceBook>>)>,
+    eval_file: Option<(PathBuf, Arc<EvalParams>)>,
+    book: Option<(PathBuf, Arc<OpeningBook>)>,
+    instant_moves: bool, // book and tablebase moves are played without a search
+    contempt: i32,
+    rating: Option<u32>,          // rating of the engine, None when unknown
+    rated: bool,                  // the game is rated
+    opponent_rating: Option<u32>, // read from UCI_Opponent
+    reload: Arc<AtomicBool>,      // set by SIGHUP, EvalFile is reloaded before the next search
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        Uci::new()
+    }
+}
+
+impl Uci {
+    pub fn new() -> Self {
+        Uci {
+            board: Board::default(),
+            history: Vec::new(),
+            search: "alphabeta".to_string(),
+            evaluator: "material-mobility".to_string(),
+            multipv: 1,
+            playouts: DEFAULT_PLAYOUTS,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            sparring: None,
+            experience: None,
+            eval_file: None,
+            book: None,
+            instant_moves: true,
+            contempt: 0,
+            rating: None,
+            rated: true,
+            opponent_rating: None,
+            reload: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // run reads commands line by line until 'quit' or end of input. Input that is not valid
+    // UTF-8 is read lossily instead of ending the session. 'go' searches in another thread, so
+    // 'stop' and 'isready' are answered while it runs, other commands wait for the search to end.
+    pub fn run<R: BufRead, W: Write + Send>(
+        &mut self,
+        mut input: R,
+        output: &mut W,
+    ) -> io::Result<()> {
+        #[cfg(all(unix, feature = "sighup"))]
+        signal_hook::flag::register(signal_hook::consts::SIGHUP, self.reload.clone())?;
+        let output = Mutex::new(output);
+        let send = |lines: &[String]| -> io::Result<()> {
+            let mut output = output.lock().expect("output is never poisoned");
+            for line in lines {
+                writeln!(output, "{}", line)?;
+            }
+            output.flush()
+        };
+        thread::scope(|scope| {
+            let mut searching: Option<(StopSignal, thread::ScopedJoinHandle<io::Result<()>>)> =
+                None;
+            let mut buffer = Vec::new();
+            loop {
+                buffer.clear();
+                if input.read_until(b'\n', &mut buffer)? == 0 {
+                    break;
+                }
+                let line = String::from_utf8_lossy(&buffer).into_owned();
+                let words = command(&line);
+                let name = words.first().copied();
+                if name == Some("isready") {
+                    send(&self.execute(&line))?;
+                    continue;
+                }
+                if let Some((stop, search)) = searching.take() {
+                    if name == Some("stop") || name == Some("quit") {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    search.join().expect("search thread panicked")?;
+                }
+                match name {
+                    Some("quit") => break,
+                    Some("go") => {
+                        if self.reload.swap(false, Ordering::Relaxed) {
+                            send(&self.reload_eval())?;
+                        }
+                        let stop = StopSignal::default();
+                        let engine = self.clone();
+                        let args: Vec<String> = words[1..].iter().map(|w| w.to_string()).collect();
+                        let signal = stop.clone();
+                        let search = scope.spawn(move || {
+                            let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+                            let mut result = Ok(());
+                            engine.go(&args, &signal, &mut |line| {
+                                if result.is_ok() {
+                                    result = send(&[line]);
+                                }
+                            });
+                            result
+                        });
+                        searching = Some((stop, search));
+                    }
+                    _ => send(&self.execute(&line))?,
+                }
+            }
+            // search is stopped at the end of input, so the GUI gets its bestmove.
+            if let Some((stop, search)) = searching {
+                stop.store(true, Ordering::Relaxed);
+                search.join().expect("search thread panicked")?;
+            }
+            send(&self.save_experience())
+        })
+    }
+
+    // execute runs single command and returns lines sent to the GUI. Malformed commands never
+    // stop the engine, problems are reported with 'info string' and the rest is done best-effort.
+    // 'go' searches synchronously here, so 'go infinite' never returns, use run to stop it.
+    pub fn execute(&mut self, line: &str) -> Vec<String> {
+        match command(line).as_slice() {
+            [] if line.trim().is_empty() => Vec::new(),
+            [] => vec![format!("info string unknown command {}", line.trim())],
+            ["uci", ..] => {
+                let mut lines = vec![
+                    format!("id name {}", ENGINE_INFO),
+                    format!("id author {}", ENGINE_INFO.author),
+                ];
+                lines.extend(options().iter().map(|o| o.to_string()));
+                lines.push("uciok".to_string());
+                lines
+            }
+            ["isready", ..] => vec!["readyok".to_string()],
+            ["ucinewgame", ..] => self.new_game(),
+            ["setoption", args @ ..] => self.set_option(args),
+            ["position", args @ ..] => self.position(args),
+            ["go", args @ ..] => {
+                let mut lines = Vec::new();
+                self.go(args, &StopSignal::default(), &mut |line| lines.push(line));
+                lines
+            }
+            // 'stop' is handled by run, there's nothing to ponder on.
+            _ => Vec::new(),
+        }
+    }
+
+    // new_game forgets everything about the previous game, options are kept: the position and
+    // positions played before it, which are used to detect repetitions. Search backends are
+    // created for every 'go', so no tables or move ordering heuristics outlive a search. Only the
+    // experience learned in the game is kept, on purpose, and saved.
+    fn new_game(&mut self) -> Vec<String> {
+        self.board = Board::default();
+        self.history.clear();
+        self.save_experience()
+    }
+
+    // set_experience saves the current learning file and loads one from path, empty path or
+    // '<empty>' turns learning off.
+    fn set_experience(&mut self, path: &str) -> Vec<String> {
+        let lines = self.save_experience();
+        self.experience = None;
+        if path.is_empty() || path == "<empty>" {
+            return lines;
+        }
+        match ExperienceBook::load(path) {
+            Ok(book) => self.experience = Some((PathBuf::from(path), Arc::new(Mutex::new(book)))),
+            Err(e) => return vec![format!("info string can't load experience {}: {}", path, e)],
+        }
+        lines
+    }
+
+    // set_eval_file loads parameters of ParamsEvaluator, empty path or '<empty>' goes back to
+    // Evaluator option. Parameters that can't be loaded leave the previous ones in use.
+    fn set_eval_file(&mut self, path: &str) -> Vec<String> {
+        if path.is_empty() || path == "<empty>" {
+            self.eval_file = None;
+            return Vec::new();
+        }
+        match EvalParams::load(path) {
+            Ok(params) => {
+                self.eval_file = Some((PathBuf::from(path), Arc::new(params)));
+                Vec::new()
+            }
+            Err(e) => vec![format!(
+                "info string can't load evaluation parameters {}",
+                e
+            )],
+        }
+    }
+
+    // set_book loads the opening book, empty path or '<empty>' turns it off. A book that can't be
+    // loaded leaves the previous one in use.
+    fn set_book(&mut self, path: &str) -> Vec<String> {
+        if path.is_empty() || path == "<empty>" {
+            self.book = None;
+            return Vec::new();
+        }
+        match OpeningBook::load(path) {
+            Ok(book) => {
+                self.book = Some((PathBuf::from(path), Arc::new(book)));
+                Vec::new()
+            }
+            Err(e) => vec![format!("info string can't load book {}: {}", path, e)],
+        }
+    }
+
+    // set_opponent reads rating of the opponent from UCI_Opponent, 'TITLE RATING computer|human
+    // NAME'. Unknown rating, 'none', and '<empty>' forget the previous one.
+    fn set_opponent(&mut self, value: &str) -> Vec<String> {
+        self.opponent_rating = None;
+        if value.is_empty() || value == "<empty>" {
+            return Vec::new();
+        }
+        match value.split_whitespace().nth(1) {
+            Some("none") => Vec::new(),
+            Some(rating) if rating.parse::<u32>().is_ok() => {
+                self.opponent_rating = rating.parse().ok();
+                Vec::new()
+            }
+            _ => vec![format!("info string invalid UCI_Opponent {}", value)],
+        }
+    }
+
+    // rating_advantage returns rating of the engine minus rating of the opponent, when both are
+    // known.
+    fn rating_advantage(&self) -> Option<i32> {
+        Some(self.rating? as i32 - self.opponent_rating? as i32)
+    }
+
+    // game_contempt returns contempt for the current game, see Contempt option.
+    fn game_contempt(&self) -> i32 {
+        let contempt = match self.rating_advantage() {
+            Some(advantage) => {
+                (self.contempt + advantage / CONTEMPT_ELO).clamp(-MAX_CONTEMPT, MAX_CONTEMPT)
+            }
+            None => self.contempt,
+        };
+        if self.rated {
+            contempt
+        } else {
+            contempt.max(self.contempt)
+        }
+    }
+
+    // time_scale returns a part of the usual clock budget used in the current game.
+    fn time_scale(&self) -> f32 {
+        let scale = match self.rating_advantage() {
+            Some(advantage) => 2f32.powf(-advantage as f32 / TIME_ELO).clamp(0.5, 2.0),
+            None => 1.0,
+        };
+        if self.rated {
+            scale
+        } else {
+            scale * CASUAL_TIME_SCALE
+        }
+    }
+
+    // reload_eval reads EvalFile again, searches that are running keep the old parameters.
+    fn reload_eval(&mut self) -> Vec<String> {
+        match &self.eval_file {
+            Some((path, _)) => {
+                let path = path.display().to_string();
+                let mut lines = self.set_eval_file(&path);
+                if lines.is_empty() {
+                    lines.push(format!(
+                        "info string reloaded evaluation parameters {}",
+                        path
+                    ));
+                }
+                lines
+            }
+            None => vec!["info string EvalFile is not set".to_string()],
+        }
+    }
+
+    // save_experience writes the learning file, returns 'info string' when it fails.
+    fn save_experience(&self) -> Vec<String> {
+        let (path, book) = match &self.experience {
+            Some(experience) => experience,
+            None => return Vec::new(),
+        };
+        match book
+            .lock()
+            .expect("experience is never poisoned")
+            .save(path)
+        {
+            Ok(()) => Vec::new(),
+            Err(e) => vec![format!(
+                "info string can't save experience {}: {}",
+                path.display(),
+                e
+            )],
+        }
+    }
+
+    // set_option handles 'setoption name NAME value VALUE', names are case insensitive.
+    fn set_option(&mut self, args: &[&str]) -> Vec<String> {
+        if args.first() != Some(&"name") {
+            return vec!["info string setoption must be followed by name".to_string()];
+        }
+        let value_at = args
+            .iter()
+            .position(|a| *a == "value")
+            .unwrap_or(args.len());
+        let name = args.get(1..value_at).unwrap_or(&[]).join(" ");
+        let value = args.get(value_at + 1..).unwrap_or(&[]).join(" ");
+        let option = match options()
+            .into_iter()
+            .find(|o| o.name.eq_ignore_ascii_case(&name))
+        {
+            Some(option) => option,
+            None => return vec![format!("info string unknown option {}", name)],
+        };
+        let valid = match &option.kind {
+            OptionKind::Spin { min, max, .. } => value
+                .parse::<i64>()
+                .is_ok_and(|v| (*min..=*max).contains(&v)),
+            OptionKind::Combo { values, .. } => values.contains(&value.as_str()),
+            OptionKind::Check { .. } => value == "true" || value == "false",
+            OptionKind::String { .. } | OptionKind::Button => true,
+        };
+        if !valid {
+            return vec![format!(
+                "info string invalid value {} of option {}",
+                value, option.name
+            )];
+        }
+        match option.name {
+            "Search" => self.search = value,
+            "Evaluator" => self.evaluator = value,
+            "MultiPV" => self.multipv = value.parse().unwrap_or(1),
+            "Playouts" => self.playouts = value.parse().unwrap_or(DEFAULT_PLAYOUTS),
+            "PollInterval" => self.poll_interval = value.parse().unwrap_or(DEFAULT_POLL_INTERVAL),
+            "Sparring" => self.sparring = value.parse().ok(),
+            "InstantMoves" => self.instant_moves = value == "true",
+            "Contempt" => self.contempt = value.parse().unwrap_or(0),
+            "Rating" => self.rating = value.parse().ok().filter(|r| *r > 0),
+            "RatedGame" => self.rated = value == "true",
+            "UCI_Opponent" => return self.set_opponent(&value),
+            "BookFile" => return self.set_book(&value),
+            "Experience" => return self.set_experience(&value),
+            "EvalFile" => return self.set_eval_file(&value),
+            "ReloadEval" => return self.reload_eval(),
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    // position handles 'position startpos|fen FEN [moves m1 m2 ...]'. Malformed FEN is repaired
+    // when possible, otherwise the previous position is kept. Moves are made up to the first
+    // illegal one, which is reported with its index in the list and the reason. UCI_Chess960 isn't
+    // offered, castles from other files than in standard chess aren't modelled, see read_castling.
+    fn position(&mut self, args: &[&str]) -> Vec<String> {
+        let moves_at = args
+            .iter()
+            .position(|a| *a == "moves")
+            .unwrap_or(args.len());
+        let mut board = Board::default();
+        let mut info = Vec::new();
+        match &args[..moves_at] {
+            ["startpos", ..] => {}
+            ["fen", fen @ ..] => match board.read_fen_lenient(&fen.join(" ")) {
+                Ok(warnings) => {
+                    info.extend(warnings.iter().map(|w| format!("info string fen: {}", w)))
+                }
+                Err(e) => {
+                    return vec![format!(
+                        "info string invalid fen: {}, position is not changed",
+                        e
+                    )]
+                }
+            },
+            [] if moves_at < args.len() => {
+                info.push("info string position without startpos or fen, using startpos".into())
+            }
+            _ => {
+                return vec![
+                    "info string position must be startpos or fen, position is not changed"
+                        .to_string(),
+                ]
+            }
+        }
+        let moves = args.get(moves_at + 1..).unwrap_or(&[]);
+        let mut history = Vec::new();
+        for (i, m) in moves.iter().enumerate() {
+            let t = match parse_uci(&board, m, false) {
+                Ok(t) => t,
+                Err(e) => {
+                    info.push(format!(
+                        "info string illegal move {} at index {}: {}, following moves are ignored",
+                        m, i, e
+                    ));
+                    break;
+                }
+            };
+            history.push(hash(&board));
+            board.make_transition(t);
+            // positions before pawn moves and captures can't be repeated.
+            if board.halfmove_clock() == 0 {
+                history.clear();
+            }
+        }
+        self.board = board;
+        self.history = history;
+        info
+    }
+
+    // go searches the position, limits are depth, nodes, movetime or clock times of players,
+    // 'go infinite' searches until 'stop'. Lines are sent to the GUI with 'send' as soon as they
+    // are known. 'go perft N' counts leaf nodes after every move instead. With InstantMoves book
+    // and tablebase moves are sent at once, except for 'go infinite' and 'go ponder'.
+    fn go(&self, args: &[&str], stop: &StopSignal, send: &mut dyn FnMut(String)) {
+        if let ["perft", depth, ..] = args {
+            return match depth.parse() {
+                Ok(depth) if depth > 0 => self.perft(depth, stop, send),
+                _ => send(format!("info string invalid perft depth {}", depth)),
+            };
+        }
+        // limits with invalid values are ignored.
+        for (i, name) in args.iter().enumerate() {
+            if GO_LIMITS.contains(name) && args.get(i + 1).is_none_or(|v| v.parse::<u64>().is_err())
+            {
+                send(format!("info string invalid value of {}, ignored", name));
+            }
+        }
+        if self.instant_moves && !args.contains(&"infinite") && !args.contains(&"ponder") {
+            let book = self.book.as_ref().map(|(_, book)| book.as_ref());
+            if let Some(m) = instant_move(&self.board, book) {
+                let t = to_internal(&m.t);
+                send(match m.source {
+                    InstantSource::Book(games) => {
+                        format!("info string book move {} played in {} games", t, games)
+                    }
+                    InstantSource::Tablebase(dtm) => format!("info string tablebase {}", dtm),
+                });
+                return send(format!("bestmove {}", t));
+            }
+        }
+        let value = |name: &str| -> Option<u64> {
+            args.windows(2)
+                .filter(|w| w[0] == name)
+                .find_map(|w| w[1].parse().ok())
+        };
+        let millis = |name: &str| value(name).map(Duration::from_millis);
+        let (time, increment) = if self.board.color_to_move == Color::WHITE {
+            (millis("wtime"), millis("winc"))
+        } else {
+            (millis("btime"), millis("binc"))
+        };
+        let clock_budget = time.map(|remaining| {
+            let clock = Clock {
+                remaining,
+                increment: increment.unwrap_or_default(),
+            };
+            let budget = match value("movestogo") {
+                Some(moves) if moves > 0 => clock.budget().max(remaining / (moves as u32 + 1)),
+                _ => clock.budget(),
+            };
+            budget.mul_f32(self.time_scale()).min(remaining / 2)
+        });
+        let time = millis("movetime").or(clock_budget);
+        let limits = SearchLimits {
+            depth: value("depth").map_or(
+                if time.is_some() || args.contains(&"infinite") {
+                    MAX_DEPTH
+                } else {
+                    DEFAULT_DEPTH
+                },
+                |d| d as usize,
+            ),
+            // Monte Carlo tree search with time limit runs until the time is up.
+            nodes: value("nodes").or(time.map_or(Some(self.playouts), |_| None)),
+            multipv: self.multipv,
+            policy: false,
+            time,
+            quiet_plies: self.board.halfmove_clock(),
+            contempt: self.game_contempt(),
+        };
+
+        let evaluator: Box<dyn Evaluator> =
+            match (&self.eval_file, evaluator_by_name(&self.evaluator)) {
+                (Some((_, params)), _) => Box::new(ParamsEvaluator {
+                    params: params.clone(),
+                }),
+                (None, Some(evaluator)) => evaluator,
+                (None, None) => {
+                    return send(format!("info string unknown evaluator {}", self.evaluator))
+                }
+            };
+        let mut backend = match backend_by_name(&self.search, evaluator.as_ref()) {
+            Some(backend) => backend,
+            None => return send(format!("info string unknown search {}", self.search)),
+        };
+        if let Some(target) = self.sparring {
+            backend = Box::new(Sparring::new(backend, target));
+        }
+        backend.set_stop(stop.clone(), self.poll_interval);
+        backend.set_history(&self.history);
+        let started = Instant::now();
+        let result = backend.search(&self.board, &limits, &mut |depth, best| {
+            for (i, line) in best.iter().enumerate() {
+                send(self.info(depth, i + 1, line, started));
+            }
+        });
+        let mut best = result.lines.first().and_then(|l| l.pv.first()).copied();
+        if let (Some((_, book)), Some(line)) = (&self.experience, result.lines.first()) {
+            let mut book = book.lock().expect("experience is never poisoned");
+            // experience of deeper searches is trusted more than the current one.
+            let (t, score, depth) = match book.choose(&self.board, &result.lines, result.depth) {
+                Some((t, e)) => {
+                    send(format!(
+                        "info string experience move {} depth {} score {}",
+                        to_internal(&t),
+                        e.depth,
+                        e.score
+                    ));
+                    (t, e.score, e.depth)
+                }
+                None => (line.pv[0], line.score, result.depth),
+            };
+            book.record(&self.board, &t, score, depth);
+            best = Some(t);
+        }
+        send(match best {
+            Some(t) => format!("bestmove {}", to_internal(&t)),
+            None => match self.board.generate_transitions().first() {
+                // backend without a line, e.g. mate solver, still has to answer with a move.
+                Some(t) => format!("bestmove {}", to_internal(t)),
+                None => "bestmove 0000".to_string(),
+            },
+        });
+    }
+
+    // perft sends number of leaf nodes after every move and their sum, as Stockfish does.
+    fn perft(&self, depth: usize, stop: &StopSignal, send: &mut dyn FnMut(String)) {
+        let mut total = 0;
+        for t in self.board.generate_transitions() {
+            let mut child = self.board.clone();
+            child.make_transition(t);
+            match child.perft_stoppable(depth - 1, stop) {
+                Some(nodes) => {
+                    total += nodes;
+                    send(format!("{}: {}", to_internal(&t), nodes));
+                }
+                None => return send("info string perft stopped".to_string()),
+            }
+        }
+        send(String::new());
+        send(format!("Nodes searched: {}", total));
+    }
+
+    // info returns 'info' line of a search line, e.g.
+    // 'info depth 5 multipv 1 score cp 35 time 120 pv e2e4 e7e5'.
+    fn info(&self, depth: usize, multipv: usize, line: &SearchLine, started: Instant) -> String {
+        let score = match line.score {
+            Score::Centipawns(cp) => format!("cp {}", cp),
+            Score::Mate(moves) => format!("mate {}", moves),
+        };
+        let pv: Vec<String> = line.pv.iter().map(to_internal).collect();
+        format!(
+            "info depth {} multipv {} score {} time {} pv {}",
+            depth,
+            multipv,
+            score,
+            started.elapsed().as_millis(),
+            pv.join(" ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::engine::ENGINE_INFO;
+    use crate::uci::{options, Uci};
+    use crate::zobrist::hash;
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_uci_handshake() {
+        let mut uci = Uci::new();
+        let lines = uci.execute("uci");
+        assert_eq!(lines[0], format!("id name chust {}", ENGINE_INFO.version));
+        assert_eq!(lines[1], format!("id author {}", ENGINE_INFO.author));
+        assert_eq!(lines.last().unwrap(), "uciok");
+        assert_eq!(lines.len(), options().len() + 3);
+        assert!(lines.contains(
+            &"option name Search type combo default alphabeta var alphabeta var mcts var mate"
+                .to_string()
+        ));
+        assert!(lines.contains(&"option name MultiPV type spin default 1 min 1 max 64".to_string()));
+        assert!(lines.contains(
+            &"option name Sparring type combo default none var none var iqp \
+              var opposite-castling var rook-endgame"
+                .to_string()
+        ));
+        assert_eq!(uci.execute("isready"), vec!["readyok"]);
+    }
+
+    #[test]
+    fn test_uci_malformed_input() {
+        let mut uci = Uci::new();
+        assert_eq!(
+            uci.execute("hello world"),
+            vec!["info string unknown command hello world"]
+        );
+        assert!(uci.execute("   ").is_empty());
+        assert_eq!(uci.execute("joho isready"), vec!["readyok"]);
+        assert_eq!(
+            uci.execute("setoption MultiPV 3"),
+            vec!["info string setoption must be followed by name"]
+        );
+        assert_eq!(
+            uci.execute("setoption name Hash value 16"),
+            vec!["info string unknown option Hash"]
+        );
+
+        // go before position searches the start position.
+        let lines = uci.execute("go depth x depth 1");
+        assert_eq!(lines[0], "info string invalid value of depth, ignored");
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+
+        let lines = uci.execute("position startpos moves e2e4 e7e5 e1e3 d2d4");
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("info string illegal move e1e3 at index 2"));
+        assert_eq!(
+            uci.board.position_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w"
+        );
+
+        assert!(uci.execute("position fen 8/8/8 w")[0].starts_with("info string invalid fen"));
+        assert_eq!(
+            uci.board.position_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w"
+        );
+        assert!(uci.execute("position moves e2e4")[0].contains("using startpos"));
+        assert_eq!(
+            uci.board.position_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b"
+        );
+
+        let mut output = Vec::new();
+        let input = b"isready\n\xff\xfe\nisready\r\nquit\nisready\n";
+        uci.run(&input[..], &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("readyok").count(), 2);
+        assert!(output.contains("info string unknown command"));
+    }
+
+    #[test]
+    fn test_uci_go() {
+        let mut uci = Uci::new();
+        // 'debug' waits for the search to finish, 'quit' would stop it.
+        let input = "setoption name multipv value 2\nposition fen 6k1/5ppp/8/8/8/8/8/R5K1 w\n\
+                     go depth 2\ndebug off\nquit\ngo depth 1\n";
+        let mut output = Vec::new();
+        uci.run(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("info depth 2 multipv 1 score mate 1"));
+        assert!(output.contains("info depth 2 multipv 2"));
+        assert!(output.ends_with("bestmove a1a8\n"));
+        assert_eq!(output.matches("bestmove").count(), 1);
+
+        assert_eq!(
+            uci.execute("setoption name MultiPV value 0"),
+            vec!["info string invalid value 0 of option MultiPV"]
+        );
+        uci.execute("position startpos moves e2e4 e7e5");
+        let lines = uci.execute("go wtime 1000 btime 1000 winc 10 binc 10");
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+
+        assert!(uci
+            .execute("setoption name Sparring value rook-endgame")
+            .is_empty());
+        assert_eq!(
+            uci.execute("setoption name Sparring value endgame"),
+            vec!["info string invalid value endgame of option Sparring"]
+        );
+        let lines = uci.execute("go depth 1");
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_uci_position_moves() {
+        let mut uci = Uci::new();
+        let fen = "position fen r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1 moves";
+        assert!(uci.execute(&format!("{} e1c1 e8g8 b7b8q", fen)).is_empty());
+        assert_eq!(uci.board.position_fen(), "rQ3rk1/8/8/8/8/8/8/2KR3R b");
+
+        let lines = uci.execute(&format!("{} e1h1 e8c8", fen));
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("info string illegal move e1h1 at index 0: castle"));
+
+        assert_eq!(
+            uci.execute("setoption name UCI_Chess960 value true"),
+            vec!["info string unknown option UCI_Chess960"]
+        );
+        uci.execute("position startpos moves e2e4 e7e5 g1f3 g8f6 f1c4 f8c5");
+        let lines = uci.execute("go perft 1");
+        assert!(lines.contains(&"e1g1: 1".to_string()));
+    }
+
+    #[test]
+    fn test_uci_new_game() {
+        let go = "position startpos moves e2e4 e7e5 g1f3\ngo depth 2";
+        let mut fresh = Uci::new();
+        let expected: Vec<String> = go.lines().flat_map(|l| fresh.execute(l)).collect();
+
+        // the same game searched after another one gets the same result.
+        let mut uci = Uci::new();
+        uci.execute("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w");
+        uci.execute("go depth 2");
+        uci.execute("position startpos moves e2e4 g8f6 g1f3 f6g8 f3g1");
+        assert_eq!(uci.history.len(), 4); // the position before e2e4 can't repeat
+        uci.execute("position startpos moves g1f3 g8f6 f3g1 f6g8");
+        assert_eq!(uci.history.len(), 4);
+        assert_eq!(uci.history[0], hash(&uci.board));
+        assert!(uci.execute("ucinewgame").is_empty());
+        assert_eq!(uci.board.position_fen(), Board::default().position_fen());
+        assert!(uci.history.is_empty());
+        let lines: Vec<String> = go.lines().flat_map(|l| uci.execute(l)).collect();
+        let without_time = |lines: &[String]| -> Vec<String> {
+            lines
+                .iter()
+                .map(|l| match l.split_once(" time ") {
+                    Some((info, rest)) => format!("{}{}", info, &rest[rest.find(" pv").unwrap()..]),
+                    None => l.clone(),
+                })
+                .collect()
+        };
+        assert_eq!(without_time(&lines), without_time(&expected));
+    }
+
+    #[test]
+    fn test_uci_experience() {
+        let path = std::env::temp_dir().join("chust_test_uci_experience.txt");
+        let _ = std::fs::remove_file(&path);
+        let set = format!("setoption name Experience value {}", path.display());
+        let mut uci = Uci::new();
+        assert!(uci.execute(&set).is_empty());
+        uci.execute("go depth 1");
+        assert!(uci.execute("ucinewgame").is_empty());
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        assert!(text.starts_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w;"));
+
+        // move learned with deeper search is played instead of the one found now.
+        let learned = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w;a2a3;9;cp 500;1\n";
+        std::fs::write(&path, learned).unwrap();
+        let mut uci = Uci::new();
+        uci.execute(&set);
+        let lines = uci.execute("go depth 1");
+        assert_eq!(
+            lines[lines.len() - 2],
+            "info string experience move a2a3 depth 9 score +5.00"
+        );
+        assert_eq!(lines.last().unwrap(), "bestmove a2a3");
+        assert!(uci
+            .execute("setoption name Experience value <empty>")
+            .is_empty());
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .ends_with(";a2a3;9;cp 500;2\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_uci_contempt() {
+        let mut uci = Uci::new();
+        uci.execute("setoption name Contempt value 20");
+        // ratings are not known yet.
+        uci.execute("setoption name UCI_Opponent value GM 2800 human Magnus Carlsen");
+        assert_eq!((uci.game_contempt(), uci.time_scale()), (20, 1.0));
+
+        uci.execute("setoption name Rating value 2400");
+        assert_eq!(uci.game_contempt(), -20);
+        assert!((uci.time_scale() - 2.0).abs() < 1e-3);
+        uci.execute("setoption name UCI_Opponent value none 2000 computer stockfish");
+        assert_eq!(uci.game_contempt(), 60);
+        assert!((uci.time_scale() - 0.5).abs() < 1e-3);
+
+        // casual games are played for a win, with less time.
+        uci.execute("setoption name UCI_Opponent value none 2800 human Magnus Carlsen");
+        uci.execute("setoption name RatedGame value false");
+        assert_eq!(uci.game_contempt(), 20);
+        assert!((uci.time_scale() - 1.5).abs() < 1e-3);
+
+        assert_eq!(
+            uci.execute("setoption name UCI_Opponent value GM strong human Magnus"),
+            vec!["info string invalid UCI_Opponent GM strong human Magnus"]
+        );
+        assert_eq!(uci.game_contempt(), 20);
+    }
+
+    #[test]
+    fn test_uci_instant_moves() {
+        let path = std::env::temp_dir().join(format!("chust-uci-book-{}", std::process::id()));
+        std::fs::write(&path, "1. d4 d5 *\n\n1. d4 Nf6 *\n\n1. e4 e5 *\n").unwrap();
+        let mut uci = Uci::new();
+        assert!(uci
+            .execute(&format!("setoption name BookFile value {}", path.display()))
+            .is_empty());
+        assert_eq!(
+            uci.execute("go wtime 1000 btime 1000"),
+            vec![
+                "info string book move d2d4 played in 2 games",
+                "bestmove d2d4"
+            ]
+        );
+        // analysis is never cut short.
+        assert!(uci.execute("go depth 1")[0].starts_with("info string book"));
+        uci.execute("position startpos moves a2a3");
+        assert!(uci.execute("go depth 1")[0].starts_with("info depth 1"));
+
+        uci.execute("position fen 7k/8/5K2/8/8/8/8/6Q1 w");
+        let lines = uci.execute("go movetime 1000");
+        assert_eq!(
+            lines,
+            vec!["info string tablebase win, mate in 1", "bestmove g1g7"]
+        );
+        assert!(uci
+            .execute("setoption name InstantMoves value false")
+            .is_empty());
+        assert!(uci.execute("go depth 1")[0].starts_with("info depth 1"));
+        assert!(
+            uci.execute("setoption name BookFile value /nonexistent/book.pgn")[0]
+                .starts_with("info string can't load book")
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_uci_eval_file() {
+        let path = std::env::temp_dir().join(format!("chust-uci-eval-{}", std::process::id()));
+        // knights are attracted to the given square.
+        let write = |square: usize| {
+            let mut pst = vec!["0"; 64];
+            pst[square] = "50";
+            std::fs::write(&path, format!("pst knight {}\n", pst.join(" "))).unwrap();
+        };
+        write(16); // a3
+        let mut uci = Uci::new();
+        assert_eq!(
+            uci.execute("setoption name ReloadEval"),
+            vec!["info string EvalFile is not set"]
+        );
+        let set = format!("setoption name EvalFile value {}", path.display());
+        assert!(uci.execute(&set).is_empty());
+        assert_eq!(uci.execute("go depth 1").last().unwrap(), "bestmove b1a3");
+
+        write(23); // h3
+        assert_eq!(
+            uci.execute("setoption name ReloadEval"),
+            vec![format!(
+                "info string reloaded evaluation parameters {}",
+                path.display()
+            )]
+        );
+        assert_eq!(uci.execute("go depth 1").last().unwrap(), "bestmove g1h3");
+
+        // SIGHUP sets the flag, parameters are reloaded before the next search.
+        write(16);
+        uci.reload.store(true, Ordering::Relaxed);
+        let mut output = Vec::new();
+        uci.run("go depth 1\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("info string reloaded evaluation parameters"));
+        assert!(output.ends_with("bestmove b1a3\n"));
+
+        std::fs::write(&path, "knight three\n").unwrap();
+        assert!(
+            uci.execute("setoption name ReloadEval")[0].ends_with("line 1: invalid number three")
+        );
+        assert_eq!(uci.execute("go depth 1").last().unwrap(), "bestmove b1a3");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_uci_stop() {
+        for search in ["alphabeta", "mcts", "mate"] {
+            let mut uci = Uci::new();
+            let input = format!(
+                "setoption name Search value {}\nsetoption name PollInterval value 64\n\
+                 go infinite\nisready\nstop\n",
+                search
+            );
+            let started = Instant::now();
+            let mut output = Vec::new();
+            uci.run(input.as_bytes(), &mut output).unwrap();
+            assert!(started.elapsed() < Duration::from_secs(5));
+            let output = String::from_utf8(output).unwrap();
+            assert!(output.contains("readyok\n"));
+            assert!(output.contains("bestmove "));
+        }
+
+        let mut uci = Uci::new();
+        let lines = uci.execute("go perft 2");
+        assert_eq!(lines.len(), 22);
+        assert_eq!(lines.last().unwrap(), "Nodes searched: 400");
+        assert!(lines.contains(&"e2e4: 20".to_string()));
+    }
+}
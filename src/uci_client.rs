@@ -0,0 +1,365 @@
+use crate::backend::{SearchBackend, SearchLimits, SearchResult};
+use crate::board::{Board, CastlingNotation};
+use crate::notation::{parse_uci, square_name};
+use crate::score::Score;
+use crate::search::{is_set, SearchLine, StopSignal};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// HANDSHAKE_TIMEOUT bounds waiting for 'uciok' and 'readyok'.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// MOVETIME_GRACE is how long after its movetime engine is sent 'stop'.
+const MOVETIME_GRACE: Duration = Duration::from_secs(1);
+// POLL is how often a waiting client checks the stop signal and time.
+const POLL: Duration = Duration::from_millis(10);
+
+// UciClient runs an external UCI engine, e.g. Stockfish, as a child process, so matches, reviews
+// and tests can compare chust with reference engines. Lines of the engine are read by a thread,
+// so a hanging engine can't block the client when it's stopped or out of time.
+pub struct UciClient {
+    pub name: String, // from 'id name', the command when the engine doesn't send it
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    stop: Option<StopSignal>,
+    multipv: usize,
+}
+
+impl UciClient {
+    // spawn starts the engine and waits for 'uciok'. Command is split at whitespace into the
+    // program and its arguments, e.g. 'stockfish' or '/usr/bin/lc0 --weights=net.pb'.
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut client = UciClient {
+            name: command.to_string(),
+            child,
+            stdin,
+            lines,
+            stop: None,
+            multipv: 1,
+        };
+        client.send("uci")?;
+        for line in client.wait_for("uciok", HANDSHAKE_TIMEOUT)? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                client.name = name.trim().to_string();
+            }
+        }
+        Ok(client)
+    }
+
+    // send writes a command to the engine.
+    pub fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    // wait_for returns lines of the engine up to the first one that starts with 'token'.
+    fn wait_for(&mut self, token: &str, timeout: Duration) -> io::Result<Vec<String>> {
+        let deadline = Instant::now() + timeout;
+        let mut lines = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(left) {
+                Ok(line) => {
+                    let done = line.split_whitespace().next() == Some(token);
+                    lines.push(line);
+                    if done {
+                        return Ok(lines);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        format!("engine didn't send {}", token),
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(ErrorKind::UnexpectedEof, "engine exited"))
+                }
+            }
+        }
+    }
+
+    // is_ready waits until the engine has processed all commands sent so far.
+    pub fn is_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        self.wait_for("readyok", HANDSHAKE_TIMEOUT).map(|_| ())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    // new_game tells the engine that following positions are from a different game.
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    // go searches the position with the engine. Time limit is sent as movetime, otherwise nodes
+    // or depth, and the engine is stopped when it overruns its time or the stop signal is set.
+    // on_info is called with the best lines whenever the engine finishes a depth.
+    pub fn go(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(usize, &[SearchLine]),
+    ) -> io::Result<SearchResult> {
+        let multipv = limits.multipv.max(1);
+        if multipv != self.multipv {
+            self.set_option("MultiPV", &multipv.to_string())?;
+            self.multipv = multipv;
+        }
+        self.send(&format!("position fen {}", fen(board, limits.quiet_plies)))?;
+        self.send(&match (limits.time, limits.nodes) {
+            (Some(time), _) => format!("go movetime {}", time.as_millis().max(1)),
+            (None, Some(nodes)) => format!("go nodes {}", nodes),
+            (None, None) => format!("go depth {}", limits.depth),
+        })?;
+        let deadline = limits.time.map(|t| Instant::now() + t + MOVETIME_GRACE);
+        let mut result = SearchResult {
+            lines: Vec::new(),
+            nodes: 0,
+            depth: 0,
+            policy: Vec::new(),
+        };
+        let mut lines: Vec<Option<SearchLine>> = vec![None; multipv];
+        let mut stopped = false;
+        let best = loop {
+            let line = match self.lines.recv_timeout(POLL) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    let late = deadline.is_some_and(|d| Instant::now() >= d);
+                    if !stopped && (late || is_set(&self.stop)) {
+                        self.send("stop")?;
+                        stopped = true;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "engine exited during search",
+                    ))
+                }
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["bestmove", best, ..] => break parse_uci(board, best, false).ok(),
+                ["info", ..] => {
+                    let info = match parse_info(board, &words) {
+                        Some(info) => info,
+                        None => continue,
+                    };
+                    result.nodes = result.nodes.max(info.nodes);
+                    if info.multipv > multipv {
+                        continue;
+                    }
+                    lines[info.multipv - 1] = Some(info.line);
+                    // lines of a depth are sent in order, the depth is done with the last one.
+                    if info.multipv == multipv {
+                        result.depth = info.depth;
+                        result.lines = lines.iter().flatten().cloned().collect();
+                        on_info(info.depth, &result.lines);
+                    }
+                }
+                _ => {}
+            }
+        };
+        if result.lines.is_empty() {
+            result.lines = lines.into_iter().flatten().collect();
+        }
+        // stopped engine may play a move that isn't the first one of its last line.
+        match (best, result.lines.first_mut()) {
+            (Some(best), Some(line))
+                if line.pv[0].from != best.from || line.pv[0].to != best.to =>
+            {
+                line.pv = vec![best]
+            }
+            (Some(best), None) => result.lines.push(SearchLine {
+                score: Score::Centipawns(0),
+                pv: vec![best],
+            }),
+            _ => {}
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for UciClient {
+    // drop asks the engine to quit and kills it when it doesn't in a second.
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..100 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(POLL);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// UciClient is a search backend, so external engines play matches like built-in searches. Errors
+// of the engine give a result without lines, like a stopped search, use go to see them.
+impl SearchBackend for UciClient {
+    fn name(&self) -> &'static str {
+        "uci"
+    }
+
+    // engine gets 'stop' when the signal is set, it checks the signal on its own.
+    fn set_stop(&mut self, stop: StopSignal, _poll_interval: u64) {
+        self.stop = Some(stop);
+    }
+
+    fn search(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(usize, &[SearchLine]),
+    ) -> SearchResult {
+        self.go(board, limits, on_info)
+            .unwrap_or_else(|_| SearchResult {
+                lines: Vec::new(),
+                nodes: 0,
+                depth: 0,
+                policy: Vec::new(),
+            })
+    }
+}
+
+// Info is a line of a search read from 'info' sent by the engine.
+struct Info {
+    depth: usize,
+    multipv: usize,
+    nodes: u64,
+    line: SearchLine,
+}
+
+// parse_info reads 'info' with score and pv, e.g.
+// 'info depth 12 multipv 1 score cp 35 nodes 51000 pv e2e4 e7e5'. Moves of pv are made up to the
+// first illegal one, info without score or legal pv is skipped.
+fn parse_info(board: &Board, words: &[&str]) -> Option<Info> {
+    let value = |name: &str| {
+        words
+            .windows(2)
+            .find(|w| w[0] == name)
+            .and_then(|w| w[1].parse::<i64>().ok())
+    };
+    let score = match words.windows(3).find(|w| w[0] == "score")? {
+        ["score", "cp", cp] => Score::Centipawns(cp.parse().ok()?),
+        ["score", "mate", moves] => Score::Mate(moves.parse().ok()?),
+        _ => return None,
+    };
+    let pv_at = words.iter().position(|w| *w == "pv")?;
+    let mut position = board.clone();
+    let mut pv = Vec::new();
+    for m in &words[pv_at + 1..] {
+        match parse_uci(&position, m, false) {
+            Ok(t) => {
+                position.make_transition(t);
+                pv.push(t);
+            }
+            Err(_) => break,
+        }
+    }
+    if pv.is_empty() {
+        return None;
+    }
+    Some(Info {
+        depth: value("depth").unwrap_or_default() as usize,
+        multipv: value("multipv").unwrap_or(1).max(1) as usize,
+        nodes: value("nodes").unwrap_or_default() as u64,
+        line: SearchLine { score, pv },
+    })
+}
+
+// fen writes the position with the halfmove clock given by the caller, which also counts plies
+// played before a position read without counters.
+fn fen(board: &Board, quiet_plies: usize) -> String {
+    let en_passant = board
+        .en_passant_square()
+        .map_or("-".to_string(), square_name);
+    format!(
+        "{} {} {} {} {}",
+        board.position_fen(),
+        board.castling_fen(CastlingNotation::Standard),
+        en_passant,
+        quiet_plies.max(board.halfmove_clock()),
+        board.fullmove_number()
+    )
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::backend::{SearchBackend, SearchLimits};
+    use crate::board::Board;
+    use crate::notation::to_internal;
+    use crate::score::Score;
+    use crate::uci_client::UciClient;
+    use std::fs;
+
+    // ENGINE is a shell script answering like a UCI engine that always plays e2e4. Positions are
+    // echoed as 'info string', which the client skips.
+    const ENGINE: &str = r#"
+while read -r cmd rest; do
+  case "$cmd" in
+    uci) echo "id name Fake 1.0"; echo "option name MultiPV type spin default 1"; echo uciok ;;
+    isready) echo readyok ;;
+    position) echo "info string $rest" ;;
+    go) echo "info depth 1 score cp 20 nodes 30 pv e2e4"
+        echo "info depth 2 multipv 1 score cp 25 nodes 90 pv e2e4 e7e5 a1a9"
+        echo "bestmove e2e4" ;;
+    quit) exit 0 ;;
+  esac
+done
+"#;
+
+    #[test]
+    fn test_uci_client() {
+        let path = std::env::temp_dir().join(format!("chust-engine-{}.sh", std::process::id()));
+        fs::write(&path, ENGINE).unwrap();
+        let mut client = UciClient::spawn(&format!("sh {}", path.display())).unwrap();
+        assert_eq!(client.name, "Fake 1.0");
+        client.new_game().unwrap();
+
+        let mut depths = Vec::new();
+        let result = client.search(&Board::default(), &SearchLimits::depth(2), &mut |d, _| {
+            depths.push(d)
+        });
+        assert_eq!(depths, vec![1, 2]);
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.nodes, 90);
+        assert_eq!(result.lines[0].score, Score::Centipawns(25));
+        let pv: Vec<String> = result.lines[0].pv.iter().map(to_internal).collect();
+        assert_eq!(pv, vec!["e2e4", "e7e5"]);
+
+        client.is_ready().unwrap();
+        assert!(UciClient::spawn("").is_err());
+        assert!(UciClient::spawn("chust-no-such-engine").is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+use crate::board::{Board, CastlingNotation};
+use crate::piece::{Color, PieceType};
+
+// Zobrist hash of a position is xor of random keys of its features: pieces on squares, color to
+// move, castling rights and en passant file. Keys are generated at compile time with splitmix64,
+// so hashes are the same in every process and can be stored in files.
+
+const PIECE_KEYS: usize = 12 * 64;
+const BLACK_TO_MOVE: usize = PIECE_KEYS;
+const CASTLING: usize = BLACK_TO_MOVE + 1; // K, Q, k and q
+const EN_PASSANT: usize = CASTLING + 4; // one key per file
+const KEYS_COUNT: usize = EN_PASSANT + 8;
+
+static KEYS: [u64; KEYS_COUNT] = generate_keys();
+
+const fn generate_keys() -> [u64; KEYS_COUNT] {
+    let mut keys = [0; KEYS_COUNT];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut i = 0;
+    while i < KEYS_COUNT {
+        // splitmix64
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+fn piece_index(p_type: PieceType, color: Color) -> Option<usize> {
+    let kind = match p_type {
+        PieceType::NONE => return None,
+        PieceType::PAWN => 0,
+        PieceType::KNIGHT => 1,
+        PieceType::BISHOP => 2,
+        PieceType::ROOK => 3,
+        PieceType::QUEEN => 4,
+        PieceType::KING => 5,
+    };
+    Some(if color == Color::WHITE {
+        kind
+    } else {
+        kind + 6
+    })
+}
+
+// hash returns Zobrist hash of the position.
+pub fn hash(board: &Board) -> u64 {
+    let mut hash = 0;
+    for (square, p) in board.squares().iter().enumerate() {
+        if let Some(index) = piece_index(p.p_type, p.color) {
+            hash ^= KEYS[index * 64 + square];
+        }
+    }
+    if board.color_to_move == Color::BLACK {
+        hash ^= KEYS[BLACK_TO_MOVE];
+    }
+    for c in board.castling_fen(CastlingNotation::Standard).chars() {
+        if let Some(i) = "KQkq".find(c) {
+            hash ^= KEYS[CASTLING + i];
+        }
+    }
+    if let Some(square) = board.en_passant_square() {
+        hash ^= KEYS[EN_PASSANT + square % 8];
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::zobrist::hash;
+
+    #[test]
+    fn test_hash() {
+        let play = |moves: &[&str]| {
+            let mut b = Board::default();
+            for m in moves {
+                b.make_move_internal_notation(m).unwrap();
+            }
+            hash(&b)
+        };
+        // transpositions have the same hash.
+        assert_eq!(
+            play(&["g1f3", "g8f6", "b1c3"]),
+            play(&["b1c3", "g8f6", "g1f3"])
+        );
+        assert_ne!(play(&[]), play(&["g1f3"]));
+        // en passant and castling rights are part of the position.
+        assert_ne!(
+            play(&["e2e4", "g8f6", "e4e5", "d7d5"]),
+            play(&["e2e4", "d7d6", "e4e5", "d6d5"])
+        );
+        assert_ne!(
+            play(&["g1f3", "g8f6", "h1g1", "f6g8", "g1h1", "g8f6"]),
+            play(&["g1f3", "g8f6"])
+        );
+    }
+}